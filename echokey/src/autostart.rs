@@ -1,11 +1,18 @@
 //! 开机自启动模块
 //!
-//! 在 Windows 上通过注册表实现开机自启动。
+//! 在 Windows 上通过注册表实现开机自启动，在 macOS 上通过 LaunchAgent 实现，
+//! 在 Linux 等其他类 Unix 系统上通过 XDG autostart 的 `.desktop` 文件实现。
 //! 
 //! 注册表位置：HKEY_CURRENT_USER\Software\Microsoft\Windows\CurrentVersion\Run
+//! LaunchAgent 位置：~/Library/LaunchAgents/com.echokey.plist
+//! XDG autostart 位置：~/.config/autostart/echokey.desktop
 
-#[cfg(windows)]
+#[cfg(any(windows, unix))]
 use std::env;
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::path::PathBuf;
 #[cfg(windows)]
 use winreg::enums::*;
 #[cfg(windows)]
@@ -14,22 +21,64 @@ use winreg::RegKey;
 #[cfg(windows)]
 const APP_NAME: &str = "EchoKey";
 
+/// 当前用户的开机启动项所在的注册表键
+#[cfg(windows)]
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// LaunchAgent 的标识，同时是 plist 的文件名
+#[cfg(any(target_os = "macos", test))]
+const LAUNCH_AGENT_LABEL: &str = "com.echokey";
+
+/// 开机自启动时附加的命令行参数，用于区分自启动和手动启动
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+/// 本次是否由开机自启动启动
+pub fn launched_by_autostart() -> bool {
+    std::env::args().skip(1).any(|arg| arg == AUTOSTART_ARG)
+}
+
+/// 当前程序的路径
+#[cfg(any(windows, unix))]
+fn current_exe() -> Result<String, String> {
+    env::current_exe()
+        .map(|path| path.to_string_lossy().into_owned())
+        .map_err(|e| format!("无法获取程序路径: {}", e))
+}
+
+/// 开机自启动时传给程序的参数
+///
+/// 通过参数启用的便携模式在自启动时也要带上参数（标记文件方式不需要）。
+#[cfg(any(windows, unix))]
+fn autostart_args() -> Vec<&'static str> {
+    let mut args = vec![AUTOSTART_ARG];
+    if crate::config::portable_arg_given() {
+        args.push(crate::config::PORTABLE_ARG);
+    }
+    args
+}
+
+/// 写入注册表的启动命令：程序路径加引号（路径中可能有空格），后接参数
+#[cfg(any(windows, test))]
+fn run_command(exe_path: &str, args: &[&str]) -> String {
+    std::iter::once(format!("\"{}\"", exe_path))
+        .chain(args.iter().map(|arg| arg.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// 启用开机自启动
 #[cfg(windows)]
 pub fn enable() -> Result<(), String> {
-    let exe_path = env::current_exe()
-        .map_err(|e| format!("无法获取程序路径: {}", e))?;
+    let exe_path = current_exe()?;
     
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let run_key = hkcu
-        .open_subkey_with_flags(
-            r"Software\Microsoft\Windows\CurrentVersion\Run",
-            KEY_WRITE,
-        )
+        .open_subkey_with_flags(RUN_KEY, KEY_WRITE)
         .map_err(|e| format!("无法打开注册表: {}", e))?;
     
+    let command = run_command(&exe_path, &autostart_args());
     run_key
-        .set_value(APP_NAME, &exe_path.to_string_lossy().to_string())
+        .set_value(APP_NAME, &command)
         .map_err(|e| format!("无法写入注册表: {}", e))?;
     
     Ok(())
@@ -40,10 +89,7 @@ pub fn enable() -> Result<(), String> {
 pub fn disable() -> Result<(), String> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let run_key = hkcu
-        .open_subkey_with_flags(
-            r"Software\Microsoft\Windows\CurrentVersion\Run",
-            KEY_WRITE,
-        )
+        .open_subkey_with_flags(RUN_KEY, KEY_WRITE)
         .map_err(|e| format!("无法打开注册表: {}", e))?;
     
     // 忽略删除失败（可能本来就没有）
@@ -57,25 +103,302 @@ pub fn disable() -> Result<(), String> {
 pub fn is_enabled() -> bool {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     
-    if let Ok(run_key) = hkcu.open_subkey(r"Software\Microsoft\Windows\CurrentVersion\Run") {
+    if let Ok(run_key) = hkcu.open_subkey(RUN_KEY) {
         run_key.get_value::<String, _>(APP_NAME).is_ok()
     } else {
         false
     }
 }
 
-// 非 Windows 平台的占位实现
-#[cfg(not(windows))]
+/// 检查注册表中的启动命令是否与当前程序一致（程序移动位置后需要重新写入）
+#[cfg(windows)]
+pub fn is_current() -> bool {
+    let Ok(exe_path) = current_exe() else {
+        return false;
+    };
+    let expected = run_command(&exe_path, &autostart_args());
+    
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    hkcu.open_subkey(RUN_KEY)
+        .and_then(|run_key| run_key.get_value::<String, _>(APP_NAME))
+        .is_ok_and(|command| command.eq_ignore_ascii_case(&expected))
+}
+
+/// LaunchAgent plist 的路径：~/Library/LaunchAgents/com.echokey.plist
+#[cfg(target_os = "macos")]
+fn launch_agent_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or_else(|| "无法获取用户目录".to_string())?;
+    Ok(home.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCH_AGENT_LABEL)))
+}
+
+/// 生成 LaunchAgent plist：登录时（`RunAtLoad`）以给定参数启动程序
+#[cfg(any(target_os = "macos", test))]
+fn launch_agent_plist(exe_path: &str, args: &[&str]) -> String {
+    let arguments: String = std::iter::once(exe_path)
+        .chain(args.iter().copied())
+        .map(|arg| format!("        <string>{}</string>\n", escape_xml(arg)))
+        .collect();
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+            "<plist version=\"1.0\">\n",
+            "<dict>\n",
+            "    <key>Label</key>\n",
+            "    <string>{}</string>\n",
+            "    <key>ProgramArguments</key>\n",
+            "    <array>\n",
+            "{}",
+            "    </array>\n",
+            "    <key>RunAtLoad</key>\n",
+            "    <true/>\n",
+            "</dict>\n",
+            "</plist>\n",
+        ),
+        LAUNCH_AGENT_LABEL,
+        arguments,
+    )
+}
+
+/// 转义 plist 字符串中的 XML 特殊字符
+#[cfg(any(target_os = "macos", test))]
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// 启用开机自启动
+#[cfg(target_os = "macos")]
+pub fn enable() -> Result<(), String> {
+    let path = launch_agent_path()?;
+    let plist = launch_agent_plist(&current_exe()?, &autostart_args());
+    
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("无法创建 LaunchAgents 目录: {}", e))?;
+    }
+    fs::write(&path, plist)
+        .map_err(|e| format!("无法写入 LaunchAgent: {}", e))?;
+    
+    Ok(())
+}
+
+/// 禁用开机自启动
+#[cfg(target_os = "macos")]
+pub fn disable() -> Result<(), String> {
+    let path = launch_agent_path()?;
+    
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        // 本来就没有
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("无法删除 LaunchAgent: {}", e)),
+    }
+}
+
+/// 检查是否已启用开机自启动（plist 存在且指向当前程序）
+#[cfg(target_os = "macos")]
+pub fn is_enabled() -> bool {
+    let (Ok(path), Ok(exe_path)) = (launch_agent_path(), current_exe()) else {
+        return false;
+    };
+    let expected = format!("<string>{}</string>", escape_xml(&exe_path));
+    fs::read_to_string(path).is_ok_and(|plist| plist.contains(&expected))
+}
+
+/// 检查 plist 是否与当前程序和参数一致（程序移动位置后需要重新写入）
+#[cfg(target_os = "macos")]
+pub fn is_current() -> bool {
+    let (Ok(path), Ok(exe_path)) = (launch_agent_path(), current_exe()) else {
+        return false;
+    };
+    fs::read_to_string(path).is_ok_and(|plist| plist == launch_agent_plist(&exe_path, &autostart_args()))
+}
+
+/// XDG autostart 文件的路径：~/.config/autostart/echokey.desktop
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_entry_path() -> Result<PathBuf, String> {
+    let config_dir = dirs::config_dir().ok_or_else(|| "无法获取用户配置目录".to_string())?;
+    Ok(config_dir.join("autostart").join("echokey.desktop"))
+}
+
+/// 生成 XDG autostart 的 `.desktop` 文件：登录时以给定参数启动程序
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn desktop_entry(exe_path: &str, args: &[&str]) -> String {
+    let exec: Vec<String> = std::iter::once(exe_path)
+        .chain(args.iter().copied())
+        .map(desktop_exec_arg)
+        .collect();
+    format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec={}\nX-GNOME-Autostart-enabled=true\n",
+        crate::config::APP_NAME,
+        exec.join(" "),
+    )
+}
+
+/// 按 Desktop Entry 规范转义 `Exec=` 中的一个参数
+///
+/// 含空格等保留字符的参数用双引号括起，引号内的 `"`、`` ` ``、`$`、`\` 加反斜杠；
+/// `Exec=` 的值本身是字符串，读取时先按字符串规则去掉一层反斜杠，因此这些反斜杠都要写成两个；
+/// `%` 写成 `%%`（否则会被当成字段代码）。
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn desktop_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`',
+    ];
+    let arg = arg.replace('%', "%%");
+    if arg.is_empty() || arg.contains(RESERVED) {
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            match c {
+                '"' | '`' | '$' => {
+                    quoted.push_str("\\\\");
+                    quoted.push(c);
+                }
+                '\\' => quoted.push_str("\\\\\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                _ => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        arg
+    }
+}
+
+/// 启用开机自启动
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn enable() -> Result<(), String> {
+    let path = desktop_entry_path()?;
+    let entry = desktop_entry(&current_exe()?, &autostart_args());
+    
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("无法创建 autostart 目录: {}", e))?;
+    }
+    fs::write(&path, entry)
+        .map_err(|e| format!("无法写入自启动文件: {}", e))?;
+    
+    Ok(())
+}
+
+/// 禁用开机自启动
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn disable() -> Result<(), String> {
+    let path = desktop_entry_path()?;
+    
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        // 本来就没有
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("无法删除自启动文件: {}", e)),
+    }
+}
+
+/// 检查是否已启用开机自启动
+///
+/// 桌面环境的启动项设置中关闭自启动时不删除文件，而是写入 `Hidden=true`
+/// 或 `X-GNOME-Autostart-enabled=false`，这两种情况都算未启用。
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_enabled() -> bool {
+    desktop_entry_path()
+        .and_then(|path| fs::read_to_string(path).map_err(|e| e.to_string()))
+        .is_ok_and(|entry| desktop_entry_enabled(&entry))
+}
+
+/// `.desktop` 文件中的自启动项是否启用（只看 `[Desktop Entry]` 组中的键）
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn desktop_entry_enabled(entry: &str) -> bool {
+    let mut in_main_group = false;
+    for line in entry.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if matches!((key.trim(), value.trim()), ("Hidden", "true") | ("X-GNOME-Autostart-enabled", "false")) {
+            return false;
+        }
+    }
+    true
+}
+
+/// 检查自启动文件是否与当前程序和参数一致（程序移动位置后需要重新写入）
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_current() -> bool {
+    let (Ok(path), Ok(exe_path)) = (desktop_entry_path(), current_exe()) else {
+        return false;
+    };
+    fs::read_to_string(path).is_ok_and(|entry| entry == desktop_entry(&exe_path, &autostart_args()))
+}
+
+// 其他平台的占位实现
+#[cfg(not(any(windows, unix)))]
 pub fn enable() -> Result<(), String> {
-    Err("开机自启动仅支持 Windows".to_string())
+    Err("当前系统不支持开机自启动".to_string())
 }
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, unix)))]
 pub fn disable() -> Result<(), String> {
-    Err("开机自启动仅支持 Windows".to_string())
+    Err("当前系统不支持开机自启动".to_string())
 }
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, unix)))]
 pub fn is_enabled() -> bool {
     false
 }
+
+#[cfg(not(any(windows, unix)))]
+pub fn is_current() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command_quotes_path() {
+        assert_eq!(
+            run_command(r"C:\Program Files\EchoKey\echokey.exe", &[AUTOSTART_ARG]),
+            r#""C:\Program Files\EchoKey\echokey.exe" --autostart"#,
+        );
+    }
+
+    #[test]
+    fn test_launch_agent_plist() {
+        let plist = launch_agent_plist("/Applications/R&D <测试>/EchoKey", &[AUTOSTART_ARG]);
+        assert!(plist.contains("<string>com.echokey</string>"));
+        assert!(plist.contains(
+            "        <string>/Applications/R&amp;D &lt;测试&gt;/EchoKey</string>\n        <string>--autostart</string>\n"
+        ));
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+    }
+
+    #[test]
+    fn test_desktop_entry_enabled() {
+        assert!(desktop_entry_enabled(&desktop_entry("/usr/bin/echokey", &[AUTOSTART_ARG])));
+        assert!(!desktop_entry_enabled("[Desktop Entry]\nType=Application\nHidden=true\n"));
+        assert!(!desktop_entry_enabled("[Desktop Entry]\nX-GNOME-Autostart-enabled = false\n"));
+        // 其他组中的同名键不影响
+        assert!(desktop_entry_enabled("[Desktop Entry]\nType=Application\n[Desktop Action New]\nHidden=true\n"));
+    }
+
+    #[test]
+    fn test_desktop_entry_escapes_exec() {
+        assert_eq!(desktop_exec_arg("/usr/bin/echokey"), "/usr/bin/echokey");
+        assert_eq!(desktop_exec_arg("/opt/Echo Key/echokey"), "\"/opt/Echo Key/echokey\"");
+        // 文件中写作 "/opt/\\$a\\\\b/100%%"
+        assert_eq!(desktop_exec_arg("/opt/$a\\b/100%"), "\"/opt/\\\\$a\\\\\\\\b/100%%\"");
+        
+        let entry = desktop_entry("/home/me/My Apps/echokey", &[AUTOSTART_ARG]);
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("\nExec=\"/home/me/My Apps/echokey\" --autostart\n"));
+        assert!(entry.contains("\nX-GNOME-Autostart-enabled=true\n"));
+    }
+}
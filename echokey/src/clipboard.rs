@@ -1,11 +1,33 @@
 //! 剪贴板模块
 //!
 //! 提供剪贴板读取功能，用于：
-//! - 检测粘贴时获取粘贴内容
+//! - 检测粘贴时获取粘贴内容（文本、文件、图片）
+//! - 记录复制时读取刚复制的内容
 //! - 手动保存时读取剪贴板内容
+//! - 累加剪贴板：检测复制的新内容并追加到缓冲区
 
 use arboard::Clipboard;
+pub use arboard::ImageData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// 重试读取剪贴板的总等待上限，避免阻塞逻辑线程
+pub const MAX_RETRY_WAIT: Duration = Duration::from_millis(200);
+
+/// 累加剪贴板时检测剪贴板变化的轮询间隔
+pub const MONITOR_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 累加剪贴板缓冲区的字符上限，满了之后新复制的内容不再加入（只计数）
+pub const MAX_ACCUMULATED_CHARS: usize = 100_000;
+
+/// 粘贴的图片保存为附件的像素上限，更大的图片只记录尺寸（读取和编码时要占用大量内存）
+pub const MAX_IMAGE_PIXELS: usize = 3840 * 2160;
+
+/// 按下 Ctrl+C 时剪贴板的更新序号，0 表示没有记录
+static COPY_PRESSED_SEQUENCE: AtomicU32 = AtomicU32::new(0);
 
 /// 全局剪贴板实例
 /// 
@@ -36,8 +58,372 @@ pub fn get_text() -> Option<String> {
     clipboard.get_text().ok()
 }
 
+/// 剪贴板自上次调用后有更新时读取文本，没有更新时不读取内容，返回 None
+///
+/// 用于定期检测剪贴板变化，剪贴板中是大段文本时不必每次都读出来比较。
+/// 无法检测更新的平台每次都读取。
+///
+/// 密码管理器等程序标记为不应被监视的内容（见 [`is_private`]）也返回 None。
+pub fn get_text_if_changed() -> Option<String> {
+    static LAST_SEQUENCE: Mutex<Option<u32>> = Mutex::new(None);
+    
+    if let Some(number) = sequence_number() {
+        let mut last = LAST_SEQUENCE.lock().ok()?;
+        if *last == Some(number) {
+            return None;
+        }
+        *last = Some(number);
+    }
+    if is_private() {
+        return None;
+    }
+    get_text()
+}
+
+/// 剪贴板内容是否被来源程序标记为不应记录
+///
+/// 密码管理器复制密码时会附带 `ExcludeClipboardContentFromMonitorProcessing`
+/// 或 `CanIncludeInClipboardHistory` 格式，要求剪贴板监视程序和剪贴板历史忽略这次内容。
+#[cfg(windows)]
+fn is_private() -> bool {
+    use windows::core::w;
+    use windows::Win32::System::DataExchange::{IsClipboardFormatAvailable, RegisterClipboardFormatW};
+    
+    [w!("ExcludeClipboardContentFromMonitorProcessing"), w!("CanIncludeInClipboardHistory")]
+        .into_iter()
+        .any(|name| unsafe {
+            let format = RegisterClipboardFormatW(name);
+            format != 0 && IsClipboardFormatAvailable(format).is_ok()
+        })
+}
+
+/// 其他平台没有通用的标记方式
+#[cfg(not(windows))]
+fn is_private() -> bool {
+    false
+}
+
+/// 读取剪贴板中的图片（RGBA 像素）
+///
+/// 剪贴板中没有图片，或图片超过 [`MAX_IMAGE_PIXELS`] 时返回 None
+pub fn get_image() -> Option<ImageData<'static>> {
+    let mut guard = CLIPBOARD.lock().ok()?;
+    let clipboard = guard.as_mut()?;
+    
+    let image = clipboard.get_image().ok()?;
+    image_fits(image.width, image.height).then_some(image)
+}
+
+/// 图片是否不超过保存为附件的像素上限
+pub fn image_fits(width: usize, height: usize) -> bool {
+    width.checked_mul(height).is_some_and(|pixels| pixels <= MAX_IMAGE_PIXELS)
+}
+
+/// 读取剪贴板文本，读不到时短暂等待后重试
+///
+/// 按下 Ctrl+V 的瞬间，来源程序可能还没把内容写入剪贴板，
+/// 通常几毫秒后就能读到。最多尝试 `attempts` 次，每次间隔 `delay`，
+/// 总等待时间不超过 [`MAX_RETRY_WAIT`]。
+pub fn get_text_retry(attempts: u32, delay: Duration) -> Option<String> {
+    let mut waited = Duration::ZERO;
+    
+    for attempt in 1..=attempts.max(1) {
+        if let Some(text) = get_text().filter(|t| !t.is_empty()) {
+            return Some(text);
+        }
+        
+        if attempt == attempts || waited + delay > MAX_RETRY_WAIT {
+            break;
+        }
+        thread::sleep(delay);
+        waited += delay;
+    }
+    
+    None
+}
+
+/// 剪贴板内容（按格式区分）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardContent {
+    /// 文本
+    Text(String),
+    /// 从资源管理器复制的文件
+    Files(Vec<PathBuf>),
+    /// 图片（像素尺寸）
+    Image { width: usize, height: usize },
+    /// 有内容，但不是支持的格式
+    Unknown,
+    /// 剪贴板为空
+    Empty,
+}
+
+impl ClipboardContent {
+    /// 非空的文本内容，其他格式返回 None
+    pub fn into_text(self) -> Option<String> {
+        match self {
+            ClipboardContent::Text(text) if !text.is_empty() => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<String>> for ClipboardContent {
+    fn from(text: Option<String>) -> Self {
+        match text {
+            Some(text) if !text.is_empty() => ClipboardContent::Text(text),
+            _ => ClipboardContent::Empty,
+        }
+    }
+}
+
+/// 读取剪贴板内容，按文件、文本、图片的顺序检测格式
+///
+/// 复制文件时剪贴板中可能同时有文件名文本，因此文件优先。
+/// 三种格式都没有时，剪贴板中还有其他内容则返回 `Unknown`。
+pub fn get_content() -> ClipboardContent {
+    let Ok(mut guard) = CLIPBOARD.lock() else {
+        return ClipboardContent::Empty;
+    };
+    let Some(clipboard) = guard.as_mut() else {
+        return ClipboardContent::Empty;
+    };
+    
+    if let Ok(files) = clipboard.get().file_list() {
+        if !files.is_empty() {
+            return ClipboardContent::Files(files);
+        }
+    }
+    if let Ok(text) = clipboard.get_text() {
+        if !text.is_empty() {
+            return ClipboardContent::Text(text);
+        }
+    }
+    if let Ok(image) = clipboard.get_image() {
+        return ClipboardContent::Image { width: image.width, height: image.height };
+    }
+    
+    if has_any_format(clipboard) {
+        ClipboardContent::Unknown
+    } else {
+        ClipboardContent::Empty
+    }
+}
+
+/// 读取剪贴板内容，为空时短暂等待后重试（规则同 [`get_text_retry`]）
+pub fn get_content_retry(attempts: u32, delay: Duration) -> ClipboardContent {
+    let mut waited = Duration::ZERO;
+    
+    for attempt in 1..=attempts.max(1) {
+        let content = get_content();
+        if content != ClipboardContent::Empty {
+            return content;
+        }
+        
+        if attempt == attempts || waited + delay > MAX_RETRY_WAIT {
+            break;
+        }
+        thread::sleep(delay);
+        waited += delay;
+    }
+    
+    ClipboardContent::Empty
+}
+
+/// 记录按下 Ctrl+C 时剪贴板的更新序号（在键盘钩子中调用）
+///
+/// 复制事件经过事件通道才到达逻辑线程，那时程序可能已经写入了剪贴板，
+/// 再读取的序号已经是更新后的，无法判断这次复制是否真的写入了内容。
+pub fn note_copy_pressed() {
+    COPY_PRESSED_SEQUENCE.store(sequence_number().unwrap_or(0), Ordering::Relaxed);
+}
+
+/// 读取刚复制的内容
+///
+/// 按下 Ctrl+C 的瞬间，程序还没有把选中的内容写入剪贴板，此时读到的是旧内容。
+/// 每隔 `delay` 检查一次剪贴板是否已更新，更新后（或等满 `attempts` 次、
+/// 总等待达到 [`MAX_RETRY_WAIT`]）再读取。无法检测更新的平台总是等满再读取。
+///
+/// 剪贴板一直没有更新时（没有选中内容，或终端中的 Ctrl+C 是中断）返回 `Empty`：
+/// 剪贴板中还是之前的内容（可能是早先复制的密码），不能当作这次复制的内容。
+pub fn get_copied_content(attempts: u32, delay: Duration) -> ClipboardContent {
+    let before = match COPY_PRESSED_SEQUENCE.swap(0, Ordering::Relaxed) {
+        0 => sequence_number(),
+        number => Some(number),
+    };
+    let mut waited = Duration::ZERO;
+    
+    for attempt in 1..=attempts.max(1) {
+        if before.is_some() && sequence_number() != before {
+            break;
+        }
+        if attempt == attempts || waited + delay > MAX_RETRY_WAIT {
+            break;
+        }
+        thread::sleep(delay);
+        waited += delay;
+    }
+    
+    if before.is_some() && sequence_number() == before {
+        return ClipboardContent::Empty;
+    }
+    get_content()
+}
+
+/// 剪贴板的更新序号，每次写入剪贴板后递增
+#[cfg(windows)]
+fn sequence_number() -> Option<u32> {
+    let number = unsafe { windows::Win32::System::DataExchange::GetClipboardSequenceNumber() };
+    (number != 0).then_some(number)
+}
+
+/// 其他平台无法检测剪贴板更新
+#[cfg(not(windows))]
+fn sequence_number() -> Option<u32> {
+    None
+}
+
+/// 剪贴板中是否有任何格式的内容
+#[cfg(windows)]
+fn has_any_format(_clipboard: &mut Clipboard) -> bool {
+    unsafe { windows::Win32::System::DataExchange::CountClipboardFormats() > 0 }
+}
+
+/// 剪贴板中是否有任何格式的内容（其他平台只能额外检测 HTML）
+#[cfg(not(windows))]
+fn has_any_format(clipboard: &mut Clipboard) -> bool {
+    clipboard.get().html().is_ok()
+}
+
 /// 检查剪贴板是否包含文本
 #[allow(dead_code)]
 pub fn has_text() -> bool {
     get_text().is_some()
 }
+
+/// 剪贴板变化检测
+///
+/// 记住上一次看到的剪贴板内容，只在内容变化时报告新内容。
+#[derive(Debug, Default)]
+pub struct ClipboardMonitor {
+    last: Option<String>,
+}
+
+impl ClipboardMonitor {
+    /// 以当前剪贴板内容为基准（开始检测前已有的内容不算变化）
+    pub fn new(current: Option<String>) -> Self {
+        Self { last: current }
+    }
+
+    /// EchoKey 自己把 `text` 复制到了剪贴板：之后看到这段内容时不算新复制
+    pub fn ignore(&mut self, text: String) {
+        self.last = Some(text);
+    }
+
+    /// 传入当前剪贴板内容，与上一次不同且不为空时返回新内容
+    pub fn observe(&mut self, current: Option<String>) -> Option<String> {
+        let current = current.filter(|t| !t.is_empty())?;
+        if self.last.as_ref() == Some(&current) {
+            return None;
+        }
+        self.last = Some(current.clone());
+        Some(current)
+    }
+}
+
+/// 累加剪贴板的缓冲区
+///
+/// 每段复制的内容按顺序以换行分隔追加到合并后的文本中，界面每一帧显示时不必重新拼接。
+/// 总字符数超过 [`MAX_ACCUMULATED_CHARS`] 后不再加入新内容，只记录跳过的段数。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Accumulator {
+    combined: String,
+    parts: usize,
+    chars: usize,
+    skipped: usize,
+}
+
+impl Accumulator {
+    /// 追加一段内容，缓冲区已满时返回 false
+    pub fn push(&mut self, text: String) -> bool {
+        let chars = text.chars().count();
+        if self.chars + chars > MAX_ACCUMULATED_CHARS {
+            self.skipped += 1;
+            return false;
+        }
+        if self.parts > 0 {
+            self.combined.push('\n');
+        }
+        self.combined.push_str(&text);
+        self.chars += chars;
+        self.parts += 1;
+        true
+    }
+
+    /// 已累加的段数
+    pub fn len(&self) -> usize {
+        self.parts
+    }
+
+    /// 是否还没有累加任何内容
+    pub fn is_empty(&self) -> bool {
+        self.parts == 0
+    }
+
+    /// 因缓冲区已满而跳过的段数
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// 合并后的内容（每段之间换行）
+    pub fn combined(&self) -> &str {
+        &self.combined
+    }
+
+    /// 清空缓冲区
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_reports_only_changes() {
+        let mut monitor = ClipboardMonitor::new(Some("旧内容".to_string()));
+        assert_eq!(monitor.observe(Some("旧内容".to_string())), None);
+        assert_eq!(monitor.observe(Some("a".to_string())), Some("a".to_string()));
+        assert_eq!(monitor.observe(Some("a".to_string())), None);
+        assert_eq!(monitor.observe(None), None);
+        assert_eq!(monitor.observe(Some(String::new())), None);
+        assert_eq!(monitor.observe(Some("b".to_string())), Some("b".to_string()));
+        
+        // EchoKey 自己复制的内容不算新复制
+        monitor.ignore("自己复制的".to_string());
+        assert_eq!(monitor.observe(Some("自己复制的".to_string())), None);
+    }
+
+    #[test]
+    fn test_image_fits() {
+        assert!(image_fits(1920, 1080));
+        assert!(image_fits(3840, 2160));
+        assert!(!image_fits(3840, 2161));
+        assert!(!image_fits(usize::MAX, 2));
+    }
+
+    #[test]
+    fn test_accumulator_limit() {
+        let mut acc = Accumulator::default();
+        assert!(acc.push("第一段".to_string()));
+        assert!(acc.push("第二段".to_string()));
+        assert_eq!(acc.combined(), "第一段\n第二段");
+
+        assert!(!acc.push("长".repeat(MAX_ACCUMULATED_CHARS)));
+        assert_eq!((acc.len(), acc.skipped()), (2, 1));
+
+        acc.clear();
+        assert!(acc.is_empty());
+        assert_eq!(acc.skipped(), 0);
+    }
+}
@@ -1,29 +1,902 @@
 //! 配置模块
 //!
 //! 定义 EchoKey 的所有配置项，包括存储路径、快捷键等。
+//!
+//! 用户可修改的设置保存在 `%LOCALAPPDATA%\EchoKey\config.toml`，
+//! 文件缺失或格式错误时使用默认值，不影响启动。
+//!
+//! 便携模式（`--portable` 参数，或程序旁边有 `portable.txt`）下，配置和日志
+//! 都保存在程序旁边的 `EchoKey_Data` 目录中，不在系统中留下数据。
 
-use std::path::PathBuf;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use std::time::Duration;
+use chrono::NaiveTime;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::{self, Locale};
+use crate::shortcuts::{self, Binding, Chord, ShortcutAction};
+use crate::logger::{validate_header_template, AutorepeatPolicy, FlushPolicy, HeaderTemplateError, LogTimezone};
 
 /// 获取日志存储目录
 ///
 /// Windows: %LOCALAPPDATA%\EchoKey\logs\
 /// 例如: C:\Users\用户名\AppData\Local\EchoKey\logs\
+///
+/// 设置了环境变量 `ECHOKEY_LOG_DIR` 时优先使用该目录；
+/// 否则配置文件中设置了 `log_directory` 时使用该目录（相对路径相对于应用数据目录）。
+///
+/// 目录在第一次调用时确定，运行中修改 `log_directory` 要重启后才生效，
+/// 这样日志写入和历史记录读取的始终是同一个目录。
 pub fn get_log_directory() -> PathBuf {
+    LOG_DIRECTORY.clone()
+}
+
+/// 本次运行使用的日志目录
+static LOG_DIRECTORY: Lazy<PathBuf> = Lazy::new(resolve_log_directory);
+
+/// 按环境变量和配置文件确定日志目录
+fn resolve_log_directory() -> PathBuf {
+    if let Some(dir) = env_log_directory(std::env::var_os(LOG_DIR_ENV)) {
+        return dir;
+    }
+    let custom = CONFIG.read().ok()
+        .and_then(|c| c.log_directory.clone())
+        .filter(|dir| !dir.as_os_str().is_empty());
+    match custom {
+        Some(dir) => get_data_directory().join(dir),
+        None => get_data_directory().join("logs"),
+    }
+}
+
+/// 指定日志目录的环境变量
+pub const LOG_DIR_ENV: &str = "ECHOKEY_LOG_DIR";
+
+/// 环境变量指定的日志目录
+///
+/// 值为空或只有空白时忽略；目录无法创建时给出警告并忽略。
+fn env_log_directory(value: Option<std::ffi::OsString>) -> Option<PathBuf> {
+    let value = value?;
+    let trimmed = value.to_str().map_or(value.as_os_str(), |v| std::ffi::OsStr::new(v.trim()));
+    if trimmed.is_empty() {
+        return None;
+    }
+    let dir = PathBuf::from(trimmed);
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(e) => {
+            eprintln!("警告: 无法使用 {} 指定的日志目录 {}（{}），使用默认目录", LOG_DIR_ENV, dir.display(), e);
+            None
+        }
+    }
+}
+
+/// 获取应用数据目录（%LOCALAPPDATA%\EchoKey\，便携模式下为程序旁边的 EchoKey_Data\）
+pub fn get_data_directory() -> PathBuf {
+    PORTABLE_DIRECTORY.clone().unwrap_or_else(default_data_directory)
+}
+
+/// 系统的应用数据目录
+fn default_data_directory() -> PathBuf {
     let base = dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."));
-    base.join("EchoKey").join("logs")
+    base.join("EchoKey")
+}
+
+/// 启用便携模式的命令行参数
+pub const PORTABLE_ARG: &str = "--portable";
+
+/// 放在程序旁边即启用便携模式的标记文件
+pub const PORTABLE_SENTINEL: &str = "portable.txt";
+
+/// 便携模式的数据目录名（位于程序所在目录）
+pub const PORTABLE_DIR_NAME: &str = "EchoKey_Data";
+
+/// 便携模式的数据目录，未启用或无法使用时为 None（首次访问时确定）
+static PORTABLE_DIRECTORY: Lazy<Option<PathBuf>> = Lazy::new(resolve_portable_directory);
+
+/// 本次是否通过命令行参数要求便携模式
+pub fn portable_arg_given() -> bool {
+    std::env::args().skip(1).any(|arg| arg == PORTABLE_ARG)
+}
+
+/// 本次是否以便携模式运行
+pub fn is_portable() -> bool {
+    PORTABLE_DIRECTORY.is_some()
 }
 
-/// 超时时间：超过这个时间没有输入，下次输入时自动添加新时间戳
+/// 确定便携模式的数据目录
+///
+/// 程序在只读介质上（例如写保护的 U 盘、光盘）时无法写入，给出警告后使用系统目录。
+fn resolve_portable_directory() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    if !portable_arg_given() && !exe_dir.join(PORTABLE_SENTINEL).is_file() {
+        return None;
+    }
+    match prepare_portable_directory(&exe_dir) {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            eprintln!("警告: 便携数据目录不可写（{}），使用系统目录", e);
+            None
+        }
+    }
+}
+
+/// 创建程序旁边的便携数据目录，并确认可以写入
+fn prepare_portable_directory(exe_dir: &Path) -> io::Result<PathBuf> {
+    let dir = exe_dir.join(PORTABLE_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    // 只读介质上目录可能已经存在，写入一个临时文件确认
+    let probe = dir.join(".write_test");
+    fs::write(&probe, b"")?;
+    let _ = fs::remove_file(&probe);
+    Ok(dir)
+}
+
+/// 获取配置文件路径
+pub fn get_config_path() -> PathBuf {
+    get_data_directory().join("config.toml")
+}
+
+/// 系统中常见的中文字体位置（按优先级排列）
+pub const SYSTEM_CJK_FONTS: &[&str] = &[
+    r"C:\Windows\Fonts\msyh.ttc",
+    r"C:\Windows\Fonts\msyh.ttf",
+    r"C:\Windows\Fonts\simhei.ttf",
+    r"C:\Windows\Fonts\simsun.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+];
+
+/// 默认时间戳超时：超过这个时间没有输入，下次输入时自动添加新时间戳
 pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// 默认会话超时：超过这个时间没有输入，下次输入前空一行，作为新会话的开始
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// 安全桌面（UAC 提示等）检测的轮询间隔
+pub const SECURE_DESKTOP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 检测剪贴板变化的最短间隔（毫秒），避免频繁读取剪贴板占用 CPU
+pub const MIN_CLIPBOARD_WATCH_MS: u64 = 100;
+
+/// 历史页面"合并短行"的默认阈值：内容少于这么多字符的时间戳行会被合并
+pub const DEFAULT_COLLAPSE_MIN_CHARS: usize = 10;
+
+/// 界面默认基础字号（与 egui 默认正文字号一致）
+pub const DEFAULT_FONT_SIZE: f32 = 12.5;
+
+/// 字号设置的可选范围
+pub const FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 10.0..=24.0;
+
+/// 单个日志文件的默认大小上限：超过后自动切换到新的日志段
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// 键盘事件通道的默认容量
+pub const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 访问密码哈希的迭代次数
+const PASSCODE_HASH_ROUNDS: u32 = 10_000;
+
 /// 应用名称
 pub const APP_NAME: &str = "EchoKey";
 
 /// 应用版本
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// 界面主题
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppTheme {
+    /// 浅色（Apple 风格）
+    Light,
+    /// 深色（Apple 风格），适合夜间使用
+    Dark,
+    /// 跟随系统的深浅色设置
+    #[default]
+    System,
+    /// 高对比度：纯黑白配色、粗边框、更大的点击区域
+    HighContrast,
+}
+
+/// 日志条目前的序号（`#0001 [HH:MM:SS] ...`），同一秒内的多条记录也能严格排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceNumbers {
+    /// 不写序号
+    #[default]
+    Off,
+    /// 每个日志文件从 1 开始
+    PerFile,
+    /// 本次运行中跨文件连续递增（从第一个打开的文件中已有的最大序号继续）
+    Global,
+}
+
+/// 恢复记录后的续写方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResumeStyle {
+    /// 暂停/恢复标记各占一行，恢复后的输入从新的时间戳行开始
+    #[default]
+    NewLine,
+    /// 暂停/恢复标记接在当前行末尾，恢复后的输入继续写在暂停前的那一行
+    Inline,
+}
+
+/// 日志文件的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// 纯文本：`[HH:MM:SS] 内容`，扩展名 `.log`
+    #[default]
+    PlainText,
+    /// JSON 行：每条事件一行 JSON 对象，扩展名 `.jsonl`，便于其他工具解析
+    JsonLines,
+}
+
+/// 快捷键设置，按键组合写作 `Ctrl+Shift+P`（键名见 [`shortcuts::key_name`]）
+///
+/// 无法识别的设置使用该动作的默认快捷键。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Shortcuts {
+    pub toggle_pause: String,
+    pub manual_save: String,
+    pub new_segment: String,
+    pub paste: String,
+    pub checkpoint: String,
+    pub toggle_focus: String,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        let name = |action| shortcuts::default_chord(action).map(|chord| chord.name()).unwrap_or_default();
+        Self {
+            toggle_pause: name(ShortcutAction::TogglePause),
+            manual_save: name(ShortcutAction::ManualSave),
+            new_segment: name(ShortcutAction::NewSegment),
+            paste: name(ShortcutAction::Paste),
+            checkpoint: name(ShortcutAction::Checkpoint),
+            toggle_focus: name(ShortcutAction::ToggleFocus),
+        }
+    }
+}
+
+impl Shortcuts {
+    /// 各动作的设置，靠前的动作在按键组合冲突时优先
+    fn specs(&self) -> [(ShortcutAction, &str); 6] {
+        [
+            (ShortcutAction::TogglePause, &self.toggle_pause),
+            (ShortcutAction::ManualSave, &self.manual_save),
+            (ShortcutAction::NewSegment, &self.new_segment),
+            (ShortcutAction::Paste, &self.paste),
+            (ShortcutAction::Checkpoint, &self.checkpoint),
+            (ShortcutAction::ToggleFocus, &self.toggle_focus),
+        ]
+    }
+
+    /// 动作的设置（按键组合文字），设置页面据此修改快捷键
+    pub fn spec_mut(&mut self, action: ShortcutAction) -> &mut String {
+        match action {
+            ShortcutAction::TogglePause => &mut self.toggle_pause,
+            ShortcutAction::ManualSave => &mut self.manual_save,
+            ShortcutAction::NewSegment => &mut self.new_segment,
+            ShortcutAction::Paste => &mut self.paste,
+            ShortcutAction::Checkpoint => &mut self.checkpoint,
+            ShortcutAction::ToggleFocus => &mut self.toggle_focus,
+        }
+    }
+
+    /// 动作的按键组合，设置无法识别时为默认快捷键；设置为空时没有快捷键
+    pub fn chord(&self, action: ShortcutAction) -> Option<Chord> {
+        let spec = self.specs().into_iter().find(|(a, _)| *a == action)?.1;
+        if spec.trim().is_empty() {
+            return None;
+        }
+        Chord::parse(spec).or_else(|| shortcuts::default_chord(action))
+    }
+
+    /// 生成快捷键表，并列出设置中的问题（无法识别的按键组合、多个动作使用同一组合）
+    ///
+    /// 多个动作使用同一组合时只保留靠前的动作。
+    pub fn resolve(&self) -> (Vec<Binding>, Vec<String>) {
+        let mut bindings: Vec<Binding> = Vec::new();
+        let mut problems = Vec::new();
+        for (action, spec) in self.specs() {
+            if !spec.trim().is_empty() && Chord::parse(spec).is_none() {
+                problems.push(match self.chord(action) {
+                    Some(chord) => format!("无法识别的快捷键「{}」，使用默认的 {}", spec, chord.name()),
+                    None => format!("无法识别的快捷键「{}」，已忽略", spec),
+                });
+            }
+            let Some(chord) = self.chord(action) else {
+                continue;
+            };
+            if let Some(first) = bindings.iter().find(|b| b.chord == chord) {
+                problems.push(format!(
+                    "「{}」和「{}」都使用了 {}，只保留前者",
+                    first.action.label(), action.label(), chord.name(),
+                ));
+                continue;
+            }
+            bindings.push(Binding { chord, action });
+        }
+        (bindings, problems)
+    }
+
+    /// 生成快捷键表，设置中的问题作为警告输出
+    pub fn bindings(&self) -> Vec<Binding> {
+        let (bindings, problems) = self.resolve();
+        for problem in problems {
+            eprintln!("警告: {}", problem);
+        }
+        bindings
+    }
+}
+
+/// 用户配置（对应 config.toml）
+///
+/// 所有字段都有默认值，配置文件中缺失的字段不会导致加载失败。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 日志目录，为空时使用应用数据目录下的 `logs`（修改后重启生效）
+    pub log_directory: Option<PathBuf>,
+    /// 界面基础字号，其他文字按比例缩放
+    pub font_size: f32,
+    /// 自定义字体文件（用于中文显示），为空时自动查找系统中文字体
+    pub font_path: Option<PathBuf>,
+    /// 界面主题
+    pub theme: AppTheme,
+    /// 日志条目前的序号
+    pub sequence_numbers: SequenceNumbers,
+    /// 日志文件的输出格式
+    pub log_format: LogFormat,
+    /// 按前台程序分文件（`2024-06-01_chrome.log`），无法识别的程序写入当天的默认文件
+    pub split_by_app: bool,
+    /// 是否以 `[Win+L]` 形式记录 Win 组合键（默认只屏蔽其字符）
+    pub log_win_combos: bool,
+    /// 详细模式：以 `[未知键:VK_xx]` 记录转换不出字符的按键，便于发现键盘布局问题；
+    /// 同时记录 Tab（制表符）、Delete（`⌦`）和方向键（`←→↑↓`、Home `⇤`、End `⇥`）
+    pub verbose_keys: bool,
+    /// 原始按键记录：以 `[↓LShift][↑LShift]` 记录每个物理按键的按下和松开（含修饰键），
+    /// 与字符同时写入，日志会大很多，适合记录复现步骤
+    pub raw_key_log: bool,
+    /// 记录输入法上屏的文字而不是拼写用的按键（通过 UI Automation 的组字事件，
+    /// 不支持该事件的程序中输入法模式下的字母不会被记录）
+    pub capture_ime_text: bool,
+    /// 大写锁定切换时写入 `--- 大写锁定开 ---` 标记（不改变记录的字符）
+    pub log_caps_lock: bool,
+    /// 按 Ctrl+C 时以 `[复制] 内容` 记录复制的文本（默认关闭，复制的内容可能含有敏感信息）
+    pub log_copies: bool,
+    /// 定期检测剪贴板，把以任何方式复制的新文本（右键菜单、其他程序写入等）记为 `[复制] 内容`（默认关闭）
+    pub watch_clipboard: bool,
+    /// 检测剪贴板变化的间隔（毫秒）
+    pub clipboard_watch_interval_ms: u64,
+    /// 远程桌面会话中无法安装键盘钩子时改用轮询采集（可能漏掉很快的按键，不支持输入法上屏的字符）
+    pub remote_polling_fallback: bool,
+    /// 同一字符连续自动重复超过多少个后折叠为 `[长按 ×N]`，0 表示不折叠
+    pub autorepeat_min_run: usize,
+    /// 相邻两次相同字符的间隔不超过多少毫秒才算自动重复（快速输入的双字母间隔通常更长）
+    pub autorepeat_interval_ms: u64,
+    /// 键盘钩子在多少毫秒内收到同一按键（含修饰键状态）时只处理一次，0 表示不去重
+    ///
+    /// 有些系统（远程桌面等）会重复投递按键事件；窗口太长会吞掉很快的连按。
+    #[serde(alias = "dedup_window_ms")]
+    pub key_dedup_ms: u64,
+    /// 同一字符在多少毫秒内再次上屏时只记录一次（部分输入法会让字母重复上屏，10 左右即可），0 表示不去重
+    pub char_dedup_ms: u64,
+    /// 每隔多少秒读取焦点控件的文本写入 `[校验]` 快照（需要 UI Automation），0 表示关闭
+    pub verify_interval_secs: u64,
+    /// 粘贴时读取剪贴板的最大尝试次数
+    pub clipboard_retry_attempts: u32,
+    /// 粘贴时读取剪贴板的重试间隔（毫秒）
+    pub clipboard_retry_delay_ms: u64,
+    /// 访问密码的加盐哈希（十六进制），为空表示未设置密码
+    pub passcode_hash: Option<String>,
+    /// 访问密码的盐（十六进制）
+    pub passcode_salt: Option<String>,
+    /// 界面语言，为空表示跟随系统
+    pub language: Option<Locale>,
+    /// 日志标记的语言（与界面语言分开，保持日志格式稳定）
+    pub marker_language: Locale,
+    /// 键盘钩子到业务逻辑线程的事件通道容量，满了之后的事件会被丢弃并计数
+    pub event_channel_capacity: usize,
+    /// 事件通道中排队的事件达到多少时在状态页显示“写入繁忙”，排空后清除
+    pub backlog_busy_events: usize,
+    /// 是否合并连续的相同粘贴（默认每次粘贴都完整记录）
+    pub dedup_pastes: bool,
+    /// 合并相同粘贴的时间窗口（毫秒）
+    pub paste_dedup_window_ms: u64,
+    /// 粘贴内容最多记录多少个字符，超出部分截断并注明总字数，0 表示不限制
+    pub max_paste_chars: usize,
+    /// 把粘贴的图片保存为日志目录下 `attachments/` 中的 PNG（关闭时只记录图片尺寸）
+    pub save_pasted_images: bool,
+    /// 开机自启动后等待多少秒再安装键盘钩子（手动启动时不等待）
+    pub startup_delay_secs: u64,
+    /// 设备名称，写入日志头部（合并多台设备的日志时区分来源）
+    pub device_label: Option<String>,
+    /// 续写已有日志时，是否在每次运行开始处写入设备标记
+    pub device_session_marker: bool,
+    /// 是否在启动后首次打开日志时和正常退出时写入启动/退出标记
+    pub app_markers: bool,
+    /// 新日志文件的头部模板，可用 `{date}`、`{time}`、`{version}`、`{device}`；
+    /// 每行开头加上 `#|` 标记；不设置时使用默认头部，空字符串表示不写头部
+    pub header_template: Option<String>,
+    /// EchoKey 自身窗口在前台时不记录输入（关闭后记录所有输入）
+    pub exclude_own_window: bool,
+    /// 切换到其他程序多少秒后自动把窗口隐藏到托盘，为空表示不自动隐藏
+    pub auto_hide_secs: Option<u64>,
+    /// 日志时间使用的时区：IANA 名称（如 `Asia/Shanghai`）或固定偏移（如 `+08:00`），
+    /// 为空表示跟随系统时区
+    pub timezone: Option<String>,
+    /// 短于多少秒的暂停不在日志中留下暂停/恢复标记，0 表示每次暂停都记录
+    pub pause_marker_min_secs: u64,
+    /// 恢复记录后是另起一行，还是接着暂停前的那一行继续写
+    pub resume_style: ResumeStyle,
+    /// 是否把日志中连续的多个空行压缩为一个（关闭时按原样保留每一个空行）
+    pub compact_whitespace: bool,
+    /// 退格键删除日志中最后输入的字符（关闭时写入 `⌫`，保留修改的痕迹）
+    pub backspace_deletes: bool,
+    /// 输入缓冲多少毫秒后再落盘（连续快速输入时合并写入），0 表示每次写入后立即落盘
+    pub flush_interval_ms: u64,
+    /// 单个日志文件超过多少 MB 后自动切换到新的日志段（`_01`、`_02`……），0 表示不限制
+    pub max_file_mb: u64,
+    /// 状态页的脉冲动画在最后一次输入后持续多少秒，之后显示为静止的圆点
+    pub pulse_active_secs: u64,
+    /// 多少秒没有输入后，下次输入另起一行并添加新时间戳
+    #[serde(alias = "idle_timeout_secs")]
+    pub timestamp_timeout_secs: u64,
+    /// 多少分钟没有输入后视为新会话（日志中空一行分隔）；
+    /// 会话是比时间戳行更大的单位，短于时间戳超时的设置按时间戳超时处理
+    pub session_timeout_mins: u64,
+    /// 导出统计 CSV 时是否为没有日志的日期输出全零行
+    pub csv_include_empty_days: bool,
+    /// 窗口置顶（开启后不再自动隐藏到托盘）
+    pub always_on_top: bool,
+    /// 写入日志的内容同时输出到标准输出（调试、管道给其他工具时使用）
+    pub tee_stdout: bool,
+    /// 每天自动新建日志段的时刻（`HH:MM`，使用日志时区），例如 `["12:00"]`
+    pub segment_times: Vec<String>,
+    /// 每日输入目标（字符数），0 表示不设目标
+    pub daily_goal: usize,
+    /// 达成每日目标时显示提示（每天一次）
+    pub daily_goal_notify: bool,
+    /// 专注时段（番茄钟）的时长（分钟）
+    pub focus_minutes: u64,
+    /// 只保留最近多少天的日志（启动时删除更早的日志文件），0 表示全部保留
+    pub keep_recent_days: usize,
+    /// 日志保留多少天，启动时删除文件名中的日期更早的日志（包括压缩的日志），None 表示一直保留
+    pub retention_days: Option<u32>,
+    /// 启动时把多少天以前的日志压缩为 `.log.gz`，None 表示不压缩
+    pub compress_after_days: Option<u32>,
+    /// 锁屏（Win+L）时自动暂停记录，解锁后恢复
+    pub pause_on_lock: bool,
+    /// 每个新时间戳后注明前台窗口的标题：`[14:03:22] «标题» 内容`
+    pub window_titles: bool,
+    /// 详细模式下新窗口在前台停留多久（毫秒）才写入窗口标记，0 表示每次切换都写入
+    pub window_marker_min_ms: u64,
+    /// 连续输入时同步字符数到界面的最短间隔（毫秒），0 表示每次按键都同步
+    pub gui_update_interval_ms: u64,
+    /// 连续输入时累计多少个按键后立即同步到界面（不等间隔到期）
+    pub gui_update_chars: usize,
+    /// 内存中保留最近多少条写入日志的事件，供中途连接的观察者回放，0 表示不保留
+    pub recent_events: usize,
+    /// 快捷键（`[shortcuts]` 表）
+    pub shortcuts: Shortcuts,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_directory: None,
+            font_size: DEFAULT_FONT_SIZE,
+            font_path: None,
+            theme: AppTheme::System,
+            sequence_numbers: SequenceNumbers::Off,
+            log_format: LogFormat::PlainText,
+            split_by_app: false,
+            log_win_combos: false,
+            verbose_keys: false,
+            capture_ime_text: false,
+            raw_key_log: false,
+            log_caps_lock: false,
+            log_copies: false,
+            watch_clipboard: false,
+            clipboard_watch_interval_ms: 1000,
+            remote_polling_fallback: false,
+            autorepeat_min_run: 0,
+            autorepeat_interval_ms: 50,
+            key_dedup_ms: shortcuts::DEFAULT_KEY_DEDUP_MS,
+            char_dedup_ms: 0,
+            verify_interval_secs: 0,
+            clipboard_retry_attempts: 3,
+            clipboard_retry_delay_ms: 20,
+            passcode_hash: None,
+            passcode_salt: None,
+            language: None,
+            marker_language: Locale::ZhCn,
+            event_channel_capacity: DEFAULT_EVENT_CHANNEL_CAPACITY,
+            backlog_busy_events: 64,
+            dedup_pastes: false,
+            paste_dedup_window_ms: 2000,
+            max_paste_chars: 100_000,
+            save_pasted_images: false,
+            startup_delay_secs: 5,
+            device_label: None,
+            device_session_marker: false,
+            app_markers: false,
+            header_template: None,
+            exclude_own_window: true,
+            auto_hide_secs: None,
+            timezone: None,
+            pause_marker_min_secs: 0,
+            resume_style: ResumeStyle::NewLine,
+            compact_whitespace: false,
+            backspace_deletes: false,
+            flush_interval_ms: 0,
+            max_file_mb: DEFAULT_MAX_FILE_BYTES / (1024 * 1024),
+            pulse_active_secs: 5,
+            timestamp_timeout_secs: IDLE_TIMEOUT.as_secs(),
+            session_timeout_mins: SESSION_TIMEOUT.as_secs() / 60,
+            csv_include_empty_days: false,
+            always_on_top: false,
+            tee_stdout: false,
+            segment_times: Vec::new(),
+            gui_update_interval_ms: 100,
+            gui_update_chars: 20,
+            window_titles: false,
+            window_marker_min_ms: 500,
+            pause_on_lock: true,
+            daily_goal: 0,
+            daily_goal_notify: true,
+            focus_minutes: 25,
+            keep_recent_days: 0,
+            retention_days: None,
+            compress_after_days: None,
+            recent_events: 0,
+            shortcuts: Shortcuts::default(),
+        }
+    }
+}
+
+/// 修改后需要重启才能生效的配置项
+pub const RESTART_REQUIRED_FIELDS: &[&str] = &["log_directory", "event_channel_capacity", "backlog_busy_events", "startup_delay_secs", "keep_recent_days", "retention_days", "compress_after_days"];
+
+/// 配置项是否需要重启才能生效
+pub fn requires_restart(field: &str) -> bool {
+    RESTART_REQUIRED_FIELDS.contains(&field)
+}
+
+/// 配置校验发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// 出问题的配置项（config.toml 中的键名）
+    pub field: &'static str,
+    /// 问题说明的界面文字键
+    key: &'static str,
+    /// 填入说明中的内容（例如无法识别的值）
+    arg: Option<String>,
+}
+
+impl ConfigIssue {
+    fn new(field: &'static str, key: &'static str) -> Self {
+        Self { field, key, arg: None }
+    }
+
+    fn with_arg(field: &'static str, key: &'static str, arg: impl std::fmt::Display) -> Self {
+        Self { field, key, arg: Some(arg.to_string()) }
+    }
+
+    /// 问题说明（当前界面语言）
+    pub fn message(&self) -> String {
+        match &self.arg {
+            Some(arg) => i18n::t_with(self.key, arg),
+            None => i18n::t(self.key).to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// 从配置文件加载
+    ///
+    /// 文件不存在时写入一份默认配置，方便用户直接编辑；无法读取或解析失败时返回默认配置
+    /// （不覆盖格式错误的文件）
+    pub fn load() -> Self {
+        // 测试中不读写用户真实的配置文件
+        if cfg!(test) {
+            return Self::default();
+        }
+        Self::load_from(&get_config_path())
+    }
+
+    /// 从指定的配置文件加载
+    fn load_from(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let config = Self::default();
+                if let Err(e) = config.save_to(path) {
+                    eprintln!("警告: 无法创建默认配置文件: {}", e);
+                }
+                return config;
+            }
+            Err(_) => return Self::default(),
+        };
+        
+        let mut config: Self = toml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("警告: 配置文件格式错误，使用默认配置: {}", e);
+            Self::default()
+        });
+        // 手动编辑的字号超出范围时界面无法使用，限制在设置页的可选范围内
+        config.font_size = if config.font_size.is_nan() {
+            DEFAULT_FONT_SIZE
+        } else {
+            config.font_size.clamp(*FONT_SIZE_RANGE.start(), *FONT_SIZE_RANGE.end())
+        };
+        config
+    }
+
+    /// 保存到配置文件
+    pub fn save(&self) -> Result<(), String> {
+        self.save_to(&get_config_path())
+    }
+
+    /// 保存到指定的配置文件
+    fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("无法创建配置目录: {}", e))?;
+        }
+        
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| format!("无法序列化配置: {}", e))?;
+        fs::write(path, content)
+            .map_err(|e| format!("无法写入配置文件: {}", e))
+    }
+
+    /// 检查配置中无法生效或互相矛盾的项，没有问题时返回空列表
+    ///
+    /// 加载时这些项会被忽略或按默认值处理；设置页面据此在对应的控件下提示，
+    /// 有问题时不能应用修改。
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if self.session_timeout_mins.saturating_mul(60) < self.timestamp_timeout_secs {
+            issues.push(ConfigIssue::new("session_timeout_mins", "config.session_shorter_than_stamp"));
+        }
+        if self.event_channel_capacity == 0 {
+            issues.push(ConfigIssue::new("event_channel_capacity", "config.channel_capacity_zero"));
+        }
+        if self.watch_clipboard && self.clipboard_watch_interval_ms < MIN_CLIPBOARD_WATCH_MS {
+            issues.push(ConfigIssue::with_arg(
+                "clipboard_watch_interval_ms",
+                "config.clipboard_watch_too_short",
+                MIN_CLIPBOARD_WATCH_MS,
+            ));
+        }
+        if self.autorepeat_min_run > 0 && self.autorepeat_interval_ms == 0 {
+            issues.push(ConfigIssue::new("autorepeat_interval_ms", "config.autorepeat_interval_zero"));
+        }
+        if let Some(name) = self.timezone.as_deref().filter(|n| LogTimezone::parse(n).is_none()) {
+            issues.push(ConfigIssue::with_arg("timezone", "config.unknown_timezone", name));
+        }
+        let invalid_times: Vec<&str> = self.segment_times.iter()
+            .map(|t| t.trim())
+            .filter(|t| NaiveTime::parse_from_str(t, "%H:%M").is_err())
+            .collect();
+        if !invalid_times.is_empty() {
+            issues.push(ConfigIssue::with_arg("segment_times", "config.invalid_segment_times", invalid_times.join(", ")));
+        }
+        if let Some(template) = &self.header_template {
+            match validate_header_template(template) {
+                Ok(()) => {}
+                Err(HeaderTemplateError::ControlCharacter) => {
+                    issues.push(ConfigIssue::new("header_template", "config.header_control_character"));
+                }
+                Err(HeaderTemplateError::UnknownToken(token)) => {
+                    issues.push(ConfigIssue::with_arg("header_template", "config.header_unknown_token", token));
+                }
+            }
+        }
+        issues
+    }
+
+    /// 合并相同粘贴的时间窗口，未启用时为 None
+    pub fn paste_dedup_window(&self) -> Option<Duration> {
+        self.dedup_pastes.then(|| Duration::from_millis(self.paste_dedup_window_ms))
+    }
+
+    /// 粘贴内容的字符上限，不限制时为 None
+    pub fn max_paste_chars(&self) -> Option<usize> {
+        (self.max_paste_chars > 0).then_some(self.max_paste_chars)
+    }
+
+    /// 检测剪贴板变化的间隔（不短于 [`MIN_CLIPBOARD_WATCH_MS`]），未开启时为 None
+    pub fn clipboard_watch_interval(&self) -> Option<Duration> {
+        self.watch_clipboard
+            .then(|| Duration::from_millis(self.clipboard_watch_interval_ms.max(MIN_CLIPBOARD_WATCH_MS)))
+    }
+
+    /// 折叠自动重复的规则，未开启时为 None
+    pub fn autorepeat_policy(&self) -> Option<AutorepeatPolicy> {
+        (self.autorepeat_min_run > 0).then(|| AutorepeatPolicy {
+            max_interval: Duration::from_millis(self.autorepeat_interval_ms),
+            min_run: self.autorepeat_min_run,
+        })
+    }
+
+    /// 同一字符重复上屏的去重窗口
+    pub fn char_dedup_window(&self) -> Duration {
+        Duration::from_millis(self.char_dedup_ms)
+    }
+
+    /// 日志的落盘策略
+    pub fn flush_policy(&self) -> FlushPolicy {
+        match self.flush_interval_ms {
+            0 => FlushPolicy::Immediate,
+            ms => FlushPolicy::Interval(Duration::from_millis(ms)),
+        }
+    }
+
+    /// 单个日志文件的大小上限（字节），None 表示不限制
+    pub fn max_file_bytes(&self) -> Option<u64> {
+        (self.max_file_mb > 0).then(|| self.max_file_mb.saturating_mul(1024 * 1024))
+    }
+
+    /// 校验快照的间隔，None 表示不写校验快照
+    pub fn verify_interval(&self) -> Option<Duration> {
+        (self.verify_interval_secs > 0).then(|| Duration::from_secs(self.verify_interval_secs))
+    }
+
+    /// 添加新时间戳的空闲时间
+    pub fn timestamp_timeout(&self) -> Duration {
+        Duration::from_secs(self.timestamp_timeout_secs)
+    }
+
+    /// 划分会话的空闲时间（不短于时间戳超时）
+    pub fn session_timeout(&self) -> Duration {
+        Duration::from_secs(self.session_timeout_mins.saturating_mul(60)).max(self.timestamp_timeout())
+    }
+
+    /// 每天自动分段的时刻，无法识别的项被忽略
+    pub fn segment_schedule(&self) -> Vec<NaiveTime> {
+        self.segment_times.iter()
+            .filter_map(|t| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok())
+            .collect()
+    }
+
+    /// 每日目标的完成比例（可能超过 1），未设目标时为 None
+    pub fn goal_progress(&self, chars: usize) -> Option<f32> {
+        (self.daily_goal > 0).then(|| chars as f32 / self.daily_goal as f32)
+    }
+
+    /// 专注时段的时长（至少 1 分钟）
+    pub fn focus_duration(&self) -> Duration {
+        Duration::from_secs(self.focus_minutes.max(1).saturating_mul(60))
+    }
+
+    /// 新窗口在前台停留多久才写入窗口标记
+    pub fn window_marker_delay(&self) -> Duration {
+        Duration::from_millis(self.window_marker_min_ms)
+    }
+
+    /// 连续输入时同步字符数到界面的最短间隔
+    pub fn gui_update_interval(&self) -> Duration {
+        Duration::from_millis(self.gui_update_interval_ms)
+    }
+
+    /// 最后一次输入后脉冲动画持续的时间
+    pub fn pulse_active_window(&self) -> Duration {
+        Duration::from_secs(self.pulse_active_secs)
+    }
+
+    /// 切换到其他程序后自动隐藏窗口的延迟，未启用时为 None
+    ///
+    /// 窗口置顶时用户希望窗口一直可见，不自动隐藏。
+    pub fn auto_hide_delay(&self) -> Option<Duration> {
+        self.auto_hide_secs
+            .filter(|_| !self.always_on_top)
+            .map(Duration::from_secs)
+    }
+
+    /// 暂停标记的最短时长，未启用时为 None
+    pub fn pause_marker_threshold(&self) -> Option<Duration> {
+        (self.pause_marker_min_secs > 0).then(|| Duration::from_secs(self.pause_marker_min_secs))
+    }
+
+    /// 日志时间使用的时区，未设置或无法识别时跟随系统时区
+    pub fn log_timezone(&self) -> LogTimezone {
+        self.timezone.as_deref()
+            .and_then(LogTimezone::parse)
+            .unwrap_or_default()
+    }
+
+    /// 是否设置了访问密码
+    pub fn has_passcode(&self) -> bool {
+        self.passcode_hash.is_some()
+    }
+
+    /// 设置访问密码，传入 None 表示移除密码
+    pub fn set_passcode(&mut self, passcode: Option<&str>) {
+        match passcode {
+            Some(passcode) => {
+                let salt = generate_salt();
+                self.passcode_hash = Some(hash_passcode(passcode, &salt));
+                self.passcode_salt = Some(salt);
+            }
+            None => {
+                self.passcode_hash = None;
+                self.passcode_salt = None;
+            }
+        }
+    }
+
+    /// 校验访问密码（未设置密码时总是通过）
+    pub fn verify_passcode(&self, passcode: &str) -> bool {
+        match (&self.passcode_hash, &self.passcode_salt) {
+            (Some(hash), Some(salt)) => hash_passcode(passcode, salt) == *hash,
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+}
+
+/// 计算访问密码的加盐哈希（SHA-256 迭代）
+pub fn hash_passcode(passcode: &str, salt: &str) -> String {
+    let mut digest = Sha256::new()
+        .chain_update(salt.as_bytes())
+        .chain_update(passcode.as_bytes())
+        .finalize();
+    for _ in 1..PASSCODE_HASH_ROUNDS {
+        digest = Sha256::new()
+            .chain_update(salt.as_bytes())
+            .chain_update(digest)
+            .finalize();
+    }
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 生成随机盐（RandomState 的种子来自操作系统随机数）
+fn generate_salt() -> String {
+    (0..2)
+        .map(|_| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default());
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// 全局配置（首次访问时从文件加载）
+static CONFIG: Lazy<RwLock<Config>> = Lazy::new(|| RwLock::new(Config::load()));
+
+/// 获取当前配置的副本
+pub fn current() -> Config {
+    CONFIG.read()
+        .map(|c| c.clone())
+        .unwrap_or_default()
+}
+
+/// 修改配置并保存到文件
+pub fn update<F: FnOnce(&mut Config)>(f: F) -> Result<(), String> {
+    let mut guard = CONFIG.write()
+        .map_err(|e| format!("无法获取配置锁: {}", e))?;
+    f(&mut guard);
+    guard.save()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,4 +906,169 @@ mod tests {
         let dir = get_log_directory();
         assert!(dir.to_string_lossy().contains("EchoKey"));
     }
+
+    #[test]
+    fn test_log_directory_from_env() {
+        let dir = std::env::temp_dir()
+            .join(format!("EchoKey-env-{}", std::process::id()))
+            .join("日志");
+        let _ = fs::remove_dir_all(&dir);
+
+        // 直接传入环境变量的值，不修改进程的环境变量（其他测试并行运行）
+        assert_eq!(env_log_directory(Some(dir.clone().into_os_string())), Some(dir.clone()));
+        assert!(dir.is_dir());
+        let padded = format!("  {}  ", dir.display());
+        assert_eq!(env_log_directory(Some(padded.into())), Some(dir.clone()));
+
+        // 空值和只有空白的值被忽略
+        assert_eq!(env_log_directory(Some("".into())), None);
+        assert_eq!(env_log_directory(Some("  \t".into())), None);
+        assert_eq!(env_log_directory(None), None);
+
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_portable_directory() {
+        let base = std::env::temp_dir()
+            .join(format!("echokey-portable-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+
+        // 程序路径中含有空格
+        let exe_dir = base.join("My Apps").join("Echo Key");
+        let dir = prepare_portable_directory(&exe_dir).unwrap();
+        assert_eq!(dir, exe_dir.join(PORTABLE_DIR_NAME));
+        assert!(dir.is_dir());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        // 无法创建目录时返回错误（调用方退回系统目录）
+        let file = base.join("not-a-dir");
+        fs::write(&file, "").unwrap();
+        assert!(prepare_portable_directory(&file).is_err());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_load_creates_default_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("echokey-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("config.toml");
+
+        // 文件不存在时写入默认配置
+        assert_eq!(Config::load_from(&path), Config::default());
+        assert_eq!(toml::from_str::<Config>(&fs::read_to_string(&path).unwrap()).unwrap(), Config::default());
+
+        // 格式错误时使用默认配置，不覆盖原文件
+        fs::write(&path, "log_directory = [").unwrap();
+        assert_eq!(Config::load_from(&path), Config::default());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "log_directory = [");
+
+        fs::write(&path, "log_directory = 'D:\\日志'\nidle_timeout_secs = 60\ndedup_window_ms = 15").unwrap();
+        let config = Config::load_from(&path);
+        assert_eq!(config.log_directory, Some(PathBuf::from("D:\\日志")));
+        assert_eq!(config.timestamp_timeout_secs, 60);
+        assert_eq!(config.key_dedup_ms, 15);
+        assert_eq!(config.char_dedup_ms, 0);
+
+        // 超出范围的字号限制在可选范围内
+        fs::write(&path, "font_size = 200.0").unwrap();
+        assert_eq!(Config::load_from(&path).font_size, *FONT_SIZE_RANGE.end());
+        fs::write(&path, "font_size = nan").unwrap();
+        assert_eq!(Config::load_from(&path).font_size, DEFAULT_FONT_SIZE);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_shortcut_bindings() {
+        let (bindings, problems) = Shortcuts::default().resolve();
+        // 默认设置与默认快捷键表相同（顺序可以不同）
+        let defaults = shortcuts::default_bindings();
+        assert_eq!(bindings.len(), defaults.len());
+        assert!(defaults.iter().all(|b| bindings.contains(b)));
+        assert!(problems.is_empty());
+
+        let config: Config = toml::from_str("[shortcuts]\ntoggle_pause = 'Alt+F9'\nnew_segment = 'Alt+F9'\npaste = 'V'").unwrap();
+        let (bindings, problems) = config.shortcuts.resolve();
+        let pause = Chord::parse("Alt+F9").unwrap();
+        assert_eq!(shortcuts::match_shortcut(&bindings, pause.modifiers, pause.vk), Some(ShortcutAction::TogglePause));
+        assert!(bindings.iter().all(|b| b.action != ShortcutAction::NewSegment));
+        // 无法识别的设置使用默认快捷键
+        assert_eq!(config.shortcuts.chord(ShortcutAction::Paste), Some(Chord::ctrl(b'V')));
+        assert_eq!(problems.len(), 2);
+
+        // 专注时段默认没有快捷键，可以在配置文件中设置
+        assert_eq!(Shortcuts::default().chord(ShortcutAction::ToggleFocus), None);
+        let config: Config = toml::from_str("[shortcuts]\ntoggle_focus = 'Ctrl+Alt+F'").unwrap();
+        let (bindings, problems) = config.shortcuts.resolve();
+        let focus = Chord::parse("Ctrl+Alt+F").unwrap();
+        assert_eq!(shortcuts::match_shortcut(&bindings, focus.modifiers, focus.vk), Some(ShortcutAction::ToggleFocus));
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: Config = toml::from_str("font_size = 16.0").unwrap();
+        assert_eq!(config.font_size, 16.0);
+        assert_eq!(config.font_path, None);
+    }
+
+    #[test]
+    fn test_passcode_verification() {
+        let mut config = Config::default();
+        assert!(config.verify_passcode("任意"));
+
+        config.set_passcode(Some("1234"));
+        assert!(config.has_passcode());
+        assert!(config.verify_passcode("1234"));
+        assert!(!config.verify_passcode("4321"));
+        assert_ne!(config.passcode_hash.as_deref(), Some("1234"));
+
+        config.set_passcode(None);
+        assert!(!config.has_passcode());
+    }
+
+    #[test]
+    fn test_goal_progress() {
+        let mut config = Config::default();
+        assert_eq!(config.goal_progress(500), None);
+        config.daily_goal = 2000;
+        assert_eq!(config.goal_progress(500), Some(0.25));
+        assert_eq!(config.goal_progress(3000), Some(1.5));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_empty());
+
+        let mut config = Config {
+            timestamp_timeout_secs: 600,
+            session_timeout_mins: 5,
+            timezone: Some("Mars/Olympus".to_string()),
+            segment_times: vec!["12:00".to_string(), "25:00".to_string(), "noon".to_string()],
+            ..Config::default()
+        };
+        let fields: Vec<&str> = config.validate().iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, vec!["session_timeout_mins", "timezone", "segment_times"]);
+        assert!(config.validate()[2].message().contains("25:00, noon"));
+
+        config.session_timeout_mins = 10;
+        config.timezone = Some("Asia/Shanghai".to_string());
+        config.segment_times.truncate(1);
+        assert!(config.validate().is_empty());
+
+        // 手写的超大值不会溢出
+        config.session_timeout_mins = u64::MAX;
+        assert!(config.validate().is_empty());
+        assert_eq!(config.session_timeout(), Duration::from_secs(u64::MAX));
+        config.max_file_mb = u64::MAX;
+        assert_eq!(config.max_file_bytes(), Some(u64::MAX));
+        config.focus_minutes = u64::MAX;
+        assert_eq!(config.focus_duration(), Duration::from_secs(u64::MAX));
+
+        assert!(requires_restart("event_channel_capacity"));
+        assert!(!requires_restart("timezone"));
+    }
 }
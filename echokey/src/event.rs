@@ -0,0 +1,1563 @@
+//! 输入事件模块
+//!
+//! 定义与平台无关的输入事件 `InputEvent`，以及业务逻辑线程处理事件的
+//! 共享逻辑。各平台的键盘监听只负责把系统事件转换成 `InputEvent`，
+//! 之后如何写入日志、如何更新界面都在这里完成，所有平台共用。
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use chrono::{NaiveDateTime, NaiveTime};
+
+use crate::clipboard::{Accumulator, ClipboardContent, ClipboardMonitor};
+use crate::config::Config;
+use crate::gui::SharedGuiState;
+use crate::logger::{self, DELETE_MARKER};
+use crate::{clipboard, i18n, shortcuts, Logger};
+
+/// 输入事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// 普通字符输入
+    Character(char),
+    /// Enter 键（换行并添加时间戳）
+    Enter,
+    /// Ctrl+Enter（只换行，不添加时间戳）
+    CtrlEnter,
+    /// Backspace 键
+    Backspace,
+    /// 粘贴操作（Ctrl+V）
+    Paste,
+    /// 复制操作（Ctrl+C，仅在开启复制记录时发送）
+    Copy,
+    /// 手动保存（Ctrl+Shift+S）
+    ManualSave,
+    /// 暂停/恢复（Ctrl+Shift+P）
+    TogglePause,
+    /// 新建日志段（Ctrl+Shift+N）
+    NewSegment,
+    /// 插入检查点（Ctrl+Shift+K），暂停时也有效
+    Checkpoint,
+    /// Win 组合键（仅在开启组合键记录时发送），内容为按键名称，如 "L"
+    WinCombo(String),
+    /// 转换不出字符的可打印按键（仅在详细模式下发送），内容为虚拟键码
+    UnknownKey(u32),
+    /// 特殊键或组合键（仅在本次运行的详细记录模式下发送），内容为按键名称，如 "Ctrl+C"
+    Key(String),
+    /// 前台窗口切换（例如 Alt+Tab 到其他应用）
+    WindowChanged(WindowInfo),
+    /// 大写锁定切换（仅在开启大写锁定标记时发送），内容为切换后是否锁定
+    CapsLock(bool),
+    /// 锁屏（true）或解锁（false）
+    SessionLock(bool),
+    /// 开始/结束专注时段（默认没有快捷键，可在配置文件中设置）
+    ToggleFocus,
+    /// Tab 键（仅在开启按键详细记录时发送），日志中写入制表符
+    Tab,
+    /// Delete 键（仅在开启按键详细记录时发送）
+    Delete,
+    /// 方向键、Home、End（仅在开启按键详细记录时发送）
+    Navigation(NavKey),
+    /// 物理按键的按下或松开，含修饰键（仅在开启原始按键记录时发送，与字符事件同时发送）
+    Raw { vk: u32, down: bool },
+}
+
+/// 移动光标的按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavKey {
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+impl NavKey {
+    /// 日志中的记号
+    pub fn symbol(self) -> &'static str {
+        match self {
+            NavKey::Left => "←",
+            NavKey::Right => "→",
+            NavKey::Up => "↑",
+            NavKey::Down => "↓",
+            NavKey::Home => "⇤",
+            NavKey::End => "⇥",
+        }
+    }
+}
+
+/// 本次运行的详细记录模式（托盘切换，不写入配置，重启后恢复关闭）
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// 开启/关闭详细记录模式：记录特殊键、组合键、未知键和窗口切换
+pub fn set_verbose(enabled: bool) {
+    VERBOSE.store(enabled, Ordering::Relaxed);
+}
+
+/// 详细记录模式是否开启
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// 已发送到事件通道、还没有被业务逻辑线程取出的事件数
+static QUEUED_EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 键盘监听成功发送一个事件后调用
+pub fn note_event_queued() {
+    QUEUED_EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 业务逻辑线程取出一个事件后调用
+pub fn note_event_dequeued() {
+    let _ = QUEUED_EVENTS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+}
+
+/// 事件通道中排队的事件数
+pub fn queued_events() -> usize {
+    QUEUED_EVENTS.load(Ordering::Relaxed)
+}
+
+/// 写入积压检测
+///
+/// 大段粘贴、磁盘缓慢时业务逻辑线程处理不过来，事件在通道中排队，输入会稍有延迟。
+/// 排队数达到阈值时视为繁忙，直到积压完全排空才清除，避免在阈值附近来回闪烁。
+#[derive(Debug, Clone, Default)]
+pub struct BacklogMonitor {
+    busy: bool,
+}
+
+impl BacklogMonitor {
+    /// 按当前排队数更新状态，返回是否繁忙
+    pub fn update(&mut self, queued: usize, threshold: usize) -> bool {
+        if queued >= threshold.max(1) {
+            self.busy = true;
+        } else if queued == 0 {
+            self.busy = false;
+        }
+        self.busy
+    }
+}
+
+/// 有按键按下而钩子这么久没有被调用时，怀疑钩子已被系统移除
+pub const HOOK_SILENCE_LIMIT: Duration = Duration::from_secs(2);
+
+/// 怀疑持续这么久（期间用户仍在输入）后才重新安装钩子，避免偶尔的误判
+pub const HOOK_CONFIRM_DELAY: Duration = Duration::from_secs(1);
+
+/// 重新安装钩子后仍然收不到按键时，第一次重试的等待时间（之后每次加倍）
+pub const HOOK_RETRY_INITIAL: Duration = Duration::from_secs(5);
+
+/// 重新安装钩子的最长重试间隔
+pub const HOOK_RETRY_MAX: Duration = Duration::from_secs(5 * 60);
+
+/// 看门狗检查钩子的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookCheck {
+    /// 钩子正常，或还不能确定
+    Ok,
+    /// 钩子已被移除，应重新安装
+    Reinstall,
+    /// 重新安装后钩子又收到了按键
+    Recovered,
+}
+
+/// 键盘钩子的看门狗
+///
+/// 钩子回调处理太慢时，Windows 会不加通知地移除低级键盘钩子，之后的输入都不再记录。
+/// 系统中有按键按下，而钩子超过 [`HOOK_SILENCE_LIMIT`] 没有被调用，并且持续
+/// [`HOOK_CONFIRM_DELAY`] 时，判定钩子已被移除，请求重新安装；
+/// 重新安装后仍然收不到按键时按退避间隔重试，直到钩子恢复。
+#[derive(Debug, Clone, Default)]
+pub struct HookWatchdog {
+    /// 开始怀疑钩子被移除的时刻
+    suspect_since: Option<Instant>,
+    /// 已请求重新安装，等待钩子恢复
+    lost: bool,
+    /// 下一次允许重新安装的时刻
+    next_attempt: Option<Instant>,
+    /// 当前的重试间隔
+    backoff: Duration,
+}
+
+impl HookWatchdog {
+    /// 检查钩子：`hook_idle` 为钩子多久没有被调用，`keys_down` 读取此刻是否有按键按下
+    ///
+    /// 钩子最近被调用过时不读取按键状态。
+    pub fn check(&mut self, now: Instant, hook_idle: Duration, keys_down: impl FnOnce() -> bool) -> HookCheck {
+        if hook_idle < HOOK_SILENCE_LIMIT {
+            self.suspect_since = None;
+            self.next_attempt = None;
+            self.backoff = Duration::ZERO;
+            return if std::mem::take(&mut self.lost) { HookCheck::Recovered } else { HookCheck::Ok };
+        }
+        if !keys_down() {
+            return HookCheck::Ok;
+        }
+        let since = *self.suspect_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < HOOK_CONFIRM_DELAY
+            || self.next_attempt.is_some_and(|at| now < at)
+        {
+            return HookCheck::Ok;
+        }
+        
+        self.lost = true;
+        self.backoff = if self.backoff.is_zero() {
+            HOOK_RETRY_INITIAL
+        } else {
+            (self.backoff * 2).min(HOOK_RETRY_MAX)
+        };
+        self.next_attempt = Some(now + self.backoff);
+        HookCheck::Reinstall
+    }
+}
+
+/// 专注时段（番茄钟）
+///
+/// 计时从开始时刻算起，暂停期间不计入；结束时统计期间输入的字符数和实际专注的分钟数。
+#[derive(Debug, Clone)]
+pub struct FocusSession {
+    started_at: Instant,
+    duration: Duration,
+    /// 开始时的今日字符数
+    chars_at_start: usize,
+    /// 暂停开始的时刻，None 表示正在计时
+    paused_at: Option<Instant>,
+    /// 之前各次暂停的累计时长
+    paused_total: Duration,
+}
+
+impl FocusSession {
+    /// 从 `now` 开始一个时长为 `duration` 的专注时段
+    pub fn new(now: Instant, duration: Duration, chars_at_start: usize) -> Self {
+        Self {
+            started_at: now,
+            duration,
+            chars_at_start,
+            paused_at: None,
+            paused_total: Duration::ZERO,
+        }
+    }
+
+    /// 已专注的时长（不含暂停）
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        let end = self.paused_at.unwrap_or(now);
+        end.saturating_duration_since(self.started_at).saturating_sub(self.paused_total)
+    }
+
+    /// 剩余时长
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.duration.saturating_sub(self.elapsed(now))
+    }
+
+    /// 已完成的比例（0 到 1）
+    pub fn progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (self.elapsed(now).as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    /// 计时已满
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.elapsed(now) >= self.duration
+    }
+
+    /// 是否处于暂停中
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// 暂停或继续计时
+    pub fn toggle_pause(&mut self, now: Instant) {
+        match self.paused_at.take() {
+            Some(paused_at) => self.paused_total += now.saturating_duration_since(paused_at),
+            None => self.paused_at = Some(now),
+        }
+    }
+
+    /// 时段内输入的字符数（跨天清零后不会出现负数）
+    pub fn chars_typed(&self, char_count: usize) -> usize {
+        char_count.saturating_sub(self.chars_at_start)
+    }
+}
+
+/// 前台窗口信息
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WindowInfo {
+    /// 窗口句柄（仅 Windows，以整数保存便于跨线程传递）
+    pub hwnd: Option<isize>,
+    /// 窗口标题
+    pub title: String,
+    /// 是否为 EchoKey 自身的窗口
+    pub own: bool,
+    /// 窗口所属程序的名称（可执行文件名，不含 `.exe`），无法获取时为 None
+    pub process: Option<String>,
+}
+
+/// 读取剪贴板的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardRead {
+    /// 粘贴：检测内容格式；来源程序可能稍后才写入剪贴板，短暂重试
+    Retry,
+    /// 复制：等待程序把复制的内容写入剪贴板后再读取
+    Copied,
+    /// 手动保存、累加剪贴板：只读取一次文本
+    Once,
+    /// 检测剪贴板变化：剪贴板没有更新时不读取
+    Changed,
+}
+
+/// 剪贴板读取函数（测试中可以替换为固定内容）
+pub type ClipboardReader = fn(&Config, ClipboardRead) -> ClipboardContent;
+
+/// 从系统剪贴板读取内容
+fn system_clipboard(settings: &Config, read: ClipboardRead) -> ClipboardContent {
+    match read {
+        ClipboardRead::Retry => clipboard::get_content_retry(
+            settings.clipboard_retry_attempts,
+            Duration::from_millis(settings.clipboard_retry_delay_ms),
+        ),
+        ClipboardRead::Copied => clipboard::get_copied_content(
+            settings.clipboard_retry_attempts,
+            Duration::from_millis(settings.clipboard_retry_delay_ms),
+        ),
+        ClipboardRead::Once => clipboard::get_text().into(),
+        ClipboardRead::Changed => clipboard::get_text_if_changed().into(),
+    }
+}
+
+/// 开启了保存粘贴的图片且图片不超过上限时，读取要保存的图片
+fn save_pasted_image(settings: &Config, width: usize, height: usize) -> Option<clipboard::ImageData<'static>> {
+    if !settings.save_pasted_images || !clipboard::image_fits(width, height) {
+        return None;
+    }
+    clipboard::get_image()
+}
+
+/// 窗口切换标记的防抖
+///
+/// 启动画面、通知等程序会在很短时间内反复抢占前台，每次切换都写入标记会淹没真正的输入。
+/// 新窗口在前台停留满设定时长（或在其中开始输入）后才写入标记，
+/// 很快又切回原来的窗口时不写入。
+#[derive(Debug, Clone, Default)]
+pub struct WindowDebounce {
+    /// 等待确认的窗口标题，以及它成为前台窗口的时间
+    pending: Option<(String, Instant)>,
+    /// 上次确认的窗口标题
+    settled: Option<String>,
+}
+
+impl WindowDebounce {
+    /// 前台切换到新窗口，替换之前等待确认的窗口
+    pub fn switch(&mut self, title: String, now: Instant) {
+        self.pending = Some((title, now));
+    }
+
+    /// 等待确认的窗口停留满 `min`（`force` 时不论停留多久）后确认，
+    /// 返回需要写入标记的标题；没有标题或与上次确认的窗口相同时返回 None
+    pub fn settle(&mut self, now: Instant, min: Duration, force: bool) -> Option<String> {
+        let (_, since) = self.pending.as_ref()?;
+        if !force && now.saturating_duration_since(*since) < min {
+            return None;
+        }
+        let (title, _) = self.pending.take()?;
+        if self.settled.as_ref() == Some(&title) {
+            return None;
+        }
+        self.settled = Some(title.clone());
+        (!title.is_empty()).then_some(title)
+    }
+}
+
+/// 同一字符重复上屏的防护
+///
+/// 部分输入法在英文模式下，同一个字母会同时经由按键转换和输入法各上屏一次。
+/// 按键层已经对同一按键做了 30ms 去重，正常输入不可能在几毫秒内产生两个相同的字符，
+/// 因此窗口内到达的相同字符视为另一来源的重复，只记录第一个。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharGuard {
+    /// 上一次记录的字符和时间
+    last: Option<(char, Instant)>,
+}
+
+impl CharGuard {
+    /// 是否记录这个字符（记录时记为上一次的字符）
+    pub fn accept(&mut self, c: char, now: Instant, window: Duration) -> bool {
+        let duplicate = self.last.is_some_and(|(last, time)| {
+            last == c && now.saturating_duration_since(time) < window
+        });
+        if !duplicate {
+            self.last = Some((c, now));
+        }
+        !duplicate
+    }
+}
+
+/// 每天定时自动分段
+#[derive(Debug, Clone, Default)]
+pub struct SegmentSchedule {
+    /// 每天分段的时刻
+    times: Vec<NaiveTime>,
+    /// 上次检查的时间：只在两次检查之间经过的时刻触发，启动前错过的时刻不补
+    last_check: Option<NaiveDateTime>,
+    /// 上次触发的时刻，系统时间被调回时同一时刻不会再次触发
+    last_fired: Option<NaiveDateTime>,
+}
+
+impl SegmentSchedule {
+    pub fn new(times: Vec<NaiveTime>) -> Self {
+        Self { times, ..Default::default() }
+    }
+
+    /// 修改分段时刻，保留检查进度（修改前已经过的时刻不补）
+    pub fn set_times(&mut self, times: Vec<NaiveTime>) {
+        self.times = times;
+    }
+
+    /// 从上次检查到 `now` 之间是否经过了某个设定的时刻
+    pub fn due(&mut self, now: NaiveDateTime) -> bool {
+        let Some(last) = self.last_check.replace(now) else {
+            return false;
+        };
+        let slot = [last.date(), now.date()].into_iter()
+            .flat_map(|date| self.times.iter().map(move |t| date.and_time(*t)))
+            .filter(|slot| last < *slot && *slot <= now)
+            .filter(|slot| self.last_fired.is_none_or(|fired| *slot > fired))
+            .max();
+        if slot.is_some() {
+            self.last_fired = slot;
+        }
+        slot.is_some()
+    }
+}
+
+/// 业务逻辑线程的状态
+pub struct AppState {
+    pub logger: Logger,
+    pub paused: bool,
+    pub char_count: usize,
+    /// EchoKey 自身的窗口在前台，此时不记录输入
+    pub own_window_focused: bool,
+    /// 最近一次记录输入的时间（GUI 据此区分正在输入和空闲）
+    pub last_input_at: Option<Instant>,
+    /// 累加剪贴板的变化检测
+    pub clipboard_monitor: ClipboardMonitor,
+    /// 检测剪贴板变化（开启后第一次检测时以当时的剪贴板内容为基准，关闭时为 None）
+    pub clipboard_watch: Option<ClipboardMonitor>,
+    /// 上次检测剪贴板变化的时间
+    last_clipboard_watch: Option<Instant>,
+    /// 每天定时自动分段
+    pub segment_schedule: SegmentSchedule,
+    /// 窗口切换标记的防抖
+    pub window_debounce: WindowDebounce,
+    /// 同一字符重复上屏的防护
+    pub char_guard: CharGuard,
+    /// 当前的暂停是锁屏时自动开始的，解锁后自动恢复
+    pub paused_by_lock: bool,
+    /// 详细记录模式：记录窗口切换（托盘切换时与 [`set_verbose`] 一起设置）
+    pub verbose: bool,
+    /// 上次同步计数到界面的时间
+    last_publish: Option<Instant>,
+    /// 上次同步后还没有同步到界面的按键数
+    unpublished_keys: usize,
+    /// 同步到界面（获取界面状态锁）的累计次数
+    pub publish_count: usize,
+    /// 启动时加载的配置
+    pub settings: Config,
+    /// 剪贴板读取函数
+    pub read_clipboard: ClipboardReader,
+}
+
+impl AppState {
+    /// 创建状态，使用系统剪贴板
+    pub fn new(logger: Logger, settings: Config) -> Self {
+        Self {
+            logger,
+            paused: false,
+            char_count: 0,
+            own_window_focused: false,
+            last_input_at: None,
+            clipboard_monitor: ClipboardMonitor::default(),
+            clipboard_watch: None,
+            last_clipboard_watch: None,
+            segment_schedule: SegmentSchedule::new(settings.segment_schedule()),
+            window_debounce: WindowDebounce::default(),
+            char_guard: CharGuard::default(),
+            paused_by_lock: false,
+            verbose: verbose(),
+            last_publish: None,
+            unpublished_keys: 0,
+            publish_count: 0,
+            settings,
+            read_clipboard: system_clipboard,
+        }
+    }
+
+    /// 在运行中应用设置页面修改后的配置
+    ///
+    /// 时间戳超时、时区、粘贴合并等写入相关的设置立即生效；
+    /// 设备标签和分段时刻只在有变化时重新设置，避免重复写入会话标记。
+    pub fn reload_settings(&mut self, settings: Config) -> Result<(), String> {
+        self.logger.set_paste_dedup_window(settings.paste_dedup_window());
+        self.logger.set_max_paste_chars(settings.max_paste_chars());
+        self.logger.set_pause_marker_threshold(settings.pause_marker_threshold());
+        self.logger.set_resume_style(settings.resume_style);
+        self.logger.set_compact_whitespace(settings.compact_whitespace);
+        self.logger.set_backspace_deletes(settings.backspace_deletes);
+        self.logger.set_window_tracking(settings.window_titles);
+        self.logger.set_max_file_bytes(settings.max_file_bytes());
+        self.logger.set_flush_policy(settings.flush_policy());
+        self.logger.set_autorepeat(settings.autorepeat_policy());
+        self.logger.set_sequence_numbers(settings.sequence_numbers);
+        self.logger.set_format(settings.log_format);
+        self.logger.set_split_by_app(settings.split_by_app);
+        self.logger.set_recent_events_capacity(settings.recent_events);
+        self.logger.set_app_markers(settings.app_markers);
+        self.logger.set_idle_timeouts(settings.timestamp_timeout(), settings.session_timeout());
+        self.logger.set_timezone(settings.log_timezone());
+        if settings.device_label != self.settings.device_label
+            || settings.device_session_marker != self.settings.device_session_marker
+        {
+            self.logger.set_device_label(settings.device_label.clone(), settings.device_session_marker);
+        }
+        if settings.segment_times != self.settings.segment_times {
+            self.segment_schedule.set_times(settings.segment_schedule());
+        }
+        let header = self.logger.set_header_template(settings.header_template.clone());
+        self.settings = settings;
+        header
+    }
+
+    /// 同步今日字符数和日志文件大小到 GUI
+    fn publish_counters(&mut self, gui_state: &Mutex<SharedGuiState>) {
+        if let Ok(mut gs) = gui_state.lock() {
+            gs.today_chars = self.char_count;
+            gs.log_stats = self.logger.file_stats();
+            gs.last_input_at = self.last_input_at;
+        }
+        self.last_publish = Some(Instant::now());
+        self.unpublished_keys = 0;
+        self.publish_count += 1;
+    }
+
+    /// 连续输入时合并同步：距上次同步满间隔或累计满一定按键数才同步
+    ///
+    /// 逐键同步时每个字符都要获取一次界面状态锁，快速输入时与界面线程争抢。
+    /// 按默认设置（100 毫秒或 20 个按键）连续输入 1000 个字符，
+    /// 获取锁的次数从 1000 次降到约 50 次（见 `test_typing_burst_coalesces_gui_updates`）。
+    /// `char_count` 始终准确，只有界面上的数字会稍有滞后，由 [`flush_counters`] 补齐。
+    fn publish_counters_coalesced(&mut self, gui_state: &Mutex<SharedGuiState>) {
+        self.unpublished_keys += 1;
+        let interval = self.settings.gui_update_interval();
+        let due = self.last_publish.is_none_or(|t| t.elapsed() >= interval)
+            || self.unpublished_keys >= self.settings.gui_update_chars.max(1);
+        if due {
+            self.publish_counters(gui_state);
+        }
+    }
+}
+
+/// 把合并中还没有同步的计数同步到界面（业务逻辑线程空闲时调用）
+pub fn flush_counters(state: &mut AppState, gui_state: &Mutex<SharedGuiState>) {
+    if state.unpublished_keys > 0 {
+        state.publish_counters(gui_state);
+    }
+}
+
+/// 窗口切换标记的防抖到期后写入标记（业务逻辑线程空闲时调用）
+pub fn poll_window_marker(state: &mut AppState) {
+    if !state.paused {
+        write_settled_window(state, false);
+    }
+}
+
+/// 确认等待中的窗口切换并写入标记，`force` 表示不等停留时长（已经在新窗口中输入）
+fn write_settled_window(state: &mut AppState, force: bool) {
+    let delay = state.settings.window_marker_delay();
+    if let Some(title) = state.window_debounce.settle(Instant::now(), delay, force) {
+        if let Err(e) = state.logger.write_window_marker(&title) {
+            eprintln!("写入错误: {}", e);
+        }
+    }
+}
+
+/// 在获取状态锁之前读取事件要用的剪贴板内容，事件不读取剪贴板时返回 None
+///
+/// 复制时要等待程序把内容写入剪贴板（最多 [`clipboard::MAX_RETRY_WAIT`]），粘贴时剪贴板暂时被占用要重试，
+/// 都在锁外等待，界面和托盘的请求不会因此卡住。暂停、在 EchoKey 自己的窗口中输入时不读取。
+pub fn prefetch_clipboard(app_state: &Mutex<AppState>, event: &InputEvent) -> Option<ClipboardContent> {
+    let read = match event {
+        InputEvent::Paste => ClipboardRead::Retry,
+        InputEvent::Copy => ClipboardRead::Copied,
+        _ => return None,
+    };
+    let (reader, settings) = {
+        let state = app_state.lock().ok()?;
+        if state.paused || state.own_window_focused {
+            return None;
+        }
+        (state.read_clipboard, state.settings.clone())
+    };
+    Some(reader(&settings, read))
+}
+
+/// 处理一个输入事件
+pub fn handle_event(state: &mut AppState, gui_state: &Mutex<SharedGuiState>, event: InputEvent) {
+    handle_event_with_clipboard(state, gui_state, event, None);
+}
+
+/// 处理一个输入事件，`prefetched` 是 [`prefetch_clipboard`] 预先读取的剪贴板内容（没有时在这里读取）
+pub fn handle_event_with_clipboard(
+    state: &mut AppState,
+    gui_state: &Mutex<SharedGuiState>,
+    event: InputEvent,
+    prefetched: Option<ClipboardContent>,
+) {
+    // 检查是否暂停（暂停/恢复、检查点和专注时段是用户主动操作，不受影响；解锁时需要恢复锁屏造成的暂停）
+    if state.paused && !matches!(
+        event,
+        InputEvent::TogglePause | InputEvent::Checkpoint | InputEvent::SessionLock(_) | InputEvent::ToggleFocus
+    ) {
+        return;
+    }
+
+    // 在 EchoKey 自己的搜索框、设置项中的输入不记录，也不计入统计（快捷键仍然有效）
+    let is_input = matches!(
+        event,
+        InputEvent::Character(_)
+            | InputEvent::Enter
+            | InputEvent::CtrlEnter
+            | InputEvent::Backspace
+            | InputEvent::Paste
+            | InputEvent::Copy
+            | InputEvent::WinCombo(_)
+            | InputEvent::UnknownKey(_)
+            | InputEvent::Key(_)
+            | InputEvent::Tab
+            | InputEvent::Delete
+            | InputEvent::Navigation(_)
+            | InputEvent::Raw { .. }
+    );
+    if is_input && state.own_window_focused {
+        return;
+    }
+    if is_input {
+        // 已经在新窗口中输入，窗口标记写在输入之前
+        write_settled_window(state, true);
+    }
+    if is_input {
+        state.last_input_at = Some(Instant::now());
+    }
+    // 逐键的输入合并同步到界面，其他事件立即同步
+    let coalesce = matches!(event, InputEvent::Character(_) | InputEvent::Backspace);
+
+    match event {
+        InputEvent::Character(c) if !state.char_guard.accept(c, Instant::now(), state.settings.char_dedup_window()) => {}
+        InputEvent::Character(c) => {
+            if let Err(e) = state.logger.write_text(&c.to_string()) {
+                eprintln!("写入错误: {}", e);
+            } else {
+                state.char_count += 1;
+            }
+        }
+        InputEvent::Enter => {
+            if let Err(e) = state.logger.handle_enter() {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::CtrlEnter => {
+            if let Err(e) = state.logger.handle_ctrl_enter() {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::Backspace => {
+            if let Err(e) = state.logger.handle_backspace() {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::Paste => match prefetched.unwrap_or_else(|| (state.read_clipboard)(&state.settings, ClipboardRead::Retry)) {
+            ClipboardContent::Text(content) if !content.is_empty() => {
+                // 超过一块的粘贴在界面上显示写入进度
+                let large = content.len() > crate::logger::PASTE_CHUNK_BYTES;
+                let result = state.logger.write_paste_with_progress(&content, |done, total| {
+                    if large {
+                        if let Ok(mut gs) = gui_state.lock() {
+                            gs.paste_progress = Some((done, total));
+                        }
+                    }
+                });
+                if large {
+                    if let Ok(mut gs) = gui_state.lock() {
+                        gs.paste_progress = None;
+                    }
+                }
+                if let Err(e) = result {
+                    eprintln!("写入错误: {}", e);
+                } else {
+                    state.char_count += content.chars().count();
+                }
+            }
+            ClipboardContent::Text(_) | ClipboardContent::Empty => {}
+            other => {
+                let result = match other {
+                    ClipboardContent::Files(paths) => state.logger.write_pasted_files(&paths),
+                    ClipboardContent::Image { width, height } => match save_pasted_image(&state.settings, width, height) {
+                        Some(image) => state.logger.write_pasted_image_file(image.width, image.height, image.bytes.into_owned()),
+                        None => state.logger.write_pasted_image(width, height),
+                    },
+                    _ => state.logger.write_unknown_paste(),
+                };
+                if let Err(e) = result {
+                    eprintln!("写入错误: {}", e);
+                }
+            }
+        },
+        InputEvent::Copy => {
+            // 只记录复制的文本，文件、图片等格式不记录
+            let content = prefetched.unwrap_or_else(|| (state.read_clipboard)(&state.settings, ClipboardRead::Copied));
+            if let Some(content) = content.into_text() {
+                if let Err(e) = state.logger.write_copy(&content) {
+                    eprintln!("写入错误: {}", e);
+                }
+                // 已经记录过，检测剪贴板变化时不再重复记录
+                if let Some(monitor) = state.clipboard_watch.as_mut() {
+                    monitor.observe(Some(content));
+                }
+            }
+        }
+        InputEvent::ManualSave => {
+            let content = (state.read_clipboard)(&state.settings, ClipboardRead::Once);
+            if let Some(content) = content.into_text() {
+                if let Err(e) = state.logger.write_manual_save(&content) {
+                    eprintln!("写入错误: {}", e);
+                } else {
+                    eprintln!("已手动保存剪贴板内容");
+                }
+            }
+        }
+        InputEvent::TogglePause => {
+            match state.logger.toggle_pause() {
+                Ok(paused) => {
+                    state.paused = paused;
+                    state.paused_by_lock = false;
+                    if let Ok(mut gs) = gui_state.lock() {
+                        gs.paused = paused;
+                    }
+                    eprintln!("{}", if paused { "已暂停记录" } else { "已恢复记录" });
+                }
+                Err(e) => eprintln!("错误: {}", e),
+            }
+        }
+        InputEvent::NewSegment => {
+            match state.logger.new_segment() {
+                Ok(true) => eprintln!("已创建新日志段"),
+                Ok(false) => eprintln!("当前日志段为空"),
+                Err(e) => eprintln!("错误: 无法创建新日志段: {}", e),
+            }
+        }
+        InputEvent::Checkpoint => {
+            if let Err(e) = state.logger.write_checkpoint() {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::ToggleFocus => {
+            if let Ok(mut gs) = gui_state.lock() {
+                toggle_focus(state, &mut gs);
+            }
+        }
+        InputEvent::WinCombo(key) => {
+            if let Err(e) = state.logger.write_key_marker(&format!("[Win+{}]", key)) {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::UnknownKey(vk) => {
+            let marker = i18n::marker_with("marker.unknown_key", format!("VK_{:02X}", vk));
+            if let Err(e) = state.logger.write_key_marker(&format!("[{}]", marker)) {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::Key(name) => {
+            if let Err(e) = state.logger.write_key_marker(&format!("[{}]", name)) {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::Raw { vk, down } => {
+            let marker = logger::raw_key_marker(&shortcuts::raw_key_name(vk), down);
+            if let Err(e) = state.logger.write_key_marker(&marker) {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::Tab | InputEvent::Delete | InputEvent::Navigation(_) => {
+            let symbol = match event {
+                InputEvent::Tab => "\t",
+                InputEvent::Navigation(key) => key.symbol(),
+                _ => DELETE_MARKER,
+            };
+            // 按键的记号不是输入的文字，开启退格删除时不能被删掉
+            if let Err(e) = state.logger.write_key_marker(symbol) {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::SessionLock(locked) => {
+            // 锁屏前已经暂停的，解锁后保持暂停
+            let result = if locked && state.settings.pause_on_lock {
+                state.logger.pause_for_lock().map(|paused| state.paused_by_lock |= paused)
+            } else if !locked && state.paused_by_lock {
+                state.paused_by_lock = false;
+                state.logger.resume_after_unlock()
+            } else {
+                Ok(())
+            };
+            if let Err(e) = result {
+                eprintln!("写入错误: {}", e);
+            }
+            if state.paused != state.logger.is_paused() {
+                state.paused = state.logger.is_paused();
+                if let Ok(mut gs) = gui_state.lock() {
+                    gs.paused = state.paused;
+                }
+            }
+        }
+        InputEvent::CapsLock(on) => {
+            if let Err(e) = state.logger.write_caps_lock_marker(on) {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+        InputEvent::WindowChanged(info) => {
+            state.own_window_focused = info.own && state.settings.exclude_own_window;
+            state.logger.set_foreground_app(info.process.as_deref());
+            if state.verbose {
+                state.window_debounce.switch(info.title, Instant::now());
+                write_settled_window(state, false);
+            }
+            // 记录离开 EchoKey 窗口的时间，GUI 据此自动隐藏窗口；回到 EchoKey 时取消
+            if let Ok(mut gs) = gui_state.lock() {
+                if info.own {
+                    gs.left_window_at = None;
+                } else if gs.left_window_at.is_none() {
+                    gs.left_window_at = Some(Instant::now());
+                }
+            }
+            // 切换窗口是自然的停顿点：确保刚输入的内容已经落盘
+            if let Err(e) = state.logger.flush() {
+                eprintln!("写入错误: {}", e);
+            }
+        }
+    }
+
+    if coalesce {
+        state.publish_counters_coalesced(gui_state);
+    } else {
+        state.publish_counters(gui_state);
+    }
+}
+
+/// 开启/关闭累加剪贴板
+///
+/// 开启时以当前剪贴板内容为基准，之后每次复制的新内容追加到缓冲区。
+/// 关闭时把缓冲区中的内容作为一条 `[累加剪贴板]` 写入日志，然后丢弃缓冲区。
+pub fn toggle_accumulation(state: &mut AppState, gs: &mut SharedGuiState) {
+    match gs.accumulator.take() {
+        Some(accumulator) => write_accumulated(state, &accumulator),
+        None => {
+            let current = (state.read_clipboard)(&state.settings, ClipboardRead::Once);
+            state.clipboard_monitor = ClipboardMonitor::new(current.into_text());
+            gs.accumulator = Some(Arc::new(Accumulator::default()));
+        }
+    }
+}
+
+/// 把累加的内容写入日志并清空缓冲区（保持开启）
+pub fn flush_accumulation(state: &mut AppState, gs: &mut SharedGuiState) {
+    if let Some(accumulator) = gs.accumulator.as_mut() {
+        write_accumulated(state, accumulator);
+        Arc::make_mut(accumulator).clear();
+    }
+}
+
+/// 检测剪贴板变化，把新复制的内容追加到缓冲区（未开启时不读取剪贴板）
+///
+/// 界面上「复制」按钮复制的内容（例如累加的全部内容）不算新复制，否则会被再次追加到缓冲区。
+pub fn poll_accumulation(state: &mut AppState, gs: &mut SharedGuiState) {
+    if let Some(text) = gs.copied_by_app.take() {
+        state.clipboard_monitor.ignore(text.clone());
+        if let Some(monitor) = state.clipboard_watch.as_mut() {
+            monitor.ignore(text);
+        }
+    }
+    if let Some(accumulator) = gs.accumulator.as_mut() {
+        let current = (state.read_clipboard)(&state.settings, ClipboardRead::Once);
+        if let Some(text) = state.clipboard_monitor.observe(current.into_text()) {
+            Arc::make_mut(accumulator).push(text);
+        }
+    }
+}
+
+/// 到了检测间隔时检测剪贴板变化，把新复制的文本记为 `[复制]` 条目
+///
+/// 开启后第一次检测只记下当时的剪贴板内容（开启前已有的内容不记录）；
+/// EchoKey 自身的窗口在前台时的复制、密码管理器标记为不应记录的内容都不记录。
+/// 未开启时不读取剪贴板。
+pub fn poll_clipboard_watch(state: &mut AppState, now: Instant) {
+    let Some(interval) = state.settings.clipboard_watch_interval() else {
+        state.clipboard_watch = None;
+        return;
+    };
+    if state.last_clipboard_watch.is_some_and(|last| now.saturating_duration_since(last) < interval) {
+        return;
+    }
+    state.last_clipboard_watch = Some(now);
+    
+    let Some(monitor) = state.clipboard_watch.as_mut() else {
+        let current = (state.read_clipboard)(&state.settings, ClipboardRead::Once);
+        state.clipboard_watch = Some(ClipboardMonitor::new(current.into_text()));
+        return;
+    };
+    let current = (state.read_clipboard)(&state.settings, ClipboardRead::Changed);
+    if let Some(text) = monitor.observe(current.into_text()) {
+        if state.own_window_focused {
+            return;
+        }
+        if let Err(e) = state.logger.write_copy(&text) {
+            eprintln!("写入错误: {}", e);
+        }
+    }
+}
+
+/// 开始或结束专注时段，在日志中写入开始/结束标记
+pub fn toggle_focus(state: &mut AppState, gs: &mut SharedGuiState) {
+    let result = match gs.focus.take() {
+        Some(session) => finish_focus(state, &session),
+        None => {
+            gs.focus = Some(FocusSession::new(Instant::now(), state.settings.focus_duration(), state.char_count));
+            state.logger.write_focus_start()
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("写入错误: {}", e);
+    }
+}
+
+/// 暂停或继续专注时段的计时
+pub fn pause_focus(gs: &mut SharedGuiState) {
+    if let Some(session) = gs.focus.as_mut() {
+        session.toggle_pause(Instant::now());
+    }
+}
+
+/// 专注时段计时已满时自动结束，返回是否结束
+pub fn poll_focus(state: &mut AppState, gs: &mut SharedGuiState) -> bool {
+    let finished = gs.focus.as_ref().is_some_and(|s| s.is_finished(Instant::now()));
+    if finished {
+        toggle_focus(state, gs);
+    }
+    finished
+}
+
+fn finish_focus(state: &mut AppState, session: &FocusSession) -> std::io::Result<()> {
+    let now = Instant::now();
+    let chars = session.chars_typed(state.char_count);
+    let minutes = (session.elapsed(now).as_secs() + 30) / 60;
+    state.logger.write_focus_end(chars, minutes)
+}
+
+/// 到了设定的时刻自动新建日志段（当前段还没有内容时不新建）
+pub fn poll_segment_schedule(state: &mut AppState) {
+    let now = state.settings.log_timezone().now().naive_local();
+    if state.segment_schedule.due(now) {
+        match state.logger.new_segment() {
+            Ok(true) => eprintln!("已按计划创建新日志段"),
+            Ok(false) => {}
+            Err(e) => eprintln!("错误: 无法创建新日志段: {}", e),
+        }
+    }
+}
+
+fn write_accumulated(state: &mut AppState, accumulator: &Accumulator) {
+    if accumulator.is_empty() {
+        return;
+    }
+    if let Err(e) = state.logger.write_accumulated(accumulator.combined()) {
+        eprintln!("写入错误: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// 在独立的临时目录中创建状态，剪贴板固定返回 "剪贴板"
+    fn test_state(name: &str) -> (AppState, PathBuf) {
+        let dir = std::env::temp_dir()
+            .join(format!("echokey-event-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        let mut state = AppState::new(Logger::with_directory(&dir).unwrap(), Config::default());
+        state.read_clipboard = |_, _| ClipboardContent::Text("剪贴板".to_string());
+        (state, dir)
+    }
+
+    /// 读取目录中所有日志（按文件名顺序拼接）
+    fn read_logs(state: AppState, dir: &PathBuf) -> String {
+        drop(state);
+        let mut files: Vec<PathBuf> = fs::read_dir(dir).unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        files.sort();
+        let content = files.iter().map(|f| fs::read_to_string(f).unwrap()).collect();
+        let _ = fs::remove_dir_all(dir);
+        content
+    }
+
+    fn run(name: &str, events: Vec<InputEvent>) -> (String, usize, bool, SharedGuiState) {
+        let (mut state, dir) = test_state(name);
+        let gui_state = Mutex::new(SharedGuiState::default());
+        for event in events {
+            handle_event(&mut state, &gui_state, event);
+        }
+        flush_counters(&mut state, &gui_state);
+        let (count, paused) = (state.char_count, state.paused);
+        (read_logs(state, &dir), count, paused, gui_state.into_inner().unwrap())
+    }
+
+    #[test]
+    fn test_characters_and_line_breaks() {
+        let (log, count, _, gui) = run("chars", vec![
+            InputEvent::Character('你'),
+            InputEvent::Character('好'),
+            InputEvent::Backspace,
+            InputEvent::CtrlEnter,
+            InputEvent::Character('a'),
+            InputEvent::Enter,
+            InputEvent::Character('b'),
+        ]);
+        assert!(log.contains("] 你好⌫\n          a\n["));
+        assert!(log.ends_with("] b"));
+        assert_eq!(count, 4);
+        assert_eq!(gui.today_chars, 4);
+        assert_eq!(gui.log_stats.bytes, log.len() as u64);
+        assert!(gui.last_input_at.is_some());
+    }
+
+    #[test]
+    fn test_typing_burst_coalesces_gui_updates() {
+        let (mut state, dir) = test_state("coalesce");
+        let gui_state = Mutex::new(SharedGuiState::default());
+        for _ in 0..1000 {
+            handle_event(&mut state, &gui_state, InputEvent::Character('a'));
+        }
+        assert_eq!(state.char_count, 1000);
+        // 按键数触发约 50 次，测试机很慢时间隔触发会多几次
+        assert!(state.publish_count <= 100, "{}", state.publish_count);
+        assert!(gui_state.lock().unwrap().today_chars <= 1000);
+
+        flush_counters(&mut state, &gui_state);
+        assert_eq!(gui_state.lock().unwrap().today_chars, 1000);
+        let publishes = state.publish_count;
+        flush_counters(&mut state, &gui_state);
+        assert_eq!(state.publish_count, publishes);
+
+        // 间隔为 0 时逐键同步
+        state.settings.gui_update_interval_ms = 0;
+        handle_event(&mut state, &gui_state, InputEvent::Character('b'));
+        assert_eq!(gui_state.lock().unwrap().today_chars, 1001);
+        read_logs(state, &dir);
+    }
+
+    #[test]
+    fn test_clipboard_events() {
+        let (log, count, _, gui) = run("clipboard", vec![
+            InputEvent::Paste,
+            InputEvent::ManualSave,
+        ]);
+        assert!(log.contains("[粘贴] 剪贴板"));
+        assert!(log.contains("[手动保存] 剪贴板"));
+        assert_eq!(count, 3);
+        assert_eq!(gui.today_chars, 3);
+    }
+
+    #[test]
+    fn test_copy_then_paste_writes_content_once() {
+        let (mut state, dir) = test_state("copy");
+        let gui_state = Mutex::new(SharedGuiState::default());
+        handle_event(&mut state, &gui_state, InputEvent::Copy);
+        handle_event(&mut state, &gui_state, InputEvent::Paste);
+        // 之后再粘贴同样的内容照常记录
+        handle_event(&mut state, &gui_state, InputEvent::Paste);
+        state.read_clipboard = |_, _| ClipboardContent::Image { width: 1, height: 1 };
+        handle_event(&mut state, &gui_state, InputEvent::Copy);
+        
+        let log = read_logs(state, &dir);
+        assert!(log.contains("] [复制] 剪贴板\n"));
+        assert!(log.contains("] [粘贴]\n"));
+        assert_eq!(log.matches("剪贴板").count(), 2);
+        assert_eq!(log.matches("[复制]").count(), 1);
+    }
+
+    #[test]
+    fn test_prefetched_copy_is_used_outside_the_lock() {
+        let (state, dir) = test_state("prefetch");
+        let app_state = Mutex::new(state);
+        let gui_state = Mutex::new(SharedGuiState::default());
+        assert_eq!(prefetch_clipboard(&app_state, &InputEvent::Character('a')), None);
+        
+        // 剪贴板没有更新（没有选中内容）时读取结果为空，不记录旧内容
+        app_state.lock().unwrap().read_clipboard = |_, _| ClipboardContent::Empty;
+        let prefetched = prefetch_clipboard(&app_state, &InputEvent::Copy);
+        assert_eq!(prefetched, Some(ClipboardContent::Empty));
+        handle_event_with_clipboard(&mut app_state.lock().unwrap(), &gui_state, InputEvent::Copy, prefetched);
+        
+        let prefetched = Some(ClipboardContent::Text("新复制".to_string()));
+        handle_event_with_clipboard(&mut app_state.lock().unwrap(), &gui_state, InputEvent::Copy, prefetched);
+        
+        // 粘贴也在锁外读取剪贴板（可能要重试等待）
+        app_state.lock().unwrap().read_clipboard = |_, read| match read {
+            ClipboardRead::Retry => ClipboardContent::Text("粘贴内容".to_string()),
+            _ => ClipboardContent::Empty,
+        };
+        let prefetched = prefetch_clipboard(&app_state, &InputEvent::Paste);
+        assert_eq!(prefetched, Some(ClipboardContent::Text("粘贴内容".to_string())));
+        handle_event_with_clipboard(&mut app_state.lock().unwrap(), &gui_state, InputEvent::Paste, prefetched);
+        
+        // 暂停时不读取剪贴板
+        app_state.lock().unwrap().paused = true;
+        assert_eq!(prefetch_clipboard(&app_state, &InputEvent::Copy), None);
+        assert_eq!(prefetch_clipboard(&app_state, &InputEvent::Paste), None);
+        
+        let log = read_logs(app_state.into_inner().unwrap(), &dir);
+        assert_eq!(log.matches("[复制]").count(), 1);
+        assert!(log.contains("] [复制] 新复制\n"));
+        assert!(log.contains("粘贴内容"));
+    }
+
+    #[test]
+    fn test_paste_formats() {
+        let (mut state, dir) = test_state("paste-formats");
+        let gui_state = Mutex::new(SharedGuiState::default());
+        state.read_clipboard = |_, _| ClipboardContent::Files(vec![
+            PathBuf::from(r"C:\a.txt"),
+            PathBuf::from(r"C:\b.txt"),
+        ]);
+        handle_event(&mut state, &gui_state, InputEvent::Paste);
+        state.read_clipboard = |_, _| ClipboardContent::Image { width: 800, height: 600 };
+        handle_event(&mut state, &gui_state, InputEvent::Paste);
+        state.read_clipboard = |_, _| ClipboardContent::Unknown;
+        handle_event(&mut state, &gui_state, InputEvent::Paste);
+        state.read_clipboard = |_, _| ClipboardContent::Empty;
+        handle_event(&mut state, &gui_state, InputEvent::Paste);
+        assert_eq!(state.char_count, 0);
+        
+        let log = read_logs(state, &dir);
+        assert!(log.contains(r"] [粘贴文件] C:\a.txt | C:\b.txt"));
+        assert!(log.contains("] [粘贴图片] 800×600\n"));
+        assert!(log.contains("] [粘贴:未知格式]\n"));
+        assert_eq!(log.matches("[粘贴").count(), 3);
+    }
+
+    #[test]
+    fn test_pause_ignores_input_until_resumed() {
+        let (log, count, paused, gui) = run("pause", vec![
+            InputEvent::TogglePause,
+            InputEvent::Character('x'),
+            InputEvent::Paste,
+            InputEvent::Checkpoint,
+            InputEvent::TogglePause,
+            InputEvent::Character('y'),
+        ]);
+        assert!(!log.contains('x'));
+        assert!(log.contains("--- 暂停记录 ---"));
+        assert!(log.contains("] [检查点]\n"));
+        assert!(log.contains("--- 恢复记录 ---"));
+        assert!(log.ends_with("] y"));
+        assert_eq!(count, 1);
+        assert!(!paused);
+        assert!(!gui.paused);
+        
+        // 暂停期间的按键不算作输入
+        let (_, _, _, gui) = run("pause-idle", vec![
+            InputEvent::TogglePause,
+            InputEvent::Character('x'),
+        ]);
+        assert!(gui.last_input_at.is_none());
+    }
+
+    #[test]
+    fn test_segment_combo_and_window_events() {
+        let (log, _, _, _) = run("segment", vec![
+            InputEvent::Character('a'),
+            InputEvent::NewSegment,
+            InputEvent::WinCombo("L".to_string()),
+            InputEvent::UnknownKey(0xE2),
+            InputEvent::WindowChanged(WindowInfo::default()),
+            InputEvent::CapsLock(true),
+            InputEvent::Character('B'),
+            InputEvent::CapsLock(false),
+        ]);
+        assert!(log.contains("] a"));
+        assert!(log.contains("[Win+L][未知键:VK_E2]"));
+        assert!(log.contains("] --- 大写锁定开 ---\n[") && log.contains("] B\n"));
+        assert!(log.contains("] --- 大写锁定关 ---\n"));
+        // 两个日志段各有一个头部
+        assert_eq!(log.matches("EchoKey 日志").count(), 2);
+    }
+
+    #[test]
+    fn test_own_window_input_is_not_logged() {
+        let own = WindowInfo { own: true, ..Default::default() };
+        let (log, count, _, gui) = run("own-window", vec![
+            InputEvent::Character('a'),
+            InputEvent::WindowChanged(own.clone()),
+            InputEvent::Character('x'),
+            InputEvent::Paste,
+            InputEvent::WindowChanged(WindowInfo::default()),
+            InputEvent::Character('b'),
+        ]);
+        assert!(log.contains("] ab"));
+        assert!(!log.contains('x'));
+        assert!(!log.contains("[粘贴]"));
+        assert_eq!(count, 2);
+        assert!(gui.left_window_at.is_some());
+
+        // 关闭排除后，自身窗口中的输入照常记录
+        let (mut state, dir) = test_state("own-window-captured");
+        state.settings.exclude_own_window = false;
+        let gui_state = Mutex::new(SharedGuiState::default());
+        handle_event(&mut state, &gui_state, InputEvent::WindowChanged(own));
+        handle_event(&mut state, &gui_state, InputEvent::Character('x'));
+        assert!(read_logs(state, &dir).contains("] x"));
+        assert!(gui_state.into_inner().unwrap().left_window_at.is_none());
+    }
+
+    #[test]
+    fn test_verbose_mode_logs_window_changes() {
+        let window = WindowInfo { title: "记事本".to_string(), ..Default::default() };
+        let (mut state, dir) = test_state("verbose");
+        state.verbose = true;
+        let gui_state = Mutex::new(SharedGuiState::default());
+        for event in [
+            InputEvent::Key("Ctrl+C".to_string()),
+            InputEvent::WindowChanged(window),
+            InputEvent::Character('x'),
+        ] {
+            handle_event(&mut state, &gui_state, event);
+        }
+        let log = read_logs(state, &dir);
+        assert!(log.contains("] [Ctrl+C]\n"));
+        assert!(log.contains("--- 窗口：记事本 ---\n[") && log.contains("] x"));
+    }
+
+    #[test]
+    fn test_raw_key_events() {
+        let (log, count, _, _) = run("raw-keys", vec![
+            InputEvent::Raw { vk: 0xA0, down: true },
+            InputEvent::Raw { vk: b'A' as u32, down: true },
+            InputEvent::Character('A'),
+            InputEvent::Raw { vk: b'A' as u32, down: false },
+            InputEvent::Raw { vk: 0xA0, down: false },
+        ]);
+        assert!(log.contains("] [↓LShift][↓A]A[↑A][↑LShift]"));
+        // 原始按键不计入字数
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_editing_keys_are_logged_as_symbols() {
+        let (log, count, _, gui) = run("editing-keys", vec![
+            InputEvent::Character('a'),
+            InputEvent::Tab,
+            InputEvent::Character('b'),
+            InputEvent::Navigation(NavKey::Left),
+            InputEvent::Delete,
+            InputEvent::Navigation(NavKey::Home),
+            InputEvent::Navigation(NavKey::End),
+        ]);
+        assert!(log.contains("] a\tb←⌦⇤⇥"));
+        assert_eq!(count, 2);
+        assert!(gui.last_input_at.is_some());
+    }
+
+    #[test]
+    fn test_key_markers_survive_backspace() {
+        let (mut state, dir) = test_state("marker-backspace");
+        state.logger.set_backspace_deletes(true);
+        let gui_state = Mutex::new(SharedGuiState::default());
+        for event in [
+            InputEvent::Character('a'),
+            InputEvent::WinCombo("L".to_string()),
+            InputEvent::Backspace,
+            InputEvent::UnknownKey(0xE8),
+            InputEvent::Backspace,
+            InputEvent::Key("F5".to_string()),
+            InputEvent::Backspace,
+            InputEvent::Navigation(NavKey::Left),
+            InputEvent::Backspace,
+        ] {
+            handle_event(&mut state, &gui_state, event);
+        }
+        
+        // 退格不会删掉记号的最后一个字符，只写入 ⌫
+        let log = read_logs(state, &dir);
+        assert!(log.contains("] a[Win+L]⌫[未知键:VK_E8]⌫[F5]⌫←⌫"));
+    }
+
+    #[test]
+    fn test_screen_lock_pauses_recording() {
+        let (log, count, paused, gui) = run("screen-lock", vec![
+            InputEvent::Character('a'),
+            InputEvent::SessionLock(true),
+            InputEvent::Character('x'),
+            InputEvent::SessionLock(false),
+            InputEvent::Character('b'),
+        ]);
+        assert!(log.contains("] a\n[") && log.contains("] --- 屏幕锁定，暂停记录 ---\n"));
+        assert!(log.contains("] --- 屏幕解锁，恢复记录 ---\n[") && log.contains("] b"));
+        assert!(!log.contains('x'));
+        assert_eq!(count, 2);
+        assert!(!paused && !gui.paused);
+
+        // 锁屏前手动暂停的，解锁后保持暂停
+        let (log, _, paused, _) = run("screen-lock-paused", vec![
+            InputEvent::TogglePause,
+            InputEvent::SessionLock(true),
+            InputEvent::SessionLock(false),
+            InputEvent::Character('x'),
+        ]);
+        assert!(paused);
+        assert!(!log.contains("屏幕") && !log.contains('x'));
+
+        // 关闭后锁屏不影响记录
+        let (mut state, dir) = test_state("screen-lock-off");
+        state.settings.pause_on_lock = false;
+        let gui_state = Mutex::new(SharedGuiState::default());
+        handle_event(&mut state, &gui_state, InputEvent::SessionLock(true));
+        handle_event(&mut state, &gui_state, InputEvent::Character('x'));
+        assert!(!state.paused);
+        assert!(read_logs(state, &dir).contains("] x"));
+    }
+
+    #[test]
+    fn test_focus_session() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut session = FocusSession::new(start, Duration::from_secs(25 * 60), 100);
+        assert_eq!(session.remaining(at(60)), Duration::from_secs(24 * 60));
+
+        // 暂停期间不计时
+        session.toggle_pause(at(60));
+        assert!(session.is_paused());
+        assert_eq!(session.elapsed(at(600)), Duration::from_secs(60));
+        session.toggle_pause(at(600));
+        assert_eq!(session.elapsed(at(660)), Duration::from_secs(120));
+        assert!(!session.is_finished(at(25 * 60 + 539)));
+        assert!(session.is_finished(at(25 * 60 + 540)));
+        assert_eq!(session.chars_typed(130), 30);
+        assert_eq!(session.chars_typed(5), 0);
+
+        // 快捷键开始/结束，暂停记录时也有效
+        let (mut state, dir) = test_state("focus");
+        let gui_state = Mutex::new(SharedGuiState::default());
+        handle_event(&mut state, &gui_state, InputEvent::ToggleFocus);
+        assert!(gui_state.lock().unwrap().focus.is_some());
+        for c in "专注".chars() {
+            handle_event(&mut state, &gui_state, InputEvent::Character(c));
+        }
+        handle_event(&mut state, &gui_state, InputEvent::TogglePause);
+        handle_event(&mut state, &gui_state, InputEvent::ToggleFocus);
+        assert!(gui_state.lock().unwrap().focus.is_none());
+        let log = read_logs(state, &dir);
+        assert!(log.contains("] ===== 专注开始 =====\n["));
+        assert!(log.contains("] ===== 专注结束: 2 字符, 0 分钟 =====\n"));
+    }
+
+    #[test]
+    fn test_backlog_indicator_clears_when_drained() {
+        let mut monitor = BacklogMonitor::default();
+        assert!(!monitor.update(10, 64));
+        assert!(monitor.update(64, 64));
+        // 正在消化积压，低于阈值时仍然显示
+        assert!(monitor.update(30, 64));
+        assert!(monitor.update(1, 64));
+        assert!(!monitor.update(0, 64));
+        assert!(!monitor.update(30, 64));
+    }
+
+    #[test]
+    fn test_hook_watchdog_reinstalls_with_backoff() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let silent = HOOK_SILENCE_LIMIT;
+        let mut watchdog = HookWatchdog::default();
+        
+        // 钩子正常时不读取按键状态；没有输入时不判断
+        assert_eq!(watchdog.check(at(0), Duration::ZERO, || unreachable!()), HookCheck::Ok);
+        assert_eq!(watchdog.check(at(0), silent, || false), HookCheck::Ok);
+        
+        // 有按键按下而钩子一直没被调用，持续一段时间后才重新安装
+        assert_eq!(watchdog.check(at(100), silent, || true), HookCheck::Ok);
+        assert_eq!(watchdog.check(at(1100), silent, || true), HookCheck::Reinstall);
+        // 重新安装后仍然收不到按键，按加倍的间隔重试
+        assert_eq!(watchdog.check(at(2000), silent, || true), HookCheck::Ok);
+        assert_eq!(watchdog.check(at(6100), silent, || true), HookCheck::Reinstall);
+        assert_eq!(watchdog.check(at(15000), silent, || true), HookCheck::Ok);
+        assert_eq!(watchdog.check(at(16100), silent, || true), HookCheck::Reinstall);
+        
+        // 钩子又被调用：报告一次恢复，重试间隔复位
+        assert_eq!(watchdog.check(at(17000), Duration::ZERO, || true), HookCheck::Recovered);
+        assert_eq!(watchdog.check(at(17050), Duration::ZERO, || true), HookCheck::Ok);
+        assert_eq!(watchdog.check(at(30000), silent, || true), HookCheck::Ok);
+        assert_eq!(watchdog.check(at(31000), silent, || true), HookCheck::Reinstall);
+    }
+
+    #[test]
+    fn test_char_guard_window() {
+        let window = Duration::from_millis(10);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut guard = CharGuard::default();
+
+        // 按键和输入法相隔 2ms 各上屏一次
+        assert!(guard.accept('a', at(0), window));
+        assert!(!guard.accept('a', at(2), window));
+        // 快速输入的双字母间隔远大于窗口
+        assert!(guard.accept('a', at(80), window));
+        assert!(guard.accept('b', at(82), window));
+        assert!(guard.accept('a', at(84), window));
+        // 关闭时不去重
+        assert!(guard.accept('a', at(85), Duration::ZERO));
+    }
+
+    #[test]
+    fn test_double_emitted_character_is_logged_once() {
+        let (mut state, dir) = test_state("char-guard");
+        let gui_state = Mutex::new(SharedGuiState::default());
+        // 第一次写入要创建文件，窗口放宽，避免慢速测试机上误判
+        state.settings.char_dedup_ms = 1000;
+        // 按键转换和输入法紧接着各上屏一次 h
+        for c in ['h', 'h', 'i'] {
+            handle_event(&mut state, &gui_state, InputEvent::Character(c));
+        }
+        assert_eq!(state.char_count, 2);
+        
+        let log = read_logs(state, &dir);
+        assert!(log.ends_with("] hi"));
+    }
+
+    #[test]
+    fn test_window_flicker_is_debounced() {
+        let min = Duration::from_millis(500);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debounce = WindowDebounce::default();
+
+        debounce.switch("记事本".to_string(), at(0));
+        assert_eq!(debounce.settle(at(499), min, false), None);
+        assert_eq!(debounce.settle(at(500), min, false).as_deref(), Some("记事本"));
+
+        // 启动画面和通知反复抢占前台，每次停留都很短
+        for i in 0..5 {
+            debounce.switch("启动画面".to_string(), at(1000 + i * 100));
+            assert_eq!(debounce.settle(at(1000 + i * 100 + 50), min, false), None);
+            debounce.switch("通知".to_string(), at(1000 + i * 100 + 50));
+        }
+        // 最后停留在新窗口上，只写入一次
+        debounce.switch("浏览器".to_string(), at(2000));
+        assert_eq!(debounce.settle(at(2100), min, false), None);
+        assert_eq!(debounce.settle(at(2600), min, false).as_deref(), Some("浏览器"));
+        assert_eq!(debounce.settle(at(3000), min, false), None);
+
+        // 很快切回原来的窗口时不写入
+        debounce.switch("记事本".to_string(), at(4000));
+        debounce.switch("浏览器".to_string(), at(4100));
+        assert_eq!(debounce.settle(at(5000), min, false), None);
+
+        // 在新窗口中输入时立即确认
+        debounce.switch("终端".to_string(), at(6000));
+        assert_eq!(debounce.settle(at(6010), min, true).as_deref(), Some("终端"));
+    }
+
+    #[test]
+    fn test_segment_schedule_fires_once_per_slot() {
+        let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        let at = |day: u32, h: u32, m: u32| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+            .and_hms_opt(h, m, 0).unwrap();
+        let mut schedule = SegmentSchedule::new(vec![noon, midnight]);
+        
+        // 启动时已经过了的时刻不补
+        assert!(!schedule.due(at(1, 13, 0)));
+        assert!(!schedule.due(at(1, 23, 59)));
+        // 跨过午夜
+        assert!(schedule.due(at(2, 0, 1)));
+        assert!(!schedule.due(at(2, 11, 59)));
+        assert!(schedule.due(at(2, 12, 0)));
+        assert!(!schedule.due(at(2, 12, 1)));
+        
+        // 系统时间被调回后，已触发的时刻不再触发
+        assert!(!schedule.due(at(2, 11, 0)));
+        assert!(!schedule.due(at(2, 12, 30)));
+    }
+
+    #[test]
+    fn test_clipboard_watch_logs_new_copies() {
+        let (mut state, dir) = test_state("clipboard-watch");
+        let gui_state = Mutex::new(SharedGuiState::default());
+        let start = Instant::now();
+        state.settings.watch_clipboard = true;
+        state.read_clipboard = |_, _| ClipboardContent::Text("开启前的内容".to_string());
+        poll_clipboard_watch(&mut state, start);
+        poll_clipboard_watch(&mut state, start + Duration::from_secs(1));
+        
+        state.read_clipboard = |_, _| ClipboardContent::Text("右键复制".to_string());
+        // 还没到检测间隔
+        poll_clipboard_watch(&mut state, start + Duration::from_millis(1500));
+        assert_eq!(state.last_clipboard_watch, Some(start + Duration::from_secs(1)));
+        poll_clipboard_watch(&mut state, start + Duration::from_secs(2));
+        poll_clipboard_watch(&mut state, start + Duration::from_secs(3));
+        
+        // 按 Ctrl+C 记录过的内容不再重复记录
+        state.read_clipboard = |_, _| ClipboardContent::Text("按键复制".to_string());
+        handle_event(&mut state, &gui_state, InputEvent::Copy);
+        poll_clipboard_watch(&mut state, start + Duration::from_secs(4));
+        
+        state.settings.watch_clipboard = false;
+        poll_clipboard_watch(&mut state, start + Duration::from_secs(5));
+        assert!(state.clipboard_watch.is_none());
+        
+        let log = read_logs(state, &dir);
+        assert!(!log.contains("开启前的内容"));
+        assert_eq!(log.matches("[复制] 右键复制\n").count(), 1);
+        assert_eq!(log.matches("[复制] 按键复制\n").count(), 1);
+    }
+
+    #[test]
+    fn test_clipboard_accumulation() {
+        let (mut state, dir) = test_state("accumulate");
+        let mut gs = SharedGuiState::default();
+        
+        // 开启前已在剪贴板中的内容不算
+        toggle_accumulation(&mut state, &mut gs);
+        poll_accumulation(&mut state, &mut gs);
+        assert!(gs.accumulator.as_ref().is_some_and(|a| a.is_empty()));
+        
+        state.read_clipboard = |_, _| ClipboardContent::Text("第一段".to_string());
+        poll_accumulation(&mut state, &mut gs);
+        poll_accumulation(&mut state, &mut gs);
+        state.read_clipboard = |_, _| ClipboardContent::Text("第二段".to_string());
+        poll_accumulation(&mut state, &mut gs);
+        assert_eq!(gs.accumulator.as_ref().map(|a| a.len()), Some(2));
+        
+        // 在界面上复制累加的内容，不会把它再追加一次
+        gs.copied_by_app = Some("第一段\n第二段".to_string());
+        state.read_clipboard = |_, _| ClipboardContent::Text("第一段\n第二段".to_string());
+        poll_accumulation(&mut state, &mut gs);
+        assert_eq!(gs.accumulator.as_ref().map(|a| a.len()), Some(2));
+        
+        // 关闭时写入日志并结束累加
+        toggle_accumulation(&mut state, &mut gs);
+        assert!(gs.accumulator.is_none());
+        let log = read_logs(state, &dir);
+        assert!(log.contains("[累加剪贴板] 第一段\n第二段\n"));
+    }
+}
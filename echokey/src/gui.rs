@@ -8,12 +8,27 @@
 //! - 字体：系统默认，清晰易读
 //! - 动画：流畅的过渡效果
 
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
 use std::path::PathBuf;
 use eframe::egui;
-use chrono::Local;
+use chrono::NaiveDate;
 
-use crate::autostart;
+use crate::{autostart, config, i18n, logger, stats, window_state};
+use crate::window_state::WindowGeometry;
+use crate::tail::LogTail;
+use crate::clipboard::{self, Accumulator};
+use crate::event::FocusSession;
+use crate::shortcuts::{Chord, ShortcutAction};
+use crate::logger::FileStats;
+
+/// 有日志的日期列表的缓存时长，过期后重新读取日志目录
+const LOG_DATES_REFRESH: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 不受限制的密码尝试次数，超过后每次失败都要等待
+const FREE_UNLOCK_ATTEMPTS: u32 = 3;
+
+/// 密码错误后的最长等待时间（秒）
+const MAX_UNLOCK_DELAY_SECS: u64 = 300;
 
 /// 当前显示的页面
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,6 +38,41 @@ pub enum Page {
     Settings,
 }
 
+/// 命令面板中的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    TogglePause,
+    NewSegment,
+    OpenLogDir,
+    ExportMarkdown,
+    JumpToDate(NaiveDate),
+}
+
+impl Command {
+    /// 命令面板中显示的名称（同时用于筛选）
+    fn label(&self, paused: bool) -> String {
+        match self {
+            Command::TogglePause => i18n::t(if paused { "status.resume" } else { "status.pause" }).to_string(),
+            Command::NewSegment => i18n::t("status.new_segment").to_string(),
+            Command::OpenLogDir => i18n::t("status.open_log_dir").to_string(),
+            Command::ExportMarkdown => i18n::t("palette.export_markdown").to_string(),
+            Command::JumpToDate(date) => i18n::t_with("palette.jump_to_date", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+/// 打开/关闭命令面板的按键组合（只在窗口内有效，修饰键必须完全一致）
+const COMMAND_PALETTE_CHORD: Chord = Chord::ctrl(b'K');
+
+/// 命令面板（[`COMMAND_PALETTE_CHORD`]）的输入状态
+#[derive(Debug, Default)]
+struct CommandPalette {
+    /// 筛选词
+    query: String,
+    /// 选中的命令在筛选结果中的位置
+    selected: usize,
+}
+
 /// GUI 应用状态
 pub struct EchoKeyApp {
     /// 当前页面
@@ -31,16 +81,115 @@ pub struct EchoKeyApp {
     is_paused: bool,
     /// 今日字符数
     today_chars: usize,
+    /// 上次同步时的今日字符数（启动后首次同步前为 None），用于判断何时达成每日目标
+    previous_chars: Option<usize>,
+    /// 最近一次提示达成每日目标的日期
+    goal_celebrated_on: Option<NaiveDate>,
+    /// 因事件通道已满而丢弃的按键事件总数
+    dropped_events: u64,
+    /// 写入积压，输入正在排队处理
+    write_busy: bool,
+    /// 键盘钩子尚未安装（开机自启动的延迟期间）
+    capture_pending: bool,
+    /// 运行在远程桌面会话中，键盘记录可能不完整
+    remote_session: bool,
+    /// 键盘钩子被系统移除，正在重新安装
+    hook_lost: bool,
+    /// 键盘钩子被移除后恢复的次数
+    hook_restores: u32,
+    safe_mode: bool,
+    /// 大段粘贴的写入进度（已写入字节数, 总字节数）
+    paste_progress: Option<(usize, usize)>,
+    /// 当前日志文件的大小和行数
+    log_stats: FileStats,
+    /// 最近一次记录输入的时间（用于状态页的脉冲动画）
+    last_input_at: Option<std::time::Instant>,
+    /// 累加剪贴板的缓冲区，为空表示未开启（与共享状态共用，每帧同步时不复制内容）
+    accumulator: Option<Arc<Accumulator>>,
+    /// 进行中的专注时段
+    focus: Option<FocusSession>,
     /// 日志目录
     log_directory: PathBuf,
     /// 是否开机自启动
     autostart_enabled: bool,
     /// 搜索关键词
     search_query: String,
+    /// 日志文件列表的筛选词（按文件名/日期模糊匹配）
+    file_filter: String,
     /// 日志内容（用于历史页面）
     log_content: String,
+    /// 日志内容中是否有条目序号（加载时检查一次）
+    log_has_sequences: bool,
+    /// 日志内容的分行布局（只排版可见的行）
+    log_lines: WrappedLines,
+    /// 跳转到时刻的输入框（`HH:MM`）
+    seek_input: String,
+    /// 正在查看的日志文件
+    viewing_path: Option<PathBuf>,
+    /// 是否允许修改（删除、重命名）正在查看的归档日志
+    archive_writable: bool,
+    /// 等待确认删除的日志文件
+    pending_delete: Option<PathBuf>,
+    /// 重命名输入框（有值时显示重命名确认窗口）
+    rename_input: Option<String>,
+    /// 历史页面的文件列表按程序分组（按程序分文件时）
+    group_by_app: bool,
+    /// 是否合并相邻的短时间戳行（仅影响显示）
+    collapse_short_lines: bool,
+    /// 查看日志时隐藏条目序号
+    hide_sequences: bool,
+    /// 合并阈值：内容少于这么多字符的时间戳行会被合并
+    collapse_min_chars: usize,
+    /// 日志对比：较早的一天
+    diff_from: Option<NaiveDate>,
+    /// 日志对比：较新的一天
+    diff_to: Option<NaiveDate>,
+    /// 日志对比结果（有值时历史页面显示对比视图）
+    diff_lines: Option<Vec<logger::DiffLine>>,
+    /// 所有日志文件的搜索结果（有值时历史页面显示搜索结果）
+    search_results: Option<Vec<logger::SearchResult>>,
+    /// 后台线程中正在进行的搜索，完成后结果放入 `search_results`
+    search_task: Option<mpsc::Receiver<Vec<logger::SearchResult>>>,
+    /// 实时预览：增量读取当前日志
+    live_tail: LogTail,
+    /// 实时预览上次刷新时间
+    last_tail_poll: Option<std::time::Instant>,
+    /// 实时预览中选中的文字
+    preview_selection: String,
     /// 状态消息
     status_message: Option<(String, std::time::Instant)>,
+    /// 用户配置（设置页面修改后写回 config.toml）
+    config: config::Config,
+    /// 自定义字体路径的编辑缓冲
+    font_path_input: String,
+    /// 高级设置中尚未应用的修改
+    advanced_draft: Option<config::Config>,
+    /// 高级设置中正在录制快捷键的动作
+    capturing_shortcut: Option<ShortcutAction>,
+    /// 是否已输入访问密码（未设置密码时视为已解锁）
+    unlocked: bool,
+    /// 解锁提示中输入的密码
+    passcode_input: String,
+    /// 设置页面中输入的新密码
+    new_passcode_input: String,
+    /// 连续输错密码的次数
+    failed_unlocks: u32,
+    /// 在此之前不接受新的密码尝试
+    unlock_blocked_until: Option<std::time::Instant>,
+    /// 命令面板，为空表示未打开
+    command_palette: Option<CommandPalette>,
+    /// 导出统计的起始日期
+    stats_from: Option<NaiveDate>,
+    /// 导出统计的结束日期
+    stats_to: Option<NaiveDate>,
+    /// 有日志的日期及读取的时间（对比、导出统计、命令面板每帧都要用，定时重新读取）
+    log_dates: Option<(std::time::Instant, Vec<NaiveDate>)>,
+    /// 启动时要恢复的窗口位置（拿到显示器大小、限制在屏幕内之后移动）
+    restore_geometry: Option<WindowGeometry>,
+    /// 当前的窗口位置和大小
+    window_geometry: Option<WindowGeometry>,
+    /// 窗口位置或大小最近一次变化、还没有保存的时间
+    geometry_changed_at: Option<std::time::Instant>,
     /// 共享状态（与主程序通信）
     shared_state: Option<Arc<Mutex<SharedGuiState>>>,
 }
@@ -51,6 +200,52 @@ pub struct SharedGuiState {
     pub today_chars: usize,
     pub request_new_segment: bool,
     pub request_open_log: bool,
+    /// 请求将摘录写入日志（已去掉时间戳）
+    pub request_excerpt: Option<String>,
+    /// 主程序发给 GUI 的状态消息（例如分段结果）
+    pub notice: Option<String>,
+    /// 因事件通道已满而丢弃的按键事件总数
+    pub dropped_events: u64,
+    /// 事件通道积压，业务逻辑线程正在追赶（积压排空后清除）
+    pub write_busy: bool,
+    /// 键盘钩子尚未安装（开机自启动的延迟期间）
+    pub capture_pending: bool,
+    /// 运行在远程桌面会话中，键盘记录可能不完整
+    pub remote_session: bool,
+    /// 键盘钩子被系统移除，看门狗正在重新安装（恢复后清除）
+    pub hook_lost: bool,
+    /// 键盘钩子被移除后恢复的次数
+    pub hook_restores: u32,
+    /// 以安全模式启动：不记录输入，只显示界面
+    pub safe_mode: bool,
+    /// 大段粘贴的写入进度（已写入字节数, 总字节数），写完后清空
+    pub paste_progress: Option<(usize, usize)>,
+    /// 当前日志文件的大小和行数
+    pub log_stats: FileStats,
+    /// 前台切换到其他程序的时间（回到 EchoKey 窗口时清空），用于自动隐藏窗口
+    pub left_window_at: Option<std::time::Instant>,
+    /// 最近一次记录输入的时间
+    pub last_input_at: Option<std::time::Instant>,
+    /// 累加剪贴板的缓冲区，为空表示未开启
+    pub accumulator: Option<Arc<Accumulator>>,
+    /// 界面复制到剪贴板的文本：检测剪贴板变化时不把它当作新复制的内容
+    pub copied_by_app: Option<String>,
+    /// 请求开启/关闭累加剪贴板
+    pub request_toggle_accumulation: bool,
+    /// 请求把累加的内容写入日志
+    pub request_flush_accumulation: bool,
+    /// 设置页面修改了配置，请求主程序重新应用
+    pub request_reload_settings: bool,
+    /// 进行中的专注时段，为空表示未开始
+    pub focus: Option<FocusSession>,
+    /// 请求开始/结束专注时段
+    pub request_toggle_focus: bool,
+    /// 请求暂停/继续专注时段的计时
+    pub request_pause_focus: bool,
+    /// 托盘请求显示窗口（隐藏到托盘或最小化后）
+    pub request_show: bool,
+    /// 界面的上下文：主程序设置请求后用它唤醒界面，界面创建后设置
+    pub repaint: Option<egui::Context>,
 }
 
 impl Default for SharedGuiState {
@@ -60,6 +255,29 @@ impl Default for SharedGuiState {
             today_chars: 0,
             request_new_segment: false,
             request_open_log: false,
+            request_excerpt: None,
+            notice: None,
+            dropped_events: 0,
+            write_busy: false,
+            capture_pending: false,
+            remote_session: false,
+            hook_lost: false,
+            hook_restores: 0,
+            safe_mode: false,
+            paste_progress: None,
+            log_stats: FileStats::default(),
+            left_window_at: None,
+            last_input_at: None,
+            accumulator: None,
+            copied_by_app: None,
+            request_toggle_accumulation: false,
+            request_flush_accumulation: false,
+            request_reload_settings: false,
+            focus: None,
+            request_toggle_focus: false,
+            request_pause_focus: false,
+            request_show: false,
+            repaint: None,
         }
     }
 }
@@ -70,11 +288,61 @@ impl Default for EchoKeyApp {
             current_page: Page::Status,
             is_paused: false,
             today_chars: 0,
+            previous_chars: None,
+            goal_celebrated_on: None,
+            dropped_events: 0,
+            write_busy: false,
+            capture_pending: false,
+            remote_session: false,
+            hook_lost: false,
+            hook_restores: 0,
+            safe_mode: false,
+            paste_progress: None,
+            log_stats: FileStats::default(),
+            last_input_at: None,
+            accumulator: None,
+            focus: None,
             log_directory: PathBuf::new(),
             autostart_enabled: autostart::is_enabled(),
             search_query: String::new(),
+            file_filter: String::new(),
             log_content: String::new(),
+            log_has_sequences: false,
+            log_lines: WrappedLines::default(),
+            seek_input: String::new(),
+            viewing_path: None,
+            archive_writable: false,
+            pending_delete: None,
+            rename_input: None,
+            group_by_app: false,
+            collapse_short_lines: false,
+            hide_sequences: false,
+            collapse_min_chars: config::DEFAULT_COLLAPSE_MIN_CHARS,
+            diff_from: None,
+            diff_to: None,
+            diff_lines: None,
+            search_results: None,
+            search_task: None,
+            live_tail: LogTail::new(),
+            last_tail_poll: None,
+            preview_selection: String::new(),
             status_message: None,
+            config: config::Config::default(),
+            font_path_input: String::new(),
+            advanced_draft: None,
+            capturing_shortcut: None,
+            unlocked: true,
+            passcode_input: String::new(),
+            new_passcode_input: String::new(),
+            failed_unlocks: 0,
+            unlock_blocked_until: None,
+            command_palette: None,
+            stats_from: None,
+            stats_to: None,
+            log_dates: None,
+            restore_geometry: None,
+            window_geometry: None,
+            geometry_changed_at: None,
             shared_state: None,
         }
     }
@@ -86,35 +354,230 @@ impl EchoKeyApp {
         cc: &eframe::CreationContext<'_>,
         log_directory: PathBuf,
         shared_state: Arc<Mutex<SharedGuiState>>,
+        saved_geometry: Option<WindowGeometry>,
     ) -> Self {
-        // 配置 Apple 风格的视觉效果
-        configure_apple_style(&cc.egui_ctx);
+        let config = config::current();
+        
+        // 配置 Apple 风格的视觉效果（安全模式下不加载自定义字体，字体文件有问题时也能启动）
+        let safe_mode = shared_state.lock().is_ok_and(|s| s.safe_mode);
+        let custom_font = config.font_path.as_deref().filter(|_| !safe_mode);
+        install_fonts(&cc.egui_ctx, custom_font);
+        configure_apple_style(&cc.egui_ctx, &config);
+        
+        let font_path_input = config.font_path.as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        
+        let unlocked = !config.has_passcode();
+        
+        if let Ok(mut s) = shared_state.lock() {
+            s.repaint = Some(cc.egui_ctx.clone());
+        }
         
         Self {
             current_page: Page::Status,
             is_paused: false,
             today_chars: 0,
+            previous_chars: None,
+            goal_celebrated_on: None,
+            dropped_events: 0,
+            write_busy: false,
+            capture_pending: false,
+            remote_session: false,
+            hook_lost: false,
+            hook_restores: 0,
+            safe_mode: false,
+            paste_progress: None,
+            log_stats: FileStats::default(),
+            last_input_at: None,
+            accumulator: None,
+            focus: None,
             log_directory,
             autostart_enabled: autostart::is_enabled(),
             search_query: String::new(),
+            file_filter: String::new(),
             log_content: String::new(),
+            log_has_sequences: false,
+            log_lines: WrappedLines::default(),
+            seek_input: String::new(),
+            viewing_path: None,
+            archive_writable: false,
+            pending_delete: None,
+            rename_input: None,
+            group_by_app: false,
+            collapse_short_lines: false,
+            hide_sequences: false,
+            collapse_min_chars: config::DEFAULT_COLLAPSE_MIN_CHARS,
+            diff_from: None,
+            diff_to: None,
+            diff_lines: None,
+            search_results: None,
+            search_task: None,
+            live_tail: LogTail::new(),
+            last_tail_poll: None,
+            preview_selection: String::new(),
             status_message: None,
+            config,
+            font_path_input,
+            advanced_draft: None,
+            capturing_shortcut: None,
+            unlocked,
+            passcode_input: String::new(),
+            new_passcode_input: String::new(),
+            failed_unlocks: 0,
+            unlock_blocked_until: None,
+            command_palette: None,
+            stats_from: None,
+            stats_to: None,
+            log_dates: None,
+            restore_geometry: saved_geometry,
+            window_geometry: None,
+            geometry_changed_at: None,
             shared_state: Some(shared_state),
         }
     }
     
+    /// 恢复上次的窗口位置；窗口大小或位置的变化停止一段时间后保存
+    fn track_window_geometry(&mut self, ctx: &egui::Context) {
+        let (outer, inner, monitor, minimized) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.outer_rect, viewport.inner_rect, viewport.monitor_size, viewport.minimized == Some(true))
+        });
+        
+        // 显示器断开后保存的位置可能在屏幕外，限制在当前（主）显示器范围内再移动
+        if let Some(saved) = self.restore_geometry.take() {
+            if let Some(screen) = monitor {
+                let (x, y) = saved.clamped_position(screen.x, screen.y);
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(x, y)));
+            }
+            return;
+        }
+        
+        let (Some(outer), Some(inner)) = (outer, inner) else {
+            return;
+        };
+        if minimized {
+            return;
+        }
+        let geometry = WindowGeometry {
+            x: outer.min.x,
+            y: outer.min.y,
+            width: inner.width(),
+            height: inner.height(),
+        };
+        if self.window_geometry != Some(geometry) {
+            // 第一次拿到的是刚打开时的位置，不需要保存
+            if self.window_geometry.is_some() {
+                self.geometry_changed_at = Some(std::time::Instant::now());
+            }
+            self.window_geometry = Some(geometry);
+        }
+        if self.geometry_changed_at.is_some_and(|at| at.elapsed() >= GEOMETRY_SAVE_DELAY) {
+            self.geometry_changed_at = None;
+            window_state::save(&geometry);
+        }
+    }
+    
+    /// 今日输入刚达到每日目标时提示一次（启动时已经达到的不提示）
+    fn check_daily_goal(&mut self) {
+        let previous = self.previous_chars.replace(self.today_chars);
+        let goal = self.config.daily_goal;
+        if goal == 0 || !self.config.daily_goal_notify {
+            return;
+        }
+        let today = self.config.log_timezone().now().date_naive();
+        let reached = previous.is_some_and(|p| p < goal) && self.today_chars >= goal;
+        if reached && self.goal_celebrated_on != Some(today) {
+            self.goal_celebrated_on = Some(today);
+            self.show_message(&i18n::t_with("status.goal_reached", goal));
+        }
+    }
+    
     /// 显示状态消息
     fn show_message(&mut self, msg: &str) {
         self.status_message = Some((msg.to_string(), std::time::Instant::now()));
     }
     
+    /// 保存配置，失败时在状态栏提示
+    fn save_config(&mut self) {
+        // 以已保存的配置为准，放弃高级设置中尚未应用的修改
+        self.advanced_draft = None;
+        self.capturing_shortcut = None;
+        let new_config = self.config.clone();
+        if let Err(e) = config::update(|c| *c = new_config) {
+            self.show_message(&e);
+        }
+    }
+    
+    /// 尝试用输入的密码解锁，连续失败后按指数增长限制尝试频率
+    fn try_unlock(&mut self) {
+        let now = std::time::Instant::now();
+        if self.unlock_blocked_until.is_some_and(|until| now < until) {
+            return;
+        }
+        
+        let passcode = std::mem::take(&mut self.passcode_input);
+        if self.config.verify_passcode(&passcode) {
+            self.unlocked = true;
+            self.failed_unlocks = 0;
+            self.unlock_blocked_until = None;
+            return;
+        }
+        
+        self.failed_unlocks += 1;
+        if self.failed_unlocks >= FREE_UNLOCK_ATTEMPTS {
+            let exponent = (self.failed_unlocks - FREE_UNLOCK_ATTEMPTS).min(10);
+            let delay = (5u64 << exponent).min(MAX_UNLOCK_DELAY_SECS);
+            self.unlock_blocked_until = Some(now + std::time::Duration::from_secs(delay));
+        }
+        self.show_message(i18n::t("lock.wrong"));
+    }
+    
+    /// 渲染解锁提示（历史页面和实时预览在输入密码前不显示内容）
+    fn render_lock_prompt(&mut self, ui: &mut egui::Ui) {
+        let remaining = self.unlock_blocked_until
+            .map(|until| until.saturating_duration_since(std::time::Instant::now()))
+            .filter(|d| !d.is_zero());
+        
+        egui::Frame::none()
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(20.0))
+            .show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(egui::RichText::new(i18n::t("lock.title")).size(scaled(ui, 18.0)).strong());
+                    ui.add_space(12.0);
+                    
+                    let response = ui.add_enabled(remaining.is_none(), egui::TextEdit::singleline(&mut self.passcode_input)
+                        .password(true)
+                        .hint_text(i18n::t("lock.hint"))
+                        .desired_width(200.0));
+                    ui.add_space(8.0);
+                    
+                    let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.add_enabled(remaining.is_none(), egui::Button::new(i18n::t("lock.unlock"))).clicked() || submitted {
+                        self.try_unlock();
+                    }
+                    
+                    if let Some(remaining) = remaining {
+                        ui.add_space(8.0);
+                        ui.label(egui::RichText::new(i18n::t_with("lock.rate_limited", remaining.as_secs() + 1))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).error));
+                    }
+                });
+            });
+    }
+    
     /// 渲染状态页面
     fn render_status_page(&mut self, ui: &mut egui::Ui) {
         ui.add_space(20.0);
         
         // 状态卡片
         egui::Frame::none()
-            .fill(egui::Color32::WHITE)
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
             .rounding(egui::Rounding::same(16.0))
             .inner_margin(egui::Margin::same(24.0))
             .shadow(egui::epaint::Shadow {
@@ -126,54 +589,146 @@ impl EchoKeyApp {
             .show(ui, |ui| {
                 ui.vertical_centered(|ui| {
                     // 状态图标
-                    let status_color = if self.is_paused {
-                        egui::Color32::from_rgb(142, 142, 147) // SF Gray
+                    let idle = self.is_paused || self.capture_pending || self.safe_mode;
+                    let status_color = if idle {
+                        palette(ui).idle
                     } else {
-                        egui::Color32::from_rgb(52, 199, 89) // SF Green
+                        palette(ui).positive
                     };
                     
-                    let status_text = if self.is_paused { "已暂停" } else { "记录中" };
+                    let status_text = if self.safe_mode {
+                        i18n::t("status.safe_mode")
+                    } else if self.capture_pending {
+                        i18n::t("status.starting")
+                    } else if self.is_paused {
+                        i18n::t("status.paused")
+                    } else {
+                        i18n::t("status.recording")
+                    };
                     
                     // 大圆形状态指示器
                     let (rect, _) = ui.allocate_exact_size(egui::vec2(80.0, 80.0), egui::Sense::hover());
                     ui.painter().circle_filled(rect.center(), 40.0, status_color);
                     
-                    // 动画效果：记录中时显示脉冲
-                    if !self.is_paused {
-                        let time = ui.ctx().input(|i| i.time);
-                        let alpha = ((time * 2.0).sin() * 0.3 + 0.3) as f32;
+                    // 动画效果：最近有输入时显示脉冲，空闲时显示静止的光圈（仍在记录）
+                    if !idle {
+                        let typing = self.last_input_at
+                            .is_some_and(|t| t.elapsed() < self.config.pulse_active_window());
+                        let (radius, alpha) = if typing {
+                            let time = ui.ctx().input(|i| i.time);
+                            (40.0 + (time * 2.0).sin() as f32 * 5.0, ((time * 2.0).sin() * 0.3 + 0.3) as f32)
+                        } else {
+                            (42.0, 0.3)
+                        };
                         ui.painter().circle_filled(
                             rect.center(),
-                            40.0 + (time * 2.0).sin() as f32 * 5.0,
-                            egui::Color32::from_rgba_unmultiplied(52, 199, 89, (alpha * 255.0) as u8),
+                            radius,
+                            egui::Color32::from_rgba_unmultiplied(
+                                status_color.r(), status_color.g(), status_color.b(), (alpha * 255.0) as u8),
                         );
                     }
                     
                     ui.add_space(16.0);
                     
                     ui.label(egui::RichText::new(status_text)
-                        .size(24.0)
+                        .size(scaled(ui, 24.0))
                         .color(status_color)
                         .strong());
                     
                     ui.add_space(24.0);
                     
                     // 今日统计
-                    ui.label(egui::RichText::new("今日输入")
-                        .size(14.0)
-                        .color(egui::Color32::from_rgb(142, 142, 147)));
+                    ui.label(egui::RichText::new(i18n::t("status.today_input"))
+                        .size(scaled(ui, 14.0))
+                        .color(palette(ui).secondary_text));
                     
-                    ui.label(egui::RichText::new(format!("{} 字符", self.today_chars))
-                        .size(36.0)
+                    ui.label(egui::RichText::new(i18n::t_with("status.char_count", self.today_chars))
+                        .size(scaled(ui, 36.0))
                         .strong());
                     
+                    // 每日目标进度环，达成后变为绿色
+                    if let Some(progress) = self.config.goal_progress(self.today_chars) {
+                        ui.add_space(8.0);
+                        let color = if progress >= 1.0 { palette(ui).positive } else { palette(ui).accent };
+                        progress_ring(ui, progress.min(1.0), color);
+                        ui.label(egui::RichText::new(i18n::t_with("status.goal", self.config.daily_goal))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).secondary_text));
+                    }
+                    
+                    // 当前日志文件大小和行数
+                    ui.label(egui::RichText::new(format!("{} · {}",
+                            format_size(self.log_stats.bytes),
+                            i18n::t_with("status.line_count", self.log_stats.lines)))
+                        .size(scaled(ui, 12.0))
+                        .color(palette(ui).secondary_text));
+                    
+                    // 详细记录模式（托盘开启，重启后关闭）
+                    if crate::event::verbose() {
+                        ui.label(egui::RichText::new(i18n::t("status.verbose"))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).warning));
+                    }
+                    
+                    // 安全模式：说明原因和退出方法
+                    if self.safe_mode {
+                        ui.label(egui::RichText::new(i18n::t("status.safe_mode_hint"))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).warning));
+                    }
+                    
+                    // 远程桌面会话：钩子可能收不到部分按键
+                    if self.remote_session {
+                        ui.label(egui::RichText::new(i18n::t("status.remote_session"))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).warning))
+                            .on_hover_text(i18n::t("status.remote_session_hint"));
+                    }
+                    
+                    // 键盘钩子被系统移除：移除期间的输入没有记录
+                    if self.hook_lost {
+                        ui.label(egui::RichText::new(i18n::t("status.hook_lost"))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).warning))
+                            .on_hover_text(i18n::t("status.hook_lost_hint"));
+                    } else if self.hook_restores > 0 {
+                        ui.label(egui::RichText::new(i18n::t_with("status.hook_restores", self.hook_restores))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).secondary_text))
+                            .on_hover_text(i18n::t("status.hook_lost_hint"));
+                    }
+                    
+                    // 诊断：事件通道溢出
+                    if self.dropped_events > 0 {
+                        ui.label(egui::RichText::new(i18n::t_with("status.dropped_events", self.dropped_events))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).warning));
+                    }
+                    
+                    // 写入积压：输入没有丢失，只是稍后写入
+                    if self.write_busy {
+                        ui.label(egui::RichText::new(i18n::t("status.write_busy"))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).secondary_text))
+                            .on_hover_text(i18n::t("status.write_busy_hint"));
+                    }
+                    
+                    // 大段粘贴的写入进度
+                    if let Some((done, total)) = self.paste_progress {
+                        ui.add_space(8.0);
+                        let fraction = done as f32 / total.max(1) as f32;
+                        ui.add(egui::ProgressBar::new(fraction)
+                            .desired_width(200.0)
+                            .text(i18n::t_with("status.paste_progress", format!("{:.0}%", fraction * 100.0))));
+                    }
+                    
                     ui.add_space(16.0);
                     
                     // 当前时间
-                    let now = Local::now();
-                    ui.label(egui::RichText::new(now.format("%Y年%m月%d日 %H:%M").to_string())
-                        .size(14.0)
-                        .color(egui::Color32::from_rgb(142, 142, 147)));
+                    let now = self.config.log_timezone().now();
+                    ui.label(egui::RichText::new(now.format(i18n::t("status.date_format")).to_string())
+                        .size(scaled(ui, 14.0))
+                        .color(palette(ui).secondary_text));
                 });
             });
         
@@ -184,23 +739,18 @@ impl EchoKeyApp {
             let button_width = (ui.available_width() - 16.0) / 2.0;
             
             // 暂停/恢复按钮
-            let pause_text = if self.is_paused { "▶ 恢复" } else { "⏸ 暂停" };
+            let pause_text = if self.is_paused { i18n::t("status.resume") } else { i18n::t("status.pause") };
             if ui.add_sized(
                 egui::vec2(button_width, 44.0),
-                egui::Button::new(egui::RichText::new(pause_text).size(16.0))
+                egui::Button::new(egui::RichText::new(pause_text).size(scaled(ui, 16.0)).color(palette(ui).on_accent))
                     .fill(if self.is_paused {
-                        egui::Color32::from_rgb(52, 199, 89)
+                        palette(ui).positive
                     } else {
-                        egui::Color32::from_rgb(255, 149, 0)
+                        palette(ui).caution
                     })
                     .rounding(egui::Rounding::same(10.0))
             ).clicked() {
-                self.is_paused = !self.is_paused;
-                if let Some(ref state) = self.shared_state {
-                    if let Ok(mut s) = state.lock() {
-                        s.paused = self.is_paused;
-                    }
-                }
+                self.toggle_pause();
             }
             
             ui.add_space(16.0);
@@ -208,8 +758,8 @@ impl EchoKeyApp {
             // 新建日志段按钮
             if ui.add_sized(
                 egui::vec2(button_width, 44.0),
-                egui::Button::new(egui::RichText::new("📝 新日志段").size(16.0))
-                    .fill(egui::Color32::from_rgb(0, 122, 255))
+                egui::Button::new(egui::RichText::new(i18n::t("status.new_segment")).size(scaled(ui, 16.0)).color(palette(ui).on_accent))
+                    .fill(palette(ui).accent)
                     .rounding(egui::Rounding::same(10.0))
             ).clicked() {
                 if let Some(ref state) = self.shared_state {
@@ -217,7 +767,6 @@ impl EchoKeyApp {
                         s.request_new_segment = true;
                     }
                 }
-                self.show_message("已创建新日志段");
             }
         });
         
@@ -226,13 +775,25 @@ impl EchoKeyApp {
         // 打开日志目录按钮
         if ui.add_sized(
             egui::vec2(ui.available_width(), 44.0),
-            egui::Button::new(egui::RichText::new("📂 打开日志目录").size(16.0))
-                .fill(egui::Color32::from_rgb(88, 86, 214))
+            egui::Button::new(egui::RichText::new(i18n::t("status.open_log_dir")).size(scaled(ui, 16.0)).color(palette(ui).on_accent))
+                .fill(palette(ui).accent_alt)
                 .rounding(egui::Rounding::same(10.0))
         ).clicked() {
             open_directory(&self.log_directory);
         }
         
+        ui.add_space(12.0);
+        
+        self.render_focus(ui);
+        
+        ui.add_space(12.0);
+        
+        self.render_accumulation(ui);
+        
+        ui.add_space(12.0);
+        
+        self.render_live_preview(ui);
+        
         // 状态消息
         if let Some((msg, time)) = &self.status_message {
             if time.elapsed().as_secs() < 3 {
@@ -240,17 +801,209 @@ impl EchoKeyApp {
                 ui.horizontal(|ui| {
                     ui.add_space((ui.available_width() - 200.0) / 2.0);
                     egui::Frame::none()
-                        .fill(egui::Color32::from_rgb(52, 199, 89))
+                        .fill(palette(ui).positive)
                         .rounding(egui::Rounding::same(8.0))
                         .inner_margin(egui::Margin::symmetric(16.0, 8.0))
                         .show(ui, |ui| {
-                            ui.label(egui::RichText::new(msg).color(egui::Color32::WHITE));
+                            ui.label(egui::RichText::new(msg).color(palette(ui).on_accent));
                         });
                 });
             }
         }
     }
     
+    /// 专注时段：开始按钮，或者倒计时和暂停/结束按钮
+    fn render_focus(&mut self, ui: &mut egui::Ui) {
+        let Some(session) = self.focus.clone() else {
+            if ui.button(i18n::t_with("focus.start", self.config.focus_minutes)).clicked() {
+                self.request(|s| s.request_toggle_focus = true);
+            }
+            return;
+        };
+        
+        let now = std::time::Instant::now();
+        let remaining = session.remaining(now).as_secs();
+        egui::Frame::none()
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let color = if session.is_paused() { palette(ui).idle } else { palette(ui).accent };
+                    progress_ring(ui, session.progress(now), color);
+                    ui.vertical(|ui| {
+                        let title = if session.is_paused() { i18n::t("focus.paused") } else { i18n::t("focus.running") };
+                        ui.label(egui::RichText::new(title)
+                            .size(scaled(ui, 14.0))
+                            .color(palette(ui).secondary_text));
+                        ui.label(egui::RichText::new(format!("{:02}:{:02}", remaining / 60, remaining % 60))
+                            .size(scaled(ui, 28.0))
+                            .strong());
+                        ui.label(egui::RichText::new(i18n::t_with("focus.chars", session.chars_typed(self.today_chars)))
+                            .size(scaled(ui, 12.0))
+                            .color(palette(ui).secondary_text));
+                    });
+                });
+                
+                ui.horizontal(|ui| {
+                    let pause_text = if session.is_paused() { i18n::t("focus.resume") } else { i18n::t("focus.pause") };
+                    if ui.button(pause_text).clicked() {
+                        self.request(|s| s.request_pause_focus = true);
+                    }
+                    if ui.button(i18n::t("focus.stop")).clicked() {
+                        self.request(|s| s.request_toggle_focus = true);
+                    }
+                });
+            });
+        
+        // 倒计时每秒刷新
+        if !session.is_paused() {
+            ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    }
+    
+    /// 累加剪贴板：开启按钮，或者已累加的内容和操作
+    fn render_accumulation(&mut self, ui: &mut egui::Ui) {
+        let Some(accumulator) = self.accumulator.clone() else {
+            if ui.button(i18n::t("accumulate.start")).clicked() {
+                self.request(|s| s.request_toggle_accumulation = true);
+            }
+            return;
+        };
+        
+        egui::Frame::none()
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(i18n::t_with("accumulate.title", accumulator.len()))
+                    .size(scaled(ui, 14.0))
+                    .color(palette(ui).secondary_text));
+                
+                if accumulator.skipped() > 0 {
+                    ui.label(egui::RichText::new(i18n::t_with("accumulate.full", accumulator.skipped()))
+                        .size(scaled(ui, 12.0))
+                        .color(palette(ui).warning));
+                }
+                
+                egui::ScrollArea::vertical()
+                    .id_salt("accumulation")
+                    .max_height(120.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        let mut text = accumulator.combined();
+                        ui.add(egui::TextEdit::multiline(&mut text)
+                            .hint_text(i18n::t_with("accumulate.hint", clipboard::MAX_ACCUMULATED_CHARS))
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY));
+                    });
+                
+                ui.horizontal(|ui| {
+                    let has_content = !accumulator.is_empty();
+                    if ui.add_enabled(has_content, egui::Button::new(i18n::t("accumulate.write"))).clicked() {
+                        self.request(|s| s.request_flush_accumulation = true);
+                        self.show_message(i18n::t("accumulate.written"));
+                    }
+                    if ui.add_enabled(has_content, egui::Button::new(i18n::t("preview.copy"))).clicked() {
+                        self.copy_text(ui, accumulator.combined().to_string());
+                        self.show_message(i18n::t("common.copied"));
+                    }
+                    if ui.button(i18n::t("accumulate.stop")).clicked() {
+                        self.request(|s| s.request_toggle_accumulation = true);
+                    }
+                });
+            });
+    }
+    
+    /// 切换暂停/恢复
+    fn toggle_pause(&mut self) {
+        self.is_paused = !self.is_paused;
+        let paused = self.is_paused;
+        self.request(|s| s.paused = paused);
+    }
+    
+    /// 修改共享状态（向业务逻辑线程发送请求）
+    fn request(&self, f: impl FnOnce(&mut SharedGuiState)) {
+        if let Some(ref state) = self.shared_state {
+            if let Ok(mut s) = state.lock() {
+                f(&mut s);
+            }
+        }
+    }
+    
+    /// 复制文本到剪贴板，并告诉业务逻辑线程这是 EchoKey 自己复制的
+    /// （累加剪贴板、剪贴板变化记录不把它当作新复制的内容）
+    fn copy_text(&self, ui: &egui::Ui, text: String) {
+        self.request(|s| s.copied_by_app = Some(text.clone()));
+        ui.output_mut(|o| o.copied_text = text);
+    }
+    
+    /// 渲染实时预览：显示最近输入的内容，可选中片段摘录
+    fn render_live_preview(&mut self, ui: &mut egui::Ui) {
+        if !self.unlocked {
+            self.render_lock_prompt(ui);
+            return;
+        }
+        
+        // 每 500ms 增量读取一次当前日志
+        let due = self.last_tail_poll
+            .is_none_or(|t| t.elapsed() >= std::time::Duration::from_millis(500));
+        if due {
+            self.last_tail_poll = Some(std::time::Instant::now());
+            if let Some(path) = logger::latest_log_path(self.today()) {
+                let _ = self.live_tail.poll(&path);
+            }
+        }
+        
+        egui::Frame::none()
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(i18n::t("preview.title"))
+                    .size(scaled(ui, 14.0))
+                    .color(palette(ui).secondary_text));
+                
+                egui::ScrollArea::vertical()
+                    .max_height(120.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        // &str 作为只读缓冲：可以选中文字但不能编辑
+                        let mut text = self.live_tail.content();
+                        let output = egui::TextEdit::multiline(&mut text)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY)
+                            .show(ui);
+                        // 取消选中后清空摘录；失去焦点（例如点击下方按钮）时保留
+                        if let Some(range) = output.cursor_range {
+                            let selected = range.slice_str(self.live_tail.content());
+                            self.preview_selection = logger::strip_timestamps(selected);
+                        }
+                    });
+                
+                ui.horizontal(|ui| {
+                    let has_selection = !self.preview_selection.is_empty();
+                    // 暂停记录时不写入摘录
+                    let mut excerpt = ui.add_enabled(has_selection && !self.is_paused, egui::Button::new(i18n::t("preview.excerpt")));
+                    if self.is_paused {
+                        excerpt = excerpt.on_disabled_hover_text(i18n::t("preview.excerpt_paused"));
+                    }
+                    if excerpt.clicked() {
+                        let excerpt = std::mem::take(&mut self.preview_selection);
+                        self.request(|s| s.request_excerpt = Some(excerpt));
+                        self.show_message(i18n::t("preview.excerpt_written"));
+                    }
+                    if ui.add_enabled(has_selection, egui::Button::new(i18n::t("preview.copy"))).clicked() {
+                        self.copy_text(ui, self.preview_selection.clone());
+                        self.show_message(i18n::t("common.copied"));
+                    }
+                });
+            });
+    }
+    
     /// 渲染历史页面
     fn render_history_page(&mut self, ui: &mut egui::Ui) {
         ui.add_space(12.0);
@@ -260,203 +1013,1415 @@ impl EchoKeyApp {
             let response = ui.add_sized(
                 egui::vec2(ui.available_width() - 80.0, 36.0),
                 egui::TextEdit::singleline(&mut self.search_query)
-                    .hint_text("🔍 搜索日志内容...")
+                    .hint_text(i18n::t("history.search_hint"))
             );
             
             if ui.add_sized(
                 egui::vec2(72.0, 36.0),
-                egui::Button::new("搜索")
-                    .fill(egui::Color32::from_rgb(0, 122, 255))
+                egui::Button::new(egui::RichText::new(i18n::t("history.search")).color(palette(ui).on_accent))
+                    .fill(palette(ui).accent)
             ).clicked() || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
-                self.load_log_content();
+                self.run_search();
             }
         });
         
         ui.add_space(12.0);
+        self.poll_search(ui.ctx());
         
         // 日志列表/内容区
         egui::Frame::none()
-            .fill(egui::Color32::WHITE)
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
             .rounding(egui::Rounding::same(12.0))
             .inner_margin(egui::Margin::same(16.0))
             .show(ui, |ui| {
-                if self.log_content.is_empty() {
+                if self.diff_lines.is_some() {
+                    self.render_diff_view(ui);
+                } else if self.search_task.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(i18n::t("history.searching"));
+                    });
+                } else if self.search_results.is_some() {
+                    self.render_search_results(ui);
+                } else if self.log_content.is_empty() {
                     // 显示日志文件列表
-                    ui.label(egui::RichText::new("最近日志")
-                        .size(16.0)
+                    ui.label(egui::RichText::new(i18n::t("history.recent"))
+                        .size(scaled(ui, 16.0))
                         .strong());
                     ui.add_space(8.0);
                     
+                    // 按文件名/日期筛选
+                    ui.add(egui::TextEdit::singleline(&mut self.file_filter)
+                        .hint_text(i18n::t("history.filter_hint"))
+                        .desired_width(f32::INFINITY));
+                    ui.add_space(8.0);
+                    
                     if let Ok(entries) = std::fs::read_dir(&self.log_directory) {
                         let mut files: Vec<_> = entries
                             .filter_map(|e| e.ok())
-                            .filter(|e| e.path().extension().map_or(false, |ext| ext == "log"))
+                            .filter(|e| {
+                                let name = e.file_name().to_string_lossy().to_string();
+                                name.ends_with(".log") || name.ends_with(".log.gz")
+                            })
                             .collect();
                         
-                        files.sort_by(|a, b| b.path().cmp(&a.path()));
+                        let total = files.len();
+                        let filtering = !self.file_filter.trim().is_empty();
+                        if filtering {
+                            files.retain(|e| logger::fuzzy_match(&self.file_filter, &e.file_name().to_string_lossy()));
+                            ui.label(egui::RichText::new(i18n::t_with("history.filter_count", files.len()))
+                                .size(scaled(ui, 12.0))
+                                .color(palette(ui).secondary_text));
+                        }
+                        
+                        // 按程序分文件时可以按程序分组，默认文件排在最前
+                        let app_of = |e: &std::fs::DirEntry| {
+                            logger::parse_log_file_app(&e.file_name().to_string_lossy()).map(str::to_string)
+                        };
+                        let has_apps = files.iter().any(|e| app_of(e).is_some());
+                        if has_apps {
+                            ui.checkbox(&mut self.group_by_app, i18n::t("history.group_by_app"));
+                        }
+                        let grouping = has_apps && self.group_by_app;
+                        if grouping {
+                            files.sort_by_cached_key(|e| (app_of(e), std::cmp::Reverse(e.path())));
+                        } else {
+                            files.sort_by_key(|e| std::cmp::Reverse(e.path()));
+                        }
                         
-                        for entry in files.iter().take(10) {
-                            let path = entry.path();
-                            let name = path.file_name()
-                                .map(|n| n.to_string_lossy().to_string())
-                                .unwrap_or_default();
-                            
-                            if ui.add(egui::Button::new(&name)
-                                .frame(false)
-                            ).clicked() {
-                                if let Ok(content) = std::fs::read_to_string(&path) {
-                                    self.log_content = content;
+                        // 未筛选时只显示最近 10 个（分组时每个程序最近 10 个），筛选时显示全部匹配
+                        let shown = if filtering { files.len() } else { 10 };
+                        egui::ScrollArea::vertical()
+                            .max_height(320.0)
+                            .show(ui, |ui| {
+                                let mut current_app: Option<Option<String>> = None;
+                                let mut in_group = 0;
+                                for entry in &files {
+                                    let app = if grouping { app_of(entry) } else { None };
+                                    if current_app.as_ref() != Some(&app) {
+                                        if grouping {
+                                            let title = app.as_deref().unwrap_or(i18n::t("history.default_app"));
+                                            ui.add_space(4.0);
+                                            ui.label(egui::RichText::new(title)
+                                                .size(scaled(ui, 13.0))
+                                                .strong()
+                                                .color(palette(ui).secondary_text));
+                                        }
+                                        current_app = Some(app);
+                                        in_group = 0;
+                                    }
+                                    in_group += 1;
+                                    if in_group > shown {
+                                        continue;
+                                    }
+                                    let path = entry.path();
+                                    let name = path.file_name()
+                                        .map(|n| n.to_string_lossy().to_string())
+                                        .unwrap_or_default();
+                                    
+                                    if ui.add(egui::Button::new(&name)
+                                        .frame(false)
+                                    ).clicked() {
+                                        self.open_log_file(path);
+                                    }
+                                    ui.add_space(4.0);
                                 }
-                            }
-                            ui.add_space(4.0);
-                        }
+                            });
                         
-                        if files.is_empty() {
-                            ui.label(egui::RichText::new("暂无日志文件")
-                                .color(egui::Color32::from_rgb(142, 142, 147)));
+                        if total == 0 {
+                            ui.label(egui::RichText::new(i18n::t("history.empty"))
+                                .color(palette(ui).secondary_text));
                         }
                     }
+                    
+                    ui.add_space(8.0);
+                    ui.separator();
+                    ui.add_space(8.0);
+                    self.render_diff_picker(ui);
                 } else {
                     // 显示日志内容
+                    let today = self.today();
+                    let archived = self.viewing_path.as_deref().is_some_and(|p| is_archived(p, today));
                     ui.horizontal(|ui| {
-                        if ui.button("← 返回").clicked() {
-                            self.log_content.clear();
+                        if ui.button(i18n::t("history.back")).clicked() {
+                            self.close_log_file();
+                        }
+                        if archived {
+                            ui.add_space(8.0);
+                            egui::Frame::none()
+                                .fill(palette(ui).control_fill)
+                                .stroke(palette(ui).card_border)
+                                .rounding(egui::Rounding::same(6.0))
+                                .inner_margin(egui::Margin::symmetric(8.0, 2.0))
+                                .show(ui, |ui| {
+                                    ui.label(egui::RichText::new(i18n::t("history.archived_badge"))
+                                        .size(scaled(ui, 12.0))
+                                        .color(palette(ui).text));
+                                });
+                        }
+                        ui.add_space(8.0);
+                        if ui.button(i18n::t("history.copy_all")).clicked() {
+                            self.copy_text(ui, self.log_content.clone());
+                            self.show_message(i18n::t("common.copied"));
                         }
                         ui.add_space(8.0);
-                        if ui.button("📋 复制全部").clicked() {
-                            ui.output_mut(|o| o.copied_text = self.log_content.clone());
-                            self.show_message("已复制到剪贴板");
+                        let mut options_changed = ui.checkbox(&mut self.collapse_short_lines, i18n::t("history.collapse")).changed();
+                        if self.log_has_sequences {
+                            options_changed |= ui.checkbox(&mut self.hide_sequences, i18n::t("history.hide_sequences")).changed();
+                        }
+                        if self.collapse_short_lines {
+                            options_changed |= ui.add(egui::DragValue::new(&mut self.collapse_min_chars)
+                                .range(1..=200)
+                                .suffix(i18n::t("history.chars_suffix")))
+                                .changed();
                         }
+                        if options_changed {
+                            self.log_lines.invalidate();
+                        }
+                        ui.add_space(8.0);
+                        self.render_seek_control(ui);
                     });
                     
+                    // 文件操作：归档日志默认只读，需要先解除只读；今天的日志正在写入，不能删除或改名
+                    if let Some(path) = self.viewing_path.clone() {
+                        ui.horizontal(|ui| {
+                            if archived {
+                                ui.checkbox(&mut self.archive_writable, i18n::t("history.allow_changes"));
+                            }
+                            let in_use = logger::is_active_log(&path, today);
+                            let writable = !in_use && (!archived || self.archive_writable);
+                            if ui.add_enabled(writable, egui::Button::new(i18n::t("history.delete")))
+                                .on_disabled_hover_text(i18n::t(if in_use { "history.in_use" } else { "history.read_only" }))
+                                .clicked()
+                            {
+                                self.pending_delete = Some(path.clone());
+                            }
+                            if ui.add_enabled(writable && archived, egui::Button::new(i18n::t("history.rename")))
+                                .on_disabled_hover_text(i18n::t(if in_use { "history.in_use" } else { "history.read_only" }))
+                                .clicked()
+                            {
+                                let stem = path.file_stem()
+                                    .map(|s| s.to_string_lossy().to_string())
+                                    .unwrap_or_default();
+                                self.rename_input = Some(stem);
+                            }
+                            let date = path.file_name()
+                                .and_then(|name| logger::parse_log_file_date(&name.to_string_lossy()));
+                            if ui.add_enabled(date.is_some(), egui::Button::new(i18n::t("history.export_pdf"))).clicked() {
+                                if let Some(date) = date {
+                                    self.export_pdf(date);
+                                }
+                            }
+                        });
+                    }
+                    
                     ui.add_space(8.0);
                     
-                    egui::ScrollArea::vertical()
-                        .auto_shrink([false; 2])
-                        .show(ui, |ui| {
-                            let content = if self.search_query.is_empty() {
-                                self.log_content.clone()
-                            } else {
-                                // 高亮搜索结果
-                                self.log_content.clone()
-                            };
-                            ui.add(egui::Label::new(
-                                egui::RichText::new(&content)
-                                    .monospace()
-                                    .size(12.0)
-                            ).wrap());
-                        });
+                    // 高亮搜索结果
+                    let (collapse, min_chars, hide_sequences) = (self.collapse_short_lines, self.collapse_min_chars, self.hide_sequences);
+                    let log_content = &self.log_content;
+                    self.log_lines.show(ui, self.search_query.trim(), || {
+                        let content = if collapse {
+                            logger::collapse_timestamps(log_content, min_chars)
+                        } else {
+                            log_content.clone()
+                        };
+                        if hide_sequences {
+                            logger::strip_sequences(&content)
+                        } else {
+                            content
+                        }
+                    });
                 }
             });
+        
+        self.render_file_action_dialogs(ui.ctx());
     }
     
-    /// 渲染设置页面
-    fn render_settings_page(&mut self, ui: &mut egui::Ui) {
-        ui.add_space(12.0);
+    /// 跳转到时刻：滚动到该时刻的时间戳行（没有恰好的时间戳时跳到之前最近的一行）
+    fn render_seek_control(&mut self, ui: &mut egui::Ui) {
+        let response = ui.add(egui::TextEdit::singleline(&mut self.seek_input)
+            .hint_text(i18n::t("history.seek_hint"))
+            .desired_width(64.0));
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if !(ui.button(i18n::t("history.seek")).clicked() || submitted) {
+            return;
+        }
         
-        egui::Frame::none()
-            .fill(egui::Color32::WHITE)
-            .rounding(egui::Rounding::same(12.0))
-            .inner_margin(egui::Margin::same(20.0))
-            .show(ui, |ui| {
-                ui.label(egui::RichText::new("通用设置").size(18.0).strong());
-                ui.add_space(16.0);
-                
-                // 开机自启动
-                ui.horizontal(|ui| {
-                    ui.label("开机自启动");
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        let mut enabled = self.autostart_enabled;
-                        if ui.add(toggle_switch(&mut enabled)).changed() {
-                            if enabled {
-                                if autostart::enable().is_ok() {
-                                    self.autostart_enabled = true;
-                                    self.show_message("已启用开机自启动");
+        let input = self.seek_input.trim();
+        let time = chrono::NaiveTime::parse_from_str(input, "%H:%M")
+            .or_else(|_| chrono::NaiveTime::parse_from_str(input, "%H:%M:%S"));
+        match time {
+            Ok(time) => self.log_lines.seek = Some(time),
+            Err(_) => self.show_message(i18n::t("history.seek_invalid")),
+        }
+    }
+    
+    /// 替换正在查看的日志内容
+    fn set_log_content(&mut self, content: String) {
+        self.log_has_sequences = content.lines().any(|line| logger::split_sequence(line).is_some());
+        self.log_content = content;
+        self.log_lines.invalidate();
+    }
+    
+    /// 打开日志文件查看（归档日志重新进入只读状态）
+    fn open_log_file(&mut self, path: PathBuf) {
+        if let Ok(content) = logger::read_log_file(&path) {
+            self.set_log_content(content);
+            self.viewing_path = Some(path);
+            self.archive_writable = false;
+        }
+    }
+    
+    /// 关闭正在查看的日志文件
+    fn close_log_file(&mut self) {
+        self.set_log_content(String::new());
+        self.viewing_path = None;
+        self.archive_writable = false;
+    }
+    
+    /// 渲染删除、重命名的确认窗口
+    fn render_file_action_dialogs(&mut self, ctx: &egui::Context) {
+        if let Some(path) = self.pending_delete.clone() {
+            let name = path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            egui::Window::new(i18n::t("history.delete_title"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .show(ctx, |ui| {
+                    ui.label(i18n::t_with("history.delete_confirm", &name));
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        if ui.button(i18n::t("history.delete")).clicked() {
+                            self.pending_delete = None;
+                            self.log_dates = None;
+                            match logger::delete_log(path.clone()) {
+                                Ok(trashed) => {
+                                    self.close_log_file();
+                                    self.show_message(i18n::t(if trashed { "history.trashed" } else { "history.deleted" }));
                                 }
-                            } else {
-                                if autostart::disable().is_ok() {
-                                    self.autostart_enabled = false;
-                                    self.show_message("已禁用开机自启动");
+                                Err(e) => self.show_message(&e.to_string()),
+                            }
+                        }
+                        if ui.button(i18n::t("history.cancel")).clicked() {
+                            self.pending_delete = None;
+                        }
+                    });
+                });
+        }
+        
+        if let Some(mut new_name) = self.rename_input.take() {
+            let mut keep_open = true;
+            egui::Window::new(i18n::t("history.rename_title"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .show(ctx, |ui| {
+                    ui.text_edit_singleline(&mut new_name);
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        if ui.button(i18n::t("history.rename")).clicked() {
+                            keep_open = false;
+                            if let Some(path) = self.viewing_path.clone() {
+                                self.log_dates = None;
+                                match logger::rename_log(&path, &new_name) {
+                                    Ok(new_path) => {
+                                        self.viewing_path = Some(new_path);
+                                        self.show_message(i18n::t("history.renamed"));
+                                    }
+                                    Err(e) => self.show_message(&e.to_string()),
                                 }
                             }
                         }
+                        if ui.button(i18n::t("history.cancel")).clicked() {
+                            keep_open = false;
+                        }
                     });
                 });
-                
-                ui.add_space(12.0);
-                ui.separator();
-                ui.add_space(12.0);
-                
-                // 日志目录
-                ui.label("日志存储位置");
-                ui.add_space(4.0);
-                ui.horizontal(|ui| {
-                    let path_str = self.log_directory.to_string_lossy();
-                    ui.add(egui::TextEdit::singleline(&mut path_str.to_string())
+            if keep_open {
+                self.rename_input = Some(new_name);
+            }
+        }
+    }
+    
+    /// 有日志的日期（从新到旧），缓存 [`LOG_DATES_REFRESH`] 后才重新读取日志目录
+    fn log_dates(&mut self) -> Vec<NaiveDate> {
+        match &self.log_dates {
+            Some((read_at, dates)) if read_at.elapsed() < LOG_DATES_REFRESH => dates.clone(),
+            _ => {
+                let dates = logger::list_log_dates();
+                self.log_dates = Some((std::time::Instant::now(), dates.clone()));
+                dates
+            }
+        }
+    }
+    
+    /// 渲染日志对比的日期选择
+    fn render_diff_picker(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new(i18n::t("history.compare_days"))
+            .size(scaled(ui, 16.0))
+            .strong());
+        ui.add_space(4.0);
+        
+        let dates = self.log_dates();
+        let format_date = |date: Option<NaiveDate>| {
+            date.map_or_else(|| i18n::t("history.pick_date").to_string(), |d| d.format("%Y-%m-%d").to_string())
+        };
+        
+        ui.horizontal(|ui| {
+            for (id, selected) in [("diff_from", &mut self.diff_from), ("diff_to", &mut self.diff_to)] {
+                egui::ComboBox::from_id_salt(id)
+                    .selected_text(format_date(*selected))
+                    .show_ui(ui, |ui| {
+                        for date in &dates {
+                            ui.selectable_value(selected, Some(*date), date.format("%Y-%m-%d").to_string());
+                        }
+                    });
+            }
+            
+            let ready = self.diff_from.is_some() && self.diff_to.is_some();
+            if ui.add_enabled(ready, egui::Button::new(i18n::t("history.compare"))).clicked() {
+                if let (Some(from), Some(to)) = (self.diff_from, self.diff_to) {
+                    self.diff_lines = Some(logger::diff_days(from, to));
+                }
+            }
+        });
+    }
+    
+    /// 在后台线程搜索所有日志文件（日志多时要读取、解压很多文件）；搜索词为空时打开今天的日志
+    ///
+    /// 再次搜索时丢弃上一次还没完成的搜索。
+    fn run_search(&mut self) {
+        let query = self.search_query.trim().to_string();
+        self.search_results = None;
+        self.search_task = None;
+        if query.is_empty() {
+            self.load_log_content();
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let dir = self.log_directory.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(logger::search_logs(&dir, &query));
+        });
+        self.search_task = Some(rx);
+    }
+    
+    /// 取回后台搜索的结果，还没完成时稍后重绘再检查
+    fn poll_search(&mut self, ctx: &egui::Context) {
+        let Some(task) = &self.search_task else {
+            return;
+        };
+        match task.try_recv() {
+            Ok(results) => {
+                self.search_results = Some(results);
+                self.search_task = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => ctx.request_repaint_after(std::time::Duration::from_millis(100)),
+            Err(mpsc::TryRecvError::Disconnected) => self.search_task = None,
+        }
+    }
+    
+    /// 渲染搜索结果：匹配总数，以及每个文件名下包含匹配的行（匹配部分黄色背景）
+    fn render_search_results(&mut self, ui: &mut egui::Ui) {
+        if ui.button(i18n::t("history.back")).clicked() {
+            self.search_results = None;
+            return;
+        }
+        
+        ui.add_space(8.0);
+        
+        let Some(results) = &self.search_results else {
+            return;
+        };
+        
+        let total: usize = results.iter().map(|r| r.matches).sum();
+        if total == 0 {
+            ui.label(egui::RichText::new(i18n::t("history.no_matches"))
+                .color(palette(ui).secondary_text));
+            return;
+        }
+        ui.label(egui::RichText::new(i18n::t_with("history.match_count", total)).strong());
+        let shown: usize = results.iter().map(|r| r.lines.len()).sum();
+        if shown == logger::MAX_SEARCH_LINES {
+            ui.label(egui::RichText::new(i18n::t_with("history.search_truncated", shown))
+                .size(scaled(ui, 12.0))
+                .color(palette(ui).warning));
+        }
+        ui.add_space(8.0);
+        
+        let query = self.search_query.trim().to_string();
+        let mut open = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for result in results {
+                    let name = result.path.file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    if ui.add(egui::Button::new(egui::RichText::new(&name).strong()).frame(false)).clicked() {
+                        open = Some(result.path.clone());
+                    }
+                    for line in &result.lines {
+                        ui.add(egui::Label::new(highlight_checkpoints(ui, line, &query)).wrap());
+                    }
+                    ui.add_space(8.0);
+                }
+            });
+        
+        // 打开文件后在文件中继续高亮搜索词
+        if let Some(path) = open {
+            self.search_results = None;
+            self.open_log_file(path);
+        }
+    }
+    
+    /// 渲染日志对比结果：新增行绿色，删除行红色
+    fn render_diff_view(&mut self, ui: &mut egui::Ui) {
+        if ui.button(i18n::t("history.back")).clicked() {
+            self.diff_lines = None;
+            return;
+        }
+        
+        ui.add_space(8.0);
+        
+        let Some(lines) = &self.diff_lines else {
+            return;
+        };
+        
+        if lines.iter().all(|line| matches!(line, logger::DiffLine::Unchanged(_))) {
+            ui.label(egui::RichText::new(i18n::t("history.no_diff"))
+                .color(palette(ui).secondary_text));
+        }
+        
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                let p = palette(ui);
+                let unchanged_color = p.secondary_text;
+                for line in lines {
+                    let (prefix, text, color, background) = match line {
+                        logger::DiffLine::Unchanged(text) => (
+                            "  ", text.as_str(),
+                            unchanged_color,
+                            egui::Color32::TRANSPARENT,
+                        ),
+                        logger::DiffLine::Added(text) => (
+                            "+ ", text.as_str(),
+                            p.diff_added,
+                            p.diff_added_fill,
+                        ),
+                        logger::DiffLine::Removed(text) => (
+                            "- ", text.as_str(),
+                            p.diff_removed,
+                            p.diff_removed_fill,
+                        ),
+                        logger::DiffLine::Truncated { skipped_lines } => {
+                            ui.label(egui::RichText::new(
+                                i18n::t_with("history.diff_truncated", skipped_lines))
+                                .color(palette(ui).warning));
+                            continue;
+                        }
+                    };
+                    ui.add(egui::Label::new(
+                        egui::RichText::new(format!("{}{}", prefix, text))
+                            .monospace()
+                            .size(scaled(ui, 12.0))
+                            .color(color)
+                            .background_color(background)
+                    ).wrap());
+                }
+            });
+    }
+    
+    /// 渲染设置页面
+    fn render_settings_page(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(12.0);
+        
+        egui::Frame::none()
+            .fill(palette(ui).card)
+            .stroke(palette(ui).card_border)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(20.0))
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(i18n::t("settings.general")).size(scaled(ui, 18.0)).strong());
+                ui.add_space(16.0);
+                
+                // 开机自启动
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.autostart"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let mut enabled = self.autostart_enabled;
+                        if ui.add(toggle_switch(&mut enabled)).changed() {
+                            if enabled {
+                                if autostart::enable().is_ok() {
+                                    self.autostart_enabled = true;
+                                    self.show_message(i18n::t("settings.autostart_enabled"));
+                                }
+                            } else {
+                                if autostart::disable().is_ok() {
+                                    self.autostart_enabled = false;
+                                    self.show_message(i18n::t("settings.autostart_disabled"));
+                                }
+                            }
+                        }
+                    });
+                });
+                
+                ui.add_space(8.0);
+                
+                // 窗口置顶（实时生效）
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.always_on_top"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.add(toggle_switch(&mut self.config.always_on_top)).changed() {
+                            ui.ctx().send_viewport_cmd(egui::ViewportCommand::WindowLevel(window_level(&self.config)));
+                            self.save_config();
+                        }
+                    });
+                });
+                
+                ui.add_space(8.0);
+                
+                // 同时输出到标准输出（实时生效）
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.tee_stdout"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.add(toggle_switch(&mut self.config.tee_stdout)).changed() {
+                            logger::set_tee_stdout(self.config.tee_stdout);
+                            self.save_config();
+                        }
+                    });
+                });
+                
+                ui.add_space(12.0);
+                ui.separator();
+                ui.add_space(12.0);
+                
+                // 界面语言（托盘菜单重启后生效）
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.language"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let selected = self.config.language
+                            .map_or(i18n::t("settings.language_auto"), i18n::Locale::native_name);
+                        let mut language = self.config.language;
+                        egui::ComboBox::from_id_salt("language")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut language, None, i18n::t("settings.language_auto"));
+                                for locale in i18n::Locale::ALL {
+                                    ui.selectable_value(&mut language, Some(locale), locale.native_name());
+                                }
+                            });
+                        if language != self.config.language {
+                            self.config.language = language;
+                            i18n::set_locale(language.or_else(i18n::system_locale).unwrap_or_default());
+                            self.save_config();
+                        }
+                    });
+                });
+                
+                ui.add_space(8.0);
+                
+                // 日志标记语言（与界面语言分开，保持日志格式稳定）
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.marker_language"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let mut marker_language = self.config.marker_language;
+                        egui::ComboBox::from_id_salt("marker_language")
+                            .selected_text(marker_language.native_name())
+                            .show_ui(ui, |ui| {
+                                for locale in i18n::Locale::ALL {
+                                    ui.selectable_value(&mut marker_language, locale, locale.native_name());
+                                }
+                            });
+                        if marker_language != self.config.marker_language {
+                            self.config.marker_language = marker_language;
+                            i18n::set_marker_locale(marker_language);
+                            self.save_config();
+                        }
+                    });
+                });
+                
+                ui.add_space(12.0);
+                ui.separator();
+                ui.add_space(12.0);
+                
+                // 界面主题（实时生效）
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.theme"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let mut theme = self.config.theme;
+                        egui::ComboBox::from_id_salt("theme")
+                            .selected_text(theme_name(theme))
+                            .show_ui(ui, |ui| {
+                                for option in [
+                                    config::AppTheme::System,
+                                    config::AppTheme::Light,
+                                    config::AppTheme::Dark,
+                                    config::AppTheme::HighContrast,
+                                ] {
+                                    ui.selectable_value(&mut theme, option, theme_name(option));
+                                }
+                            });
+                        if theme != self.config.theme {
+                            self.config.theme = theme;
+                            configure_apple_style(ui.ctx(), &self.config);
+                            self.save_config();
+                        }
+                    });
+                });
+                
+                ui.add_space(8.0);
+                
+                // 界面字号（实时生效）
+                ui.horizontal(|ui| {
+                    ui.label(i18n::t("settings.font_size"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let response = ui.add(egui::Slider::new(&mut self.config.font_size, config::FONT_SIZE_RANGE)
+                            .step_by(0.5));
+                        if response.changed() {
+                            configure_apple_style(ui.ctx(), &self.config);
+                        }
+                        if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                            self.save_config();
+                        }
+                    });
+                });
+                
+                ui.add_space(8.0);
+                
+                // 自定义中文字体
+                ui.label(i18n::t("settings.font_file"));
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.font_path_input)
+                        .hint_text(i18n::t("settings.font_hint"))
+                        .desired_width(ui.available_width() - 150.0));
+                    if ui.button(i18n::t("settings.browse")).clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Font", &["ttf", "ttc", "otf"])
+                            .pick_file()
+                        {
+                            self.font_path_input = path.to_string_lossy().to_string();
+                        }
+                    }
+                    if ui.button(i18n::t("settings.apply")).clicked() {
+                        let trimmed = self.font_path_input.trim();
+                        let font_path = (!trimmed.is_empty()).then(|| PathBuf::from(trimmed));
+                        // 指定的字体无法加载时不保存（改用了系统字体也算失败）
+                        let installed = install_fonts(ui.ctx(), font_path.as_deref());
+                        if font_path.is_none() || installed == font_path {
+                            self.config.font_path = font_path;
+                            self.save_config();
+                            self.show_message(i18n::t("settings.font_updated"));
+                        } else {
+                            self.show_message(i18n::t("settings.font_failed"));
+                        }
+                    }
+                });
+                
+                ui.add_space(12.0);
+                ui.separator();
+                ui.add_space(12.0);
+                
+                // 访问密码（历史页面和实时预览需要先输入密码）
+                ui.label(i18n::t("settings.passcode"));
+                ui.add_space(4.0);
+                if !self.unlocked {
+                    ui.label(egui::RichText::new(i18n::t("settings.passcode_locked"))
+                        .size(scaled(ui, 12.0))
+                        .color(palette(ui).secondary_text));
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.new_passcode_input)
+                            .password(true)
+                            .hint_text(if self.config.has_passcode() { i18n::t("settings.passcode_new") } else { i18n::t("settings.passcode_set_hint") })
+                            .desired_width(ui.available_width() - 160.0));
+                        if ui.add_enabled(!self.new_passcode_input.is_empty(), egui::Button::new(i18n::t("settings.save"))).clicked() {
+                            let passcode = std::mem::take(&mut self.new_passcode_input);
+                            self.config.set_passcode(Some(&passcode));
+                            self.save_config();
+                            self.show_message(i18n::t("settings.passcode_saved"));
+                        }
+                        if self.config.has_passcode() {
+                            if ui.button(i18n::t("settings.remove")).clicked() {
+                                self.config.set_passcode(None);
+                                self.save_config();
+                                self.show_message(i18n::t("settings.passcode_removed"));
+                            }
+                            if ui.button(i18n::t("settings.lock")).clicked() {
+                                self.unlocked = false;
+                            }
+                        }
+                    });
+                }
+                
+                ui.add_space(12.0);
+                ui.separator();
+                ui.add_space(12.0);
+                
+                // 日志目录
+                ui.label(i18n::t("settings.log_location"));
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    let path_str = self.log_directory.to_string_lossy();
+                    ui.add(egui::TextEdit::singleline(&mut path_str.to_string())
                         .desired_width(ui.available_width() - 80.0)
                         .interactive(false));
-                    if ui.button("打开").clicked() {
+                    if ui.button(i18n::t("settings.open")).clicked() {
                         open_directory(&self.log_directory);
                     }
                 });
                 
+                ui.add_space(12.0);
+                ui.separator();
+                ui.add_space(12.0);
+                
+                self.render_advanced_settings(ui);
+                
+                ui.add_space(12.0);
+                ui.separator();
+                ui.add_space(12.0);
+                
+                self.render_stats_export(ui);
+                
                 ui.add_space(20.0);
-                ui.label(egui::RichText::new("快捷键").size(18.0).strong());
+                ui.label(egui::RichText::new(i18n::t("settings.shortcuts")).size(scaled(ui, 18.0)).strong());
                 ui.add_space(16.0);
                 
-                // 快捷键说明
-                let shortcuts = [
-                    ("Ctrl+Shift+P", "暂停/恢复记录"),
-                    ("Ctrl+Shift+S", "手动保存剪贴板"),
-                    ("Ctrl+Shift+N", "新建日志段"),
-                ];
+                // 快捷键说明（按配置文件中的设置显示）
+                let mut shortcuts: Vec<(String, &str)> = SHORTCUT_ACTIONS
+                .into_iter()
+                .map(|action| {
+                    let key = self.config.shortcuts.chord(action)
+                        .map_or_else(|| i18n::t("shortcut.unbound").to_string(), |chord| chord.name());
+                    (key, action.label())
+                })
+                .collect();
+                shortcuts.push((COMMAND_PALETTE_CHORD.name(), i18n::t("shortcut.command_palette")));
                 
                 for (key, desc) in shortcuts {
                     ui.horizontal(|ui| {
                         egui::Frame::none()
-                            .fill(egui::Color32::from_rgb(229, 229, 234))
+                            .fill(palette(ui).control_fill)
+                            .stroke(palette(ui).card_border)
                             .rounding(egui::Rounding::same(6.0))
                             .inner_margin(egui::Margin::symmetric(8.0, 4.0))
                             .show(ui, |ui| {
-                                ui.label(egui::RichText::new(key).monospace().size(12.0));
+                                ui.label(egui::RichText::new(key).monospace().size(scaled(ui, 12.0)));
                             });
                         ui.add_space(12.0);
                         ui.label(desc);
                     });
                     ui.add_space(8.0);
                 }
+                // 配置文件中的快捷键有问题（无法识别、多个动作使用同一组合）时提示
+                for problem in self.config.shortcuts.resolve().1 {
+                    ui.label(egui::RichText::new(problem)
+                        .size(scaled(ui, 12.0))
+                        .color(palette(ui).warning));
+                }
                 
                 ui.add_space(20.0);
-                ui.label(egui::RichText::new("关于").size(18.0).strong());
+                ui.label(egui::RichText::new(i18n::t("settings.about")).size(scaled(ui, 18.0)).strong());
                 ui.add_space(16.0);
                 
                 ui.label(format!("EchoKey v{}", env!("CARGO_PKG_VERSION")));
-                ui.label(egui::RichText::new("你打下的每一个字，都有回声")
-                    .size(12.0)
-                    .color(egui::Color32::from_rgb(142, 142, 147)));
+                ui.label(egui::RichText::new(i18n::t("app.tagline"))
+                    .size(scaled(ui, 12.0))
+                    .color(palette(ui).secondary_text));
             });
     }
     
+    /// 渲染高级设置：配置文件中的其余选项
+    ///
+    /// 修改保留在草稿中并随时校验，在对应控件下方提示问题；
+    /// 点击「应用」且全部通过校验时才写回配置文件并通知主程序重新应用。
+    fn render_advanced_settings(&mut self, ui: &mut egui::Ui) {
+        let mut draft = self.advanced_draft.take().unwrap_or_else(|| self.config.clone());
+        let issues = draft.validate();
+        let mut capturing = self.capturing_shortcut;
+        let mut apply = false;
+        let mut revert = false;
+        
+        egui::CollapsingHeader::new(i18n::t("settings.advanced"))
+            .id_salt("advanced_settings")
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(i18n::t("settings.advanced_hint"))
+                    .size(scaled(ui, 12.0))
+                    .color(palette(ui).secondary_text));
+                
+                // 录制快捷键：取下一个按下的键，Esc 取消
+                if let Some(action) = capturing {
+                    let pressed = ui.input(|i| i.events.iter().find_map(|event| match event {
+                        egui::Event::Key { key, pressed: true, modifiers, .. } => Some((*key, *modifiers)),
+                        _ => None,
+                    }));
+                    match pressed {
+                        Some((egui::Key::Escape, _)) => capturing = None,
+                        Some((key, modifiers)) => {
+                            if let Some(chord) = captured_chord(key, modifiers) {
+                                *draft.shortcuts.spec_mut(action) = chord.name();
+                                capturing = None;
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                
+                setting_group(ui, i18n::t("settings.group_recording"));
+                setting_row(ui, &issues, "timestamp_timeout_secs", i18n::t("settings.timestamp_timeout"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.timestamp_timeout_secs).range(0..=3600));
+                });
+                setting_row(ui, &issues, "session_timeout_mins", i18n::t("settings.session_timeout"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.session_timeout_mins).range(1..=24 * 60));
+                });
+                setting_row(ui, &issues, "pause_marker_min_secs", i18n::t("settings.pause_marker_min"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.pause_marker_min_secs).range(0..=3600));
+                });
+                setting_row(ui, &issues, "resume_style", i18n::t("settings.resume_style"), |ui| {
+                    egui::ComboBox::from_id_salt("resume_style")
+                        .selected_text(resume_style_name(draft.resume_style))
+                        .show_ui(ui, |ui| {
+                            for style in [config::ResumeStyle::NewLine, config::ResumeStyle::Inline] {
+                                ui.selectable_value(&mut draft.resume_style, style, resume_style_name(style));
+                            }
+                        });
+                });
+                setting_row(ui, &issues, "compact_whitespace", i18n::t("settings.compact_whitespace"), |ui| {
+                    ui.add(toggle_switch(&mut draft.compact_whitespace));
+                });
+                setting_row(ui, &issues, "backspace_deletes", i18n::t("settings.backspace_deletes"), |ui| {
+                    ui.add(toggle_switch(&mut draft.backspace_deletes));
+                });
+                setting_row(ui, &issues, "flush_interval_ms", i18n::t("settings.flush_interval"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.flush_interval_ms).range(0..=5_000));
+                });
+                setting_row(ui, &issues, "max_file_mb", i18n::t("settings.max_file_mb"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.max_file_mb).range(0..=1024));
+                });
+                setting_row(ui, &issues, "pause_on_lock", i18n::t("settings.pause_on_lock"), |ui| {
+                    ui.add(toggle_switch(&mut draft.pause_on_lock));
+                });
+                setting_row(ui, &issues, "window_titles", i18n::t("settings.window_titles"), |ui| {
+                    ui.add(toggle_switch(&mut draft.window_titles));
+                });
+                setting_row(ui, &issues, "window_marker_min_ms", i18n::t("settings.window_marker_min"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.window_marker_min_ms).range(0..=10_000));
+                });
+                setting_row(ui, &issues, "sequence_numbers", i18n::t("settings.sequence_numbers"), |ui| {
+                    egui::ComboBox::from_id_salt("sequence_numbers")
+                        .selected_text(sequence_numbers_name(draft.sequence_numbers))
+                        .show_ui(ui, |ui| {
+                            for mode in [config::SequenceNumbers::Off, config::SequenceNumbers::PerFile, config::SequenceNumbers::Global] {
+                                ui.selectable_value(&mut draft.sequence_numbers, mode, sequence_numbers_name(mode));
+                            }
+                        });
+                });
+                setting_row(ui, &issues, "log_format", i18n::t("settings.log_format"), |ui| {
+                    egui::ComboBox::from_id_salt("log_format")
+                        .selected_text(log_format_name(draft.log_format))
+                        .show_ui(ui, |ui| {
+                            for format in [config::LogFormat::PlainText, config::LogFormat::JsonLines] {
+                                ui.selectable_value(&mut draft.log_format, format, log_format_name(format));
+                            }
+                        });
+                });
+                setting_row(ui, &issues, "split_by_app", i18n::t("settings.split_by_app"), |ui| {
+                    ui.add(toggle_switch(&mut draft.split_by_app));
+                });
+                setting_row(ui, &issues, "timezone", i18n::t("settings.timezone"), |ui| {
+                    let mut timezone = draft.timezone.clone().unwrap_or_default();
+                    if ui.add(egui::TextEdit::singleline(&mut timezone)
+                        .hint_text("Asia/Shanghai")
+                        .desired_width(160.0)).changed()
+                    {
+                        draft.timezone = (!timezone.trim().is_empty()).then_some(timezone);
+                    }
+                });
+                setting_row(ui, &issues, "segment_times", i18n::t("settings.segment_times"), |ui| {
+                    let mut times = draft.segment_times.join(", ");
+                    if ui.add(egui::TextEdit::singleline(&mut times)
+                        .hint_text("08:00, 12:00")
+                        .desired_width(160.0)).changed()
+                    {
+                        draft.segment_times = if times.trim().is_empty() {
+                            Vec::new()
+                        } else {
+                            times.split(',').map(|t| t.trim().to_string()).collect()
+                        };
+                    }
+                });
+                setting_row(ui, &issues, "device_label", i18n::t("settings.device_label"), |ui| {
+                    let mut label = draft.device_label.clone().unwrap_or_default();
+                    if ui.add(egui::TextEdit::singleline(&mut label).desired_width(160.0)).changed() {
+                        draft.device_label = (!label.is_empty()).then_some(label);
+                    }
+                });
+                setting_row(ui, &issues, "device_session_marker", i18n::t("settings.device_session_marker"), |ui| {
+                    ui.add(toggle_switch(&mut draft.device_session_marker));
+                });
+                setting_row(ui, &issues, "app_markers", i18n::t("settings.app_markers"), |ui| {
+                    ui.add(toggle_switch(&mut draft.app_markers));
+                });
+                setting_row(ui, &issues, "header_template", i18n::t("settings.custom_header"), |ui| {
+                    let mut custom = draft.header_template.is_some();
+                    if ui.add(toggle_switch(&mut custom)).changed() {
+                        draft.header_template = custom.then(String::new);
+                    }
+                });
+                if let Some(template) = &mut draft.header_template {
+                    ui.add(egui::TextEdit::multiline(template)
+                        .hint_text(i18n::t("settings.header_template_hint"))
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY));
+                    ui.label(egui::RichText::new(logger::HEADER_TOKENS.join(" "))
+                        .size(scaled(ui, 11.0))
+                        .color(palette(ui).secondary_text));
+                    ui.add_space(6.0);
+                }
+                
+                setting_group(ui, i18n::t("settings.group_input"));
+                for (field, key, value) in [
+                    ("log_win_combos", "settings.log_win_combos", &mut draft.log_win_combos),
+                    ("verbose_keys", "settings.verbose_keys", &mut draft.verbose_keys),
+                    ("capture_ime_text", "settings.capture_ime_text", &mut draft.capture_ime_text),
+                    ("raw_key_log", "settings.raw_key_log", &mut draft.raw_key_log),
+                    ("log_caps_lock", "settings.log_caps_lock", &mut draft.log_caps_lock),
+                    ("log_copies", "settings.log_copies", &mut draft.log_copies),
+                    ("remote_polling_fallback", "settings.remote_polling_fallback", &mut draft.remote_polling_fallback),
+                    ("exclude_own_window", "settings.exclude_own_window", &mut draft.exclude_own_window),
+                ] {
+                    setting_row(ui, &issues, field, i18n::t(key), |ui| {
+                        ui.add(toggle_switch(value));
+                    });
+                }
+                setting_row(ui, &issues, "clipboard_watch_interval_ms", i18n::t("settings.watch_clipboard"), |ui| {
+                    ui.add(toggle_switch(&mut draft.watch_clipboard));
+                    ui.add_enabled(draft.watch_clipboard, egui::DragValue::new(&mut draft.clipboard_watch_interval_ms).range(0..=60_000));
+                });
+                setting_row(ui, &issues, "paste_dedup_window_ms", i18n::t("settings.dedup_pastes"), |ui| {
+                    ui.add(toggle_switch(&mut draft.dedup_pastes));
+                    ui.add_enabled(draft.dedup_pastes, egui::DragValue::new(&mut draft.paste_dedup_window_ms).range(0..=60_000));
+                });
+                setting_row(ui, &issues, "max_paste_chars", i18n::t("settings.max_paste_chars"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.max_paste_chars).range(0..=10_000_000));
+                });
+                setting_row(ui, &issues, "save_pasted_images", i18n::t("settings.save_pasted_images"), |ui| {
+                    ui.add(toggle_switch(&mut draft.save_pasted_images));
+                });
+                setting_row(ui, &issues, "autorepeat_min_run", i18n::t("settings.autorepeat_min_run"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.autorepeat_min_run).range(0..=1000));
+                });
+                setting_row(ui, &issues, "autorepeat_interval_ms", i18n::t("settings.autorepeat_interval"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.autorepeat_interval_ms).range(0..=1000));
+                });
+                setting_row(ui, &issues, "key_dedup_ms", i18n::t("settings.key_dedup"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.key_dedup_ms).range(0..=200));
+                });
+                setting_row(ui, &issues, "char_dedup_ms", i18n::t("settings.char_dedup"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.char_dedup_ms).range(0..=100));
+                });
+                setting_row(ui, &issues, "verify_interval_secs", i18n::t("settings.verify_interval"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.verify_interval_secs).range(0..=3600));
+                });
+                setting_row(ui, &issues, "clipboard_retry_attempts", i18n::t("settings.clipboard_retry"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.clipboard_retry_attempts).range(0..=20));
+                });
+                setting_row(ui, &issues, "clipboard_retry_delay_ms", i18n::t("settings.clipboard_retry_delay"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.clipboard_retry_delay_ms).range(0..=1000));
+                });
+                
+                setting_group(ui, i18n::t("settings.group_window"));
+                setting_row(ui, &issues, "daily_goal", i18n::t("settings.daily_goal"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.daily_goal).range(0..=1_000_000).speed(10));
+                });
+                setting_row(ui, &issues, "daily_goal_notify", i18n::t("settings.daily_goal_notify"), |ui| {
+                    ui.add_enabled(draft.daily_goal > 0, toggle_switch(&mut draft.daily_goal_notify));
+                });
+                setting_row(ui, &issues, "focus_minutes", i18n::t("settings.focus_minutes"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.focus_minutes).range(1..=240));
+                });
+                setting_row(ui, &issues, "auto_hide_secs", i18n::t("settings.auto_hide"), |ui| {
+                    let mut enabled = draft.auto_hide_secs.is_some();
+                    let mut secs = draft.auto_hide_secs.unwrap_or(60);
+                    ui.add(toggle_switch(&mut enabled));
+                    ui.add_enabled(enabled, egui::DragValue::new(&mut secs).range(1..=3600));
+                    draft.auto_hide_secs = enabled.then_some(secs);
+                });
+                setting_row(ui, &issues, "pulse_active_secs", i18n::t("settings.pulse_active"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.pulse_active_secs).range(0..=600));
+                });
+                setting_row(ui, &issues, "gui_update_interval_ms", i18n::t("settings.gui_update_interval"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.gui_update_interval_ms).range(0..=5000));
+                });
+                setting_row(ui, &issues, "gui_update_chars", i18n::t("settings.gui_update_chars"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.gui_update_chars).range(1..=1000));
+                });
+                
+                setting_group(ui, i18n::t("settings.group_startup"));
+                setting_row(ui, &issues, "startup_delay_secs", i18n::t("settings.startup_delay"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.startup_delay_secs).range(0..=600));
+                });
+                setting_row(ui, &issues, "keep_recent_days", i18n::t("settings.keep_recent_days"), |ui| {
+                    let mut enabled = draft.keep_recent_days > 0;
+                    let mut days = if enabled { draft.keep_recent_days } else { 30 };
+                    ui.add(toggle_switch(&mut enabled));
+                    ui.add_enabled(enabled, egui::DragValue::new(&mut days).range(1..=3650));
+                    draft.keep_recent_days = if enabled { days } else { 0 };
+                });
+                setting_row(ui, &issues, "retention_days", i18n::t("settings.retention_days"), |ui| {
+                    let mut enabled = draft.retention_days.is_some();
+                    let mut days = draft.retention_days.unwrap_or(90);
+                    ui.add(toggle_switch(&mut enabled));
+                    ui.add_enabled(enabled, egui::DragValue::new(&mut days).range(1..=3650));
+                    draft.retention_days = enabled.then_some(days);
+                });
+                setting_row(ui, &issues, "compress_after_days", i18n::t("settings.compress_after_days"), |ui| {
+                    let mut enabled = draft.compress_after_days.is_some();
+                    let mut days = draft.compress_after_days.unwrap_or(7);
+                    ui.add(toggle_switch(&mut enabled));
+                    ui.add_enabled(enabled, egui::DragValue::new(&mut days).range(1..=3650));
+                    draft.compress_after_days = enabled.then_some(days);
+                });
+                setting_row(ui, &issues, "event_channel_capacity", i18n::t("settings.event_channel_capacity"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.event_channel_capacity).range(1..=65_536));
+                });
+                setting_row(ui, &issues, "backlog_busy_events", i18n::t("settings.backlog_busy_events"), |ui| {
+                    ui.add(egui::DragValue::new(&mut draft.backlog_busy_events).range(1..=65_536));
+                });
+                setting_row(ui, &issues, "log_directory", i18n::t("settings.log_directory"), |ui| {
+                    if draft.log_directory.is_some() && ui.button(i18n::t("settings.reset")).clicked() {
+                        draft.log_directory = None;
+                    }
+                    if ui.button(i18n::t("settings.browse")).clicked() {
+                        if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                            draft.log_directory = Some(dir);
+                        }
+                    }
+                    let dir = draft.log_directory.as_ref().map_or_else(
+                        || i18n::t("settings.log_directory_default").to_string(),
+                        |dir| dir.display().to_string(),
+                    );
+                    ui.label(egui::RichText::new(dir).monospace().size(scaled(ui, 12.0)));
+                });
+                
+                setting_group(ui, i18n::t("settings.group_shortcuts"));
+                for action in SHORTCUT_ACTIONS {
+                    setting_row(ui, &issues, "shortcuts", action.label(), |ui| {
+                        let spec = draft.shortcuts.spec_mut(action);
+                        if !spec.trim().is_empty() && ui.button(i18n::t("settings.clear_shortcut")).clicked() {
+                            spec.clear();
+                            capturing = None;
+                        }
+                        let text = if capturing == Some(action) {
+                            i18n::t("settings.capture_shortcut").to_string()
+                        } else if spec.trim().is_empty() {
+                            i18n::t("shortcut.unbound").to_string()
+                        } else {
+                            spec.clone()
+                        };
+                        if ui.button(egui::RichText::new(text).monospace()).clicked() {
+                            capturing = Some(action);
+                        }
+                    });
+                }
+                
+                ui.add_space(8.0);
+                let changed = draft != self.config;
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(changed && issues.is_empty(), egui::Button::new(i18n::t("settings.apply"))).clicked() {
+                        apply = true;
+                    }
+                    if ui.add_enabled(changed, egui::Button::new(i18n::t("settings.revert"))).clicked() {
+                        revert = true;
+                    }
+                });
+            });
+        
+        self.capturing_shortcut = capturing;
+        if revert {
+            self.capturing_shortcut = None;
+        } else if apply {
+            self.commit_advanced_settings(draft);
+        } else if draft != self.config {
+            self.advanced_draft = Some(draft);
+        }
+    }
+    
+    /// 应用高级设置的修改：通过校验时保存并通知主程序重新应用，否则留作草稿
+    fn commit_advanced_settings(&mut self, draft: config::Config) {
+        if !draft.validate().is_empty() {
+            self.advanced_draft = Some(draft);
+            return;
+        }
+        self.config = draft;
+        self.save_config();
+        if let Some(state) = &self.shared_state {
+            if let Ok(mut gs) = state.lock() {
+                gs.request_reload_settings = true;
+            }
+        }
+    }
+    
+    /// 渲染统计导出：选择日期范围，导出为 CSV
+    fn render_stats_export(&mut self, ui: &mut egui::Ui) {
+        ui.label(i18n::t("settings.stats"));
+        ui.add_space(4.0);
+        
+        // 默认范围为最早到最新的日志
+        let dates = self.log_dates();
+        let from = self.stats_from.or(dates.last().copied());
+        let to = self.stats_to.or(dates.first().copied());
+        let format_date = |date: Option<NaiveDate>| {
+            date.map_or_else(|| i18n::t("history.pick_date").to_string(), |d| d.format("%Y-%m-%d").to_string())
+        };
+        
+        ui.horizontal(|ui| {
+            for (id, current, selected) in [("stats_from", from, &mut self.stats_from), ("stats_to", to, &mut self.stats_to)] {
+                egui::ComboBox::from_id_salt(id)
+                    .selected_text(format_date(current))
+                    .show_ui(ui, |ui| {
+                        for date in &dates {
+                            ui.selectable_value(selected, Some(*date), date.format("%Y-%m-%d").to_string());
+                        }
+                    });
+            }
+        });
+        
+        if ui.checkbox(&mut self.config.csv_include_empty_days, i18n::t("settings.stats_include_empty")).changed() {
+            self.save_config();
+        }
+        
+        let range = from.zip(to).map(|(from, to)| from.min(to)..=from.max(to));
+        if ui.add_enabled(range.is_some(), egui::Button::new(i18n::t("settings.stats_export"))).clicked() {
+            let Some(range) = range else {
+                return;
+            };
+            let file_name = format!("echokey-{}-{}.csv", range.start().format("%Y%m%d"), range.end().format("%Y%m%d"));
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_file_name(file_name)
+                .save_file()
+            else {
+                return;
+            };
+            match stats::export_csv(range, &path, self.config.csv_include_empty_days) {
+                Ok(rows) => self.show_message(&i18n::t_with("settings.stats_exported", rows)),
+                Err(e) => self.show_message(&e.to_string()),
+            }
+        }
+    }
+    
+    /// 命令面板中的全部命令：常用操作，以及跳转到每个有日志的日期
+    fn palette_commands(&mut self) -> Vec<Command> {
+        let mut commands = vec![
+            Command::TogglePause,
+            Command::NewSegment,
+            Command::OpenLogDir,
+            Command::ExportMarkdown,
+        ];
+        commands.extend(self.log_dates().into_iter().map(Command::JumpToDate));
+        commands
+    }
+    
+    /// 渲染命令面板：输入筛选，↑/↓ 选择，Enter 执行，Esc 关闭
+    fn render_command_palette(&mut self, ctx: &egui::Context) {
+        let Some(mut state) = self.command_palette.take() else {
+            return;
+        };
+        
+        let commands: Vec<(Command, String)> = self.palette_commands().into_iter()
+            .map(|command| (command, command.label(self.is_paused)))
+            .filter(|(_, label)| logger::fuzzy_match(&state.query, label))
+            .collect();
+        
+        let (escape, enter, up, down) = ctx.input(|i| (
+            i.key_pressed(egui::Key::Escape),
+            i.key_pressed(egui::Key::Enter),
+            i.key_pressed(egui::Key::ArrowUp),
+            i.key_pressed(egui::Key::ArrowDown),
+        ));
+        if down {
+            state.selected += 1;
+        }
+        if up {
+            state.selected = state.selected.saturating_sub(1);
+        }
+        state.selected = state.selected.min(commands.len().saturating_sub(1));
+        
+        let mut chosen = if enter { commands.get(state.selected).map(|(command, _)| *command) } else { None };
+        egui::Window::new(i18n::t("palette.title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut state.query)
+                    .hint_text(i18n::t("palette.hint"))
+                    .desired_width(320.0));
+                response.request_focus();
+                if response.changed() {
+                    state.selected = 0;
+                }
+                ui.add_space(8.0);
+                
+                if commands.is_empty() {
+                    ui.label(egui::RichText::new(i18n::t("palette.empty"))
+                        .color(palette(ui).secondary_text));
+                }
+                egui::ScrollArea::vertical()
+                    .id_salt("command_palette")
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for (i, (command, label)) in commands.iter().enumerate() {
+                            let item = ui.selectable_label(i == state.selected, label);
+                            if i == state.selected && (up || down) {
+                                item.scroll_to_me(None);
+                            }
+                            if item.clicked() {
+                                chosen = Some(*command);
+                            }
+                        }
+                    });
+            });
+        
+        if escape {
+            return;
+        }
+        match chosen {
+            Some(command) => self.run_command(command),
+            None => self.command_palette = Some(state),
+        }
+    }
+    
+    /// 执行命令面板中选中的命令
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::TogglePause => self.toggle_pause(),
+            Command::NewSegment => self.request(|s| s.request_new_segment = true),
+            Command::OpenLogDir => open_directory(&self.log_directory),
+            Command::ExportMarkdown => {
+                // 导出正在查看的那一天，没有查看日志时导出今天
+                let date = self.viewing_path.as_ref()
+                    .and_then(|p| logger::parse_log_file_date(&p.file_name()?.to_string_lossy()))
+                    .unwrap_or_else(|| self.today());
+                match logger::export_markdown(date) {
+                    Ok(path) => self.show_message(&i18n::t_with("palette.exported", path.display())),
+                    Err(e) => self.show_message(&e.to_string()),
+                }
+            }
+            Command::JumpToDate(date) => {
+                self.current_page = Page::History;
+                self.diff_lines = None;
+                if let Some(path) = logger::day_log_files(date).into_iter().next() {
+                    self.open_log_file(path);
+                }
+            }
+        }
+    }
+    
+    /// 选择保存位置，把某一天的日志导出为 PDF
+    fn export_pdf(&mut self, date: NaiveDate) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PDF", &["pdf"])
+            .set_file_name(format!("echokey-{}.pdf", date.format("%Y%m%d")))
+            .save_file()
+        else {
+            return;
+        };
+        match logger::export_pdf(date, &path) {
+            Ok(pages) => self.show_message(&i18n::t_with("history.pdf_exported", pages)),
+            Err(e) => self.show_message(&e.to_string()),
+        }
+    }
+    
+    /// 今天的日期（使用日志配置的时区）
+    fn today(&self) -> NaiveDate {
+        self.config.log_timezone().now().date_naive()
+    }
+    
     /// 加载日志内容
     fn load_log_content(&mut self) {
         // 加载今天的日志
-        let today = Local::now().format("%Y-%m-%d").to_string();
+        let today = self.today().format("%Y-%m-%d").to_string();
         let log_path = self.log_directory.join(format!("{}.log", today));
         
         if let Ok(content) = std::fs::read_to_string(&log_path) {
-            self.log_content = content;
+            self.set_log_content(content);
+            self.viewing_path = Some(log_path);
         }
     }
 }
 
 impl eframe::App for EchoKeyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.track_window_geometry(ctx);
+        
+        // 跟随系统主题：系统切换深浅色后重新配置样式
+        if self.config.theme == config::AppTheme::System {
+            let applied = ctx.data(|d| d.get_temp::<config::AppTheme>(applied_theme_id()));
+            if applied != Some(effective_theme(ctx, config::AppTheme::System)) {
+                configure_apple_style(ctx, &self.config);
+            }
+        }
+        
         // 同步共享状态
+        let mut notice = None;
         if let Some(ref state) = self.shared_state {
-            if let Ok(s) = state.lock() {
+            if let Ok(mut s) = state.lock() {
                 self.today_chars = s.today_chars;
+                self.dropped_events = s.dropped_events;
+                self.write_busy = s.write_busy;
+                self.capture_pending = s.capture_pending;
+                self.remote_session = s.remote_session;
+                self.hook_lost = s.hook_lost;
+                self.hook_restores = s.hook_restores;
+                self.safe_mode = s.safe_mode;
+                self.paste_progress = s.paste_progress;
+                self.log_stats = s.log_stats;
+                self.last_input_at = s.last_input_at;
+                self.accumulator = s.accumulator.clone();
+                self.focus = s.focus.clone();
+                notice = s.notice.take();
+                
+                // 托盘请求显示窗口
+                if s.request_show {
+                    s.request_show = false;
+                    s.left_window_at = None;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                
+                // 切换到其他程序超过设定时间后隐藏到托盘
+                let delay = self.config.auto_hide_delay();
+                if s.left_window_at.zip(delay).is_some_and(|(at, delay)| at.elapsed() >= delay) {
+                    s.left_window_at = None;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+                }
             }
         }
+        if let Some(notice) = notice {
+            self.show_message(&notice);
+        }
+        self.check_daily_goal();
+        
+        // 打开/关闭命令面板（多按了 Shift 等修饰键时不算，Ctrl+Shift+K 是插入检查点）
+        let palette_pressed = ctx.input_mut(|i| {
+            let index = i.events.iter().position(|event| matches!(
+                event,
+                egui::Event::Key { key, modifiers, pressed: true, repeat: false, .. }
+                    if captured_chord(*key, *modifiers) == Some(COMMAND_PALETTE_CHORD)
+            ));
+            index.map(|index| i.events.remove(index)).is_some()
+        });
+        if palette_pressed && self.command_palette.take().is_none() {
+            self.command_palette = Some(CommandPalette::default());
+        }
         
         // 清除过期的状态消息
         if let Some((_, time)) = &self.status_message {
@@ -468,7 +2433,7 @@ impl eframe::App for EchoKeyApp {
         // 主面板
         egui::CentralPanel::default()
             .frame(egui::Frame::none()
-                .fill(egui::Color32::from_rgb(242, 242, 247)) // SF Gray 6
+                .fill(palette_of(ctx).background)
                 .inner_margin(egui::Margin::same(0.0)))
             .show(ctx, |ui| {
                 // 自定义标题栏
@@ -481,24 +2446,26 @@ impl eframe::App for EchoKeyApp {
                     ui.add_space(16.0);
                     
                     let nav_items = [
-                        (Page::Status, "状态"),
-                        (Page::History, "历史"),
-                        (Page::Settings, "设置"),
+                        (Page::Status, i18n::t("nav.status")),
+                        (Page::History, i18n::t("nav.history")),
+                        (Page::Settings, i18n::t("nav.settings")),
                     ];
                     
                     for (page, label) in nav_items {
                         let is_selected = self.current_page == page;
-                        let text_color = if is_selected {
-                            egui::Color32::from_rgb(0, 122, 255)
+                        let p = palette(ui);
+                        let (text_color, fill) = if is_selected {
+                            (p.nav_selected_text, p.nav_selected_fill)
                         } else {
-                            egui::Color32::from_rgb(142, 142, 147)
+                            (p.nav_text, egui::Color32::TRANSPARENT)
                         };
                         
+                        // 高对比度主题下选中项用实心背景标出，不只靠文字颜色区分
                         if ui.add(egui::Button::new(
                             egui::RichText::new(label)
-                                .size(15.0)
+                                .size(scaled(ui, 15.0))
                                 .color(text_color)
-                        ).frame(false)).clicked() {
+                        ).fill(fill).frame(fill != egui::Color32::TRANSPARENT)).clicked() {
                             self.current_page = page;
                         }
                         
@@ -514,24 +2481,313 @@ impl eframe::App for EchoKeyApp {
                     .show(ui, |ui| {
                         match self.current_page {
                             Page::Status => self.render_status_page(ui),
+                            Page::History if !self.unlocked => {
+                                ui.add_space(12.0);
+                                self.render_lock_prompt(ui);
+                            }
                             Page::History => self.render_history_page(ui),
                             Page::Settings => self.render_settings_page(ui),
                         }
                     });
             });
         
+        self.render_command_palette(ctx);
+        
         // 请求持续重绘（为了动画效果）
         ctx.request_repaint_after(std::time::Duration::from_millis(100));
     }
     
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        // 退出时的清理工作
+        // 退出时的清理工作：结束键盘监听线程的消息循环，主线程随后等待它退出
+        #[cfg(windows)]
+        crate::keyboard_win::stop_listening();
+        
+        // 刚调整过、还没来得及保存的窗口位置
+        if self.geometry_changed_at.take().is_some() {
+            if let Some(geometry) = &self.window_geometry {
+                window_state::save(geometry);
+            }
+        }
+    }
+}
+
+/// 配置对应的窗口层级
+fn window_level(config: &config::Config) -> egui::WindowLevel {
+    if config.always_on_top {
+        egui::WindowLevel::AlwaysOnTop
+    } else {
+        egui::WindowLevel::Normal
+    }
+}
+
+/// 按当前字号设置缩放固定字号
+///
+/// 页面里的 `RichText::size` 都以默认字号为基准书写，通过这里按用户设置等比例放大。
+fn scaled(ui: &egui::Ui, size: f32) -> f32 {
+    let body = ui.style().text_styles
+        .get(&egui::TextStyle::Body)
+        .map_or(config::DEFAULT_FONT_SIZE, |font| font.size);
+    size * body / config::DEFAULT_FONT_SIZE
+}
+
+/// 安装中文字体
+///
+/// egui 自带字体不包含中文字形，优先加载用户指定的字体文件，
+/// 否则依次尝试系统中文字体。返回加载的字体文件，都无法加载时返回 None。
+///
+/// 不是有效字体的文件（选错了文件）交给 egui 会直接崩溃，先检查能否解析，不能解析时尝试下一个。
+fn install_fonts(ctx: &egui::Context, custom: Option<&std::path::Path>) -> Option<PathBuf> {
+    let candidates = custom.into_iter()
+        .map(|p| p.to_path_buf())
+        .chain(config::SYSTEM_CJK_FONTS.iter().map(PathBuf::from));
+    
+    for path in candidates {
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        if ab_glyph::FontRef::try_from_slice(&bytes).is_err() {
+            eprintln!("警告: {} 不是有效的字体文件，已跳过", path.display());
+            continue;
+        }
+        
+        let mut fonts = egui::FontDefinitions::default();
+        fonts.font_data.insert("cjk".to_owned(), egui::FontData::from_owned(bytes));
+        // 作为后备字体：英文仍使用默认字体，中文回退到此字体
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts.families.entry(family).or_default().push("cjk".to_owned());
+        }
+        ctx.set_fonts(fonts);
+        return Some(path);
+    }
+    
+    None
+}
+
+/// 主题在界面上显示的名称
+fn theme_name(theme: config::AppTheme) -> &'static str {
+    match theme {
+        config::AppTheme::Light => i18n::t("theme.light"),
+        config::AppTheme::Dark => i18n::t("theme.dark"),
+        config::AppTheme::System => i18n::t("theme.system"),
+        config::AppTheme::HighContrast => i18n::t("theme.high_contrast"),
+    }
+}
+
+/// 界面配色（随主题切换，保存在 egui 上下文中供各处绘制时读取）
+#[derive(Debug, Clone, Copy)]
+struct Palette {
+    /// 窗口背景
+    background: egui::Color32,
+    /// 卡片背景
+    card: egui::Color32,
+    /// 卡片边框
+    card_border: egui::Stroke,
+    /// 标题栏背景
+    title_bar: egui::Color32,
+    /// 正文
+    text: egui::Color32,
+    /// 次要文字（说明、时间等）
+    secondary_text: egui::Color32,
+    /// 主要按钮
+    accent: egui::Color32,
+    /// 次要按钮
+    accent_alt: egui::Color32,
+    /// 彩色按钮上的文字
+    on_accent: egui::Color32,
+    /// 记录中、成功
+    positive: egui::Color32,
+    /// 暂停按钮
+    caution: egui::Color32,
+    /// 已暂停的状态指示
+    idle: egui::Color32,
+    /// 警告文字
+    warning: egui::Color32,
+    /// 错误文字
+    error: egui::Color32,
+    /// 标签、按键提示的背景
+    control_fill: egui::Color32,
+    /// 开关等自绘控件的边框
+    control_border: egui::Stroke,
+    /// 导航：未选中文字
+    nav_text: egui::Color32,
+    /// 导航：选中文字
+    nav_selected_text: egui::Color32,
+    /// 导航：选中背景（浅色主题下透明）
+    nav_selected_fill: egui::Color32,
+    /// 开关：关闭时背景
+    toggle_off: egui::Color32,
+    /// 开关：打开时背景
+    toggle_on: egui::Color32,
+    /// 开关：关闭时圆钮
+    toggle_knob_off: egui::Color32,
+    /// 开关：打开时圆钮
+    toggle_knob_on: egui::Color32,
+    /// 日志对比：新增行文字
+    diff_added: egui::Color32,
+    /// 日志对比：新增行背景
+    diff_added_fill: egui::Color32,
+    /// 日志对比：删除行文字
+    diff_removed: egui::Color32,
+    /// 日志对比：删除行背景
+    diff_removed_fill: egui::Color32,
+}
+
+impl Palette {
+    /// 主题对应的配色（跟随系统时由调用方先换成浅色或深色，见 [`effective_theme`]）
+    fn for_theme(theme: config::AppTheme) -> Self {
+        match theme {
+            config::AppTheme::Light | config::AppTheme::System => Self {
+                background: egui::Color32::from_rgb(242, 242, 247), // SF Gray 6
+                card: egui::Color32::WHITE,
+                card_border: egui::Stroke::NONE,
+                title_bar: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 230),
+                text: egui::Color32::from_rgb(28, 28, 30),
+                secondary_text: egui::Color32::from_rgb(142, 142, 147), // SF Gray
+                accent: egui::Color32::from_rgb(0, 122, 255),
+                accent_alt: egui::Color32::from_rgb(88, 86, 214),
+                on_accent: egui::Color32::WHITE,
+                positive: egui::Color32::from_rgb(52, 199, 89), // SF Green
+                caution: egui::Color32::from_rgb(255, 149, 0),
+                idle: egui::Color32::from_rgb(142, 142, 147),
+                warning: egui::Color32::from_rgb(255, 149, 0),
+                error: egui::Color32::from_rgb(255, 59, 48),
+                control_fill: egui::Color32::from_rgb(229, 229, 234),
+                control_border: egui::Stroke::NONE,
+                nav_text: egui::Color32::from_rgb(142, 142, 147),
+                nav_selected_text: egui::Color32::from_rgb(0, 122, 255),
+                nav_selected_fill: egui::Color32::TRANSPARENT,
+                toggle_off: egui::Color32::from_rgb(142, 142, 147),
+                toggle_on: egui::Color32::from_rgb(52, 199, 89),
+                toggle_knob_off: egui::Color32::WHITE,
+                toggle_knob_on: egui::Color32::WHITE,
+                diff_added: egui::Color32::from_rgb(36, 138, 61),
+                diff_added_fill: egui::Color32::from_rgb(227, 248, 232),
+                diff_removed: egui::Color32::from_rgb(215, 0, 21),
+                diff_removed_fill: egui::Color32::from_rgb(255, 232, 232),
+            },
+            config::AppTheme::Dark => Self {
+                background: egui::Color32::BLACK,
+                card: egui::Color32::from_rgb(28, 28, 30), // SF Gray 6（深色）
+                card_border: egui::Stroke::NONE,
+                title_bar: egui::Color32::from_rgba_unmultiplied(28, 28, 30, 230),
+                text: egui::Color32::from_rgb(242, 242, 247),
+                secondary_text: egui::Color32::from_rgb(152, 152, 159),
+                accent: egui::Color32::from_rgb(10, 132, 255),
+                accent_alt: egui::Color32::from_rgb(94, 92, 230),
+                on_accent: egui::Color32::WHITE,
+                positive: egui::Color32::from_rgb(48, 209, 88),
+                caution: egui::Color32::from_rgb(255, 159, 10),
+                idle: egui::Color32::from_rgb(142, 142, 147),
+                warning: egui::Color32::from_rgb(255, 159, 10),
+                error: egui::Color32::from_rgb(255, 69, 58),
+                control_fill: egui::Color32::from_rgb(44, 44, 46),
+                control_border: egui::Stroke::NONE,
+                nav_text: egui::Color32::from_rgb(142, 142, 147),
+                nav_selected_text: egui::Color32::from_rgb(10, 132, 255),
+                nav_selected_fill: egui::Color32::TRANSPARENT,
+                toggle_off: egui::Color32::from_rgb(72, 72, 74),
+                toggle_on: egui::Color32::from_rgb(48, 209, 88),
+                toggle_knob_off: egui::Color32::WHITE,
+                toggle_knob_on: egui::Color32::WHITE,
+                diff_added: egui::Color32::from_rgb(48, 209, 88),
+                diff_added_fill: egui::Color32::from_rgb(22, 56, 32),
+                diff_removed: egui::Color32::from_rgb(255, 105, 97),
+                diff_removed_fill: egui::Color32::from_rgb(70, 24, 24),
+            },
+            config::AppTheme::HighContrast => {
+                let border = egui::Stroke::new(2.0, egui::Color32::BLACK);
+                Self {
+                    background: egui::Color32::WHITE,
+                    card: egui::Color32::WHITE,
+                    card_border: border,
+                    title_bar: egui::Color32::WHITE,
+                    text: egui::Color32::BLACK,
+                    secondary_text: egui::Color32::BLACK,
+                    accent: egui::Color32::BLACK,
+                    accent_alt: egui::Color32::BLACK,
+                    on_accent: egui::Color32::WHITE,
+                    positive: egui::Color32::BLACK,
+                    caution: egui::Color32::BLACK,
+                    idle: egui::Color32::from_gray(160),
+                    warning: egui::Color32::from_rgb(153, 61, 0),
+                    error: egui::Color32::from_rgb(180, 0, 0),
+                    control_fill: egui::Color32::WHITE,
+                    control_border: border,
+                    nav_text: egui::Color32::BLACK,
+                    nav_selected_text: egui::Color32::WHITE,
+                    nav_selected_fill: egui::Color32::BLACK,
+                    toggle_off: egui::Color32::WHITE,
+                    toggle_on: egui::Color32::BLACK,
+                    toggle_knob_off: egui::Color32::BLACK,
+                    toggle_knob_on: egui::Color32::WHITE,
+                    diff_added: egui::Color32::from_rgb(36, 138, 61),
+                    diff_added_fill: egui::Color32::from_rgb(227, 248, 232),
+                    diff_removed: egui::Color32::from_rgb(215, 0, 21),
+                    diff_removed_fill: egui::Color32::from_rgb(255, 232, 232),
+                }
+            }
+        }
     }
 }
 
+/// 配色在 egui 上下文中的存储键
+fn palette_id() -> egui::Id {
+    egui::Id::new("echokey_palette")
+}
+
+/// 当前主题的配色
+fn palette_of(ctx: &egui::Context) -> Palette {
+    ctx.data(|d| d.get_temp(palette_id()))
+        .unwrap_or_else(|| Palette::for_theme(config::AppTheme::Light))
+}
+
+/// 当前主题的配色
+fn palette(ui: &egui::Ui) -> Palette {
+    palette_of(ui.ctx())
+}
+
+/// 实际使用的主题：跟随系统时按系统的深浅色选择（无法获取系统设置时用浅色）
+fn effective_theme(ctx: &egui::Context, theme: config::AppTheme) -> config::AppTheme {
+    match theme {
+        config::AppTheme::System => match ctx.system_theme() {
+            Some(egui::Theme::Dark) => config::AppTheme::Dark,
+            _ => config::AppTheme::Light,
+        },
+        theme => theme,
+    }
+}
+
+/// 上次配置样式时实际使用的主题在 egui 上下文中的存储键
+fn applied_theme_id() -> egui::Id {
+    egui::Id::new("echokey_applied_theme")
+}
+
 /// 配置 Apple 风格的视觉效果
-fn configure_apple_style(ctx: &egui::Context) {
-    let mut style = (*ctx.style()).clone();
+fn configure_apple_style(ctx: &egui::Context, config: &config::Config) {
+    // 每次都从初始样式开始配置，切换主题时不会残留上一个主题的设置
+    let base_id = egui::Id::new("echokey_base_style");
+    let base = match ctx.data(|d| d.get_temp::<Arc<egui::Style>>(base_id)) {
+        Some(base) => base,
+        None => {
+            let base = ctx.style();
+            ctx.data_mut(|d| d.insert_temp(base_id, Arc::clone(&base)));
+            base
+        }
+    };
+    let mut style = (*base).clone();
+    let theme = effective_theme(ctx, config.theme);
+    let dark = theme == config::AppTheme::Dark;
+    let palette = Palette::for_theme(theme);
+    style.visuals = if dark { egui::Visuals::dark() } else { egui::Visuals::light() };
+    
+    // 字号：所有文字样式按基础字号等比例缩放
+    let scale = config.font_size / config::DEFAULT_FONT_SIZE;
+    let default_style = egui::Style::default();
+    for (text_style, font) in style.text_styles.iter_mut() {
+        if let Some(default_font) = default_style.text_styles.get(text_style) {
+            font.size = default_font.size * scale;
+        }
+    }
     
     // 圆角设置
     style.visuals.window_rounding = egui::Rounding::same(12.0);
@@ -541,28 +2797,85 @@ fn configure_apple_style(ctx: &egui::Context) {
     style.visuals.widgets.active.rounding = egui::Rounding::same(8.0);
     
     // 颜色
-    style.visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(242, 242, 247);
-    style.visuals.selection.bg_fill = egui::Color32::from_rgb(0, 122, 255);
-    style.visuals.hyperlink_color = egui::Color32::from_rgb(0, 122, 255);
+    style.visuals.widgets.noninteractive.bg_fill = palette.background;
+    style.visuals.selection.bg_fill = palette.accent;
+    style.visuals.hyperlink_color = palette.accent;
     
     // 按钮样式
-    style.visuals.widgets.inactive.weak_bg_fill = egui::Color32::from_rgb(229, 229, 234);
-    style.visuals.widgets.hovered.weak_bg_fill = egui::Color32::from_rgb(209, 209, 214);
+    style.visuals.widgets.inactive.weak_bg_fill = palette.control_fill;
+    style.visuals.widgets.hovered.weak_bg_fill = if dark {
+        egui::Color32::from_rgb(58, 58, 60)
+    } else {
+        egui::Color32::from_rgb(209, 209, 214)
+    };
     
     // 文本颜色
-    style.visuals.widgets.noninteractive.fg_stroke.color = egui::Color32::from_rgb(28, 28, 30);
+    style.visuals.widgets.noninteractive.fg_stroke.color = palette.text;
+    if dark {
+        style.visuals.panel_fill = palette.background;
+        style.visuals.window_fill = palette.card;
+        style.visuals.extreme_bg_color = palette.control_fill;
+    }
     
     // 间距
     style.spacing.item_spacing = egui::vec2(8.0, 8.0);
     style.spacing.button_padding = egui::vec2(12.0, 6.0);
     
+    // 高对比度：纯黑白、粗边框、更大的点击区域
+    if config.theme == config::AppTheme::HighContrast {
+        let rounding = style.visuals.widgets.inactive.rounding;
+        style.visuals = egui::Visuals::light();
+        
+        let border = egui::Stroke::new(2.0, egui::Color32::BLACK);
+        let visuals = &mut style.visuals;
+        visuals.panel_fill = egui::Color32::WHITE;
+        visuals.window_fill = egui::Color32::WHITE;
+        visuals.window_stroke = border;
+        visuals.extreme_bg_color = egui::Color32::WHITE;
+        visuals.faint_bg_color = egui::Color32::WHITE;
+        visuals.window_rounding = egui::Rounding::same(12.0);
+        visuals.selection.bg_fill = egui::Color32::BLACK;
+        visuals.selection.stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+        visuals.hyperlink_color = egui::Color32::BLACK;
+        
+        for widget in [
+            &mut visuals.widgets.noninteractive,
+            &mut visuals.widgets.inactive,
+            &mut visuals.widgets.hovered,
+            &mut visuals.widgets.active,
+            &mut visuals.widgets.open,
+        ] {
+            widget.bg_fill = egui::Color32::WHITE;
+            widget.weak_bg_fill = egui::Color32::WHITE;
+            widget.bg_stroke = border;
+            widget.fg_stroke = egui::Stroke::new(2.0, egui::Color32::BLACK);
+            widget.rounding = rounding;
+        }
+        visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(1.0, egui::Color32::BLACK);
+        // 悬停、按下时反色，保证状态清晰可辨
+        visuals.widgets.hovered.weak_bg_fill = egui::Color32::from_gray(220);
+        visuals.widgets.hovered.bg_stroke = egui::Stroke::new(3.0, egui::Color32::BLACK);
+        visuals.widgets.active.weak_bg_fill = egui::Color32::BLACK;
+        visuals.widgets.active.fg_stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+        
+        style.spacing.interact_size = egui::vec2(48.0, 32.0);
+        style.spacing.button_padding = egui::vec2(16.0, 10.0);
+        style.spacing.item_spacing = egui::vec2(10.0, 10.0);
+    }
+    
+    // egui 为深浅色各保存一套样式，先切换到对应的一套再设置
+    ctx.set_theme(if dark { egui::Theme::Dark } else { egui::Theme::Light });
     ctx.set_style(style);
+    ctx.data_mut(|d| {
+        d.insert_temp(palette_id(), palette);
+        d.insert_temp(applied_theme_id(), theme);
+    });
 }
 
 /// 渲染自定义标题栏
 fn render_title_bar(ui: &mut egui::Ui, ctx: &egui::Context) {
     egui::Frame::none()
-        .fill(egui::Color32::from_rgba_unmultiplied(255, 255, 255, 230))
+        .fill(palette(ui).title_bar)
         .inner_margin(egui::Margin::symmetric(16.0, 12.0))
         .show(ui, |ui| {
             ui.horizontal(|ui| {
@@ -604,8 +2917,8 @@ fn render_title_bar(ui: &mut egui::Ui, ctx: &egui::Context) {
                 // 标题
                 ui.with_layout(egui::Layout::centered_and_justified(egui::Direction::LeftToRight), |ui| {
                     ui.label(egui::RichText::new("EchoKey")
-                        .size(14.0)
-                        .color(egui::Color32::from_rgb(28, 28, 30)));
+                        .size(scaled(ui, 14.0))
+                        .color(palette(ui).text));
                 });
             });
         });
@@ -622,6 +2935,248 @@ fn render_title_bar(ui: &mut egui::Ui, ctx: &egui::Context) {
     }
 }
 
+/// 是否为归档日志（今天以前的日志，默认只读）
+fn is_archived(path: &std::path::Path, today: NaiveDate) -> bool {
+    path.file_name()
+        .and_then(|n| logger::parse_log_file_date(&n.to_string_lossy()))
+        .is_none_or(|date| date != today)
+}
+
+/// 文件大小的可读形式（B / KB / MB）
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// 窗口位置或大小停止变化多久后保存（拖动、调整大小的过程中不反复写文件）
+const GEOMETRY_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 搜索词匹配部分的背景色
+const SEARCH_HIGHLIGHT: egui::Color32 = egui::Color32::from_rgb(255, 221, 87);
+
+/// 日志正文排版：检查点行使用强调色，便于快速定位；与搜索词匹配的部分（忽略大小写）加黄色背景
+fn highlight_checkpoints(ui: &egui::Ui, content: &str, query: &str) -> egui::text::LayoutJob {
+    let font = egui::FontId::monospace(scaled(ui, 12.0));
+    let highlight = egui::TextFormat {
+        font_id: font.clone(),
+        color: egui::Color32::BLACK,
+        background: SEARCH_HIGHLIGHT,
+        ..Default::default()
+    };
+    let mut job = egui::text::LayoutJob::default();
+    for line in content.split_inclusive('\n') {
+        let color = if logger::is_checkpoint_line(line.trim_end()) {
+            palette(ui).accent
+        } else {
+            ui.visuals().text_color()
+        };
+        let plain = egui::TextFormat::simple(font.clone(), color);
+        let mut last = 0;
+        for range in logger::find_matches(line, query) {
+            job.append(&line[last..range.start], 0.0, plain.clone());
+            job.append(&line[range.clone()], 0.0, highlight.clone());
+            last = range.end;
+        }
+        job.append(&line[last..], 0.0, plain);
+    }
+    job
+}
+
+/// 历史页面日志内容的分行布局
+///
+/// 每个逻辑行单独排版（按 egui 的规则自动换行）。显示的内容只在日志或显示选项
+/// 改变后（见 [`WrappedLines::invalidate`]）重新生成，各行高度只在内容、宽度或字号
+/// 变化时重新计算；绘制时只排版滚动区域中可见的行，很长的日志也不会拖慢界面。
+#[derive(Default)]
+struct WrappedLines {
+    /// 显示的内容（按折叠短行、隐藏序号处理后），为 None 时下次显示前重新生成
+    content: Option<String>,
+    /// 计算布局时的宽度和字号
+    layout_for: (f32, f32),
+    /// 每行顶部的纵坐标，最后一项为总高度
+    offsets: Vec<f32>,
+    /// 下一帧要跳转到的时刻
+    seek: Option<chrono::NaiveTime>,
+}
+
+impl WrappedLines {
+    /// 日志内容或显示选项改变后调用，下次显示时重新生成内容和布局
+    fn invalidate(&mut self) {
+        self.content = None;
+        self.offsets.clear();
+    }
+    
+    /// 在滚动区域中显示内容，与 `query` 匹配的部分高亮
+    ///
+    /// 内容失效后由 `make_content` 重新生成。
+    fn show(&mut self, ui: &mut egui::Ui, query: &str, make_content: impl FnOnce() -> String) {
+        let content = match self.content.take() {
+            Some(content) => content,
+            None => {
+                self.offsets.clear();
+                make_content()
+            }
+        };
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show_viewport(ui, |ui, viewport| {
+                let lines: Vec<&str> = content.lines().collect();
+                let width = ui.available_width();
+                self.update(ui, &lines, width);
+                ui.set_height(self.offsets.last().copied().unwrap_or_default());
+                let origin = ui.max_rect().min;
+                
+                if let Some(line) = self.seek.take().and_then(|time| logger::seek_line(&content, time)) {
+                    let top = origin + egui::vec2(0.0, self.offsets[line]);
+                    ui.scroll_to_rect(egui::Rect::from_min_size(top, egui::vec2(width, 1.0)), Some(egui::Align::TOP));
+                }
+                
+                // 只排版与可见区域相交的行
+                let first = self.offsets.partition_point(|&y| y <= viewport.min.y).saturating_sub(1);
+                let end = self.offsets.partition_point(|&y| y < viewport.max.y).min(lines.len());
+                for (i, line) in lines.iter().enumerate().take(end).skip(first) {
+                    let rect = egui::Rect::from_min_max(
+                        origin + egui::vec2(0.0, self.offsets[i]),
+                        origin + egui::vec2(width, self.offsets[i + 1]),
+                    );
+                    ui.allocate_new_ui(egui::UiBuilder::new().max_rect(rect), |ui| {
+                        ui.add(egui::Label::new(highlight_checkpoints(ui, line, query)).wrap());
+                    });
+                }
+            });
+        self.content = Some(content);
+    }
+    
+    /// 内容失效、宽度或字号变化时重新计算每行的高度
+    fn update(&mut self, ui: &egui::Ui, lines: &[&str], width: f32) {
+        let layout_for = (width, scaled(ui, 12.0));
+        if layout_for == self.layout_for && !self.offsets.is_empty() {
+            return;
+        }
+        
+        self.layout_for = layout_for;
+        self.offsets.clear();
+        let mut y = 0.0;
+        for line in lines {
+            self.offsets.push(y);
+            // 高亮不改变行高，排版时不需要搜索词
+            let mut job = highlight_checkpoints(ui, line, "");
+            job.wrap.max_width = width;
+            y += ui.fonts(|f| f.layout_job(job)).size().y;
+        }
+        self.offsets.push(y);
+    }
+}
+
+/// 绘制目标进度环，中间显示百分比
+fn progress_ring(ui: &mut egui::Ui, fraction: f32, color: egui::Color32) {
+    let size = scaled(ui, 56.0);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+    let center = rect.center();
+    let radius = size / 2.0 - 4.0;
+    let painter = ui.painter();
+    painter.circle_stroke(center, radius, egui::Stroke::new(5.0, palette(ui).control_fill));
+    
+    // 从 12 点钟方向顺时针绘制
+    if fraction > 0.0 {
+        let segments = ((fraction * 64.0).ceil() as usize).max(1);
+        let points: Vec<egui::Pos2> = (0..=segments)
+            .map(|i| {
+                let angle = -std::f32::consts::FRAC_PI_2 + std::f32::consts::TAU * fraction * i as f32 / segments as f32;
+                center + radius * egui::vec2(angle.cos(), angle.sin())
+            })
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(5.0, color)));
+    }
+    
+    painter.text(
+        center,
+        egui::Align2::CENTER_CENTER,
+        format!("{:.0}%", fraction * 100.0),
+        egui::FontId::proportional(scaled(ui, 12.0)),
+        palette(ui).text,
+    );
+}
+
+/// 高级设置中一组设置的小标题
+fn setting_group(ui: &mut egui::Ui, title: &str) {
+    ui.add_space(10.0);
+    ui.label(egui::RichText::new(title).strong());
+    ui.add_space(4.0);
+}
+
+/// 高级设置中的一行：名称（需要重启才能生效时加注）、右侧的控件，
+/// 以及该配置项的校验问题
+fn setting_row(ui: &mut egui::Ui, issues: &[config::ConfigIssue], field: &str, label: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        if config::requires_restart(field) {
+            ui.label(egui::RichText::new(i18n::t("settings.restart_required"))
+                .size(scaled(ui, 11.0))
+                .color(palette(ui).warning));
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), add_contents);
+    });
+    for issue in issues.iter().filter(|issue| issue.field == field) {
+        ui.label(egui::RichText::new(issue.message())
+            .size(scaled(ui, 12.0))
+            .color(palette(ui).error));
+    }
+    ui.add_space(4.0);
+}
+
+/// 设置页面中列出的快捷键动作（按显示顺序）
+const SHORTCUT_ACTIONS: [ShortcutAction; 6] = [
+    ShortcutAction::TogglePause,
+    ShortcutAction::ManualSave,
+    ShortcutAction::NewSegment,
+    ShortcutAction::Checkpoint,
+    ShortcutAction::ToggleFocus,
+    ShortcutAction::Paste,
+];
+
+/// 录制快捷键时按下的键转换为按键组合，没有修饰键或是不支持的键时返回 None
+///
+/// egui 的键名与 [`shortcuts::key_name`](crate::shortcuts::key_name) 一致，直接按文字解析。
+fn captured_chord(key: egui::Key, modifiers: egui::Modifiers) -> Option<Chord> {
+    let mut spec = String::new();
+    for (pressed, name) in [(modifiers.ctrl, "Ctrl+"), (modifiers.alt, "Alt+"), (modifiers.shift, "Shift+")] {
+        if pressed {
+            spec.push_str(name);
+        }
+    }
+    spec.push_str(key.name());
+    Chord::parse(&spec)
+}
+
+/// 恢复记录后续写方式的显示名称
+fn resume_style_name(style: config::ResumeStyle) -> &'static str {
+    match style {
+        config::ResumeStyle::NewLine => i18n::t("settings.resume_new_line"),
+        config::ResumeStyle::Inline => i18n::t("settings.resume_inline"),
+    }
+}
+
+/// 输出格式选项的显示名称
+fn log_format_name(format: config::LogFormat) -> &'static str {
+    match format {
+        config::LogFormat::PlainText => i18n::t("settings.log_format_text"),
+        config::LogFormat::JsonLines => i18n::t("settings.log_format_jsonl"),
+    }
+}
+
+/// 条目序号选项的显示名称
+fn sequence_numbers_name(mode: config::SequenceNumbers) -> &'static str {
+    match mode {
+        config::SequenceNumbers::Off => i18n::t("settings.sequence_off"),
+        config::SequenceNumbers::PerFile => i18n::t("settings.sequence_per_file"),
+        config::SequenceNumbers::Global => i18n::t("settings.sequence_global"),
+    }
+}
+
 /// 创建 iOS 风格的开关
 fn toggle_switch(on: &mut bool) -> impl egui::Widget + '_ {
     move |ui: &mut egui::Ui| {
@@ -635,18 +3190,21 @@ fn toggle_switch(on: &mut bool) -> impl egui::Widget + '_ {
         
         if ui.is_rect_visible(rect) {
             let how_on = ui.ctx().animate_bool_responsive(response.id, *on);
+            let p = palette(ui);
+            let (off, on_color) = (p.toggle_off, p.toggle_on);
             let bg_color = egui::Color32::from_rgb(
-                (142.0 + (52.0 - 142.0) * how_on) as u8,
-                (142.0 + (199.0 - 142.0) * how_on) as u8,
-                (147.0 + (89.0 - 147.0) * how_on) as u8,
+                egui::lerp(off.r() as f32..=on_color.r() as f32, how_on) as u8,
+                egui::lerp(off.g() as f32..=on_color.g() as f32, how_on) as u8,
+                egui::lerp(off.b() as f32..=on_color.b() as f32, how_on) as u8,
             );
             
             let rounding = rect.height() / 2.0;
-            ui.painter().rect_filled(rect, rounding, bg_color);
+            ui.painter().rect(rect, rounding, bg_color, p.control_border);
             
             let circle_x = egui::lerp(rect.left() + 15.5..=rect.right() - 15.5, how_on);
             let circle_center = egui::pos2(circle_x, rect.center().y);
-            ui.painter().circle_filled(circle_center, 13.5, egui::Color32::WHITE);
+            let knob = if how_on > 0.5 { p.toggle_knob_on } else { p.toggle_knob_off };
+            ui.painter().circle_filled(circle_center, 13.5, knob);
         }
         
         response
@@ -671,20 +3229,24 @@ fn open_directory(path: &std::path::Path) {
 
 /// 启动 GUI
 pub fn run_gui(log_directory: PathBuf, shared_state: Arc<Mutex<SharedGuiState>>) -> eframe::Result<()> {
+    // 按上次保存的大小打开，位置在界面拿到显示器大小后恢复
+    let saved_geometry = window_state::load();
+    let size = saved_geometry.map_or([380.0, 600.0], |g| [g.width, g.height]);
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([380.0, 600.0])
+            .with_inner_size(size)
             .with_min_inner_size([320.0, 480.0])
             .with_decorations(false) // 无边框窗口
             .with_transparent(true)
-            .with_resizable(true),
+            .with_resizable(true)
+            .with_window_level(window_level(&config::current())),
         ..Default::default()
     };
     
     eframe::run_native(
         "EchoKey",
         native_options,
-        Box::new(move |cc| Ok(Box::new(EchoKeyApp::new(cc, log_directory, shared_state)))),
+        Box::new(move |cc| Ok(Box::new(EchoKeyApp::new(cc, log_directory, shared_state, saved_geometry)))),
     )
 }
 
@@ -0,0 +1,748 @@
+//! 界面文字本地化模块
+//!
+//! 所有界面文字通过 `t("status.recording")` 这样的键查表获得，
+//! 翻译表内嵌在程序中（目前有简体中文和英文）。
+//!
+//! 日志中的标记（`[粘贴]`、`--- 暂停记录 ---` 等）使用单独的语言设置，
+//! 通过 `marker()` 查表。这样切换界面语言不会让日志格式前后不一致，
+//! 历史日志的解析也不受影响。
+//!
+//! 找不到翻译时先回退到简体中文，仍然没有则直接返回键本身。
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Locale {
+    /// 简体中文
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    /// 英文
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Locale {
+    /// 所有支持的语言（用于设置页面的下拉框）
+    pub const ALL: [Locale; 2] = [Locale::ZhCn, Locale::En];
+
+    /// 语言自身的名称
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Locale::ZhCn => "简体中文",
+            Locale::En => "English",
+        }
+    }
+
+    /// 根据语言标签（如 `zh-CN`、`en_US.UTF-8`）选择语言
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let tag = tag.to_ascii_lowercase();
+        if tag.starts_with("zh") {
+            Some(Locale::ZhCn)
+        } else if tag.starts_with("en") {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::ZhCn => ZH_CN,
+            Locale::En => EN,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    fn from_u8(value: u8) -> Locale {
+        match value {
+            1 => Locale::En,
+            _ => Locale::ZhCn,
+        }
+    }
+}
+
+/// 当前界面语言
+static UI_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// 当前日志标记语言
+static MARKER_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// 设置界面语言
+pub fn set_locale(locale: Locale) {
+    UI_LOCALE.store(locale.to_u8(), Ordering::Relaxed);
+}
+
+/// 当前界面语言
+pub fn locale() -> Locale {
+    Locale::from_u8(UI_LOCALE.load(Ordering::Relaxed))
+}
+
+/// 设置日志标记语言
+pub fn set_marker_locale(locale: Locale) {
+    MARKER_LOCALE.store(locale.to_u8(), Ordering::Relaxed);
+}
+
+/// 根据配置设置语言（未指定界面语言时跟随系统）
+pub fn init(language: Option<Locale>, marker_language: Locale) {
+    set_locale(language.or_else(system_locale).unwrap_or_default());
+    set_marker_locale(marker_language);
+}
+
+/// 检测系统语言
+#[cfg(windows)]
+pub fn system_locale() -> Option<Locale> {
+    use windows::Win32::Globalization::GetUserDefaultLocaleName;
+
+    let mut buffer = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    if len <= 1 {
+        return None;
+    }
+    Locale::from_tag(&String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+/// 检测系统语言
+#[cfg(not(windows))]
+pub fn system_locale() -> Option<Locale> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| Locale::from_tag(&value))
+}
+
+/// 在指定语言中查找翻译，回退到简体中文，再回退到键本身
+pub fn translate(locale: Locale, key: &'static str) -> &'static str {
+    lookup(locale.table(), key)
+        .or_else(|| lookup(ZH_CN, key))
+        .unwrap_or(key)
+}
+
+fn lookup(table: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// 界面文字
+pub fn t(key: &'static str) -> &'static str {
+    translate(locale(), key)
+}
+
+/// 带一个参数的界面文字（替换译文中的 `{}`）
+pub fn t_with(key: &'static str, arg: impl std::fmt::Display) -> String {
+    t(key).replacen("{}", &arg.to_string(), 1)
+}
+
+/// 日志标记文字（使用单独的标记语言）
+pub fn marker(key: &'static str) -> &'static str {
+    translate(Locale::from_u8(MARKER_LOCALE.load(Ordering::Relaxed)), key)
+}
+
+/// 带一个参数的日志标记文字（替换译文中的 `{}`）
+pub fn marker_with(key: &'static str, arg: impl std::fmt::Display) -> String {
+    marker(key).replacen("{}", &arg.to_string(), 1)
+}
+
+/// 简体中文
+const ZH_CN: &[(&str, &str)] = &[
+    // 导航
+    ("nav.status", "状态"),
+    ("nav.history", "历史"),
+    ("nav.settings", "设置"),
+    // 状态页面
+    ("status.recording", "记录中"),
+    ("status.paused", "已暂停"),
+    ("status.starting", "即将开始记录"),
+    ("status.safe_mode", "安全模式"),
+    ("status.safe_mode_hint", "本次未记录输入。可在设置中修改配置或关闭开机自启动，然后重新启动 EchoKey"),
+    ("status.today_input", "今日输入"),
+    ("status.char_count", "{} 字符"),
+    ("status.goal", "每日目标 {} 字符"),
+    ("status.goal_reached", "🎉 今天已达成 {} 字符的目标"),
+    ("status.line_count", "{} 行"),
+    ("status.verbose", "🔍 详细记录模式已开启（重启后自动关闭）"),
+    ("status.date_format", "%Y年%m月%d日 %H:%M"),
+    ("status.resume", "▶ 恢复"),
+    ("status.pause", "⏸ 暂停"),
+    ("status.new_segment", "📝 新日志段"),
+    ("status.open_log_dir", "📂 打开日志目录"),
+    ("status.segment_created", "已创建新日志段"),
+    ("status.segment_empty", "当前日志段为空"),
+    ("status.dropped_events", "⚠ 已丢失 {} 个按键事件"),
+    ("status.write_busy", "⏳ 写入繁忙"),
+    // 专注时段
+    ("focus.start", "⏱ 开始专注（{} 分钟）"),
+    ("focus.running", "专注中"),
+    ("focus.paused", "专注已暂停"),
+    ("focus.chars", "本次已输入 {} 字符"),
+    ("focus.pause", "暂停计时"),
+    ("focus.resume", "继续计时"),
+    ("focus.stop", "结束专注"),
+    ("focus.finished", "专注时段结束"),
+    ("status.write_busy_hint", "输入正在排队写入，不会丢失"),
+    ("status.remote_session", "🖥 远程桌面会话：记录可能不完整"),
+    ("status.remote_session_hint", "远程会话中键盘钩子可能收不到部分按键；无法安装钩子时可以在设置中开启轮询采集"),
+    ("status.hook_lost", "⚠ 键盘钩子被系统移除，正在重新安装"),
+    ("status.hook_lost_hint", "系统繁忙导致钩子响应太慢时，Windows 会移除键盘钩子；移除期间的输入没有记录，EchoKey 会自动重新安装"),
+    ("status.hook_restores", "钩子已恢复（{} 次）"),
+    ("status.hook_restored", "键盘钩子已恢复"),
+    ("status.paste_progress", "正在写入粘贴内容 {}"),
+    // 实时预览
+    ("preview.title", "实时预览"),
+    ("preview.excerpt", "📌 摘录到日志"),
+    ("preview.excerpt_written", "已写入摘录"),
+    ("preview.excerpt_paused", "记录已暂停，恢复后才能摘录"),
+    ("preview.copy", "📋 复制摘录"),
+    ("common.copied", "已复制到剪贴板"),
+    // 历史页面
+    ("history.search_hint", "🔍 搜索日志内容..."),
+    ("history.search", "搜索"),
+    ("history.match_count", "找到 {} 处"),
+    ("history.no_matches", "所有日志中都没有找到匹配的内容"),
+    ("history.searching", "正在搜索所有日志…"),
+    ("history.search_truncated", "只显示前 {} 行"),
+    ("history.recent", "最近日志"),
+    ("history.empty", "暂无日志文件"),
+    ("history.filter_hint", "按日期筛选，例如 0105"),
+    ("history.filter_count", "{} 个匹配的文件"),
+    ("history.back", "← 返回"),
+    ("history.copy_all", "📋 复制全部"),
+    ("history.collapse", "合并短行"),
+    ("history.group_by_app", "按程序分组"),
+    ("history.default_app", "其他程序"),
+    ("history.hide_sequences", "隐藏序号"),
+    ("history.chars_suffix", " 字"),
+    ("history.seek_hint", "14:30"),
+    ("history.seek", "⏱ 跳转"),
+    ("history.seek_invalid", "时间格式应为 HH:MM"),
+    ("history.compare_days", "对比两天"),
+    ("history.pick_date", "选择日期"),
+    ("history.compare", "对比"),
+    ("history.no_diff", "两天内容没有差异"),
+    ("history.diff_truncated", "⚠ 日志过大，已省略 {} 行未参与对比"),
+    ("history.archived_badge", "归档"),
+    ("history.allow_changes", "🔓 解除只读"),
+    ("history.read_only", "归档日志默认只读，先解除只读"),
+    ("history.in_use", "今天的日志正在写入，不能删除或重命名"),
+    ("history.delete", "🗑 删除"),
+    ("history.delete_title", "删除日志"),
+    ("history.delete_confirm", "确定删除 {} 吗？文件会移到回收站（系统不支持时永久删除）。"),
+    ("history.trashed", "已移到回收站"),
+    ("history.deleted", "已永久删除"),
+    ("history.rename", "✏ 重命名"),
+    ("history.export_pdf", "📄 导出 PDF"),
+    ("history.pdf_exported", "已导出 PDF（{} 页）"),
+    ("history.rename_title", "重命名日志"),
+    ("history.renamed", "已重命名"),
+    ("history.cancel", "取消"),
+    // 命令面板
+    ("palette.title", "命令"),
+    ("palette.hint", "输入筛选，Enter 执行，Esc 关闭"),
+    ("palette.empty", "没有匹配的命令"),
+    ("palette.export_markdown", "📄 导出为 Markdown"),
+    ("palette.jump_to_date", "📅 跳转到 {}"),
+    ("palette.exported", "已导出到 {}"),
+    // 访问密码
+    ("lock.title", "🔒 需要访问密码"),
+    ("lock.hint", "输入密码"),
+    ("lock.unlock", "解锁"),
+    ("lock.wrong", "密码错误"),
+    ("lock.rate_limited", "尝试次数过多，请 {} 秒后再试"),
+    // 设置页面
+    ("settings.general", "通用设置"),
+    ("settings.autostart", "开机自启动"),
+    ("settings.autostart_enabled", "已启用开机自启动"),
+    ("settings.autostart_disabled", "已禁用开机自启动"),
+    ("settings.language", "界面语言"),
+    ("settings.language_auto", "跟随系统"),
+    ("settings.marker_language", "日志标记语言"),
+    ("settings.theme", "界面主题"),
+    ("theme.light", "浅色"),
+    ("theme.dark", "深色"),
+    ("theme.system", "跟随系统"),
+    ("theme.high_contrast", "高对比度"),
+    ("settings.font_size", "界面字号"),
+    ("settings.font_file", "中文字体文件（留空则自动查找系统字体）"),
+    ("settings.font_hint", "例如 C:\\Windows\\Fonts\\msyh.ttc"),
+    ("settings.apply", "应用"),
+    ("settings.font_updated", "字体已更新"),
+    ("settings.font_failed", "无法加载字体文件"),
+    ("settings.passcode", "访问密码"),
+    ("settings.passcode_locked", "请先在历史页面输入密码"),
+    ("settings.passcode_new", "新密码"),
+    ("settings.passcode_set_hint", "设置密码"),
+    ("settings.save", "保存"),
+    ("settings.passcode_saved", "访问密码已设置"),
+    ("settings.remove", "移除"),
+    ("settings.passcode_removed", "访问密码已移除"),
+    ("settings.lock", "锁定"),
+    ("settings.always_on_top", "窗口置顶"),
+    ("settings.tee_stdout", "同时输出到标准输出（调试用）"),
+    ("settings.log_location", "日志存储位置"),
+    ("settings.browse", "浏览…"),
+    ("settings.advanced", "高级设置"),
+    ("settings.advanced_hint", "修改后点击「应用」保存，有问题的修改不能应用"),
+    ("settings.revert", "撤销修改"),
+    ("settings.restart_required", "重启后生效"),
+    ("settings.group_recording", "记录"),
+    ("settings.group_input", "输入"),
+    ("settings.group_window", "窗口"),
+    ("settings.group_startup", "启动"),
+    ("settings.group_shortcuts", "快捷键"),
+    ("settings.log_directory", "日志目录"),
+    ("settings.log_directory_default", "默认位置"),
+    ("settings.reset", "恢复默认"),
+    ("settings.capture_shortcut", "按下新的组合键…（Esc 取消）"),
+    ("settings.clear_shortcut", "清除"),
+    ("config.session_shorter_than_stamp", "会话超时不能短于时间戳超时"),
+    ("config.channel_capacity_zero", "事件队列容量必须大于 0"),
+    ("config.clipboard_watch_too_short", "剪贴板检测间隔不能短于 {} 毫秒"),
+    ("config.autorepeat_interval_zero", "折叠长按时重复间隔必须大于 0"),
+    ("config.unknown_timezone", "无法识别的时区：{}"),
+    ("config.invalid_segment_times", "无法识别的时刻（格式应为 HH:MM）：{}"),
+    ("config.header_control_character", "头部模板不能包含控制字符"),
+    ("config.header_unknown_token", "头部模板中有未知的占位符：{}"),
+    ("settings.timestamp_timeout", "空闲多久后写入新时间戳（秒，0 表示每个字符单独一行）"),
+    ("settings.session_timeout", "空闲多久后划分新会话（分钟）"),
+    ("settings.pause_marker_min", "暂停标记的最短时长（秒，0 表示总是写入）"),
+    ("settings.resume_style", "恢复记录后"),
+    ("settings.resume_new_line", "另起一行"),
+    ("settings.resume_inline", "接着暂停前的那一行"),
+    ("settings.compact_whitespace", "压缩连续的空行"),
+    ("settings.backspace_deletes", "退格键删除最后输入的字符（关闭时记为 ⌫）"),
+    ("settings.flush_interval", "合并写入的间隔（毫秒，0 表示每次输入后立即写入）"),
+    ("settings.max_file_mb", "单个日志文件的大小上限（MB，0 表示不限制）"),
+    ("settings.window_titles", "在时间戳后注明正在输入的窗口标题"),
+    ("settings.window_marker_min", "窗口停留多久后写入窗口标记（毫秒）"),
+    ("settings.pause_on_lock", "锁屏时自动暂停记录"),
+    ("settings.daily_goal", "每日输入目标（字符，0 表示不设目标）"),
+    ("settings.daily_goal_notify", "达成目标时提示"),
+    ("settings.focus_minutes", "专注时段时长（分钟）"),
+    ("settings.sequence_numbers", "条目序号"),
+    ("settings.sequence_off", "不编号"),
+    ("settings.sequence_per_file", "每个文件重新编号"),
+    ("settings.sequence_global", "连续编号"),
+    ("settings.log_format", "输出格式"),
+    ("settings.log_format_text", "纯文本（.log）"),
+    ("settings.log_format_jsonl", "JSON 行（.jsonl）"),
+    ("settings.split_by_app", "按程序分文件记录"),
+    ("settings.timezone", "日志时区（留空跟随系统）"),
+    ("settings.segment_times", "每天自动分段的时刻（HH:MM，逗号分隔）"),
+    ("settings.device_label", "设备标签"),
+    ("settings.device_session_marker", "启动时写入设备标记"),
+    ("settings.app_markers", "写入启动/退出标记"),
+    ("settings.custom_header", "自定义日志头部"),
+    ("settings.header_template_hint", "留空表示不写头部"),
+    ("settings.log_win_combos", "记录 Win 组合键"),
+    ("settings.verbose_keys", "记录无法识别的按键和 Tab、Delete、方向键"),
+    ("settings.capture_ime_text", "记录输入法上屏的文字（需要程序支持 UI Automation）"),
+    ("settings.raw_key_log", "记录每个按键的按下和松开（日志会大很多）"),
+    ("settings.log_caps_lock", "记录大写锁定切换"),
+    ("settings.log_copies", "记录复制的内容（Ctrl+C）"),
+    ("settings.watch_clipboard", "检测剪贴板变化，记录任何方式复制的文本（间隔毫秒）"),
+    ("settings.remote_polling_fallback", "远程桌面中无法安装钩子时改用轮询采集"),
+    ("settings.exclude_own_window", "不记录 EchoKey 窗口中的输入"),
+    ("settings.dedup_pastes", "合并短时间内相同的粘贴（毫秒）"),
+    ("settings.max_paste_chars", "粘贴内容最多记录的字符数（0 表示不限制）"),
+    ("settings.save_pasted_images", "把粘贴的图片保存到 attachments 文件夹"),
+    ("settings.autorepeat_min_run", "长按重复多少次后折叠（0 表示不折叠）"),
+    ("settings.autorepeat_interval", "长按重复的最大间隔（毫秒）"),
+    ("settings.key_dedup", "同一按键重复事件的去重窗口（毫秒，0 表示关闭）"),
+    ("settings.char_dedup", "同一字符重复上屏的去重窗口（毫秒，0 表示关闭）"),
+    ("settings.verify_interval", "校验快照间隔（秒，0 表示关闭）"),
+    ("settings.clipboard_retry", "剪贴板被占用时的重试次数"),
+    ("settings.clipboard_retry_delay", "剪贴板重试间隔（毫秒）"),
+    ("settings.auto_hide", "切换到其他程序后自动隐藏窗口（秒）"),
+    ("settings.pulse_active", "输入动画持续时间（秒）"),
+    ("settings.gui_update_interval", "界面计数的刷新间隔（毫秒）"),
+    ("settings.gui_update_chars", "界面计数每多少个按键刷新"),
+    ("settings.startup_delay", "开机自启动后延迟记录（秒）"),
+    ("settings.keep_recent_days", "只保留最近几天的日志（启动时删除更早的）"),
+    ("settings.retention_days", "日志保留天数（启动时删除更早日期的日志）"),
+    ("settings.compress_after_days", "压缩几天以前的日志（启动时压缩为 .log.gz）"),
+    ("settings.event_channel_capacity", "按键事件队列容量"),
+    ("settings.backlog_busy_events", "排队多少个事件时提示写入繁忙"),
+    ("settings.stats", "输入统计"),
+    ("settings.stats_include_empty", "包含没有日志的日期（输出全零行）"),
+    ("settings.stats_export", "📊 导出 CSV"),
+    ("settings.stats_exported", "已导出 {} 天的统计"),
+    ("settings.open", "打开"),
+    ("settings.shortcuts", "快捷键"),
+    ("shortcut.toggle_pause", "暂停/恢复记录"),
+    ("shortcut.paste", "粘贴（记录剪贴板内容）"),
+    ("shortcut.manual_save", "手动保存剪贴板"),
+    ("shortcut.new_segment", "新建日志段"),
+    ("shortcut.checkpoint", "插入检查点"),
+    ("shortcut.toggle_focus", "开始/结束专注时段"),
+    ("shortcut.command_palette", "命令面板（窗口内）"),
+    ("shortcut.unbound", "未设置"),
+    ("settings.about", "关于"),
+    ("app.tagline", "你打下的每一个字，都有回声"),
+    // 托盘
+    ("tray.show", "显示窗口"),
+    ("tray.pause", "暂停记录"),
+    ("tray.new_segment", "新建日志段"),
+    ("tray.open_log_dir", "打开日志目录"),
+    ("tray.verbose", "详细记录（本次运行）"),
+    ("tray.accumulate", "开始/结束累加剪贴板"),
+    ("tray.quit", "退出"),
+    ("tray.tooltip_recording", "EchoKey - 记录中"),
+    ("tray.tooltip_paused", "EchoKey - 已暂停"),
+    // 日志标记
+    ("accumulate.start", "📋 累加剪贴板"),
+    ("accumulate.title", "累加剪贴板 · {} 段"),
+    ("accumulate.hint", "复制的内容会追加到这里（最多 {} 字）"),
+    ("accumulate.full", "⚠ 已达到上限，{} 段内容未加入"),
+    ("accumulate.write", "写入日志"),
+    ("accumulate.written", "已写入日志"),
+    ("accumulate.stop", "结束并写入日志"),
+    ("marker.paste", "粘贴"),
+    ("marker.copy", "复制"),
+    ("marker.paste_files", "粘贴文件"),
+    ("marker.paste_image", "粘贴图片"),
+    ("marker.paste_unknown", "粘贴:未知格式"),
+    ("marker.paste_truncated", "…（已截断，共 {} 字）"),
+    ("marker.manual_save", "手动保存"),
+    ("marker.excerpt", "摘录"),
+    ("marker.verify", "校验"),
+    ("marker.pause", "暂停记录"),
+    ("marker.resume", "恢复记录"),
+    ("marker.secure_desktop", "安全桌面"),
+    ("marker.secure_desktop_end", "安全桌面结束"),
+    ("marker.remote_session", "远程桌面会话，记录可能不完整"),
+    ("marker.remote_session_end", "远程桌面会话结束"),
+    ("marker.screen_locked", "屏幕锁定，暂停记录"),
+    ("marker.screen_unlocked", "屏幕解锁，恢复记录"),
+    ("marker.dropped_events", "丢失 {} 个事件"),
+    ("marker.device", "设备：{}"),
+    ("marker.checkpoint", "检查点"),
+    ("marker.focus_start", "专注开始"),
+    ("marker.focus_end", "专注结束: {chars} 字符, {minutes} 分钟"),
+    ("marker.app_start", "EchoKey 启动"),
+    ("marker.app_exit", "EchoKey 退出"),
+    ("marker.unknown_key", "未知键:{}"),
+    ("marker.window", "窗口：{}"),
+    ("marker.caps_lock_on", "大写锁定开"),
+    ("marker.caps_lock_off", "大写锁定关"),
+    ("marker.autorepeat", "长按 ×{}"),
+    ("marker.accumulated", "累加剪贴板"),
+];
+
+/// 英文
+const EN: &[(&str, &str)] = &[
+    // 导航
+    ("nav.status", "Status"),
+    ("nav.history", "History"),
+    ("nav.settings", "Settings"),
+    // 状态页面
+    ("status.recording", "Recording"),
+    ("status.paused", "Paused"),
+    ("status.starting", "Starting soon"),
+    ("status.safe_mode", "Safe mode"),
+    ("status.safe_mode_hint", "Input is not being recorded. Fix the settings or turn off autostart, then restart EchoKey"),
+    ("status.today_input", "Typed today"),
+    ("status.char_count", "{} chars"),
+    ("status.goal", "Daily goal: {} chars"),
+    ("status.goal_reached", "🎉 You reached today's goal of {} chars"),
+    ("status.line_count", "{} lines"),
+    ("status.verbose", "🔍 Verbose capture is on (off after restart)"),
+    ("status.date_format", "%Y-%m-%d %H:%M"),
+    ("status.resume", "▶ Resume"),
+    ("status.pause", "⏸ Pause"),
+    ("status.new_segment", "📝 New segment"),
+    ("status.open_log_dir", "📂 Open log folder"),
+    ("status.segment_created", "New log segment created"),
+    ("status.segment_empty", "Current segment is empty"),
+    ("status.dropped_events", "⚠ {} key events were dropped"),
+    ("status.write_busy", "⏳ Writing is busy"),
+    // Focus sessions
+    ("focus.start", "⏱ Start focus ({} min)"),
+    ("focus.running", "Focusing"),
+    ("focus.paused", "Focus paused"),
+    ("focus.chars", "{} chars typed this session"),
+    ("focus.pause", "Pause timer"),
+    ("focus.resume", "Resume timer"),
+    ("focus.stop", "End focus"),
+    ("focus.finished", "Focus session finished"),
+    ("status.write_busy_hint", "Input is queued and will be written shortly"),
+    ("status.remote_session", "🖥 Remote desktop session: capture may be incomplete"),
+    ("status.remote_session_hint", "The keyboard hook may miss keys in remote sessions; enable polling capture in Settings if the hook cannot be installed"),
+    ("status.hook_lost", "⚠ Windows removed the keyboard hook, reinstalling"),
+    ("status.hook_lost_hint", "Windows removes the keyboard hook when it responds too slowly on a busy system; input while it was removed is not logged. EchoKey reinstalls it automatically"),
+    ("status.hook_restores", "Hook restored ({} times)"),
+    ("status.hook_restored", "Keyboard hook restored"),
+    ("status.paste_progress", "Writing pasted text {}"),
+    // 实时预览
+    ("preview.title", "Live preview"),
+    ("preview.excerpt", "📌 Excerpt to log"),
+    ("preview.excerpt_written", "Excerpt written"),
+    ("preview.excerpt_paused", "Recording is paused; resume to add excerpts"),
+    ("preview.copy", "📋 Copy excerpt"),
+    ("common.copied", "Copied to clipboard"),
+    // 历史页面
+    ("history.search_hint", "🔍 Search logs..."),
+    ("history.search", "Search"),
+    ("history.match_count", "{} matches found"),
+    ("history.no_matches", "No matches in any log"),
+    ("history.searching", "Searching all logs…"),
+    ("history.search_truncated", "Showing the first {} lines"),
+    ("history.recent", "Recent logs"),
+    ("history.empty", "No log files yet"),
+    ("history.filter_hint", "Filter by date, e.g. 0105"),
+    ("history.filter_count", "{} matching files"),
+    ("history.back", "← Back"),
+    ("history.copy_all", "📋 Copy all"),
+    ("history.collapse", "Merge short lines"),
+    ("history.group_by_app", "Group by app"),
+    ("history.default_app", "Other apps"),
+    ("history.hide_sequences", "Hide sequence numbers"),
+    ("history.chars_suffix", " chars"),
+    ("history.seek_hint", "14:30"),
+    ("history.seek", "⏱ Go to"),
+    ("history.seek_invalid", "Time should look like HH:MM"),
+    ("history.compare_days", "Compare two days"),
+    ("history.pick_date", "Pick a date"),
+    ("history.compare", "Compare"),
+    ("history.no_diff", "No differences between the two days"),
+    ("history.diff_truncated", "⚠ Log too large, {} lines were left out of the comparison"),
+    ("history.archived_badge", "Archived"),
+    ("history.allow_changes", "🔓 Allow changes"),
+    ("history.read_only", "Archived logs are read-only until you allow changes"),
+    ("history.in_use", "Today's log is being written and can't be deleted or renamed"),
+    ("history.delete", "🗑 Delete"),
+    ("history.delete_title", "Delete log"),
+    ("history.delete_confirm", "Delete {}? The file is moved to the recycle bin (or deleted permanently if unsupported)."),
+    ("history.trashed", "Moved to the recycle bin"),
+    ("history.deleted", "Deleted permanently"),
+    ("history.rename", "✏ Rename"),
+    ("history.export_pdf", "📄 Export PDF"),
+    ("history.pdf_exported", "Exported PDF ({} pages)"),
+    ("history.rename_title", "Rename log"),
+    ("history.renamed", "Renamed"),
+    ("history.cancel", "Cancel"),
+    // 命令面板
+    ("palette.title", "Commands"),
+    ("palette.hint", "Type to filter, Enter to run, Esc to close"),
+    ("palette.empty", "No matching commands"),
+    ("palette.export_markdown", "📄 Export as Markdown"),
+    ("palette.jump_to_date", "📅 Go to {}"),
+    ("palette.exported", "Exported to {}"),
+    // 访问密码
+    ("lock.title", "🔒 Passcode required"),
+    ("lock.hint", "Enter passcode"),
+    ("lock.unlock", "Unlock"),
+    ("lock.wrong", "Wrong passcode"),
+    ("lock.rate_limited", "Too many attempts, try again in {} s"),
+    // 设置页面
+    ("settings.general", "General"),
+    ("settings.autostart", "Start at login"),
+    ("settings.autostart_enabled", "Start at login enabled"),
+    ("settings.autostart_disabled", "Start at login disabled"),
+    ("settings.language", "Language"),
+    ("settings.language_auto", "System default"),
+    ("settings.marker_language", "Log marker language"),
+    ("settings.theme", "Theme"),
+    ("theme.light", "Light"),
+    ("theme.dark", "Dark"),
+    ("theme.system", "Follow system"),
+    ("theme.high_contrast", "High contrast"),
+    ("settings.font_size", "Font size"),
+    ("settings.font_file", "CJK font file (leave empty to detect a system font)"),
+    ("settings.font_hint", "e.g. C:\\Windows\\Fonts\\msyh.ttc"),
+    ("settings.apply", "Apply"),
+    ("settings.font_updated", "Font updated"),
+    ("settings.font_failed", "Could not load the font file"),
+    ("settings.passcode", "Passcode"),
+    ("settings.passcode_locked", "Unlock the History page first"),
+    ("settings.passcode_new", "New passcode"),
+    ("settings.passcode_set_hint", "Set a passcode"),
+    ("settings.save", "Save"),
+    ("settings.passcode_saved", "Passcode set"),
+    ("settings.remove", "Remove"),
+    ("settings.passcode_removed", "Passcode removed"),
+    ("settings.lock", "Lock"),
+    ("settings.always_on_top", "Keep window on top"),
+    ("settings.tee_stdout", "Also write to stdout (for debugging)"),
+    ("settings.log_location", "Log location"),
+    ("settings.browse", "Browse…"),
+    ("settings.advanced", "Advanced"),
+    ("settings.advanced_hint", "Click Apply to save your changes; invalid changes cannot be applied"),
+    ("settings.revert", "Discard changes"),
+    ("settings.restart_required", "Requires restart"),
+    ("settings.group_recording", "Recording"),
+    ("settings.group_input", "Input"),
+    ("settings.group_window", "Window"),
+    ("settings.group_startup", "Startup"),
+    ("settings.group_shortcuts", "Shortcuts"),
+    ("settings.log_directory", "Log directory"),
+    ("settings.log_directory_default", "Default location"),
+    ("settings.reset", "Reset"),
+    ("settings.capture_shortcut", "Press the new key combination… (Esc to cancel)"),
+    ("settings.clear_shortcut", "Clear"),
+    ("config.session_shorter_than_stamp", "The session timeout cannot be shorter than the timestamp timeout"),
+    ("config.channel_capacity_zero", "The event queue capacity must be greater than 0"),
+    ("config.clipboard_watch_too_short", "The clipboard check interval cannot be shorter than {} ms"),
+    ("config.autorepeat_interval_zero", "The repeat interval must be greater than 0 when collapsing held keys"),
+    ("config.unknown_timezone", "Unknown time zone: {}"),
+    ("config.invalid_segment_times", "Unrecognized times (expected HH:MM): {}"),
+    ("config.header_control_character", "The header template cannot contain control characters"),
+    ("config.header_unknown_token", "Unknown placeholder in the header template: {}"),
+    ("settings.timestamp_timeout", "New timestamp after idle (s, 0 = every character)"),
+    ("settings.session_timeout", "New session after idle (min)"),
+    ("settings.pause_marker_min", "Minimum pause for a marker (s, 0 = always)"),
+    ("settings.resume_style", "After resuming"),
+    ("settings.resume_new_line", "Start a new line"),
+    ("settings.resume_inline", "Continue the paused line"),
+    ("settings.compact_whitespace", "Collapse consecutive blank lines"),
+    ("settings.backspace_deletes", "Backspace deletes the last typed character (off: log ⌫)"),
+    ("settings.flush_interval", "Write batching interval (ms, 0 = write on every keystroke)"),
+    ("settings.max_file_mb", "Max log file size (MB, 0 = unlimited)"),
+    ("settings.window_titles", "Show the active window title after each timestamp"),
+    ("settings.window_marker_min", "Log window switches after staying (ms)"),
+    ("settings.pause_on_lock", "Pause recording while the screen is locked"),
+    ("settings.daily_goal", "Daily typing goal (chars, 0 = off)"),
+    ("settings.daily_goal_notify", "Notify when the goal is reached"),
+    ("settings.focus_minutes", "Focus session length (minutes)"),
+    ("settings.sequence_numbers", "Entry numbers"),
+    ("settings.sequence_off", "Off"),
+    ("settings.sequence_per_file", "Per file"),
+    ("settings.sequence_global", "Continuous"),
+    ("settings.log_format", "Output format"),
+    ("settings.log_format_text", "Plain text (.log)"),
+    ("settings.log_format_jsonl", "JSON lines (.jsonl)"),
+    ("settings.split_by_app", "Separate log file per app"),
+    ("settings.timezone", "Log time zone (empty = system)"),
+    ("settings.segment_times", "Daily segment times (HH:MM, comma-separated)"),
+    ("settings.device_label", "Device label"),
+    ("settings.device_session_marker", "Write device marker on start"),
+    ("settings.app_markers", "Write start/exit markers"),
+    ("settings.custom_header", "Custom log header"),
+    ("settings.header_template_hint", "Leave empty for no header"),
+    ("settings.log_win_combos", "Log Win key combinations"),
+    ("settings.verbose_keys", "Log unrecognized keys, Tab, Delete and arrow keys"),
+    ("settings.capture_ime_text", "Log text committed by the IME (needs UI Automation support)"),
+    ("settings.raw_key_log", "Log every key press and release (much larger logs)"),
+    ("settings.log_caps_lock", "Log Caps Lock changes"),
+    ("settings.log_copies", "Log copied text (Ctrl+C)"),
+    ("settings.watch_clipboard", "Watch the clipboard and log text copied any way (interval ms)"),
+    ("settings.remote_polling_fallback", "Fall back to polling in remote desktop sessions"),
+    ("settings.exclude_own_window", "Ignore input in the EchoKey window"),
+    ("settings.dedup_pastes", "Merge repeated pastes within (ms)"),
+    ("settings.max_paste_chars", "Max characters logged per paste (0 = unlimited)"),
+    ("settings.save_pasted_images", "Save pasted images to the attachments folder"),
+    ("settings.autorepeat_min_run", "Collapse key repeats after (0 = off)"),
+    ("settings.autorepeat_interval", "Maximum key-repeat interval (ms)"),
+    ("settings.key_dedup", "Drop repeated key events within (ms, 0 = off)"),
+    ("settings.char_dedup", "Drop doubled characters within (ms, 0 = off)"),
+    ("settings.verify_interval", "Verification snapshot interval (s, 0 = off)"),
+    ("settings.clipboard_retry", "Clipboard retries when busy"),
+    ("settings.clipboard_retry_delay", "Clipboard retry delay (ms)"),
+    ("settings.auto_hide", "Hide window after switching away (s)"),
+    ("settings.pulse_active", "Typing pulse duration (s)"),
+    ("settings.gui_update_interval", "Counter refresh interval (ms)"),
+    ("settings.gui_update_chars", "Refresh counter every N keys"),
+    ("settings.startup_delay", "Delay recording after autostart (s)"),
+    ("settings.keep_recent_days", "Keep only the most recent days of logs (older ones deleted at startup)"),
+    ("settings.retention_days", "Delete logs older than (days, at startup)"),
+    ("settings.compress_after_days", "Compress logs older than (days, gzipped at startup)"),
+    ("settings.event_channel_capacity", "Key event queue capacity"),
+    ("settings.backlog_busy_events", "Show busy indicator at queued events"),
+    ("settings.stats", "Typing statistics"),
+    ("settings.stats_include_empty", "Include days without logs (all-zero rows)"),
+    ("settings.stats_export", "📊 Export CSV"),
+    ("settings.stats_exported", "Exported statistics for {} days"),
+    ("settings.open", "Open"),
+    ("settings.shortcuts", "Shortcuts"),
+    ("shortcut.toggle_pause", "Pause/resume recording"),
+    ("shortcut.paste", "Paste (log clipboard content)"),
+    ("shortcut.manual_save", "Save clipboard manually"),
+    ("shortcut.new_segment", "New log segment"),
+    ("shortcut.checkpoint", "Insert checkpoint"),
+    ("shortcut.toggle_focus", "Start/end focus session"),
+    ("shortcut.command_palette", "Command palette (in window)"),
+    ("shortcut.unbound", "Not set"),
+    ("settings.about", "About"),
+    ("app.tagline", "Every keystroke you type has an echo"),
+    // 托盘
+    ("tray.show", "Show window"),
+    ("tray.pause", "Pause recording"),
+    ("tray.new_segment", "New log segment"),
+    ("tray.open_log_dir", "Open log folder"),
+    ("tray.verbose", "Verbose capture (this session)"),
+    ("tray.accumulate", "Start/stop collecting clipboard"),
+    ("tray.quit", "Quit"),
+    ("tray.tooltip_recording", "EchoKey - Recording"),
+    ("tray.tooltip_paused", "EchoKey - Paused"),
+    // 日志标记
+    ("accumulate.start", "📋 Collect clipboard"),
+    ("accumulate.title", "Collected clipboard · {} snippets"),
+    ("accumulate.hint", "Copied text is appended here (up to {} chars)"),
+    ("accumulate.full", "⚠ Limit reached, {} snippets were not added"),
+    ("accumulate.write", "Write to log"),
+    ("accumulate.written", "Written to log"),
+    ("accumulate.stop", "Stop and write to log"),
+    ("marker.paste", "Paste"),
+    ("marker.copy", "Copy"),
+    ("marker.paste_files", "Pasted files"),
+    ("marker.paste_image", "Pasted image"),
+    ("marker.paste_unknown", "Paste: unknown format"),
+    ("marker.paste_truncated", "…(truncated, {} chars total)"),
+    ("marker.manual_save", "Manual save"),
+    ("marker.excerpt", "Excerpt"),
+    ("marker.verify", "Verify"),
+    ("marker.pause", "Recording paused"),
+    ("marker.resume", "Recording resumed"),
+    ("marker.secure_desktop", "Secure desktop"),
+    ("marker.secure_desktop_end", "Secure desktop ended"),
+    ("marker.remote_session", "Remote desktop session, capture may be incomplete"),
+    ("marker.remote_session_end", "Remote desktop session ended"),
+    ("marker.screen_locked", "Screen locked, recording paused"),
+    ("marker.screen_unlocked", "Screen unlocked, recording resumed"),
+    ("marker.dropped_events", "{} events lost"),
+    ("marker.device", "Device: {}"),
+    ("marker.checkpoint", "Checkpoint"),
+    ("marker.focus_start", "Focus started"),
+    ("marker.focus_end", "Focus ended: {chars} chars, {minutes} min"),
+    ("marker.app_start", "EchoKey started"),
+    ("marker.app_exit", "EchoKey exited"),
+    ("marker.unknown_key", "Unknown key:{}"),
+    ("marker.window", "Window: {}"),
+    ("marker.caps_lock_on", "Caps Lock on"),
+    ("marker.caps_lock_off", "Caps Lock off"),
+    ("marker.autorepeat", "held ×{}"),
+    ("marker.accumulated", "Collected clipboard"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_key_falls_back_to_key() {
+        assert_eq!(translate(Locale::En, "no.such.key"), "no.such.key");
+        assert_eq!(translate(Locale::En, "nav.history"), "History");
+        assert_eq!(translate(Locale::ZhCn, "nav.history"), "历史");
+    }
+
+    #[test]
+    fn test_tables_have_same_keys() {
+        for (key, _) in ZH_CN {
+            assert!(lookup(EN, key).is_some(), "英文缺少翻译: {}", key);
+        }
+        for (key, _) in EN {
+            assert!(lookup(ZH_CN, key).is_some(), "简体中文缺少翻译: {}", key);
+        }
+    }
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("zh-CN"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_tag("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr-FR"), None);
+    }
+}
@@ -0,0 +1,212 @@
+//! 输入法上屏文字记录模块（Windows）
+//!
+//! 低级键盘钩子只能看到按键，看不到输入法组字后上屏的文字（拼音 `nihao` → `你好`）。
+//! 输入法上屏时发出的 WM_IME_CHAR/WM_CHAR 只发给目标程序自己的窗口，
+//! 本进程的窗口（包括仅消息窗口）收不到，因此改用 UI Automation 的 TextEdit 事件：
+//! - 组字更新（Composition）：标记正在组字，此时的按键只是拼写，不记录
+//! - 组字完成（CompositionFinalized）：把上屏的文字作为字符事件发送
+//!
+//! 另外定时查询前台窗口的输入法是否处于中文等本地语言模式，
+//! 这时按下的字母是组字的开头，同样交给输入法。
+//! 不支持 TextEdit 事件的程序中无法记录上屏的文字，关闭本功能即可恢复按键记录。
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use windows::core::{implement, Interface, Result, BSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED, SAFEARRAY,
+};
+use windows::Win32::System::Ole::{SafeArrayGetElement, SafeArrayGetLBound, SafeArrayGetUBound};
+use windows::Win32::UI::Accessibility::{
+    CUIAutomation, IUIAutomation, IUIAutomation3, IUIAutomationCacheRequest, IUIAutomationElement,
+    IUIAutomationTextEditTextChangedEventHandler, IUIAutomationTextEditTextChangedEventHandler_Impl,
+    TextEditChangeType, TextEditChangeType_Composition, TextEditChangeType_CompositionFinalized,
+    TreeScope_Subtree,
+};
+use windows::Win32::UI::Input::Ime::ImmGetDefaultIMEWnd;
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, SendMessageTimeoutW, SMTO_ABORTIFHUNG, WM_IME_CONTROL,
+};
+
+use crate::keyboard_win;
+
+/// 是否记录输入法上屏的文字（默认关闭）
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 已成功注册 TextEdit 事件，注册失败时照常记录按键
+static AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// 焦点控件中正在组字
+static COMPOSING: AtomicBool = AtomicBool::new(false);
+
+/// 前台窗口的输入法处于本地语言模式（中文、日文假名、韩文等）
+static NATIVE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// 监听线程只启动一次，关闭功能后保持运行但不再处理事件
+static START: Once = Once::new();
+
+/// 查询前台窗口输入法状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 查询输入法状态时等待目标窗口响应的最长时间（毫秒），程序无响应时不阻塞
+const IME_QUERY_TIMEOUT_MS: u32 = 50;
+
+/// WM_IME_CONTROL 的子命令：输入法是否打开
+const IMC_GETOPENSTATUS: usize = 0x0005;
+
+/// WM_IME_CONTROL 的子命令：转换模式
+const IMC_GETCONVERSIONMODE: usize = 0x0001;
+
+/// 转换模式中的本地语言标志（IME_CMODE_NATIVE）
+const IME_CMODE_NATIVE: usize = 0x0001;
+
+/// 开启/关闭输入法上屏文字的记录，第一次开启时启动监听线程
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        COMPOSING.store(false, Ordering::Relaxed);
+        NATIVE_MODE.store(false, Ordering::Relaxed);
+        return;
+    }
+    START.call_once(|| {
+        thread::spawn(run);
+    });
+}
+
+/// 是否正在组字：此时的按键（含 Enter、退格）作用于输入法的组字窗口
+pub fn composing() -> bool {
+    active() && COMPOSING.load(Ordering::Relaxed)
+}
+
+/// 输入法是否处于本地语言模式：此时按下的字母会开始组字
+pub fn native_mode() -> bool {
+    active() && NATIVE_MODE.load(Ordering::Relaxed)
+}
+
+/// 功能已开启且 TextEdit 事件可用
+fn active() -> bool {
+    ENABLED.load(Ordering::Relaxed) && AVAILABLE.load(Ordering::Relaxed)
+}
+
+/// 监听线程：注册 TextEdit 事件，之后定时查询前台窗口的输入法状态
+fn run() {
+    // 事件处理器注册在 automation 上，线程退出前必须保持它存活
+    let _automation = match register() {
+        Ok(automation) => {
+            AVAILABLE.store(true, Ordering::Relaxed);
+            Some(automation)
+        }
+        Err(e) => {
+            eprintln!("警告: 无法监听输入法上屏的文字: {:?}", e);
+            None
+        }
+    };
+    loop {
+        let native = ENABLED.load(Ordering::Relaxed) && unsafe { native_mode_of(GetForegroundWindow()) };
+        NATIVE_MODE.store(native, Ordering::Relaxed);
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// 在整个桌面上注册组字更新和组字完成事件
+fn register() -> Result<IUIAutomation> {
+    unsafe {
+        // 当前线程已用其他模式初始化 COM 时返回错误，不影响后续调用
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let automation: IUIAutomation = CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER)?;
+        // TextEdit 事件需要 Windows 8.1 及以上
+        let automation3: IUIAutomation3 = automation.cast()?;
+        let root = automation.GetRootElement()?;
+        let handler: IUIAutomationTextEditTextChangedEventHandler = CompositionHandler.into();
+        for change in [TextEditChangeType_Composition, TextEditChangeType_CompositionFinalized] {
+            automation3.AddTextEditTextChangedEventHandler(
+                &root,
+                TreeScope_Subtree,
+                change,
+                None::<&IUIAutomationCacheRequest>,
+                &handler,
+            )?;
+        }
+        Ok(automation)
+    }
+}
+
+/// TextEdit 事件处理器（在 UI Automation 的线程上调用）
+#[implement(IUIAutomationTextEditTextChangedEventHandler)]
+struct CompositionHandler;
+
+impl IUIAutomationTextEditTextChangedEventHandler_Impl for CompositionHandler_Impl {
+    fn HandleTextEditTextChangedEvent(
+        &self,
+        sender: Option<&IUIAutomationElement>,
+        change: TextEditChangeType,
+        strings: *const SAFEARRAY,
+    ) -> Result<()> {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        // 密码框中的输入不记录
+        let password = sender.is_some_and(|element| unsafe {
+            element.CurrentIsPassword().is_ok_and(|password| password.as_bool())
+        });
+        let text = if password { None } else { unsafe { first_string(strings) } };
+        if change == TextEditChangeType_Composition {
+            // 组字被取消（Esc 或删光拼音）时组字串为空
+            COMPOSING.store(text.is_some_and(|t| !t.is_empty()), Ordering::Relaxed);
+        } else if change == TextEditChangeType_CompositionFinalized {
+            COMPOSING.store(false, Ordering::Relaxed);
+            if let Some(text) = text.filter(|t| !t.is_empty()) {
+                keyboard_win::send_ime_text(&text);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 事件附带的第一个字符串（组字串或上屏的文字）
+unsafe fn first_string(strings: *const SAFEARRAY) -> Option<String> {
+    if strings.is_null() {
+        return None;
+    }
+    let lower = SafeArrayGetLBound(strings, 1).ok()?;
+    let upper = SafeArrayGetUBound(strings, 1).ok()?;
+    if upper < lower {
+        return None;
+    }
+    let mut value = BSTR::new();
+    SafeArrayGetElement(strings, &lower, &mut value as *mut BSTR as *mut c_void).ok()?;
+    Some(value.to_string())
+}
+
+/// 窗口的输入法是否打开且处于本地语言模式
+unsafe fn native_mode_of(hwnd: HWND) -> bool {
+    if hwnd.is_invalid() {
+        return false;
+    }
+    let ime = ImmGetDefaultIMEWnd(hwnd);
+    if ime.is_invalid() {
+        return false;
+    }
+    ime_control(ime, IMC_GETOPENSTATUS).is_some_and(|open| open != 0)
+        && ime_control(ime, IMC_GETCONVERSIONMODE).is_some_and(|mode| mode & IME_CMODE_NATIVE != 0)
+}
+
+/// 向输入法的默认窗口发送 WM_IME_CONTROL 查询，超时或失败时返回 None
+unsafe fn ime_control(ime: HWND, command: usize) -> Option<usize> {
+    let mut result = 0usize;
+    let sent = SendMessageTimeoutW(
+        ime,
+        WM_IME_CONTROL,
+        WPARAM(command),
+        LPARAM(0),
+        SMTO_ABORTIFHUNG,
+        IME_QUERY_TIMEOUT_MS,
+        Some(&mut result as *mut usize),
+    );
+    (sent.0 != 0).then_some(result)
+}
@@ -6,48 +6,46 @@
 //! - 过滤 LLKHF_INJECTED 标志，避免软件注入事件的重复
 //! - 时间戳去重：防止短时间内的重复事件
 //! - 使用 GetMessage 消息循环，确保钩子稳定运行
-//! - 不在钩子回调中调用 ToUnicode，避免破坏键盘状态
+//! - ToUnicode 不改变键盘状态，死键由本模块记住并与下一个按键组合（´ + e → é）
 
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{SyncSender, TrySendError};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::time::{Instant, Duration};
 use once_cell::sync::Lazy;
 
-use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use crate::event::{self, InputEvent, NavKey, WindowInfo};
+use crate::{clipboard, ime_win, session_win};
+use crate::shortcuts::{self, Binding, KeyDedup, Modifiers, ShortcutAction};
+
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{BOOL, CloseHandle, HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
+use windows::Win32::System::StationsAndDesktops::{
+    CloseDesktop, GetThreadDesktop, GetUserObjectInformationW, OpenInputDesktop,
+    DESKTOP_CONTROL_FLAGS, DESKTOP_READOBJECTS, HDESK, UOI_NAME,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcessId, GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW,
+    PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
     CallNextHookEx, SetWindowsHookExW, UnhookWindowsHookEx,
-    GetMessageW, TranslateMessage, DispatchMessageW, PostQuitMessage,
-    HHOOK, KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL,
-    WM_KEYDOWN, WM_SYSKEYDOWN,
+    GetMessageW, PeekMessageW, TranslateMessage, DispatchMessageW, PostThreadMessageW, GetWindowTextW,
+    GetForegroundWindow, GetWindowThreadProcessId, GetSystemMetrics,
+    EnumWindows, IsIconic, SetForegroundWindow, ShowWindow, SW_RESTORE, SW_SHOW,
+    HHOOK, KBDLLHOOKSTRUCT, MSG, PM_NOREMOVE, PM_REMOVE, SM_REMOTESESSION, WH_KEYBOARD_LL, WM_QUIT,
+    WM_APP, WM_USER, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP, EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT,
 };
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    GetKeyState, GetKeyboardState, ToUnicode,
-    VK_CONTROL, VK_SHIFT, VK_MENU, VK_RETURN, VK_BACK,
+    GetAsyncKeyState, GetKeyState, GetKeyboardState, MapVirtualKeyW, SetKeyboardState, ToUnicode,
+    MAPVK_VK_TO_VSC,
+    VK_CONTROL, VK_SHIFT, VK_MENU, VK_RETURN, VK_BACK, VK_CAPITAL,
     VK_LCONTROL, VK_RCONTROL, VK_LSHIFT, VK_RSHIFT, VK_LMENU, VK_RMENU,
+    VK_LWIN, VK_RWIN, VK_C, VK_TAB, VK_DELETE, VK_LEFT, VK_RIGHT, VK_UP, VK_DOWN, VK_HOME, VK_END,
     VIRTUAL_KEY,
 };
 
-/// 键盘事件类型
-#[derive(Debug, Clone)]
-pub enum KeyboardEvent {
-    /// 普通字符输入
-    Character(char),
-    /// Enter 键（换行并添加时间戳）
-    Enter,
-    /// Ctrl+Enter（只换行，不添加时间戳）
-    CtrlEnter,
-    /// Backspace 键
-    Backspace,
-    /// 粘贴操作（Ctrl+V）
-    Paste,
-    /// 手动保存（Ctrl+Shift+S）
-    ManualSave,
-    /// 暂停/恢复（Ctrl+Shift+P）
-    TogglePause,
-    /// 新建日志段（Ctrl+Shift+N）
-    NewSegment,
-}
-
 /// 线程安全的钩子句柄包装
 struct HookHandle(HHOOK);
 unsafe impl Send for HookHandle {}
@@ -56,30 +54,74 @@ unsafe impl Sync for HookHandle {}
 /// 全局钩子句柄
 static HOOK_HANDLE: Lazy<Mutex<Option<HookHandle>>> = Lazy::new(|| Mutex::new(None));
 
-/// 全局事件发送器
-static EVENT_SENDER: Lazy<Mutex<Option<Sender<KeyboardEvent>>>> = Lazy::new(|| Mutex::new(None));
+/// 全局事件发送器（有界通道，钩子回调中不能阻塞）
+static EVENT_SENDER: Lazy<Mutex<Option<SyncSender<InputEvent>>>> = Lazy::new(|| Mutex::new(None));
 
-/// 去重状态：记录上一次按键的虚拟键码和时间
-static LAST_KEY_EVENT: Lazy<Mutex<LastKeyEvent>> = Lazy::new(|| {
-    Mutex::new(LastKeyEvent {
-        vk_code: 0,
-        scan_code: 0,
-        time: None,
-    })
-});
+/// 通道已满时丢弃的事件数（业务逻辑线程取走后清零）
+static DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
 
-/// 上一次按键事件信息
-struct LastKeyEvent {
-    vk_code: u32,
-    scan_code: u32,
-    time: Option<Instant>,
-}
+/// 当前生效的快捷键表
+static SHORTCUTS: Lazy<Mutex<Vec<Binding>>> = Lazy::new(|| Mutex::new(shortcuts::default_bindings()));
+
+/// 是否以 `[Win+L]` 形式记录 Win 组合键（默认只屏蔽，不记录）
+static LOG_WIN_COMBOS: AtomicBool = AtomicBool::new(false);
+
+/// 详细模式：记录转换不出字符的可打印按键，以及 Tab、Delete、方向键（默认关闭）
+static LOG_UNKNOWN_KEYS: AtomicBool = AtomicBool::new(false);
+
+/// 大写锁定切换时发送 `CapsLock` 事件（默认关闭）
+static LOG_CAPS_LOCK: AtomicBool = AtomicBool::new(false);
+
+/// 按 Ctrl+C 时发送 `Copy` 事件（默认关闭）
+static LOG_COPIES: AtomicBool = AtomicBool::new(false);
+
+/// 原始按键记录：每个物理按键的按下和松开（含修饰键）都发送 `Raw` 事件（默认关闭）
+static RAW_MODE: AtomicBool = AtomicBool::new(false);
+
+/// 远程桌面会话中无法安装键盘钩子时，改用 GetAsyncKeyState 轮询采集（默认关闭）
+static REMOTE_POLLING_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+/// 当前是否在使用轮询方式采集（没有安装键盘钩子）
+static POLLING_CAPTURE: AtomicBool = AtomicBool::new(false);
+
+/// 去重状态：记录上一次按键的键码、修饰键状态和时间
+static LAST_KEY_EVENT: Lazy<Mutex<KeyDedup>> = Lazy::new(|| Mutex::new(KeyDedup::default()));
+
+/// 钩子回调最近一次被调用的时刻（相对 [`HOOK_CLOCK`] 的毫秒数），看门狗据此判断钩子是否被系统移除
+static LAST_HOOK_CALL: AtomicU64 = AtomicU64::new(0);
+
+/// [`LAST_HOOK_CALL`] 的计时起点
+static HOOK_CLOCK: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// 安装了钩子的键盘监听线程 ID，0 表示没有（未安装、轮询采集或已退出）
+static HOOK_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+/// 运行 [`start_listening`] 的线程 ID，0 表示没有在监听；[`stop_listening`] 向它发送 WM_QUIT
+static LISTENER_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+/// 已请求停止监听（可能在监听开始之前请求，例如启动延迟期间退出）
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 看门狗请求键盘监听线程重新安装钩子的线程消息
+const WM_REINSTALL_HOOK: u32 = WM_APP + 1;
+
+/// 等待与下一个按键组合的死键（重音符号的空格形式，如 `´`）
+static PENDING_DEAD_KEY: Mutex<Option<char>> = Mutex::new(None);
+
+/// ToUnicode 的标志：不改变键盘状态（Windows 10 1607 起）
+///
+/// 死键的状态保存在系统的键盘状态中，钩子里转换字符时如果改变了它，
+/// 前台程序就收不到死键，无法合成带重音的字符。死键改为由 [`PENDING_DEAD_KEY`] 记住。
+const TOUNICODE_KEEP_STATE: u32 = 0x4;
 
 /// LLKHF_INJECTED 标志值 (0x10)
 const INJECTED_FLAG: u32 = 0x10;
 
-/// 去重时间窗口（毫秒）- 同一按键在此时间内只记录一次
-const DEDUP_WINDOW_MS: u64 = 30;
+/// 去重时间窗口（毫秒）- 同一按键在此时间内只记录一次，0 表示不去重
+static DEDUP_WINDOW_MS: AtomicU64 = AtomicU64::new(shortcuts::DEFAULT_KEY_DEDUP_MS);
+
+/// 轮询采集的间隔：短于这个时间的按键可能漏掉
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 /// 低级键盘钩子回调函数
 /// 
@@ -93,6 +135,7 @@ unsafe extern "system" fn low_level_keyboard_proc(
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    note_hook_call();
     if code >= 0 {
         let kbd = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
         
@@ -102,7 +145,7 @@ unsafe extern "system" fn low_level_keyboard_proc(
             return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
         }
         
-        // 只处理按键按下事件
+        // 只处理按键按下事件（原始按键记录另外记下松开）
         let msg_type = wparam.0 as u32;
         if msg_type == WM_KEYDOWN || msg_type == WM_SYSKEYDOWN {
             // 关键2：时间戳去重 - 防止短时间内的重复按键
@@ -110,7 +153,10 @@ unsafe extern "system" fn low_level_keyboard_proc(
                 return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
             }
             
-            process_key_down(kbd);
+            send_raw_key(kbd.vkCode, true);
+            process_key_down(kbd.vkCode, kbd.scanCode);
+        } else if msg_type == WM_KEYUP || msg_type == WM_SYSKEYUP {
+            send_raw_key(kbd.vkCode, false);
         }
     }
     
@@ -119,40 +165,34 @@ unsafe extern "system" fn low_level_keyboard_proc(
 
 /// 检查是否应该处理此按键（去重逻辑）
 /// 
-/// 如果与上一次按键相同且在时间窗口内，则跳过
+/// 如果与上一次按键相同（包括修饰键状态）且在时间窗口内，则跳过
 fn should_process_key(vk_code: u32, scan_code: u32) -> bool {
     let mut last_event = match LAST_KEY_EVENT.lock() {
         Ok(guard) => guard,
         Err(_) => return true, // 锁失败时默认处理
     };
     
-    let now = Instant::now();
-    let should_process = if let Some(last_time) = last_event.time {
-        // 如果是同一个按键且在去重窗口内，跳过
-        if last_event.vk_code == vk_code 
-            && last_event.scan_code == scan_code
-            && now.duration_since(last_time) < Duration::from_millis(DEDUP_WINDOW_MS) 
-        {
-            false
-        } else {
-            true
-        }
-    } else {
-        true
-    };
-    
-    // 更新上一次按键信息
-    if should_process {
-        last_event.vk_code = vk_code;
-        last_event.scan_code = scan_code;
-        last_event.time = Some(now);
+    last_event.should_process(
+        vk_code,
+        scan_code,
+        current_modifiers(),
+        Instant::now(),
+        Duration::from_millis(DEDUP_WINDOW_MS.load(Ordering::Relaxed)),
+    )
+}
+
+/// 当前按下的修饰键
+fn current_modifiers() -> Modifiers {
+    Modifiers {
+        ctrl: is_key_pressed(VK_CONTROL) || is_key_pressed(VK_LCONTROL) || is_key_pressed(VK_RCONTROL),
+        shift: is_key_pressed(VK_SHIFT) || is_key_pressed(VK_LSHIFT) || is_key_pressed(VK_RSHIFT),
+        alt: is_key_pressed(VK_MENU) || is_key_pressed(VK_LMENU) || is_key_pressed(VK_RMENU),
+        win: is_key_pressed(VK_LWIN) || is_key_pressed(VK_RWIN),
     }
-    
-    should_process
 }
 
 /// 处理按键按下事件
-fn process_key_down(kbd: &KBDLLHOOKSTRUCT) {
+fn process_key_down(vk_code: u32, scan_code: u32) {
     let sender_guard = match EVENT_SENDER.lock() {
         Ok(g) => g,
         Err(_) => return,
@@ -163,82 +203,283 @@ fn process_key_down(kbd: &KBDLLHOOKSTRUCT) {
         None => return,
     };
     
-    let vk = VIRTUAL_KEY(kbd.vkCode as u16);
+    let vk = VIRTUAL_KEY(vk_code as u16);
+    
+    // 死键只与紧接着的按键组合（修饰键除外：死键后按 Shift+E 组合为 É）
+    let dead_key = if shortcuts::is_modifier_vk(vk_code) { None } else { take_dead_key() };
     
     // 获取修饰键状态
-    let ctrl_pressed = is_key_pressed(VK_CONTROL) || is_key_pressed(VK_LCONTROL) || is_key_pressed(VK_RCONTROL);
-    let shift_pressed = is_key_pressed(VK_SHIFT) || is_key_pressed(VK_LSHIFT) || is_key_pressed(VK_RSHIFT);
-    let _alt_pressed = is_key_pressed(VK_MENU) || is_key_pressed(VK_LMENU) || is_key_pressed(VK_RMENU);
+    let modifiers = current_modifiers();
+    let (ctrl_pressed, win_pressed) = (modifiers.ctrl, modifiers.win);
+    
+    // 输入法正在组字：按键（含 Enter、退格）只作用于组字窗口，上屏的文字由 ime_win 记录
+    if ime_win::composing() && !ctrl_pressed && !win_pressed && !modifiers.alt {
+        return;
+    }
     
     // 处理特殊按键
     match vk {
         VK_RETURN => {
             let event = if ctrl_pressed {
-                KeyboardEvent::CtrlEnter
+                InputEvent::CtrlEnter
             } else {
-                KeyboardEvent::Enter
+                InputEvent::Enter
             };
-            let _ = sender.send(event);
+            send_event(sender, event);
             return;
         }
         VK_BACK => {
-            let _ = sender.send(KeyboardEvent::Backspace);
+            send_event(sender, InputEvent::Backspace);
+            return;
+        }
+        VK_CAPITAL if LOG_CAPS_LOCK.load(Ordering::Relaxed) => {
+            // 钩子在系统处理按键之前调用，此时读到的还是切换前的状态
+            send_event(sender, InputEvent::CapsLock(!caps_lock_on()));
             return;
         }
         _ => {}
     }
     
-    // 处理快捷键组合
+    // 处理快捷键组合（修饰键必须与绑定完全一致）
+    if let Some(action) = match_shortcut(modifiers, vk_code) {
+        send_event(sender, shortcut_event(action));
+        return;
+    }
+    
+    // 本次运行的详细记录模式（托盘开启）
+    let verbose = event::verbose();
+    
+    // 不带 Ctrl/Alt/Win 的 Tab、Delete 和方向键记录为编辑符号（Alt+Tab 等组合键不算）
+    if (verbose || LOG_UNKNOWN_KEYS.load(Ordering::Relaxed)) && !ctrl_pressed && !win_pressed && !modifiers.alt {
+        if let Some(event) = editing_key_event(vk) {
+            send_event(sender, event);
+            return;
+        }
+    }
+    
+    // Win 组合键（Win+D、Win+L 等）不记录字符，可选以符号形式记录
+    if win_pressed {
+        let is_win_key = vk == VK_LWIN || vk == VK_RWIN;
+        if !is_win_key && (verbose || LOG_WIN_COMBOS.load(Ordering::Relaxed)) {
+            send_event(sender, InputEvent::WinCombo(shortcuts::key_name(vk_code)));
+        }
+        return;
+    }
+    
+    // Ctrl 组合键不记录字符，详细模式下记录组合键名称
     if ctrl_pressed {
-        match vk.0 {
-            // Ctrl+V - 粘贴
-            0x56 if !shift_pressed => {
-                let _ = sender.send(KeyboardEvent::Paste);
-                return;
-            }
-            // Ctrl+Shift+S - 手动保存
-            0x53 if shift_pressed => {
-                let _ = sender.send(KeyboardEvent::ManualSave);
-                return;
-            }
-            // Ctrl+Shift+P - 暂停/恢复
-            0x50 if shift_pressed => {
-                let _ = sender.send(KeyboardEvent::TogglePause);
-                return;
+        // 只按着 Ctrl 的 Ctrl+C：开启复制记录时读取复制的内容
+        if vk == VK_C && modifiers.count() == 1 && LOG_COPIES.load(Ordering::Relaxed) {
+            clipboard::note_copy_pressed();
+            send_event(sender, InputEvent::Copy);
+            return;
+        }
+        if verbose && !shortcuts::is_modifier_vk(vk_code) {
+            send_event(sender, InputEvent::Key(shortcuts::combo_name(modifiers, vk_code)));
+        }
+        return;
+    }
+    
+    // 输入法处于中文等本地语言模式时，字母是组字的开头，不按键盘布局转换
+    if shortcuts::is_letter_vk(vk_code) && ime_win::native_mode() {
+        return;
+    }
+    
+    // 尝试将按键转换为字符
+    match vk_to_char(vk_code, scan_code) {
+        KeyText::Dead(accent) => match dead_key {
+            // 连按两个死键：两个重音符号都按原样输出
+            Some(previous) => send_text(sender, &shortcuts::compose_dead_key(previous, accent)),
+            None => {
+                if let Ok(mut pending) = PENDING_DEAD_KEY.lock() {
+                    *pending = Some(accent);
+                }
             }
-            // Ctrl+Shift+N - 新建日志段
-            0x4E if shift_pressed => {
-                let _ = sender.send(KeyboardEvent::NewSegment);
-                return;
+        },
+        KeyText::Char(c) if !c.is_control() => match dead_key {
+            Some(accent) => send_text(sender, &shortcuts::compose_dead_key(accent, c)),
+            None => send_event(sender, InputEvent::Character(c)),
+        },
+        KeyText::Char(_) => {
+            if verbose {
+                // Tab、Esc 等控制字符
+                send_event(sender, InputEvent::Key(shortcuts::combo_name(modifiers, vk_code)));
             }
-            _ => {
-                // Ctrl 组合键不记录字符
-                return;
+        }
+        // 应该产生字符却没有转换出来（部分非美式布局的 OEM 键），详细模式下记录键码
+        KeyText::None => {
+            let log_unknown = verbose || LOG_UNKNOWN_KEYS.load(Ordering::Relaxed);
+            if shortcuts::is_printable_vk(vk_code) {
+                if log_unknown {
+                    send_event(sender, InputEvent::UnknownKey(vk_code));
+                }
+            } else if verbose && !shortcuts::is_modifier_vk(vk_code) {
+                // 方向键、功能键等
+                send_event(sender, InputEvent::Key(shortcuts::combo_name(modifiers, vk_code)));
             }
         }
     }
-    
-    // 尝试将按键转换为字符
-    if let Some(c) = vk_to_char(kbd.vkCode, kbd.scanCode) {
-        if !c.is_control() {
-            let _ = sender.send(KeyboardEvent::Character(c));
+}
+
+/// Tab、Delete 和方向键对应的键盘事件
+fn editing_key_event(vk: VIRTUAL_KEY) -> Option<InputEvent> {
+    let key = match vk {
+        VK_TAB => return Some(InputEvent::Tab),
+        VK_DELETE => return Some(InputEvent::Delete),
+        VK_LEFT => NavKey::Left,
+        VK_RIGHT => NavKey::Right,
+        VK_UP => NavKey::Up,
+        VK_DOWN => NavKey::Down,
+        VK_HOME => NavKey::Home,
+        VK_END => NavKey::End,
+        _ => return None,
+    };
+    Some(InputEvent::Navigation(key))
+}
+
+/// 原始按键记录开启时发送按键的按下或松开
+fn send_raw_key(vk_code: u32, down: bool) {
+    // 轮询采集时 GetAsyncKeyState 也包括鼠标按键，不记录
+    if !RAW_MODE.load(Ordering::Relaxed) || matches!(vk_code, 0x01 | 0x02 | 0x04..=0x06) {
+        return;
+    }
+    if let Ok(guard) = EVENT_SENDER.lock() {
+        if let Some(sender) = guard.as_ref() {
+            send_event(sender, InputEvent::Raw { vk: vk_code, down });
         }
     }
 }
 
+/// 在当前快捷键表中查找匹配的动作
+fn match_shortcut(modifiers: Modifiers, vk_code: u32) -> Option<ShortcutAction> {
+    // 没有修饰键时不可能是快捷键，避免每次按键都加锁
+    if modifiers.count() == 0 {
+        return None;
+    }
+    let bindings = SHORTCUTS.lock().ok()?;
+    shortcuts::match_shortcut(&bindings, modifiers, vk_code)
+}
+
+/// 快捷键动作对应的键盘事件
+fn shortcut_event(action: ShortcutAction) -> InputEvent {
+    match action {
+        ShortcutAction::Paste => InputEvent::Paste,
+        ShortcutAction::ManualSave => InputEvent::ManualSave,
+        ShortcutAction::TogglePause => InputEvent::TogglePause,
+        ShortcutAction::NewSegment => InputEvent::NewSegment,
+        ShortcutAction::Checkpoint => InputEvent::Checkpoint,
+        ShortcutAction::ToggleFocus => InputEvent::ToggleFocus,
+    }
+}
+
+/// 发送事件，通道已满时丢弃并计数（不阻塞钩子回调）
+fn send_event(sender: &SyncSender<InputEvent>, event: InputEvent) {
+    match sender.try_send(event) {
+        Ok(()) => event::note_event_queued(),
+        Err(TrySendError::Full(_)) => {
+            DROPPED_EVENTS.fetch_add(1, Ordering::Relaxed);
+        }
+        Err(TrySendError::Disconnected(_)) => {}
+    }
+}
+
+/// 取走自上次调用以来丢弃的事件数
+pub fn take_dropped_events() -> u64 {
+    DROPPED_EVENTS.swap(0, Ordering::Relaxed)
+}
+
+/// 设置是否以 `[Win+L]` 形式记录 Win 组合键
+pub fn set_log_win_combos(enabled: bool) {
+    LOG_WIN_COMBOS.store(enabled, Ordering::Relaxed);
+}
+
+/// 设置是否记录转换不出字符的按键，以及 Tab、Delete、方向键（详细模式）
+pub fn set_log_unknown_keys(enabled: bool) {
+    LOG_UNKNOWN_KEYS.store(enabled, Ordering::Relaxed);
+}
+
+/// 设置是否记录每个物理按键的按下和松开（原始按键记录）
+pub fn set_raw_mode(enabled: bool) {
+    RAW_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// 设置是否在大写锁定切换时写入标记
+pub fn set_log_caps_lock(enabled: bool) {
+    LOG_CAPS_LOCK.store(enabled, Ordering::Relaxed);
+}
+
+/// 设置是否在按 Ctrl+C 时记录复制的内容
+pub fn set_log_copies(enabled: bool) {
+    LOG_COPIES.store(enabled, Ordering::Relaxed);
+}
+
+/// 设置按键去重的时间窗口（毫秒），0 表示不去重
+pub fn set_dedup_window(ms: u64) {
+    DEDUP_WINDOW_MS.store(ms, Ordering::Relaxed);
+}
+
+/// 设置远程桌面会话中无法安装键盘钩子时是否改用轮询采集（下次启动监听时生效）
+pub fn set_remote_polling_fallback(enabled: bool) {
+    REMOTE_POLLING_FALLBACK.store(enabled, Ordering::Relaxed);
+}
+
+/// 大写锁定当前是否开启
+pub fn caps_lock_on() -> bool {
+    unsafe { (GetKeyState(VK_CAPITAL.0 as i32) & 1) != 0 }
+}
+
+/// 替换快捷键表
+pub fn set_shortcuts(bindings: Vec<Binding>) {
+    if let Ok(mut guard) = SHORTCUTS.lock() {
+        *guard = bindings;
+    }
+}
+
 /// 检查按键是否被按下
 fn is_key_pressed(vk: VIRTUAL_KEY) -> bool {
     unsafe { (GetKeyState(vk.0 as i32) & 0x8000u16 as i16) != 0 }
 }
 
+/// 按键转换为字符的结果
+enum KeyText {
+    /// 普通字符
+    Char(char),
+    /// 死键（重音符号的空格形式），与下一个按键组合
+    Dead(char),
+    /// 不产生字符
+    None,
+}
+
+/// 取出等待组合的死键
+fn take_dead_key() -> Option<char> {
+    PENDING_DEAD_KEY.lock().ok().and_then(|mut pending| pending.take())
+}
+
+/// 把组合后的文字逐个字符发送
+fn send_text(sender: &SyncSender<InputEvent>, text: &str) {
+    for c in text.chars() {
+        send_event(sender, InputEvent::Character(c));
+    }
+}
+
+/// 发送输入法上屏的文字（由 [`ime_win`] 在 UI Automation 的线程上调用）
+pub fn send_ime_text(text: &str) {
+    if let Ok(guard) = EVENT_SENDER.lock() {
+        if let Some(sender) = guard.as_ref() {
+            send_text(sender, text);
+        }
+    }
+}
+
 /// 将虚拟键码转换为字符
 /// 
-/// 注意：此函数在钩子线程中调用，应尽量简单
-fn vk_to_char(vk_code: u32, scan_code: u32) -> Option<char> {
+/// 注意：此函数在钩子线程中调用，应尽量简单。
+/// 每个按键只调用一次 ToUnicode，并且不改变键盘状态（见 [`TOUNICODE_KEEP_STATE`]）。
+fn vk_to_char(vk_code: u32, scan_code: u32) -> KeyText {
     unsafe {
         let mut keyboard_state = [0u8; 256];
         if GetKeyboardState(&mut keyboard_state).is_err() {
-            return None;
+            return KeyText::None;
         }
         
         let mut buffer = [0u16; 4];
@@ -247,13 +488,14 @@ fn vk_to_char(vk_code: u32, scan_code: u32) -> Option<char> {
             scan_code,
             Some(&keyboard_state),
             &mut buffer,
-            0, // 不处理死键
+            TOUNICODE_KEEP_STATE,
         );
         
-        if result == 1 {
-            char::from_u32(buffer[0] as u32)
-        } else {
-            None
+        // 返回 -1 表示死键，缓冲区中是重音符号的空格形式
+        match (result, char::from_u32(buffer[0] as u32)) {
+            (1, Some(c)) => KeyText::Char(c),
+            (-1, Some(accent)) => KeyText::Dead(accent),
+            _ => KeyText::None,
         }
     }
 }
@@ -262,7 +504,7 @@ fn vk_to_char(vk_code: u32, scan_code: u32) -> Option<char> {
 /// 
 /// 此函数会在当前线程运行消息循环，直到收到退出信号。
 /// 必须在专用线程中调用。
-pub fn start_listening(sender: Sender<KeyboardEvent>) -> Result<(), String> {
+pub fn start_listening(sender: SyncSender<InputEvent>) -> Result<(), String> {
     // 保存发送器
     {
         let mut guard = EVENT_SENDER.lock()
@@ -270,7 +512,19 @@ pub fn start_listening(sender: Sender<KeyboardEvent>) -> Result<(), String> {
         *guard = Some(sender);
     }
     
-    // 安装钩子
+    // 先建立本线程的消息队列再公布线程 ID，之后发来的 WM_QUIT 不会丢失；
+    // 在此之前已请求停止的，直接返回
+    unsafe {
+        let mut msg = MSG::default();
+        let _ = PeekMessageW(&mut msg, None, WM_USER, WM_USER, PM_NOREMOVE);
+    }
+    LISTENER_THREAD_ID.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+    if STOP_REQUESTED.load(Ordering::Relaxed) {
+        finish_listening();
+        return Ok(());
+    }
+    
+    // 安装钩子（远程桌面会话中安装失败时可以改用轮询）
     let hook = unsafe {
         SetWindowsHookExW(
             WH_KEYBOARD_LL,
@@ -278,18 +532,79 @@ pub fn start_listening(sender: Sender<KeyboardEvent>) -> Result<(), String> {
             None,
             0,
         )
-    }.map_err(|e| format!("无法安装键盘钩子: {:?}", e))?;
+    };
+    let polling = match hook {
+        Ok(hook) => {
+            let mut guard = HOOK_HANDLE.lock()
+                .map_err(|_| "无法获取钩子句柄锁".to_string())?;
+            *guard = Some(HookHandle(hook));
+            eprintln!("键盘钩子已安装");
+            note_hook_call();
+            HOOK_THREAD_ID.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+            false
+        }
+        Err(e) if is_remote_session() && REMOTE_POLLING_FALLBACK.load(Ordering::Relaxed) => {
+            eprintln!("警告: 无法安装键盘钩子: {:?}，远程桌面会话中改用轮询方式采集", e);
+            true
+        }
+        Err(e) => {
+            finish_listening();
+            return Err(format!("无法安装键盘钩子: {:?}", e));
+        }
+    };
+    POLLING_CAPTURE.store(polling, Ordering::Relaxed);
     
-    {
-        let mut guard = HOOK_HANDLE.lock()
-            .map_err(|_| "无法获取钩子句柄锁".to_string())?;
-        *guard = Some(HookHandle(hook));
+    // 监听前台窗口切换（与键盘钩子共用本线程的消息循环）
+    let foreground_hook = unsafe {
+        SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            None,
+            Some(foreground_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT,
+        )
+    };
+    if foreground_hook.is_invalid() {
+        eprintln!("警告: 无法监听前台窗口切换");
     }
     
-    eprintln!("键盘钩子已安装");
+    // 报告当前的前台窗口（启动时 EchoKey 自己的窗口可能就在前台）
+    let foreground = unsafe { GetForegroundWindow() };
+    if !foreground.is_invalid() {
+        if let Ok(guard) = EVENT_SENDER.lock() {
+            if let Some(sender) = guard.as_ref() {
+                send_event(sender, InputEvent::WindowChanged(window_info(foreground)));
+            }
+        }
+    }
+    
+    // 监听锁屏/解锁（同样通过本线程的消息循环接收）
+    let session_notifier = match session_win::register(on_session_change) {
+        Ok(notifier) => Some(notifier),
+        Err(e) => {
+            eprintln!("警告: {}", e);
+            None
+        }
+    };
     
     // 运行消息循环（必须！否则钩子无法工作）
-    run_message_loop();
+    if polling {
+        run_polling_loop();
+    } else {
+        run_message_loop();
+    }
+    POLLING_CAPTURE.store(false, Ordering::Relaxed);
+    HOOK_THREAD_ID.store(0, Ordering::Relaxed);
+    
+    drop(session_notifier);
+    
+    if !foreground_hook.is_invalid() {
+        unsafe {
+            let _ = UnhookWinEvent(foreground_hook);
+        }
+    }
     
     // 清理
     {
@@ -303,9 +618,129 @@ pub fn start_listening(sender: Sender<KeyboardEvent>) -> Result<(), String> {
         }
     }
     
+    finish_listening();
     Ok(())
 }
 
+/// 监听结束：清除线程 ID，释放发送器
+///
+/// 发送器是事件通道唯一的发送端，释放后业务逻辑线程收到通道关闭并退出。
+fn finish_listening() {
+    LISTENER_THREAD_ID.store(0, Ordering::Relaxed);
+    if let Ok(mut guard) = EVENT_SENDER.lock() {
+        *guard = None;
+    }
+}
+
+/// 前台窗口切换回调
+///
+/// 使用 WINEVENT_OUTOFCONTEXT，回调在本线程的消息循环中执行
+unsafe extern "system" fn foreground_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if event != EVENT_SYSTEM_FOREGROUND || hwnd.is_invalid() {
+        return;
+    }
+    
+    if let Ok(guard) = EVENT_SENDER.lock() {
+        if let Some(sender) = guard.as_ref() {
+            send_event(sender, InputEvent::WindowChanged(window_info(hwnd)));
+        }
+    }
+}
+
+/// 锁屏/解锁回调
+fn on_session_change(locked: bool) {
+    if let Ok(guard) = EVENT_SENDER.lock() {
+        if let Some(sender) = guard.as_ref() {
+            send_event(sender, InputEvent::SessionLock(locked));
+        }
+    }
+}
+
+/// 收集窗口信息
+fn window_info(hwnd: HWND) -> WindowInfo {
+    let process_id = window_process_id(hwnd);
+    WindowInfo {
+        hwnd: Some(hwnd.0 as isize),
+        title: window_title(hwnd),
+        // EchoKey 自身的窗口（主窗口及其对话框都在本进程中）
+        own: process_id == unsafe { GetCurrentProcessId() },
+        process: process_name(process_id),
+    }
+}
+
+/// 窗口所属的进程 ID
+fn window_process_id(hwnd: HWND) -> u32 {
+    let mut process_id = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+    }
+    process_id
+}
+
+/// 进程的可执行文件名（不含路径和 `.exe`），没有权限查询（例如管理员进程）时返回 None
+fn process_name(process_id: u32) -> Option<String> {
+    if process_id == 0 {
+        return None;
+    }
+    let mut buffer = [0u16; 260];
+    let mut len = buffer.len() as u32;
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+        result.ok()?;
+    }
+    let path = String::from_utf16_lossy(&buffer[..len as usize]);
+    let file_name = path.rsplit('\\').next()?;
+    let stem = file_name.strip_suffix(".exe")
+        .or_else(|| file_name.strip_suffix(".EXE"))
+        .unwrap_or(file_name);
+    (!stem.is_empty()).then(|| stem.to_string())
+}
+
+/// 前台窗口的标题，没有前台窗口时返回 None
+pub fn foreground_window_title() -> Option<String> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    (!hwnd.is_invalid()).then(|| window_title(hwnd))
+}
+
+/// 读取窗口标题
+fn window_title(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+/// 显示并激活本进程的主窗口（标题为 `EchoKey`），用于托盘的「显示窗口」
+///
+/// 窗口隐藏到托盘后系统不再让它重绘，界面自己的刷新循环不会运行，
+/// 因此先从这里把窗口显示出来，界面随后处理显示请求。
+pub fn show_own_window() {
+    unsafe {
+        // 找到窗口后回调返回 FALSE 停止枚举，EnumWindows 因此返回错误，忽略即可
+        let _ = EnumWindows(Some(show_if_own_window), LPARAM(0));
+    }
+}
+
+/// [`show_own_window`] 的枚举回调
+unsafe extern "system" fn show_if_own_window(hwnd: HWND, _lparam: LPARAM) -> BOOL {
+    if window_process_id(hwnd) != GetCurrentProcessId() || window_title(hwnd) != "EchoKey" {
+        return BOOL(1);
+    }
+    let command = if IsIconic(hwnd).as_bool() { SW_RESTORE } else { SW_SHOW };
+    let _ = ShowWindow(hwnd, command);
+    let _ = SetForegroundWindow(hwnd);
+    BOOL(0)
+}
+
 /// 运行 Windows 消息循环
 /// 
 /// 这是正确的阻塞等待方式：
@@ -318,24 +753,150 @@ fn run_message_loop() {
         // GetMessage 返回 0 表示收到 WM_QUIT
         // 返回 -1 表示错误
         while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            // 看门狗的请求是线程消息，没有目标窗口，不需要分发
+            if msg.message == WM_REINSTALL_HOOK {
+                reinstall_hook();
+                continue;
+            }
             let _ = TranslateMessage(&msg);
             DispatchMessageW(&msg);
         }
     }
 }
 
-/// 停止键盘监听
+/// 卸载原来的钩子（可能已被系统移除）并重新安装
+///
+/// 钩子属于安装它的线程，必须在键盘监听线程中调用。安装失败时保持没有钩子，
+/// 看门狗按退避间隔再次请求。
+fn reinstall_hook() {
+    let Ok(mut guard) = HOOK_HANDLE.lock() else {
+        return;
+    };
+    if let Some(old) = guard.take() {
+        unsafe {
+            let _ = UnhookWindowsHookEx(old.0);
+        }
+    }
+    match unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(low_level_keyboard_proc), None, 0) } {
+        Ok(hook) => {
+            *guard = Some(HookHandle(hook));
+            eprintln!("键盘钩子已重新安装");
+        }
+        Err(e) => eprintln!("警告: 重新安装键盘钩子失败: {:?}", e),
+    }
+}
+
+/// 记下钩子回调被调用的时刻
+fn note_hook_call() {
+    LAST_HOOK_CALL.store(HOOK_CLOCK.elapsed().as_millis() as u64, Ordering::Relaxed);
+}
+
+/// 钩子多久没有被调用；没有在使用钩子（未安装、轮询采集）时为 None
+pub fn hook_idle() -> Option<Duration> {
+    if HOOK_THREAD_ID.load(Ordering::Relaxed) == 0 {
+        return None;
+    }
+    let now = HOOK_CLOCK.elapsed().as_millis() as u64;
+    Some(Duration::from_millis(now.saturating_sub(LAST_HOOK_CALL.load(Ordering::Relaxed))))
+}
+
+/// 此刻是否有任何键盘按键按下（不含鼠标按键）
+///
+/// GetAsyncKeyState 反映物理按键状态，不依赖钩子；看门狗据此确认用户正在输入。
+pub fn any_key_down() -> bool {
+    (0x08..=0xFE).any(|vk| unsafe { (GetAsyncKeyState(vk) as u16 & 0x8000) != 0 })
+}
+
+/// 请求键盘监听线程重新安装钩子，监听线程没有在使用钩子时返回 false
+pub fn request_hook_reinstall() -> bool {
+    let thread_id = HOOK_THREAD_ID.load(Ordering::Relaxed);
+    thread_id != 0
+        && unsafe { PostThreadMessageW(thread_id, WM_REINSTALL_HOOK, WPARAM(0), LPARAM(0)) }.is_ok()
+}
+
+/// 轮询采集循环：没有键盘钩子时用 GetAsyncKeyState 检测按键按下
+///
+/// 同时处理本线程的窗口消息（前台窗口切换、锁屏通知），收到 WM_QUIT 时退出。
+/// 局限：
+/// - 按下后在一个轮询间隔内松开的按键可能漏掉，长按不产生自动重复的字符
+/// - 无法区分软件注入的按键，也无法获取输入法确认的字符
+/// - 大写锁定状态在本线程中跟踪，启动后在其他地方切换可能不同步
+fn run_polling_loop() {
+    let mut was_down = [false; 256];
+    let mut caps_lock = caps_lock_on();
+    loop {
+        unsafe {
+            let mut msg = MSG::default();
+            while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                if msg.message == WM_QUIT {
+                    return;
+                }
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        
+        // 按 GetAsyncKeyState 的结果构造本线程的键盘状态，
+        // 这样修饰键判断和字符转换（GetKeyState/ToUnicode）可以沿用钩子的处理逻辑
+        let mut keyboard_state = [0u8; 256];
+        let mut pressed = Vec::new();
+        for vk in 1..256 {
+            let down = unsafe { (GetAsyncKeyState(vk as i32) as u16 & 0x8000) != 0 };
+            if down {
+                keyboard_state[vk] = 0x80;
+                if !was_down[vk] {
+                    pressed.push(vk as u32);
+                }
+            } else if was_down[vk] {
+                send_raw_key(vk as u32, false);
+            }
+            was_down[vk] = down;
+        }
+        if caps_lock {
+            keyboard_state[VK_CAPITAL.0 as usize] |= 0x01;
+        }
+        unsafe {
+            let _ = SetKeyboardState(&keyboard_state);
+        }
+        
+        for vk_code in pressed {
+            let scan_code = unsafe { MapVirtualKeyW(vk_code, MAPVK_VK_TO_VSC) };
+            if should_process_key(vk_code, scan_code) {
+                send_raw_key(vk_code, true);
+                process_key_down(vk_code, scan_code);
+            }
+            if vk_code == VK_CAPITAL.0 as u32 {
+                caps_lock = !caps_lock;
+            }
+        }
+        
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// 停止键盘监听，可以在任意线程调用
 /// 
-/// 向消息循环发送 WM_QUIT 消息
+/// 向键盘监听线程的消息循环发送 WM_QUIT（PostQuitMessage 只能退出调用者自己的消息循环）。
+/// 监听还没有开始时记下请求，[`start_listening`] 随后直接返回。
 pub fn stop_listening() {
-    unsafe {
-        PostQuitMessage(0);
+    STOP_REQUESTED.store(true, Ordering::Relaxed);
+    let thread_id = LISTENER_THREAD_ID.load(Ordering::Relaxed);
+    if thread_id != 0 {
+        unsafe {
+            let _ = PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
     }
 }
 
-/// 检查钩子是否仍然有效
+/// 是否已请求停止监听（启动延迟、安全模式下等待的键盘监听线程据此提前结束）
+pub fn stop_requested() -> bool {
+    STOP_REQUESTED.load(Ordering::Relaxed)
+}
+
+/// 检查是否持有钩子句柄
 /// 
-/// 用于 Watchdog 检测钩子是否被系统移除
+/// 钩子被系统移除后句柄仍然保留，因此不能据此判断钩子是否在工作，
+/// 看门狗使用 [`hook_idle`] 和 [`any_key_down`]。
 pub fn is_hook_active() -> bool {
     if let Ok(guard) = HOOK_HANDLE.lock() {
         guard.is_some()
@@ -343,3 +904,60 @@ pub fn is_hook_active() -> bool {
         false
     }
 }
+
+/// 当前是否运行在远程桌面会话中
+///
+/// 远程会话中低级键盘钩子的行为与本地不同，可能收不到部分按键。
+pub fn is_remote_session() -> bool {
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+/// 当前是否在使用轮询方式采集（远程桌面会话中无法安装键盘钩子）
+pub fn is_polling_capture() -> bool {
+    POLLING_CAPTURE.load(Ordering::Relaxed)
+}
+
+/// 检查安全桌面（UAC 提示、Ctrl+Alt+Del 界面）是否处于活动状态
+///
+/// 安全桌面激活时，普通进程无法打开输入桌面（OpenInputDesktop 失败），
+/// 或者输入桌面与本线程所在的桌面不是同一个（名称为 Winlogon 而非 Default）。
+/// 调用开销较小，但仍应在逻辑循环中低频轮询。
+pub fn is_secure_desktop_active() -> bool {
+    unsafe {
+        let input_desktop = match OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_READOBJECTS) {
+            Ok(desk) => desk,
+            Err(_) => return true,
+        };
+        
+        let input_name = desktop_name(input_desktop);
+        let _ = CloseDesktop(input_desktop);
+        
+        // 本线程所在桌面的句柄不需要关闭
+        let own_name = GetThreadDesktop(GetCurrentThreadId())
+            .ok()
+            .and_then(desktop_name);
+        
+        match (input_name, own_name) {
+            (Some(input), Some(own)) => !input.eq_ignore_ascii_case(&own),
+            (Some(input), None) => !input.eq_ignore_ascii_case("Default"),
+            _ => false,
+        }
+    }
+}
+
+/// 读取桌面对象的名称（例如 "Default"、"Winlogon"）
+fn desktop_name(desktop: HDESK) -> Option<String> {
+    let mut buffer = [0u16; 64];
+    let mut needed = 0u32;
+    unsafe {
+        GetUserObjectInformationW(
+            HANDLE(desktop.0),
+            UOI_NAME,
+            Some(buffer.as_mut_ptr() as *mut core::ffi::c_void),
+            (buffer.len() * 2) as u32,
+            Some(&mut needed),
+        ).ok()?;
+    }
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
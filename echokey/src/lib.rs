@@ -10,26 +10,46 @@
 //!
 //! # 模块说明
 //! - `config`: 配置项（存储路径、超时时间等）
+//! - `i18n`: 界面文字本地化（简体中文、英文）
 //! - `logger`: 日志写入（核心模块）
+//! - `event`: 与平台无关的输入事件及其处理逻辑
 //! - `keyboard_win`: Windows 原生键盘监听（使用 windows-rs）
+//! - `uia_win`: 读取焦点控件文本（UI Automation，用于校验快照）
+//! - `ime_win`: 输入法上屏的文字（UI Automation 的组字事件）
+//! - `shortcuts`: 快捷键表与匹配规则
+//! - `tail`: 实时预览（增量读取日志文件）
+//! - `stats`: 输入统计与 CSV 导出
 //! - `clipboard`: 剪贴板操作
 //! - `autostart`: 开机自启动
+//! - `safe_mode`: 安全模式（启动后反复崩溃时只显示界面）
+//! - `window_state`: 窗口位置和大小（下次启动时恢复）
 //! - `gui`: 图形用户界面（Apple 风格）
 
 pub mod config;
+pub mod i18n;
 pub mod logger;
+pub mod event;
 pub mod clipboard;
 pub mod autostart;
+pub mod safe_mode;
+pub mod window_state;
 pub mod gui;
 pub mod tray;
+pub mod shortcuts;
+pub mod tail;
+pub mod stats;
 
 // Windows 专用模块
 #[cfg(windows)]
 pub mod keyboard_win;
+#[cfg(windows)]
+pub mod session_win;
+#[cfg(windows)]
+pub mod uia_win;
+#[cfg(windows)]
+pub mod ime_win;
 
 // 重新导出常用类型
 pub use logger::Logger;
 pub use gui::{EchoKeyApp, SharedGuiState};
-
-#[cfg(windows)]
-pub use keyboard_win::KeyboardEvent;
+pub use event::InputEvent;
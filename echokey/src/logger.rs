@@ -3,74 +3,750 @@
 //! 负责将用户输入实时写入本地日志文件。
 //! 
 //! 设计原则：
-//! - 实时写入：每次写入后立即 flush，确保数据不丢失
+//! - 实时写入：默认每次写入后立即 flush，确保数据不丢失；
+//!   开启 [`FlushPolicy::Interval`] 时缓冲写入的内容，按间隔批量 flush
 //! - 按日期分文件：每天一个新文件
 //! - 支持手动分段：用户可以手动创建新的日志段
 
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, Write, BufWriter};
-use std::path::PathBuf;
-use std::time::Instant;
-use chrono::{Local, NaiveDate};
-use crate::config;
+use std::io::{self, Read, Write, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use crate::{config, i18n};
+
+/// 粘贴内容分块写入的大小（字节），超过一块的粘贴会报告写入进度
+pub const PASTE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 最近事件中单条事件保留的最大字节数，更长的内容（例如大段粘贴）只保留开头
+pub const RECENT_EVENT_MAX_BYTES: usize = 16 * 1024;
+
+/// 退格键在日志中的记号（未开启退格删除，或没有可删除的输入时写入）
+pub const BACKSPACE_MARKER: &str = "⌫";
+
+/// Delete 键在日志中的记号（开启按键详细记录时写入）
+pub const DELETE_MARKER: &str = "⌦";
+
+/// 编辑键在日志中的记号：退格、Delete、方向键、Home/End（`⇤` `⇥`）和 Tab，统计字数时不计入
+pub const EDIT_KEY_MARKERS: &[char] = &['⌫', '⌦', '←', '→', '↑', '↓', '⇤', '⇥', '\t'];
+
+/// 退格删除时当前行保留的最大字节数，更早的输入不再能被退格删除
+pub const LINE_TEXT_MAX_BYTES: usize = 4 * 1024;
+
+/// 复制后多久内粘贴相同内容时，粘贴条目不再重复内容
+pub const COPY_PASTE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 续行（Ctrl+Enter、多行粘贴的后续行）的缩进，与时间戳 `[HH:MM:SS]` 对齐
+const CONTINUATION_INDENT: &str = "          ";
+
+/// 粘贴的图片保存在日志目录下的这个文件夹中
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// 写入文件的内容是否同时输出到标准输出（调试、管道给其他工具时使用）
+static TEE_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// 开启/关闭同时输出到标准输出，立即生效
+///
+/// Windows 发布版没有控制台窗口，开启时尝试连接到启动 EchoKey 的命令行窗口，
+/// 没有命令行窗口时输出被丢弃。
+pub fn set_tee_stdout(enabled: bool) {
+    #[cfg(windows)]
+    if enabled {
+        static ATTACH: std::sync::Once = std::sync::Once::new();
+        ATTACH.call_once(|| unsafe {
+            use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+            let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+        });
+    }
+    TEE_STDOUT.store(enabled, Ordering::Relaxed);
+}
+
+/// 统计写入字节数和行数的包装器
+///
+/// 位于 BufWriter 内层，统计的是已经交给文件的字节数，
+/// 因此总能与磁盘上的文件长度对应。开启同时输出到标准输出时，
+/// 交给文件的内容原样写到标准输出（标准输出按行缓冲，不随每次按键刷新）。
+struct CountingWriter<W: Write> {
+    inner: W,
+    bytes: u64,
+    lines: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n as u64;
+        self.lines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+        if TEE_STDOUT.load(Ordering::Relaxed) {
+            // 标准输出不可用（例如管道已关闭）不影响写入日志
+            let _ = io::stdout().lock().write_all(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 代替日志文件的写入目标（例如测试中的内存缓冲区）
+///
+/// 日志写入器在重新打开“文件”（换日、写入失败后重试等）时继续写入同一个目标，
+/// 并由这里记录已写入的长度、行数和末尾字节，代替读取已有文件。
+#[derive(Clone)]
+struct SharedSink(Arc<Mutex<SinkState>>);
+
+struct SinkState {
+    target: Box<dyn Write + Send>,
+    len: u64,
+    lines: u64,
+    /// 最后写入的两个字节
+    tail: [u8; 2],
+}
+
+impl Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.0.lock().map_err(|_| io::Error::other("写入目标的锁已损坏"))?;
+        let n = state.target.write(buf)?;
+        let written = &buf[..n];
+        state.len += n as u64;
+        state.lines += written.iter().filter(|&&b| b == b'\n').count() as u64;
+        for &b in &written[n.saturating_sub(2)..] {
+            state.tail = [state.tail[1], b];
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.0.lock().map_err(|_| io::Error::other("写入目标的锁已损坏"))?;
+        state.target.flush()
+    }
+}
+
+impl SharedSink {
+    /// 重新“打开”这个目标：继续写入，已有内容的概况取自之前的写入
+    fn opened(&self) -> OpenedTarget {
+        let (len, lines, tail) = self.0.lock()
+            .map(|state| (state.len, state.lines, state.tail))
+            .unwrap_or((0, 0, [0; 2]));
+        OpenedTarget {
+            inner: Box::new(self.clone()),
+            len,
+            lines,
+            ends_with_newline: len == 0 || tail[1] == b'\n',
+            ends_with_blank_line: len >= 2 && tail == *b"\n\n",
+            last_sequence: None,
+        }
+    }
+}
+
+/// 打开的写入目标及其中已有内容的概况
+struct OpenedTarget {
+    inner: Box<dyn Write + Send>,
+    len: u64,
+    lines: u64,
+    ends_with_newline: bool,
+    ends_with_blank_line: bool,
+    last_sequence: Option<u64>,
+}
+
+/// 日志写入器读取时间的来源
+///
+/// 默认使用系统时钟 [`SystemClock`]；测试中可以换成 [`ManualClock`]，手动推进时间，
+/// 确定性地检查时间戳、空行等与空闲时间有关的写入。
+pub trait Clock: Send {
+    /// 当前的本地时间
+    fn now_local(&self) -> DateTime<Local>;
+
+    /// 当前的单调时间（用于计算空闲时长）
+    fn now_instant(&self) -> Instant;
+}
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_local(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// 手动推进的时钟，克隆出的副本共用同一个时间
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    base: Instant,
+    start: DateTime<Local>,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    /// 从指定时刻开始的时钟
+    pub fn new<Z: TimeZone>(start: DateTime<Z>) -> Self {
+        Self {
+            base: Instant::now(),
+            start: start.with_timezone(&Local),
+            offset: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// 把时间向后推进
+    pub fn advance(&self, duration: Duration) {
+        if let Ok(mut offset) = self.offset.lock() {
+            *offset += duration;
+        }
+    }
+
+    fn offset(&self) -> Duration {
+        self.offset.lock().map(|offset| *offset).unwrap_or_default()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_local(&self) -> DateTime<Local> {
+        self.start + self.offset()
+    }
+
+    fn now_instant(&self) -> Instant {
+        self.base + self.offset()
+    }
+}
+
+/// 日志时间使用的时区
+///
+/// 默认跟随系统时区；出差等系统时区变化时，可以固定为某个时区，
+/// 让时间戳和文件名的日期保持连续。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogTimezone {
+    /// 跟随系统时区
+    #[default]
+    Local,
+    /// 固定偏移，例如 `+08:00`
+    Fixed(FixedOffset),
+    /// IANA 时区，例如 `Asia/Shanghai`
+    Named(Tz),
+}
+
+impl LogTimezone {
+    /// 解析配置中的时区：`local`、`+08:00` 形式的固定偏移，或 IANA 时区名称
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("local") {
+            return Some(Self::Local);
+        }
+        if let Ok(offset) = value.parse::<FixedOffset>() {
+            return Some(Self::Fixed(offset));
+        }
+        value.parse::<Tz>().ok().map(Self::Named)
+    }
+
+    /// 该时区的当前时间
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.at(Utc::now())
+    }
+
+    /// 把某一时刻换算为该时区的时间
+    pub fn at(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Self::Local => time.with_timezone(&Local).fixed_offset(),
+            Self::Fixed(offset) => time.with_timezone(offset),
+            Self::Named(tz) => time.with_timezone(tz).fixed_offset(),
+        }
+    }
+}
+
+/// 当前日志文件的大小统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileStats {
+    /// 文件大小（字节）
+    pub bytes: u64,
+    /// 行数（换行符个数）
+    pub lines: u64,
+}
+
+/// 最近写入日志的一条事件，供中途连接的观察者回放最近的上下文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedEvent {
+    /// 写入的时间
+    pub time: DateTime<FixedOffset>,
+    pub kind: LoggedEventKind,
+    /// 输入的文字、`[标记] 内容` 形式的条目或标记文字；换行时为空
+    pub text: String,
+}
+
+/// 最近事件的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoggedEventKind {
+    /// 输入的文字（同一时间戳行中连续的输入合并为一条）
+    Text,
+    /// Enter 或 Ctrl+Enter 换行
+    LineBreak,
+    /// 带标记的条目，例如粘贴、手动保存
+    Entry,
+    /// 状态标记，例如暂停、窗口切换
+    Marker,
+}
+
+/// 长按按键（或按键卡住）自动重复的识别规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutorepeatPolicy {
+    /// 相邻两次相同字符的间隔不超过这个时长才算自动重复
+    /// （系统在第一次重复前有较长的延迟，第一次重复只要求不超过 [`AUTOREPEAT_DELAY`]）
+    pub max_interval: Duration,
+    /// 连续重复超过这么多个字符才折叠，之前的字符照常写入
+    pub min_run: usize,
+}
+
+/// 日志写入后何时落盘
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// 每次写入后立即落盘
+    #[default]
+    Immediate,
+    /// 缓冲写入的内容，由 [`Logger::maybe_flush`] 按间隔落盘
+    /// （缓冲超过 [`FLUSH_BUFFER_BYTES`] 时立即落盘）
+    Interval(Duration),
+}
+
+/// 定时刷新时缓冲的内容超过这个大小立即落盘（例如大段粘贴）
+pub const FLUSH_BUFFER_BYTES: usize = 4 * 1024;
+
+/// 按住按键到开始自动重复的最长延迟（Windows 的键盘重复延迟最长为 1 秒）
+pub const AUTOREPEAT_DELAY: Duration = Duration::from_secs(1);
+
+/// 正在进行的相同字符连续输入
+#[derive(Debug, Clone, Copy)]
+struct RepeatRun {
+    ch: char,
+    len: usize,
+    last: Instant,
+}
+
+impl RepeatRun {
+    /// 在 `now` 输入的字符 `ch` 是否延续这一串重复
+    fn continues(&self, ch: char, now: Instant, max_interval: Duration) -> bool {
+        let limit = if self.len == 1 { AUTOREPEAT_DELAY.max(max_interval) } else { max_interval };
+        self.ch == ch && now.saturating_duration_since(self.last) <= limit
+    }
+}
 
 /// 日志写入器
 /// 
 /// 管理日志文件的创建、写入和分段。
 pub struct Logger {
+    /// 日志目录
+    log_dir: PathBuf,
     /// 当前日志文件的写入器
-    writer: Option<BufWriter<File>>,
+    writer: Option<BufWriter<CountingWriter<Box<dyn Write + Send>>>>,
+    /// 代替日志文件的写入目标，None 表示写入日志目录中的文件
+    sink: Option<SharedSink>,
+    /// 读取时间的来源
+    clock: Box<dyn Clock>,
+    /// 当前日志文件路径
+    current_path: Option<PathBuf>,
+    /// 打开文件时文件已有的长度（加上写入的字节数即为预期长度）
+    file_len_at_open: u64,
+    /// 打开文件时文件已有的行数
+    file_lines_at_open: u64,
     /// 当前日志文件的日期
     current_date: Option<NaiveDate>,
     /// 当前日志文件的段号（用于手动分段）
     segment_number: u32,
+    /// 是否按前台程序分文件
+    split_by_app: bool,
+    /// 当前日志文件对应的程序（文件名中的程序名），None 表示当天的默认文件
+    app: Option<String>,
+    /// 前台切换到的程序，下次写入时才切换文件
+    pending_app: Option<Option<String>>,
+    /// 当天切换走的各程序文件的段号，切换回来时接着写入该程序最新的日志段
+    app_segments: HashMap<Option<String>, u32>,
     /// 上次写入时间（用于判断是否需要添加时间戳）
     last_write_time: Option<Instant>,
+    /// 落盘策略
+    flush_policy: FlushPolicy,
+    /// 上次落盘的时间（定时刷新用）
+    last_flush: Instant,
     /// 当前行是否为空（用于判断是否需要添加时间戳）
     current_line_empty: bool,
+    /// 上一个写完的行是否为空行（压缩空行时用于跳过多余的空行）
+    last_line_blank: bool,
+    /// 是否把连续的多个空行压缩为一个
+    compact_whitespace: bool,
+    /// 退格键是否删除当前行中最后输入的字符（关闭时写入 `⌫`）
+    backspace_deletes: bool,
+    /// 是否在每个时间戳后注明前台窗口的标题：`[14:03:22] «标题» 内容`
+    window_tracking: bool,
+    /// 读取前台窗口标题（测试中可以替换）
+    window_title: fn() -> Option<String>,
+    /// 当前行中输入的文字（退格删除用），之后写入了其他内容时作废
+    line_text: String,
+    /// `line_text` 最后一个字符在文件中的结束位置
+    line_text_end: u64,
+    /// 单个文件的大小上限，写入会超过上限时自动切换到新的日志段，None 表示不限制
+    max_file_bytes: Option<u64>,
+    /// 因超过大小上限切换文件时，上一个文件的文件名（写入新文件的头部）
+    continues_from: Option<String>,
     /// 是否暂停记录
     paused: bool,
     /// 文件是否已写入头部（防止重复写入）
     header_written: bool,
+    /// 当前日志段是否写入过内容（空段不再分段）
+    segment_has_content: bool,
+    /// 合并相同粘贴的时间窗口，None 表示每次粘贴都完整记录
+    paste_dedup_window: Option<Duration>,
+    /// 粘贴内容的字符上限，超出部分不写入；None 表示不限制
+    max_paste_chars: Option<usize>,
+    /// 上一次粘贴：内容、时间、连续次数
+    last_paste: Option<(String, Instant, u32)>,
+    /// 上一次复制：内容、时间（下一次粘贴相同内容时不重复写入）
+    last_copy: Option<(String, Instant)>,
+    /// 设备名称（写入文件头部，合并多台设备的日志时用于区分来源）
+    device_label: Option<String>,
+    /// 本次运行是否还需要写入设备标记
+    session_marker_pending: bool,
+    /// 是否在启动后首次打开文件时和正常退出时写入启动/退出标记
+    app_markers: bool,
+    /// 本次运行的启动时间（启动标记使用这个时间）
+    launched_at: DateTime<Utc>,
+    /// 本次运行是否还没有打开过日志文件
+    first_open_pending: bool,
+    /// 自定义的文件头部模板，None 表示使用默认头部，空字符串表示不写头部
+    header_template: Option<String>,
+    /// 时间戳和文件日期使用的时区
+    timezone: LogTimezone,
+    /// 空闲超过这个时间后，下次输入添加新时间戳
+    idle_timeout: Duration,
+    /// 空闲超过这个时间后，下次输入前空一行分隔会话（不短于 idle_timeout）
+    session_timeout: Duration,
+    /// 短于这个时长的暂停不写入暂停/恢复标记，None 表示总是写入
+    pause_marker_threshold: Option<Duration>,
+    /// 推迟写入的暂停标记：暂停开始的时刻和当时的时间戳
+    pending_pause: Option<(Instant, DateTime<FixedOffset>)>,
+    /// 暂停/恢复标记另起一行还是接在当前行末尾
+    resume_style: config::ResumeStyle,
+    /// 输出格式：纯文本或 JSON 行
+    format: config::LogFormat,
+    /// 折叠自动重复的规则，None 表示不折叠
+    autorepeat: Option<AutorepeatPolicy>,
+    /// 当前的相同字符连续输入
+    repeat_run: Option<RepeatRun>,
+    /// 条目序号的计数方式
+    sequence_numbers: config::SequenceNumbers,
+    /// 下一条目的序号
+    next_sequence: u64,
+    /// 最近写入的事件（只包含当前文件中仍然存在的内容）
+    recent_events: VecDeque<LoggedEvent>,
+    /// 最多保留多少条最近事件，0 表示不保留
+    recent_events_capacity: usize,
+    /// 正在后台编码保存的粘贴图片，退出时等待写完
+    image_writers: Vec<std::thread::JoinHandle<()>>,
 }
 
 impl Logger {
     /// 创建新的日志写入器
     pub fn new() -> io::Result<Self> {
+        Self::with_directory(config::get_log_directory())
+    }
+
+    /// 创建写入指定目录的日志写入器
+    pub fn with_directory(log_dir: impl Into<PathBuf>) -> io::Result<Self> {
         // 确保日志目录存在
-        let log_dir = config::get_log_directory();
+        let log_dir = log_dir.into();
         fs::create_dir_all(&log_dir)?;
-        
-        Ok(Self {
+        Ok(Self::new_in(log_dir))
+    }
+
+    /// 写入器的初始状态（不访问文件系统）
+    fn new_in(log_dir: PathBuf) -> Self {
+        Self {
+            log_dir,
             writer: None,
+            sink: None,
+            clock: Box::new(SystemClock),
+            current_path: None,
+            file_len_at_open: 0,
+            file_lines_at_open: 0,
             current_date: None,
             segment_number: 0,
+            split_by_app: false,
+            app: None,
+            pending_app: None,
+            app_segments: HashMap::new(),
             last_write_time: None,
+            flush_policy: FlushPolicy::Immediate,
+            last_flush: Instant::now(),
             current_line_empty: true,
+            last_line_blank: false,
+            compact_whitespace: false,
+            backspace_deletes: false,
+            window_tracking: false,
+            window_title: foreground_window_title,
+            line_text: String::new(),
+            line_text_end: 0,
+            max_file_bytes: Some(config::DEFAULT_MAX_FILE_BYTES),
+            continues_from: None,
             paused: false,
             header_written: false,
-        })
+            segment_has_content: false,
+            paste_dedup_window: None,
+            max_paste_chars: None,
+            last_paste: None,
+            last_copy: None,
+            device_label: None,
+            session_marker_pending: false,
+            app_markers: false,
+            launched_at: Utc::now(),
+            first_open_pending: true,
+            header_template: None,
+            timezone: LogTimezone::Local,
+            idle_timeout: config::IDLE_TIMEOUT,
+            session_timeout: config::SESSION_TIMEOUT,
+            pause_marker_threshold: None,
+            pending_pause: None,
+            resume_style: config::ResumeStyle::NewLine,
+            format: config::LogFormat::PlainText,
+            autorepeat: None,
+            repeat_run: None,
+            sequence_numbers: config::SequenceNumbers::Off,
+            next_sequence: 1,
+            recent_events: VecDeque::new(),
+            recent_events_capacity: 0,
+            image_writers: Vec::new(),
+        }
+    }
+
+    /// 创建写入指定目标（而不是日志文件）的日志写入器，例如测试中的内存缓冲区
+    ///
+    /// 换日、写入失败后重试时继续写入同一个目标，只在第一次写入时写入头部。
+    /// 按日期、程序、大小切换文件的逻辑照常运行，只是都写入这一个目标。
+    pub fn with_writer(writer: impl Write + Send + 'static) -> Self {
+        let mut logger = Self::new_in(PathBuf::new());
+        logger.sink = Some(SharedSink(Arc::new(Mutex::new(SinkState {
+            target: Box::new(writer),
+            len: 0,
+            lines: 0,
+            tail: [0; 2],
+        }))));
+        logger
+    }
+
+    /// 改用指定的时钟读取时间，测试中用来控制时间戳和空闲判断
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.launched_at = clock.now_local().with_timezone(&Utc);
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// 按保留设置把较早的日志（`.log` 和 `.log.gz`）移到回收站，返回删除的文件
+    ///
+    /// `keep_days`：删除文件名中的日期早于这么多天以前的日志；`keep_recent_days`：只保留最近这么多个有日志的日期，
+    /// 同一天的所有日志段一起保留或删除，历史、统计中不会出现只剩部分内容的日期，0 表示不限制。
+    /// 两项都设置时删除满足任一条件的日志。
+    ///
+    /// 系统不支持回收站时永久删除。今天的日志和正在写入的文件不删除（今天计入最近的天数）；
+    /// 文件名不是日志格式的文件不处理。某个文件删除失败时跳过它，继续处理其余的文件。
+    pub fn enforce_retention(&self, keep_days: Option<u32>, keep_recent_days: usize) -> io::Result<Vec<PathBuf>> {
+        self.enforce_retention_with(keep_days, keep_recent_days, trash_log_file)
+    }
+
+    /// 按保留设置清理日志，`remove` 删除单个文件（测试中直接删除，不经过回收站）
+    fn enforce_retention_with(
+        &self,
+        keep_days: Option<u32>,
+        keep_recent_days: usize,
+        mut remove: impl FnMut(&Path) -> io::Result<()>,
+    ) -> io::Result<Vec<PathBuf>> {
+        let today = self.now().date_naive();
+        let files: Vec<(NaiveDate, PathBuf)> = fs::read_dir(&self.log_dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| Some((parse_log_file_date(&e.file_name().to_string_lossy())?, e.path())))
+            .collect();
+        
+        // 早于 cutoff 的日期都要删除，取两项设置中较晚的那个
+        let mut cutoff = keep_days.map(|days| today - chrono::Days::new(days as u64));
+        if keep_recent_days > 0 {
+            let mut dates: Vec<NaiveDate> = files.iter().map(|(date, _)| *date).collect();
+            dates.sort_unstable_by(|a, b| b.cmp(a));
+            dates.dedup();
+            if let Some(&oldest_kept) = dates.get(keep_recent_days - 1) {
+                cutoff = cutoff.max(Some(oldest_kept));
+            }
+        }
+        let Some(cutoff) = cutoff else {
+            return Ok(Vec::new());
+        };
+        
+        let mut deleted = Vec::new();
+        for (date, path) in files {
+            if date >= cutoff || date == today || self.current_path.as_ref() == Some(&path) {
+                continue;
+            }
+            match remove(&path) {
+                Ok(()) => deleted.push(path),
+                Err(e) => {
+                    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                    eprintln!("警告: 无法删除过期日志 {}: {}", name, e);
+                }
+            }
+        }
+        deleted.sort();
+        Ok(deleted)
+    }
+
+    /// 把 `older_than_days` 天以前的日志压缩为 `.log.gz`，删除原文件，返回压缩的文件数
+    ///
+    /// 今天的日志和正在写入的文件不压缩；已压缩的文件和文件名不是日志格式的文件不处理。
+    /// 某个文件压缩失败时跳过它，继续处理其余的文件。
+    pub fn compress_old_logs(&self, older_than_days: u32) -> io::Result<usize> {
+        let today = self.now().date_naive();
+        let cutoff = today - chrono::Days::new(older_than_days as u64);
+        let mut count = 0;
+        for entry in fs::read_dir(&self.log_dir)?.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let Some(date) = parse_log_file_date(&name) else {
+                continue;
+            };
+            if is_compressed_log(&path) || date >= cutoff || date == today
+                || self.current_path.as_ref() == Some(&path)
+            {
+                continue;
+            }
+            match compress_log_file(&path) {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("警告: 无法压缩日志 {}: {}", name, e),
+            }
+        }
+        Ok(count)
     }
 
     /// 获取当前日志文件路径
+    ///
+    /// 按程序分文件时文件名中带有程序名：`2024-06-01_chrome.log`、`2024-06-01_chrome_01.log`。
+    /// JSON 行格式的文件扩展名为 `.jsonl`。
     fn get_log_path(&self, date: NaiveDate) -> PathBuf {
-        let log_dir = config::get_log_directory();
-        let filename = if self.segment_number == 0 {
-            format!("{}.log", date.format("%Y-%m-%d"))
-        } else {
-            format!("{}_{:02}.log", date.format("%Y-%m-%d"), self.segment_number)
+        let mut filename = date.format("%Y-%m-%d").to_string();
+        if let Some(app) = &self.app {
+            filename.push('_');
+            filename.push_str(app);
+        }
+        if self.segment_number > 0 {
+            filename.push_str(&format!("_{:02}", self.segment_number));
+        }
+        filename.push_str(match self.format {
+            config::LogFormat::PlainText => ".log",
+            config::LogFormat::JsonLines => ".jsonl",
+        });
+        self.log_dir.join(filename)
+    }
+
+    /// 设置是否按前台程序分文件
+    ///
+    /// 关闭后下次写入回到当天的默认文件。
+    pub fn set_split_by_app(&mut self, enabled: bool) {
+        self.split_by_app = enabled;
+        if !enabled {
+            self.pending_app = Some(None);
+        }
+    }
+
+    /// 前台切换到另一个程序（按程序分文件时），程序名无法获取时传入 None
+    ///
+    /// 不立即切换文件，下次写入时才切换到该程序的日志文件：
+    /// 快速切换窗口时经过的程序不会留下空文件，也不会在文件之间来回写入换行。
+    /// 程序名无法用作文件名时写入当天的默认文件。
+    pub fn set_foreground_app(&mut self, name: Option<&str>) {
+        if self.split_by_app {
+            self.pending_app = Some(name.and_then(app_file_label));
+        }
+    }
+
+    /// 切换到前台程序的日志文件（在写入前调用）
+    fn apply_pending_app(&mut self) -> io::Result<()> {
+        let Some(app) = self.pending_app.take() else {
+            return Ok(());
         };
-        log_dir.join(filename)
+        if app == self.app {
+            return Ok(());
+        }
+        
+        // 结束旧文件中的当前行，再关闭旧文件
+        if let Some(ref mut writer) = self.writer {
+            if !self.current_line_empty {
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+        }
+        self.writer = None;
+        let previous = std::mem::replace(&mut self.app, app);
+        self.app_segments.insert(previous, self.segment_number);
+        self.segment_number = self.app_segments.get(&self.app).copied().unwrap_or(0);
+        self.header_written = false;
+        self.current_line_empty = true;
+        Ok(())
+    }
+
+    /// 当前文件的预期长度（打开时的长度 + 已写入的字节数）
+    fn expected_file_len(&self) -> Option<u64> {
+        self.writer.as_ref()
+            .map(|w| self.file_len_at_open + w.get_ref().bytes)
+    }
+
+    /// 当前日志文件的大小和行数
+    ///
+    /// 由写入路径增量维护，不访问文件系统；还没有打开文件时为零。
+    /// 包括尚未落盘的缓冲内容。
+    pub fn file_stats(&self) -> FileStats {
+        self.writer.as_ref()
+            .map(|w| {
+                let buffered_lines = w.buffer().iter().filter(|&&b| b == b'\n').count() as u64;
+                FileStats {
+                    bytes: self.file_len_at_open + w.get_ref().bytes + w.buffer().len() as u64,
+                    lines: self.file_lines_at_open + w.get_ref().lines + buffered_lines,
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// 设置日志时间使用的时区
+    pub fn set_timezone(&mut self, timezone: LogTimezone) {
+        self.timezone = timezone;
+    }
+
+    /// 当前时间（使用配置的时区）
+    fn now(&self) -> DateTime<FixedOffset> {
+        self.timezone.at(self.clock.now_local().with_timezone(&Utc))
+    }
+
+    /// 从 `since` 到现在经过的时间
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.clock.now_instant().saturating_duration_since(since)
     }
 
     /// 确保日志文件已打开且日期正确
     fn ensure_file(&mut self) -> io::Result<()> {
-        let today = Local::now().date_naive();
+        self.apply_pending_app()?;
+        let today = self.now().date_naive();
         
         // 如果日期变了，需要创建新文件
         if self.current_date != Some(today) {
             self.current_date = Some(today);
             self.segment_number = 0;
+            self.app_segments.clear();
             self.header_written = false;
             self.writer = None; // 关闭旧文件
             return self.open_or_create_file();
@@ -81,64 +757,248 @@ impl Logger {
             return self.open_or_create_file();
         }
         
+        // 检查文件是否被外部修改（例如用户手动删改了内容）
+        if self.sink.is_some() {
+            return Ok(());
+        }
+        if let (Some(path), Some(expected)) = (&self.current_path, self.expected_file_len()) {
+            let actual = fs::metadata(path).map(|m| m.len()).ok();
+            if actual != Some(expected) {
+                // 重新以追加模式打开，从文件真实的末尾继续写，不覆盖也不重复
+                if actual.unwrap_or(0) == 0 {
+                    // 文件已被删除或清空，重新写入头部
+                    self.header_written = false;
+                }
+                // 外部删改过的内容不应继续留在内存中
+                self.recent_events.clear();
+                self.writer = None;
+                return self.open_or_create_file();
+            }
+        }
+        
         Ok(())
     }
 
     /// 打开或创建日志文件
     fn open_or_create_file(&mut self) -> io::Result<()> {
-        let date = self.current_date.unwrap_or_else(|| Local::now().date_naive());
+        let date = self.current_date.unwrap_or_else(|| self.now().date_naive());
         let path = self.get_log_path(date);
+        let OpenedTarget { inner, len: file_len, lines: file_lines, ends_with_newline, ends_with_blank_line, last_sequence } =
+            match &self.sink {
+                Some(sink) => sink.opened(),
+                None => self.open_file(&path)?,
+            };
+        let file_has_content = file_len > 0;
+        // 头部以空行结尾，新文件开头的空行也是多余的
+        self.last_line_blank = ends_with_blank_line || !file_has_content;
         
-        // 确保目录存在
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+        // 切换文件时序号接着文件中已有的序号（按文件计数时新文件从 1 开始）
+        if self.current_path.as_ref() != Some(&path) {
+            let next = last_sequence.map_or(1, |n| n + 1);
+            self.next_sequence = match self.sequence_numbers {
+                config::SequenceNumbers::Global => self.next_sequence.max(next),
+                _ => next,
+            };
         }
         
-        // 检查文件是否已存在且有内容
-        let file_exists = path.exists();
-        let file_has_content = file_exists && fs::metadata(&path).map(|m| m.len() > 0).unwrap_or(false);
-        
-        // 打开文件（追加模式）
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)?;
+        let mut writer = BufWriter::new(CountingWriter { inner, bytes: 0, lines: 0 });
         
-        let mut writer = BufWriter::new(file);
+        // 切换到另一个文件时，已有内容的文件（例如上次运行留下的）视为非空段
+        if self.current_path.as_ref() != Some(&path) {
+            self.segment_has_content = file_has_content;
+            self.recent_events.clear();
+        }
         
-        // 只在新文件时写入头部
-        if !file_has_content && !self.header_written {
-            self.write_header_to(&mut writer)?;
+        // 只在新文件时写入头部（JSON 行格式没有头部，每一行都是一条事件）
+        if self.format == config::LogFormat::JsonLines {
+            self.header_written = true;
+        } else if !file_has_content && !self.header_written {
+            self.write_header_to(&mut writer, false)?;
             self.header_written = true;
+            self.continues_from = None;
+            // 头部已包含设备名称（自定义模板中没有设备名称时仍写入设备标记）
+            if self.header_template.as_deref().is_none_or(|t| t.contains("{device}")) {
+                self.session_marker_pending = false;
+            }
         } else if file_has_content {
             // 文件已存在且有内容，标记头部已写入
             self.header_written = true;
         }
         
+        // 上次运行或写入失败留下的最后一行没有换行符时先结束它，新内容从新行开始
+        if !ends_with_newline {
+            writeln!(writer)?;
+            writer.flush()?;
+            self.last_line_blank = false;
+        }
+        
+        // 续写已有文件时，本次运行的第一段输入前写入设备标记
+        if self.session_marker_pending {
+            if let Some(label) = &self.device_label {
+                let marker = i18n::marker_with("marker.device", label);
+                if self.format == config::LogFormat::JsonLines {
+                    writeln!(writer, "{}", json_event_line(self.now(), "marker", None, &marker))?;
+                } else {
+                    let stamp = self.stamp(self.now());
+                    writeln!(writer, "{} --- {} ---", stamp, marker)?;
+                }
+                writer.flush()?;
+                self.last_line_blank = false;
+            }
+            self.session_marker_pending = false;
+        }
+        
+        // 本次运行第一次打开文件时写入启动标记，区分输入间隔和程序没有运行的时段
+        if self.first_open_pending {
+            self.first_open_pending = false;
+            if self.app_markers {
+                let launched_at = self.timezone.at(self.launched_at);
+                let marker = i18n::marker("marker.app_start");
+                if self.format == config::LogFormat::JsonLines {
+                    writeln!(writer, "{}", json_event_line(launched_at, "marker", None, marker))?;
+                } else {
+                    let stamp = self.stamp(launched_at);
+                    writeln!(writer, "{} ===== {} =====", stamp, marker)?;
+                }
+                writer.flush()?;
+                self.last_line_blank = false;
+            }
+        }
+        
         self.writer = Some(writer);
+        self.current_path = Some(path);
+        self.file_len_at_open = file_len;
+        self.file_lines_at_open = file_lines;
         self.current_line_empty = true;
+        self.line_text.clear();
         
         Ok(())
     }
 
-    /// 写入文件头部
-    fn write_header_to(&self, writer: &mut BufWriter<File>) -> io::Result<()> {
-        let now = Local::now();
-        writeln!(writer, "================== EchoKey 日志 ==================")?;
+    /// 以追加模式打开日志文件，读取其中已有内容的概况
+    fn open_file(&self, path: &Path) -> io::Result<OpenedTarget> {
+        // 确保目录存在
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        // 上次写入头部时中断（例如崩溃）留下的不完整头部，替换为完整的头部
+        if self.header_template.is_none() {
+            self.repair_truncated_header(path)?;
+        }
+        
+        // 打开文件（追加模式）
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        
+        // 已有的行数只在打开时统计一次，之后由写入器增量累加
+        let len = file.metadata()?.len();
+        let (lines, ends_with_newline, ends_with_blank_line, last_sequence) = if len > 0 {
+            let existing = fs::read(path)?;
+            let lines = existing.iter().filter(|&&b| b == b'\n').count() as u64;
+            let sequence = last_sequence(&String::from_utf8_lossy(&existing));
+            (lines, existing.last() == Some(&b'\n'), existing.ends_with(b"\n\n"), sequence)
+        } else {
+            (0, true, false, None)
+        };
+        Ok(OpenedTarget { inner: Box::new(file), len, lines, ends_with_newline, ends_with_blank_line, last_sequence })
+    }
+
+    /// 文件开头是不完整的默认头部时，重写为完整的头部（标明是修复的），其后的内容保持不变
+    ///
+    /// 先写入临时文件再改名替换，重写中断时原文件保持不变。
+    fn repair_truncated_header(&self, path: &Path) -> io::Result<()> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(());
+        };
+        let Some(len) = truncated_header_len(&content) else {
+            return Ok(());
+        };
+        
+        let mut repaired = Vec::new();
+        self.write_header_to(&mut repaired, true)?;
+        repaired.extend_from_slice(&content.as_bytes()[len..]);
+        
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let temp = path.with_file_name(format!("{}.tmp", name));
+        let result = File::create(&temp)
+            .and_then(|mut file| {
+                file.write_all(&repaired)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// 写入文件头部，`recovered` 为 true 时注明这是替换不完整头部的新头部
+    fn write_header_to(&self, writer: &mut impl Write, recovered: bool) -> io::Result<()> {
+        let now = self.now();
+        if let Some(template) = &self.header_template {
+            let header = expand_header_template(template, now, self.device_label.as_deref());
+            // 展开后仍是合法的文本才使用（设备名称中可能有控制字符），否则退回默认头部
+            if is_valid_header_text(&header) {
+                if !header.is_empty() {
+                    // 每行加上标记，读取日志时能和输入内容区分开
+                    for line in header.trim_end_matches('\n').lines() {
+                        if line.is_empty() {
+                            writeln!(writer, "{}", CUSTOM_HEADER_MARK)?;
+                        } else {
+                            writeln!(writer, "{} {}", CUSTOM_HEADER_MARK, line)?;
+                        }
+                    }
+                    writeln!(writer)?;
+                    writer.flush()?;
+                }
+                return Ok(());
+            }
+        }
+        writeln!(writer, "{}", HEADER_TITLE)?;
         writeln!(writer, "日期：{}", now.format("%Y-%m-%d"))?;
         writeln!(writer, "创建时间：{}", now.format("%H:%M:%S"))?;
-        writeln!(writer, "==================================================")?;
+        if let Some(label) = &self.device_label {
+            writeln!(writer, "设备：{}", label)?;
+        }
+        if let Some(app) = &self.app {
+            writeln!(writer, "程序：{}", app)?;
+        }
+        if let Some(previous) = &self.continues_from {
+            writeln!(writer, "续接：{}（超过大小上限）", previous)?;
+        }
+        if recovered {
+            writeln!(writer, "恢复：原头部不完整，已重新写入")?;
+        }
+        writeln!(writer, "{}", HEADER_RULE)?;
         writeln!(writer)?;
         writer.flush()?;
         Ok(())
     }
 
-    /// 写入时间戳
+    /// 条目开头的时间戳，开启序号时带上序号：`#0001 [HH:MM:SS]`
+    fn stamp(&mut self, time: DateTime<FixedOffset>) -> String {
+        let time = time.format("%H:%M:%S");
+        if self.sequence_numbers == config::SequenceNumbers::Off {
+            return format!("[{}]", time);
+        }
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        format!("#{:04} [{}]", sequence, time)
+    }
+
+    /// 写入时间戳，开启窗口标题时其后注明前台窗口：`[14:03:22] «标题» `
     fn write_timestamp(&mut self) -> io::Result<()> {
+        let mut stamp = self.stamp(self.now());
+        if self.window_tracking {
+            if let Some(title) = (self.window_title)().as_deref().and_then(sanitize_window_title) {
+                stamp = format!("{} {}{}{}", stamp, WINDOW_TITLE_OPEN, title, WINDOW_TITLE_CLOSE);
+            }
+        }
         if let Some(ref mut writer) = self.writer {
-            let now = Local::now();
-            write!(writer, "[{}] ", now.format("%H:%M:%S"))?;
-            writer.flush()?;
+            write!(writer, "{} ", stamp)?;
         }
         Ok(())
     }
@@ -150,9 +1010,9 @@ impl Logger {
             return true;
         }
         
-        // 如果超过 30 秒没有输入，需要添加时间戳
+        // 如果超过空闲时间没有输入，需要添加时间戳（超时为 0 时每次写入都另起一行）
         if let Some(last_time) = self.last_write_time {
-            if last_time.elapsed() > config::IDLE_TIMEOUT {
+            if self.idle_timeout.is_zero() || self.elapsed(last_time) > self.idle_timeout {
                 return true;
             }
         }
@@ -160,244 +1020,3668 @@ impl Logger {
         false
     }
 
+    /// 执行一次写入，失败时恢复到干净的行状态
+    ///
+    /// 写入可能在中途失败（例如时间戳已落盘而内容没有），此时丢弃写入器中
+    /// 尚未交给文件的内容并关闭文件。下次写入会重新打开文件，先结束残留的
+    /// 半行，再写出格式完整的新行，而不是接在残缺的内容后面。
+    ///
+    /// 文件句柄失效（例如网络驱动器断开后重连）时，立即重新打开文件重试一次，
+    /// 重试仍然失败才返回错误。
+    fn transactional<T>(&mut self, mut write: impl FnMut(&mut Self) -> io::Result<T>) -> io::Result<T> {
+        let result = write(self);
+        match result {
+            Err(e) if is_stale_handle(&e) => {
+                self.discard_writer();
+                eprintln!("日志文件句柄失效，重新打开: {}", e);
+                let retry = write(self);
+                if retry.is_err() {
+                    self.discard_writer();
+                }
+                retry
+            }
+            Err(e) => {
+                self.discard_writer();
+                Err(e)
+            }
+            ok => ok,
+        }
+    }
+
+    /// 关闭写入失败的文件，丢弃尚未交给文件的内容
+    fn discard_writer(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            // 不再尝试写出缓冲区中的内容
+            let _ = writer.into_parts();
+        }
+        self.line_text.clear();
+        self.current_line_empty = true;
+        self.last_write_time = None;
+        self.last_paste = None;
+    }
+
     /// 写入文本内容
     /// 
     /// 这是最常用的写入方法，用于记录用户输入的字符。
+    /// 开启折叠自动重复时，同一字符连续重复超过设定个数后不再写入，
+    /// 这一串结束时在其后写入 `[长按 ×N]`（N 为这一串的总字符数）。
     pub fn write_text(&mut self, text: &str) -> io::Result<()> {
         if self.paused {
             return Ok(());
         }
         
-        self.ensure_file()?;
-        
-        // 检查是否需要换行并添加时间戳
-        let need_timestamp = self.should_add_timestamp();
-        
-        if need_timestamp {
-            // 如果不是空行，先换行
-            if !self.current_line_empty {
-                if let Some(ref mut writer) = self.writer {
-                    writeln!(writer)?;
-                    writer.flush()?;
+        if let Some(policy) = self.autorepeat {
+            let now = self.clock.now_instant();
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => {
+                    if let Some(run) = self.repeat_run.as_mut().filter(|r| r.continues(ch, now, policy.max_interval)) {
+                        run.len += 1;
+                        run.last = now;
+                        if run.len > policy.min_run {
+                            return Ok(());
+                        }
+                    } else {
+                        self.finish_repeat_run()?;
+                        self.repeat_run = Some(RepeatRun { ch, len: 1, last: now });
+                    }
                 }
+                _ => self.finish_repeat_run()?,
             }
-            self.write_timestamp()?;
-            self.current_line_empty = false;
         }
         
-        // 写入内容
-        if let Some(ref mut writer) = self.writer {
-            write!(writer, "{}", text)?;
-            writer.flush()?;
+        self.write_raw_text(text)
+    }
+
+    /// 写入原始按键记录的记号（[`raw_key_marker`]），接在当前行中
+    ///
+    /// 记号不是输入的文字：不能被退格删除，也不参与自动重复的折叠。
+    /// JSON 行格式中写为 `key` 事件。
+    pub fn write_key_marker(&mut self, marker: &str) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
         }
-        
-        self.last_write_time = Some(Instant::now());
-        
+        self.finish_repeat_run()?;
+        if self.format == config::LogFormat::JsonLines {
+            return self.write_json_event("key", LoggedEventKind::Text, None, marker);
+        }
+        self.write_raw_text(marker)?;
+        self.line_text.clear();
         Ok(())
     }
 
-    /// 处理 Enter 键：换行并添加新时间戳
-    pub fn handle_enter(&mut self) -> io::Result<()> {
-        if self.paused {
+    /// 结束当前的连续输入，被折叠过时写入 `[长按 ×N]`
+    fn finish_repeat_run(&mut self) -> io::Result<()> {
+        let Some(run) = self.repeat_run.take() else {
+            return Ok(());
+        };
+        if self.autorepeat.is_some_and(|p| run.len > p.min_run) {
+            self.write_raw_text(&format!("[{}]", i18n::marker_with("marker.autorepeat", run.len)))?;
+            // 折叠标记不是输入的字符，不能被退格删除
+            self.line_text.clear();
+        }
+        Ok(())
+    }
+
+    /// 写入文本内容（不做自动重复折叠）
+    fn write_raw_text(&mut self, text: &str) -> io::Result<()> {
+        if self.format == config::LogFormat::JsonLines {
+            return self.write_json_event("char", LoggedEventKind::Text, None, text);
+        }
+        self.rotate_if_full(text.len())?;
+        self.transactional(|this| {
+            this.ensure_file()?;
+            
+            // 检查是否需要换行并添加时间戳
+            let need_timestamp = this.should_add_timestamp();
+            // 另起一行，或上次输入之后写入过其他内容时，之前的输入不再能被退格删除
+            if need_timestamp || this.line_text_end != this.file_stats().bytes {
+                this.line_text.clear();
+            }
+            
+            if need_timestamp {
+                // 如果不是空行，先换行
+                if !this.current_line_empty {
+                    if let Some(ref mut writer) = this.writer {
+                        writeln!(writer)?;
+                    }
+                }
+                // 空闲超过会话超时，空一行表示新会话开始
+                if this.last_write_time.is_some_and(|t| this.elapsed(t) > this.session_timeout) {
+                    this.write_blank_line()?;
+                }
+                this.write_timestamp()?;
+                this.current_line_empty = false;
+            }
+            
+            // 写入内容
+            if let Some(ref mut writer) = this.writer {
+                write!(writer, "{}", text)?;
+            }
+            this.flush_writer()?;
+            this.remember_line_text(text);
+            
+            this.record_event(LoggedEventKind::Text, text, !need_timestamp);
+            this.last_line_blank = false;
+            this.segment_has_content = true;
+            this.last_paste = None;
+            this.last_write_time = Some(this.clock.now_instant());
+            
+            Ok(())
+        })
+    }
+
+    /// 写入一个空行；压缩空行时上一行已经是空行则跳过
+    fn write_blank_line(&mut self) -> io::Result<()> {
+        if self.compact_whitespace && self.last_line_blank {
             return Ok(());
         }
-        
-        self.ensure_file()?;
-        
         if let Some(ref mut writer) = self.writer {
             writeln!(writer)?;
-            writer.flush()?;
         }
-        
-        self.current_line_empty = true;
-        self.last_write_time = Some(Instant::now());
-        
+        self.last_line_blank = true;
         Ok(())
     }
 
-    /// 处理 Ctrl+Enter：只换行，不添加新时间戳
-    pub fn handle_ctrl_enter(&mut self) -> io::Result<()> {
-        if self.paused {
+    /// 按落盘策略处理刚写入的内容
+    ///
+    /// 立即落盘时每次写入后都落盘；定时刷新时只在缓冲超过 [`FLUSH_BUFFER_BYTES`] 时落盘，
+    /// 其余的由 [`maybe_flush`](Self::maybe_flush) 按间隔落盘。
+    fn flush_writer(&mut self) -> io::Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
             return Ok(());
+        };
+        if self.flush_policy == FlushPolicy::Immediate || writer.buffer().len() >= FLUSH_BUFFER_BYTES {
+            writer.flush()?;
+            self.last_flush = self.clock.now_instant();
         }
-        
+        Ok(())
+    }
+
+    /// JSON 行格式：以当前时间写入一条事件
+    fn write_json_event(&mut self, kind: &str, event: LoggedEventKind, tag: Option<&str>, text: &str) -> io::Result<()> {
+        let now = self.now();
+        self.write_json_event_at(now, kind, event, tag, text)
+    }
+
+    /// JSON 行格式：写入一条使用指定时间的事件，每条事件独占一行
+    fn write_json_event_at(
+        &mut self,
+        time: DateTime<FixedOffset>,
+        kind: &str,
+        event: LoggedEventKind,
+        tag: Option<&str>,
+        text: &str,
+    ) -> io::Result<()> {
+        let line = json_event_line(time, kind, tag, text);
+        self.rotate_if_full(line.len() + 1)?;
+        self.transactional(|this| {
+            this.ensure_file()?;
+            
+            if let Some(ref mut writer) = this.writer {
+                writeln!(writer, "{}", line)?;
+            }
+            this.flush_writer()?;
+            
+            let recorded = match tag {
+                Some(tag) if text.is_empty() => format!("[{}]", tag),
+                Some(tag) => format!("[{}] {}", tag, text),
+                None => text.to_string(),
+            };
+            this.record_event(event, &recorded, false);
+            if event != LoggedEventKind::Marker {
+                this.segment_has_content = true;
+                this.last_write_time = Some(this.clock.now_instant());
+            }
+            this.last_paste = None;
+            this.current_line_empty = true;
+            
+            Ok(())
+        })
+    }
+
+    /// 写入 `incoming` 字节后会超过大小上限时，切换到新的日志段
+    ///
+    /// 新段的头部注明接着哪个文件。当前段没有内容时不切换（单条内容本身超过上限时照常写入）。
+    fn rotate_if_full(&mut self, incoming: usize) -> io::Result<()> {
+        let Some(max) = self.max_file_bytes else {
+            return Ok(());
+        };
         self.ensure_file()?;
-        
-        if let Some(ref mut writer) = self.writer {
-            writeln!(writer)?;
-            // 写入缩进对齐时间戳
-            write!(writer, "          ")?; // 与时间戳 [HH:MM:SS] 对齐
-            writer.flush()?;
+        // 续写已有的段也可能已经超过上限，一直切换到放得下的段为止
+        while self.file_stats().bytes + incoming as u64 > max {
+            self.continues_from = self.current_path.as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned());
+            let created = self.new_segment();
+            // 只用于新段的头部：新段是已有内容的文件时没有写入头部，也不能留到之后的段
+            self.continues_from = None;
+            if !created? {
+                break;
+            }
         }
-        
-        // 不设置 current_line_empty = true，这样下次写入不会添加时间戳
-        self.last_write_time = Some(Instant::now());
-        
         Ok(())
     }
 
-    /// 写入粘贴内容
-    pub fn write_paste(&mut self, content: &str) -> io::Result<()> {
+    /// 处理 Enter 键：换行并添加新时间戳
+    pub fn handle_enter(&mut self) -> io::Result<()> {
         if self.paused {
             return Ok(());
         }
+        self.finish_repeat_run()?;
+        if self.format == config::LogFormat::JsonLines {
+            return self.write_json_event("enter", LoggedEventKind::LineBreak, None, "");
+        }
         
-        self.ensure_file()?;
-        
-        // 先换行
-        if !self.current_line_empty {
-            if let Some(ref mut writer) = self.writer {
+        self.transactional(|this| {
+            this.ensure_file()?;
+            
+            // 当前行为空时换行会留下一个空行
+            if this.current_line_empty {
+                this.write_blank_line()?;
+            } else if let Some(ref mut writer) = this.writer {
                 writeln!(writer)?;
             }
-        }
-        
-        // 写入粘贴标记和内容
-        if let Some(ref mut writer) = self.writer {
-            let now = Local::now();
-            writeln!(writer, "[{}] [粘贴] {}", now.format("%H:%M:%S"), content)?;
-            writer.flush()?;
-        }
-        
-        self.current_line_empty = true;
-        self.last_write_time = Some(Instant::now());
-        
-        Ok(())
+            this.flush_writer()?;
+            
+            this.record_event(LoggedEventKind::LineBreak, "", false);
+            this.current_line_empty = true;
+            this.last_write_time = Some(this.clock.now_instant());
+            
+            Ok(())
+        })
     }
 
-    /// 写入手动保存内容
-    pub fn write_manual_save(&mut self, content: &str) -> io::Result<()> {
+    /// 处理 Ctrl+Enter：只换行，不添加新时间戳
+    pub fn handle_ctrl_enter(&mut self) -> io::Result<()> {
         if self.paused {
             return Ok(());
         }
+        self.finish_repeat_run()?;
+        if self.format == config::LogFormat::JsonLines {
+            return self.write_json_event("ctrl_enter", LoggedEventKind::LineBreak, None, "");
+        }
         
-        self.ensure_file()?;
-        
-        // 先换行
-        if !self.current_line_empty {
-            if let Some(ref mut writer) = self.writer {
+        self.transactional(|this| {
+            this.ensure_file()?;
+            
+            if let Some(ref mut writer) = this.writer {
                 writeln!(writer)?;
+                // 写入缩进对齐时间戳
+                write!(writer, "{}", CONTINUATION_INDENT)?;
             }
+            this.flush_writer()?;
+            
+            // 不设置 current_line_empty = true，这样下次写入不会添加时间戳
+            this.record_event(LoggedEventKind::LineBreak, "", false);
+            this.last_line_blank = false;
+            this.last_write_time = Some(this.clock.now_instant());
+            
+            Ok(())
+        })
+    }
+
+    /// 处理 Backspace 键
+    ///
+    /// 开启退格删除时，从文件末尾删掉当前行中最后输入的一个字符；当前行中没有可删除的输入
+    /// （例如刚换过行，或最后写入的是粘贴、标记等条目）时仍写入 `⌫`。未开启时总是写入 `⌫`。
+    pub fn handle_backspace(&mut self) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
         }
-        
-        // 写入手动保存标记和内容
-        if let Some(ref mut writer) = self.writer {
-            let now = Local::now();
-            writeln!(writer, "[{}] [手动保存] {}", now.format("%H:%M:%S"), content)?;
-            writer.flush()?;
+        if !self.backspace_deletes {
+            return self.write_text(BACKSPACE_MARKER);
         }
+        self.finish_repeat_run()?;
         
-        self.current_line_empty = true;
-        self.last_write_time = Some(Instant::now());
-        
+        if !self.transactional(|this| this.delete_last_char())? {
+            self.write_raw_text(BACKSPACE_MARKER)?;
+            // 写入的记号不是输入的字符，不能被下一次退格删除
+            self.line_text.clear();
+        }
         Ok(())
     }
 
-    /// 手动创建新的日志段
-    pub fn new_segment(&mut self) -> io::Result<()> {
-        // 关闭当前文件
-        if let Some(ref mut writer) = self.writer {
-            writer.flush()?;
+    /// 从文件末尾删除当前行中最后输入的字符，返回是否删除了
+    ///
+    /// JSON 行格式和写入内存目标时不能删除已写入的内容，返回 false。
+    fn delete_last_char(&mut self) -> io::Result<bool> {
+        if self.format != config::LogFormat::PlainText || self.sink.is_some() {
+            return Ok(false);
         }
-        self.writer = None;
+        self.ensure_file()?;
+        // 上次输入之后写入过其他内容，文件末尾已经不是输入的字符
+        if self.line_text_end != self.file_stats().bytes {
+            self.line_text.clear();
+        }
+        let (Some(ch), Some(path), Some(writer)) =
+            (self.line_text.chars().next_back(), self.current_path.as_ref(), self.writer.as_mut())
+        else {
+            return Ok(false);
+        };
         
-        // 增加段号
-        self.segment_number += 1;
-        self.header_written = false;
+        writer.flush()?;
+        let removed = ch.len_utf8() as u64;
+        let len = self.file_len_at_open + writer.get_ref().bytes;
+        OpenOptions::new().write(true).open(path)?.set_len(len - removed)?;
+        // 删除的字符都是通过当前写入器写入的，从计数中减去即可与文件保持一致
+        writer.get_mut().bytes -= removed;
+        self.line_text.pop();
+        self.line_text_end -= removed;
         
-        // 创建新文件
-        self.open_or_create_file()?;
+        if let Some(last) = self.recent_events.back_mut().filter(|e| e.kind == LoggedEventKind::Text) {
+            last.text.pop();
+        }
+        self.last_flush = self.clock.now_instant();
+        self.last_write_time = Some(self.clock.now_instant());
+        Ok(true)
+    }
+
+    /// 记下刚写入当前行的输入，只保留末尾的 [`LINE_TEXT_MAX_BYTES`] 字节
+    fn remember_line_text(&mut self, text: &str) {
+        if !self.backspace_deletes {
+            return;
+        }
+        self.line_text.push_str(text);
+        if self.line_text.len() > LINE_TEXT_MAX_BYTES {
+            let excess = self.line_text.len() - LINE_TEXT_MAX_BYTES;
+            let cut = (excess..=self.line_text.len())
+                .find(|&i| self.line_text.is_char_boundary(i))
+                .unwrap_or(self.line_text.len());
+            self.line_text.drain(..cut);
+        }
+        self.line_text_end = self.file_stats().bytes;
+    }
+
+    /// 写入带标记的独立条目，例如 `[14:31:00] [粘贴] 内容`
+    fn write_tagged_entry(&mut self, tag: &str, content: &str) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
         
-        Ok(())
+        self.write_tagged_entry_with_progress(tag, content, |_, _| {})
     }
 
-    /// 暂停记录
-    pub fn pause(&mut self) -> io::Result<()> {
-        if !self.paused {
-            self.paused = true;
-            
-            self.ensure_file()?;
+    /// 写入带标记的条目，内容分块写入并报告进度（已写入字节数, 总字节数）
+    ///
+    /// 不检查暂停状态，由调用方决定暂停时是否写入。
+    fn write_tagged_entry_with_progress(
+        &mut self,
+        tag: &str,
+        content: &str,
+        mut progress: impl FnMut(usize, usize),
+    ) -> io::Result<()> {
+        self.finish_repeat_run()?;
+        if self.format == config::LogFormat::JsonLines {
+            self.write_json_event("entry", LoggedEventKind::Entry, Some(tag), content)?;
+            progress(content.len(), content.len());
+            return Ok(());
+        }
+        self.rotate_if_full(tag.len() + content.len())?;
+        self.transactional(|this| {
+            this.ensure_file()?;
             
-            // 写入暂停标记
-            if !self.current_line_empty {
-                if let Some(ref mut writer) = self.writer {
+            // 先换行
+            if !this.current_line_empty {
+                if let Some(ref mut writer) = this.writer {
                     writeln!(writer)?;
                 }
             }
             
-            if let Some(ref mut writer) = self.writer {
-                let now = Local::now();
-                writeln!(writer, "[{}] --- 暂停记录 ---", now.format("%H:%M:%S"))?;
-                writer.flush()?;
+            // 写入标记和内容（没有内容时只写标记）
+            let stamp = this.stamp(this.now());
+            if let Some(ref mut writer) = this.writer {
+                if content.is_empty() {
+                    writeln!(writer, "{} [{}]", stamp, tag)?;
+                } else {
+                    write!(writer, "{} [{}] ", stamp, tag)?;
+                    let mut done = 0;
+                    for chunk in chunks_at_char_boundaries(content, PASTE_CHUNK_BYTES) {
+                        writer.write_all(chunk.as_bytes())?;
+                        done += chunk.len();
+                        progress(done, content.len());
+                    }
+                    writeln!(writer)?;
+                }
             }
+            this.flush_writer()?;
             
-            self.current_line_empty = true;
-        }
-        Ok(())
+            let entry = if content.is_empty() { format!("[{}]", tag) } else { format!("[{}] {}", tag, content) };
+            this.record_event(LoggedEventKind::Entry, &entry, false);
+            this.last_line_blank = false;
+            this.segment_has_content = true;
+            this.last_paste = None;
+            this.current_line_empty = true;
+            this.last_write_time = Some(this.clock.now_instant());
+            
+            Ok(())
+        })
     }
 
-    /// 恢复记录
-    pub fn resume(&mut self) -> io::Result<()> {
+    /// 写入粘贴内容
+    ///
+    /// 启用合并时，时间窗口内连续粘贴相同内容只记录 `[粘贴 ×2]`，不重复内容。
+    /// 粘贴的正是刚刚复制（[`COPY_PASTE_WINDOW`] 内）的内容时，只记录 `[粘贴]`，
+    /// 内容见上方的 `[复制]` 条目。
+    pub fn write_paste(&mut self, content: &str) -> io::Result<()> {
+        self.write_paste_with_progress(content, |_, _| {})
+    }
+
+    /// 写入粘贴内容，并报告写入进度（已写入字节数, 总字节数）
+    ///
+    /// 内容按 [`PASTE_CHUNK_BYTES`] 分块写入，每写完一块回调一次，
+    /// 用于在界面上显示大段粘贴的进度。合并为 `[粘贴 ×N]` 时不回调。
+    /// 超过字符上限的内容截断并注明总字数；多行内容的后续行缩进到时间戳之后。
+    pub fn write_paste_with_progress(
+        &mut self,
+        content: &str,
+        mut progress: impl FnMut(usize, usize),
+    ) -> io::Result<()> {
         if self.paused {
-            self.paused = false;
-            
-            self.ensure_file()?;
-            
-            // 写入恢复标记
-            if let Some(ref mut writer) = self.writer {
-                let now = Local::now();
-                writeln!(writer, "[{}] --- 恢复记录 ---", now.format("%H:%M:%S"))?;
-                writer.flush()?;
+            return Ok(());
+        }
+        let written = match self.max_paste_chars {
+            Some(max_chars) => truncate_paste(content, max_chars),
+            None => Cow::Borrowed(content),
+        };
+        // JSON 行格式每次粘贴都写出（截断后的）完整内容，由下游工具自行合并
+        if self.format == config::LogFormat::JsonLines {
+            self.finish_repeat_run()?;
+            self.write_json_event("paste", LoggedEventKind::Entry, None, &written)?;
+            progress(written.len(), written.len());
+            return Ok(());
+        }
+        
+        let tag = i18n::marker("marker.paste");
+        let copied = self.last_copy.take()
+            .is_some_and(|(last, time)| last == content && self.elapsed(time) <= COPY_PASTE_WINDOW);
+        let repeat = match (&self.last_paste, self.paste_dedup_window) {
+            (Some((last, time, count)), Some(window)) if last == content && self.elapsed(*time) <= window => {
+                Some(count + 1)
             }
-            
-            self.current_line_empty = true;
+            _ => None,
+        };
+        
+        match repeat {
+            Some(count) => self.write_tagged_entry(&format!("{} ×{}", tag, count), "")?,
+            None if copied => self.write_tagged_entry(tag, "")?,
+            None => self.write_tagged_entry_with_progress(tag, &indent_continuation_lines(&written), progress)?,
         }
+        self.last_paste = Some((content.to_string(), self.clock.now_instant(), repeat.unwrap_or(1)));
         Ok(())
     }
 
-    /// 切换暂停/恢复状态
-    pub fn toggle_pause(&mut self) -> io::Result<bool> {
-        if self.paused {
-            self.resume()?;
-        } else {
-            self.pause()?;
-        }
-        Ok(self.paused)
+    /// 设置设备名称
+    ///
+    /// 名称写入新日志文件的头部；`session_marker` 为 true 时，续写已有文件
+    /// 也会在本次运行的第一段输入前写入 `--- 设备：名称 ---` 标记。
+    pub fn set_device_label(&mut self, label: Option<String>, session_marker: bool) {
+        self.device_label = label.filter(|l| !l.trim().is_empty());
+        self.session_marker_pending = session_marker && self.device_label.is_some();
     }
 
-    /// 是否处于暂停状态
-    pub fn is_paused(&self) -> bool {
-        self.paused
+    /// 设置是否写入启动/退出标记
+    ///
+    /// 启动标记在本次运行第一次打开日志文件时写入，使用程序启动的时间；
+    /// 退出标记由 [`Logger::write_exit_marker`] 在正常退出时写入。
+    pub fn set_app_markers(&mut self, enabled: bool) {
+        self.app_markers = enabled;
     }
 
-    /// 设置暂停状态
-    pub fn set_paused(&mut self, paused: bool) -> io::Result<()> {
-        if paused != self.paused {
-            if paused {
-                self.pause()?;
-            } else {
-                self.resume()?;
-            }
+    /// 设置单个文件的大小上限，写入会超过上限时自动切换到新的日志段，None 表示不限制
+    pub fn set_max_file_bytes(&mut self, max: Option<u64>) {
+        self.max_file_bytes = max;
+    }
+
+    /// 设置是否把连续的多个空行压缩为一个（只跳过多余的空行，不影响有内容的行）
+    pub fn set_compact_whitespace(&mut self, enabled: bool) {
+        self.compact_whitespace = enabled;
+    }
+
+    /// 设置是否在每个新时间戳后注明前台窗口的标题
+    pub fn set_window_tracking(&mut self, enabled: bool) {
+        self.window_tracking = enabled;
+    }
+
+    /// 设置退格键是删除最后输入的字符，还是写入 `⌫`
+    pub fn set_backspace_deletes(&mut self, enabled: bool) {
+        self.backspace_deletes = enabled;
+        self.line_text.clear();
+    }
+
+    /// 设置自定义的文件头部模板
+    ///
+    /// 模板中可以使用 `{date}`、`{time}`、`{version}`、`{device}` 占位符；
+    /// 空字符串表示不写头部，None 恢复默认头部。模板不合法时返回错误并保持默认头部。
+    pub fn set_header_template(&mut self, template: Option<String>) -> Result<(), String> {
+        self.header_template = None;
+        if let Some(template) = &template {
+            validate_header_template(template).map_err(|e| e.to_string())?;
         }
+        self.header_template = template;
         Ok(())
     }
 
-    /// 获取日志目录路径
-    pub fn get_log_directory(&self) -> PathBuf {
-        config::get_log_directory()
+    /// 设置时间戳和会话的空闲超时
+    ///
+    /// `stamp` 决定多久没有输入后另起一行添加新时间戳，`session` 决定多久没有输入后
+    /// 空一行开始新会话。会话包含若干时间戳行，因此 `session` 短于 `stamp` 时按 `stamp` 处理。
+    pub fn set_idle_timeouts(&mut self, stamp: Duration, session: Duration) {
+        self.idle_timeout = stamp;
+        self.session_timeout = session.max(stamp);
     }
-}
 
-impl Default for Logger {
-    fn default() -> Self {
-        Self::new().expect("无法创建日志目录")
+    /// 只设置添加新时间戳的空闲超时，会话超时不变（仍不短于时间戳超时）
+    ///
+    /// 超时为 0 时每次写入都另起一行添加时间戳。
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.set_idle_timeouts(timeout, self.session_timeout);
     }
-}
 
-impl Drop for Logger {
-    fn drop(&mut self) {
-        // 确保文件被正确关闭
+    /// 设置条目序号的计数方式
+    pub fn set_sequence_numbers(&mut self, mode: config::SequenceNumbers) {
+        self.sequence_numbers = mode;
+    }
+
+    /// 设置自动重复折叠规则，None 表示不折叠
+    pub fn set_autorepeat(&mut self, policy: Option<AutorepeatPolicy>) {
+        self.autorepeat = policy;
+        self.repeat_run = None;
+    }
+
+    /// 设置暂停标记的最短时长，None 表示每次暂停都写入标记
+    ///
+    /// 设置后暂停标记推迟到暂停持续满这个时长才写入（时间戳仍是暂停开始的时刻），
+    /// 在此之前恢复则暂停和恢复标记都不写入，短暂的暂停在日志中不留痕迹。
+    pub fn set_pause_marker_threshold(&mut self, threshold: Option<Duration>) {
+        self.pause_marker_threshold = threshold;
+    }
+
+    /// 设置恢复记录后的续写方式
+    ///
+    /// [`Inline`](config::ResumeStyle::Inline) 时暂停和恢复标记以 `[暂停记录 HH:MM:SS]`
+    /// 的形式接在当前行末尾，恢复后的输入不添加新时间戳，继续写在暂停前的那一行；
+    /// 暂停时当前行为空（例如刚按过 Enter）则仍然写成独立的标记行。
+    pub fn set_resume_style(&mut self, style: config::ResumeStyle) {
+        self.resume_style = style;
+    }
+
+    /// 设置输出格式
+    ///
+    /// 格式改变时关闭当前文件，下次写入打开对应扩展名（`.log` 或 `.jsonl`）的文件。
+    pub fn set_format(&mut self, format: config::LogFormat) {
+        if self.format == format {
+            return;
+        }
+        self.finish_repeat_run().ok();
         if let Some(ref mut writer) = self.writer {
-            let _ = writer.flush();
+            writer.flush().ok();
+        }
+        self.format = format;
+        self.writer = None;
+        self.segment_number = 0;
+        self.app_segments.clear();
+        self.header_written = false;
+        self.current_line_empty = true;
+    }
+
+    /// 设置粘贴内容的字符上限，None 表示不限制
+    pub fn set_max_paste_chars(&mut self, max_chars: Option<usize>) {
+        self.max_paste_chars = max_chars;
+    }
+
+    /// 设置合并相同粘贴的时间窗口，None 表示不合并
+    pub fn set_paste_dedup_window(&mut self, window: Option<Duration>) {
+        self.paste_dedup_window = window;
+        self.last_paste = None;
+    }
+
+    /// 设置最多保留多少条最近事件，0 表示不保留（并清空已保留的事件）
+    pub fn set_recent_events_capacity(&mut self, capacity: usize) {
+        self.recent_events_capacity = capacity;
+        while self.recent_events.len() > capacity {
+            self.recent_events.pop_front();
+        }
+    }
+
+    /// 最近的 `n` 条事件（从旧到新），供中途连接的观察者回放
+    ///
+    /// 只保留当前日志文件中的事件，且与文件同进退：暂停期间不记录，
+    /// 切换文件或文件被外部删改（例如删掉敏感内容）后清空，
+    /// 内存中不会留下文件里已经没有的内容。
+    pub fn recent_events(&mut self, n: usize) -> Vec<LoggedEvent> {
+        if let (Some(path), Some(expected), None) = (&self.current_path, self.expected_file_len(), &self.sink) {
+            if fs::metadata(path).map(|m| m.len()).ok() != Some(expected) {
+                self.recent_events.clear();
+            }
+        }
+        let skip = self.recent_events.len().saturating_sub(n);
+        self.recent_events.iter().skip(skip).cloned().collect()
+    }
+
+    /// 记录一条最近事件，`continues` 为 true 时文字接在上一条文字事件后面
+    fn record_event(&mut self, kind: LoggedEventKind, text: &str, continues: bool) {
+        if self.recent_events_capacity == 0 {
+            return;
+        }
+        if let Some(last) = self.recent_events.back_mut() {
+            if continues && kind == LoggedEventKind::Text && last.kind == LoggedEventKind::Text {
+                if last.text.len() < RECENT_EVENT_MAX_BYTES {
+                    last.text.push_str(text);
+                }
+                return;
+            }
+        }
+        let text = chunks_at_char_boundaries(text, RECENT_EVENT_MAX_BYTES).next().unwrap_or_default();
+        let time = self.now();
+        self.recent_events.push_back(LoggedEvent { time, kind, text: text.to_string() });
+        while self.recent_events.len() > self.recent_events_capacity {
+            self.recent_events.pop_front();
+        }
+    }
+
+    /// 写入粘贴的文件列表：`[粘贴文件] C:\a.txt | C:\b.txt`
+    pub fn write_pasted_files(&mut self, paths: &[PathBuf]) -> io::Result<()> {
+        let list: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        self.write_tagged_entry(i18n::marker("marker.paste_files"), &list.join(" | "))
+    }
+
+    /// 写入粘贴的图片：`[粘贴图片] 宽×高`
+    pub fn write_pasted_image(&mut self, width: usize, height: usize) -> io::Result<()> {
+        self.write_tagged_entry(i18n::marker("marker.paste_image"), &format!("{}×{}", width, height))
+    }
+
+    /// 把粘贴的图片（RGBA 像素）保存为 PNG 附件，写入 `[粘贴图片] 宽×高 attachments/文件名.png`
+    ///
+    /// 大图编码很慢，只在这里占好文件名，编码和写入在后台线程中进行（调用方持有状态锁，
+    /// 不能阻塞之后的按键）。像素数据与尺寸不符或无法创建文件时只记录图片尺寸；
+    /// 后台编码失败时删除占位的文件。
+    pub fn write_pasted_image_file(&mut self, width: usize, height: usize, rgba: Vec<u8>) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        let image = match (u32::try_from(width), u32::try_from(height)) {
+            (Ok(w), Ok(h)) => image::RgbaImage::from_raw(w, h, rgba),
+            _ => None,
+        };
+        let Some(image) = image else {
+            eprintln!("警告: 保存粘贴的图片失败: 图片数据与尺寸不符");
+            return self.write_pasted_image(width, height);
+        };
+        let (path, name) = match self.reserve_attachment() {
+            Ok(reserved) => reserved,
+            Err(e) => {
+                eprintln!("警告: 保存粘贴的图片失败: {}", e);
+                return self.write_pasted_image(width, height);
+            }
+        };
+        
+        self.image_writers.retain(|writer| !writer.is_finished());
+        self.image_writers.push(std::thread::spawn(move || {
+            if let Err(e) = image.save_with_format(&path, image::ImageFormat::Png) {
+                eprintln!("警告: 无法写入 PNG {}: {}", path.display(), e);
+                let _ = fs::remove_file(&path);
+            }
+        }));
+        self.write_tagged_entry(
+            i18n::marker("marker.paste_image"),
+            &format!("{}×{} {}/{}", width, height, ATTACHMENTS_DIR, name),
+        )
+    }
+
+    /// 在附件文件夹中创建一个空文件占住文件名，返回路径和文件名
+    ///
+    /// 按粘贴时刻命名，同一秒内的加序号。
+    fn reserve_attachment(&self) -> io::Result<(PathBuf, String)> {
+        let dir = self.log_dir.join(ATTACHMENTS_DIR);
+        fs::create_dir_all(&dir)?;
+        let stem = self.clock.now_local().format("%Y-%m-%d_%H%M%S").to_string();
+        for n in 1.. {
+            let name = match n {
+                1 => format!("{}.png", stem),
+                n => format!("{}_{}.png", stem, n),
+            };
+            let path = dir.join(&name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok((path, name)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
         }
+        unreachable!("序号用完之前总能创建文件")
+    }
+
+    /// 剪贴板中是不支持的格式时，只记录发生过一次粘贴
+    pub fn write_unknown_paste(&mut self) -> io::Result<()> {
+        self.write_tagged_entry(i18n::marker("marker.paste_unknown"), "")
+    }
+
+    /// 写入复制的内容：`[复制] 内容`
+    ///
+    /// 与粘贴一样，超过字符上限的内容截断并注明总字数，多行内容的后续行缩进到时间戳之后。
+    pub fn write_copy(&mut self, content: &str) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        let written = match self.max_paste_chars {
+            Some(max_chars) => truncate_paste(content, max_chars),
+            None => Cow::Borrowed(content),
+        };
+        let tag = i18n::marker("marker.copy");
+        match self.format {
+            config::LogFormat::JsonLines => self.write_tagged_entry(tag, &written)?,
+            config::LogFormat::PlainText => self.write_tagged_entry(tag, &indent_continuation_lines(&written))?,
+        }
+        self.last_copy = Some((content.to_string(), self.clock.now_instant()));
+        Ok(())
+    }
+
+    /// 写入手动保存内容
+    pub fn write_manual_save(&mut self, content: &str) -> io::Result<()> {
+        if self.format == config::LogFormat::JsonLines {
+            self.finish_repeat_run()?;
+            return self.write_json_event("manual_save", LoggedEventKind::Entry, None, content);
+        }
+        self.write_tagged_entry(i18n::marker("marker.manual_save"), content)
+    }
+
+    /// 写入检查点：只有时间戳和 `[检查点]` 标记的一行
+    ///
+    /// 检查点由用户主动触发，暂停时也会写入。
+    pub fn write_checkpoint(&mut self) -> io::Result<()> {
+        self.write_pending_pause(true)?;
+        self.write_tagged_entry_with_progress(i18n::marker("marker.checkpoint"), "", |_, _| {})
+    }
+
+    /// 写入累加剪贴板的合并内容
+    pub fn write_accumulated(&mut self, content: &str) -> io::Result<()> {
+        self.write_tagged_entry(i18n::marker("marker.accumulated"), content)
+    }
+
+    /// 写入窗口切换标记（详细记录模式）
+    pub fn write_window_marker(&mut self, title: &str) -> io::Result<()> {
+        self.write_marker(&i18n::marker_with("marker.window", title))
+    }
+
+    /// 写入专注时段开始标记
+    pub fn write_focus_start(&mut self) -> io::Result<()> {
+        self.write_banner(i18n::marker("marker.focus_start"))
+    }
+
+    /// 正常退出时写入退出标记（未开启启动/退出标记时不写）
+    pub fn write_exit_marker(&mut self) -> io::Result<()> {
+        if !self.app_markers {
+            return Ok(());
+        }
+        self.write_banner(i18n::marker("marker.app_exit"))
+    }
+
+    /// 写入专注时段结束标记，附带期间输入的字符数和专注的分钟数
+    pub fn write_focus_end(&mut self, chars: usize, minutes: u64) -> io::Result<()> {
+        let banner = i18n::marker("marker.focus_end")
+            .replace("{chars}", &chars.to_string())
+            .replace("{minutes}", &minutes.to_string());
+        self.write_banner(&banner)
+    }
+
+    /// 写入大写锁定切换标记，解释日志中连续大写的来源
+    pub fn write_caps_lock_marker(&mut self, on: bool) -> io::Result<()> {
+        let key = if on { "marker.caps_lock_on" } else { "marker.caps_lock_off" };
+        self.write_marker(i18n::marker(key))
+    }
+
+    /// 屏幕锁定时暂停记录，写入锁屏标记代替暂停标记
+    ///
+    /// 已经暂停时不做任何事，返回 false。
+    pub fn pause_for_lock(&mut self) -> io::Result<bool> {
+        if self.paused {
+            return Ok(false);
+        }
+        self.write_marker(i18n::marker("marker.screen_locked"))?;
+        self.paused = true;
+        Ok(true)
+    }
+
+    /// 解锁后恢复因锁屏暂停的记录，写入解锁标记代替恢复标记
+    pub fn resume_after_unlock(&mut self) -> io::Result<()> {
+        if self.paused {
+            self.paused = false;
+            self.write_marker(i18n::marker("marker.screen_unlocked"))?;
+        }
+        Ok(())
+    }
+
+    /// 写入从实时预览中摘录的内容
+    pub fn write_excerpt(&mut self, content: &str) -> io::Result<()> {
+        self.write_tagged_entry(i18n::marker("marker.excerpt"), content)
+    }
+
+    /// 写入焦点控件文本的校验快照，用于与记录的按键流对照
+    pub fn write_verification(&mut self, content: &str) -> io::Result<()> {
+        self.write_tagged_entry(i18n::marker("marker.verify"), content)
+    }
+
+    /// 将已写入的内容落盘
+    ///
+    /// 切换到其他窗口等自然的停顿点会调用此方法，作为确定的持久化边界。
+    /// 立即落盘时每次写入后都已 flush，此调用实际上不产生额外 I/O。
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(ref mut writer) = self.writer {
+            writer.flush()?;
+        }
+        self.last_flush = self.clock.now_instant();
+        Ok(())
+    }
+
+    /// 设置落盘策略
+    ///
+    /// 改为立即落盘时先把缓冲的内容落盘（失败时下次写入会再次尝试）。
+    /// 定时刷新时写入失败会丢弃尚未落盘的内容（最多一个间隔内的输入）。
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.flush_policy = policy;
+        if policy == FlushPolicy::Immediate {
+            let _ = self.flush();
+        }
+    }
+
+    /// 定时刷新时，距上次落盘超过间隔且有缓冲的内容则落盘，返回是否落盘
+    ///
+    /// 由逻辑循环每轮调用；立即落盘时不做任何事。
+    pub fn maybe_flush(&mut self) -> io::Result<bool> {
+        let FlushPolicy::Interval(interval) = self.flush_policy else {
+            return Ok(false);
+        };
+        let pending = self.writer.as_ref().is_some_and(|w| !w.buffer().is_empty());
+        if !pending || self.elapsed(self.last_flush) < interval {
+            return Ok(false);
+        }
+        self.transactional(|this| this.flush())?;
+        Ok(true)
+    }
+
+    /// 手动创建新的日志段
+    ///
+    /// 当前段还没有写入任何内容时不创建新文件，避免连续触发产生一串空段。
+    /// 返回是否真的创建了新段。
+    pub fn new_segment(&mut self) -> io::Result<bool> {
+        self.ensure_file()?;
+        if !self.segment_has_content {
+            return Ok(false);
+        }
+        
+        // 关闭当前文件
+        if let Some(ref mut writer) = self.writer {
+            writer.flush()?;
+        }
+        self.writer = None;
+        
+        // 增加段号
+        self.segment_number += 1;
+        self.header_written = false;
+        
+        // 创建新文件
+        self.open_or_create_file()?;
+        
+        Ok(true)
+    }
+
+    /// 写入一行独立的状态标记，例如 `[14:33:00] --- 暂停记录 ---`
+    fn write_marker(&mut self, marker: &str) -> io::Result<()> {
+        let now = self.now();
+        self.write_marker_at(marker, now)
+    }
+
+    /// 写入一行使用指定时间戳的状态标记
+    fn write_marker_at(&mut self, marker: &str, time: DateTime<FixedOffset>) -> io::Result<()> {
+        self.write_standalone_at(marker, "---", time)
+    }
+
+    /// 写入一行醒目的分隔标记，例如 `[14:33:00] ===== 专注开始 =====`
+    fn write_banner(&mut self, banner: &str) -> io::Result<()> {
+        let now = self.now();
+        self.write_standalone_at(banner, "=====", now)
+    }
+
+    /// 在时间戳后写入独立的一行（前后不与输入内容相连）
+    ///
+    /// 纯文本格式中标记前后加上 `decoration`；JSON 行格式原样写入标记文本。
+    fn write_standalone_at(&mut self, marker: &str, decoration: &str, time: DateTime<FixedOffset>) -> io::Result<()> {
+        self.finish_repeat_run()?;
+        if self.format == config::LogFormat::JsonLines {
+            return self.write_json_event_at(time, "marker", LoggedEventKind::Marker, None, marker);
+        }
+        let text = format!("{} {} {}", decoration, marker, decoration);
+        let text = text.as_str();
+        self.transactional(|this| {
+            this.ensure_file()?;
+            
+            // 先换行
+            if !this.current_line_empty {
+                if let Some(ref mut writer) = this.writer {
+                    writeln!(writer)?;
+                }
+            }
+            
+            let stamp = this.stamp(time);
+            if let Some(ref mut writer) = this.writer {
+                writeln!(writer, "{} {}", stamp, text)?;
+                writer.flush()?;
+            }
+            
+            this.record_event(LoggedEventKind::Marker, text, false);
+            this.last_line_blank = false;
+            this.current_line_empty = true;
+            Ok(())
+        })
+    }
+
+    /// 写入暂停/恢复标记，按续写方式写成独立的一行或接在当前行末尾
+    ///
+    /// 接在行末的恢复标记后留一个空格，与之后继续输入的内容隔开。
+    fn write_pause_marker_at(&mut self, key: &'static str, time: DateTime<FixedOffset>) -> io::Result<()> {
+        let marker = i18n::marker(key);
+        if self.resume_style == config::ResumeStyle::NewLine
+            || self.current_line_empty
+            || self.format == config::LogFormat::JsonLines
+        {
+            return self.write_marker_at(marker, time);
+        }
+        self.finish_repeat_run()?;
+        self.transactional(|this| {
+            this.ensure_file()?;
+            
+            // 重新打开了文件（例如日期变了），没有可以接续的行
+            if this.current_line_empty {
+                let stamp = this.stamp(time);
+                if let Some(ref mut writer) = this.writer {
+                    writeln!(writer, "{} --- {} ---", stamp, marker)?;
+                    writer.flush()?;
+                }
+                this.record_event(LoggedEventKind::Marker, &format!("--- {} ---", marker), false);
+                return Ok(());
+            }
+            
+            if let Some(ref mut writer) = this.writer {
+                let gap = if key == "marker.resume" { " " } else { "" };
+                write!(writer, " [{} {}]{}", marker, time.format("%H:%M:%S"), gap)?;
+                writer.flush()?;
+            }
+            this.record_event(LoggedEventKind::Marker, &format!("[{}]", marker), false);
+            
+            // 恢复后的输入接着这一行，不因暂停期间的空闲另起新时间戳
+            this.last_write_time = Some(this.clock.now_instant());
+            Ok(())
+        })
+    }
+
+    /// 暂停记录
+    pub fn pause(&mut self) -> io::Result<()> {
+        if !self.paused {
+            self.paused = true;
+            if self.pause_marker_threshold.is_some() {
+                self.pending_pause = Some((self.clock.now_instant(), self.now()));
+            } else {
+                let now = self.now();
+                self.write_pause_marker_at("marker.pause", now)?;
+            }
+            // 暂停期间没有新的写入，缓冲的内容现在落盘
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// 恢复记录
+    ///
+    /// 暂停时长不足 [`set_pause_marker_threshold`](Self::set_pause_marker_threshold)
+    /// 设置的阈值时，暂停和恢复标记都不写入。
+    pub fn resume(&mut self) -> io::Result<()> {
+        if self.paused {
+            self.paused = false;
+            if self.pending_pause.is_some() && !self.write_pending_pause(false)? {
+                self.pending_pause = None;
+                return Ok(());
+            }
+            let now = self.now();
+            self.write_pause_marker_at("marker.resume", now)?;
+        }
+        Ok(())
+    }
+
+    /// 写入推迟的暂停标记
+    ///
+    /// 暂停已持续满阈值（或 `force` 为 true）时写入，返回暂停标记是否已在日志中。
+    /// 业务线程定期调用，让长时间的暂停及时出现在日志里。
+    pub fn write_pending_pause(&mut self, force: bool) -> io::Result<bool> {
+        let Some((since, time)) = self.pending_pause else {
+            return Ok(true);
+        };
+        let threshold = self.pause_marker_threshold.unwrap_or_default();
+        if !force && self.elapsed(since) < threshold {
+            return Ok(false);
+        }
+        self.write_pause_marker_at("marker.pause", time)?;
+        self.pending_pause = None;
+        Ok(true)
+    }
+
+    /// 进入安全桌面（UAC 提示、Ctrl+Alt+Del 界面等）
+    ///
+    /// 钩子在安全桌面上收不到任何按键，写入标记让日志中的空白有据可查。
+    pub fn enter_secure_desktop(&mut self) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        self.write_marker(i18n::marker("marker.secure_desktop"))
+    }
+
+    /// 离开安全桌面，之后的输入会从新的时间戳行开始
+    pub fn leave_secure_desktop(&mut self) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        self.write_marker(i18n::marker("marker.secure_desktop_end"))
+    }
+
+    /// 进入或离开远程桌面会话时写入标记（远程会话中键盘记录可能不完整）
+    pub fn write_remote_session_marker(&mut self, remote: bool) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        let key = if remote { "marker.remote_session" } else { "marker.remote_session_end" };
+        self.write_marker(i18n::marker(key))
+    }
+
+    /// 记录因事件通道已满而丢弃的按键数，让日志中的缺口有据可查
+    pub fn write_dropped_events(&mut self, count: u64) -> io::Result<()> {
+        self.write_marker(&i18n::marker_with("marker.dropped_events", count))
+    }
+
+    /// 切换暂停/恢复状态
+    pub fn toggle_pause(&mut self) -> io::Result<bool> {
+        if self.paused {
+            self.resume()?;
+        } else {
+            self.pause()?;
+        }
+        Ok(self.paused)
+    }
+
+    /// 是否处于暂停状态
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 设置暂停状态
+    pub fn set_paused(&mut self, paused: bool) -> io::Result<()> {
+        if paused != self.paused {
+            if paused {
+                self.pause()?;
+            } else {
+                self.resume()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取日志目录路径
+    pub fn get_log_directory(&self) -> &Path {
+        &self.log_dir
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new().expect("无法创建日志目录")
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        // 补写被折叠的连续输入的次数
+        let _ = self.finish_repeat_run();
+        // 退出时仍在暂停中，补写推迟的暂停标记
+        let _ = self.write_pending_pause(true);
+        // 确保文件被正确关闭
+        if let Some(ref mut writer) = self.writer {
+            let _ = writer.flush();
+        }
+        // 等待后台保存的图片写完
+        for writer in self.image_writers.drain(..) {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// 读取前台窗口的标题，没有前台窗口（或不是 Windows）时返回 None
+fn foreground_window_title() -> Option<String> {
+    #[cfg(windows)]
+    {
+        crate::keyboard_win::foreground_window_title()
+    }
+    #[cfg(not(windows))]
+    {
+        None
+    }
+}
+
+/// 窗口标题前后的符号：`[14:03:22] «标题» 输入的内容`
+///
+/// 不用圆括号：行首输入的 `(注释) ` 不会被当成窗口标题。
+const WINDOW_TITLE_OPEN: char = '«';
+const WINDOW_TITLE_CLOSE: char = '»';
+
+/// 把窗口标题整理为单行：换行、制表符等连续的空白合并为一个空格，去掉其他控制字符
+///
+/// 标题中的 `»` 换成 `›`，读取时第一个 `»` 就是标题的结尾。整理后为空时返回 None（不写入空的标题）。
+fn sanitize_window_title(title: &str) -> Option<String> {
+    let line = title
+        .split_whitespace()
+        .map(|word| word.chars()
+            .filter(|c| !c.is_control())
+            .map(|c| if c == WINDOW_TITLE_CLOSE { '›' } else { c })
+            .collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!line.is_empty()).then_some(line)
+}
+
+/// JSON 行格式中的一条事件
+#[derive(Serialize)]
+struct JsonEvent<'a> {
+    /// 事件时间（RFC 3339，带时区偏移）
+    ts: String,
+    /// 事件类型：`char`、`enter`、`ctrl_enter`、`paste`、`manual_save`、`entry`、`marker`
+    kind: &'a str,
+    /// 其他带标记条目的标记（例如 `复制`），只在 `entry` 中出现
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+    text: &'a str,
+}
+
+/// 把一条事件编码为一行 JSON（不含换行符）：
+/// `{"ts":"2024-06-01T14:00:00+08:00","kind":"char","text":"abc"}`
+fn json_event_line(time: DateTime<FixedOffset>, kind: &str, tag: Option<&str>, text: &str) -> String {
+    let event = JsonEvent { ts: time.to_rfc3339_opts(SecondsFormat::Secs, false), kind, tag, text };
+    serde_json::to_string(&event).expect("事件只包含字符串字段，编码不会失败")
+}
+
+/// 拆分行首的序号
+///
+/// 对 `#0001 [HH:MM:SS] 内容` 格式的行返回 `(1, "[HH:MM:SS] 内容")`，没有序号的行返回 None。
+pub fn split_sequence(line: &str) -> Option<(u64, &str)> {
+    let (number, rest) = line.strip_prefix('#')?.split_once(' ')?;
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) || !rest.starts_with('[') {
+        return None;
+    }
+    Some((number.parse().ok()?, rest))
+}
+
+/// 去掉所有行首的序号（阅读时不需要序号的场合）
+pub fn strip_sequences(content: &str) -> String {
+    content.split_inclusive('\n')
+        .map(|line| split_sequence(line).map_or(line, |(_, rest)| rest))
+        .collect()
+}
+
+/// 日志中最后一个序号
+fn last_sequence(content: &str) -> Option<u64> {
+    content.lines().rev().find_map(|line| split_sequence(line).map(|(n, _)| n))
+}
+
+/// 时间戳后的内容是否为状态标记（`--- 暂停记录 ---`）或分隔标记（`===== 专注开始 =====`）
+pub fn is_marker_text(text: &str) -> bool {
+    text.starts_with("---") || text.starts_with("=====")
+}
+
+/// 原始按键记录的记号：按下为 `[↓LShift]`，松开为 `[↑LShift]`
+pub fn raw_key_marker(name: &str, down: bool) -> String {
+    format!("[{}{}]", if down { '↓' } else { '↑' }, name)
+}
+
+/// 文本开头是原始按键记录的记号时返回记号的字节长度
+fn raw_key_marker_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix("[↓").or_else(|| text.strip_prefix("[↑"))?;
+    let name_len = rest.find(']')?;
+    let name = &rest[..name_len];
+    let valid = !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+    valid.then(|| text.len() - rest.len() + name_len + 1)
+}
+
+/// 去掉文本中原始按键记录的记号（统计字数时记号不算输入的文字）
+pub fn strip_raw_key_markers(text: &str) -> Cow<'_, str> {
+    if !text.contains("[↓") && !text.contains("[↑") {
+        return Cow::Borrowed(text);
+    }
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('[') {
+        stripped.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        match raw_key_marker_len(rest) {
+            Some(len) => rest = &rest[len..],
+            None => {
+                stripped.push('[');
+                rest = &rest[1..];
+            }
+        }
+    }
+    stripped.push_str(rest);
+    Cow::Owned(stripped)
+}
+
+/// 拆分行首的时间戳
+///
+/// 对 `[HH:MM:SS] 内容` 格式的行返回 `("HH:MM:SS", "内容")`，
+/// 其他行（文件头、续行等）返回 None。行首有序号时忽略序号。
+pub fn split_timestamp(line: &str) -> Option<(&str, &str)> {
+    let line = split_sequence(line).map_or(line, |(_, rest)| rest);
+    let bytes = line.as_bytes();
+    if bytes.len() < 10 || bytes[0] != b'[' || bytes[9] != b']' {
+        return None;
+    }
+    
+    let time = &line[1..9];
+    let well_formed = time.bytes().enumerate().all(|(i, b)| {
+        if i == 2 || i == 5 { b == b':' } else { b.is_ascii_digit() }
+    });
+    if !well_formed {
+        return None;
+    }
+    
+    let rest = &line[10..];
+    Some((time, rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+/// 拆分时间戳后开头的窗口标题（见 [`Logger::set_window_tracking`]），返回 (标题, 其后的内容)
+///
+/// 没有窗口标题时返回 (None, 原内容)。
+pub fn split_window_title(text: &str) -> (Option<&str>, &str) {
+    text.strip_prefix(WINDOW_TITLE_OPEN)
+        .and_then(|rest| rest.split_once(WINDOW_TITLE_CLOSE))
+        .map_or((None, text), |(title, rest)| (Some(title), rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+/// 带时间戳的行：(行号, 时间)，按行号顺序
+pub fn timestamp_positions(content: &str) -> Vec<(usize, NaiveTime)> {
+    content.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let (time, _) = split_timestamp(line)?;
+            NaiveTime::parse_from_str(time, "%H:%M:%S").ok().map(|t| (i, t))
+        })
+        .collect()
+}
+
+/// 跳转到某个时刻时应显示的行号
+///
+/// 有时间戳恰好等于 `target` 的行时跳到该行；否则跳到 `target` 之前最近的
+/// 时间戳行（时间戳稀疏时，这一行的内容覆盖了 `target`）；`target` 早于所有时间戳时
+/// 跳到第一个时间戳行。没有时间戳行时返回 None。
+pub fn seek_line(content: &str, target: NaiveTime) -> Option<usize> {
+    let positions = timestamp_positions(content);
+    let time = match positions.iter().position(|&(_, t)| t >= target) {
+        Some(i) if positions[i].1 == target => target,
+        Some(0) => positions[0].1,
+        Some(i) => positions[i - 1].1,
+        None => positions.last()?.1,
+    };
+    // 同一秒可能有多行，跳到其中第一行
+    positions.iter().find(|&&(_, t)| t == time).map(|&(line, _)| line)
+}
+
+/// 合并相邻的短时间戳行（仅用于阅读，不修改磁盘文件）
+///
+/// 空闲超时频繁触发时，日志里会出现很多几乎为空的时间戳行。
+/// 连续多条内容少于 `min_chars` 个字符的时间戳行会合并为一行，
+/// 时间戳显示为起止范围，例如 `[14:30:05 - 14:31:20] 内容A 内容B`。
+/// 标记行（粘贴、暂停等）和带续行的段落保持原样。
+pub fn collapse_timestamps(content: &str, min_chars: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output = String::with_capacity(content.len());
+    // 待合并的短行：(时间, 内容, 原始行)
+    let mut group: Vec<(&str, &str, &str)> = Vec::new();
+    
+    fn flush_group(group: &mut Vec<(&str, &str, &str)>, output: &mut String) {
+        match group.len() {
+            0 => {}
+            1 => {
+                output.push_str(group[0].2);
+                output.push('\n');
+            }
+            _ => {
+                let first = group[0].0;
+                let last = group[group.len() - 1].0;
+                let texts: Vec<&str> = group.iter()
+                    .map(|(_, text, _)| text.trim())
+                    .filter(|text| !text.is_empty())
+                    .collect();
+                output.push_str(&format!("[{} - {}] {}\n", first, last, texts.join(" ")));
+            }
+        }
+        group.clear();
+    }
+    
+    for (i, line) in lines.iter().enumerate() {
+        // 下一行是 Ctrl+Enter 的续行时，当前段落不参与合并
+        let has_continuation = lines.get(i + 1)
+            .is_some_and(|next| next.starts_with("          "));
+        
+        let short_entry = split_timestamp(line).filter(|(_, text)| {
+            let is_marker = text.starts_with('[') || is_marker_text(text);
+            !is_marker && !has_continuation && text.trim().chars().count() < min_chars
+        });
+        
+        match short_entry {
+            Some((time, text)) => group.push((time, text, line)),
+            None => {
+                flush_group(&mut group, &mut output);
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+    flush_group(&mut group, &mut output);
+    
+    output
+}
+
+/// 日志对比中参与比较的最大行数（每一侧），超出部分截断
+pub const MAX_DIFF_LINES: usize = 5000;
+
+/// 日志对比结果中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// 两天都有的行
+    Unchanged(String),
+    /// 只在较新一天出现的行
+    Added(String),
+    /// 只在较早一天出现的行
+    Removed(String),
+    /// 文件过大，超出部分未参与比较
+    Truncated { skipped_lines: usize },
+}
+
+/// 列出日志目录中所有有日志的日期（从新到旧）
+pub fn list_log_dates() -> Vec<NaiveDate> {
+    let mut dates: Vec<NaiveDate> = fs::read_dir(config::get_log_directory())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|name| !is_json_lines_log(name))
+                .filter_map(|name| parse_log_file_date(&name))
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    dates.sort_unstable_by(|a, b| b.cmp(a));
+    dates.dedup();
+    dates
+}
+
+/// 模糊匹配日志文件名（用于历史页面的文件筛选）
+///
+/// 忽略大小写和分隔符（`-`、`_`、`/`、`.`、空格），筛选词中的字符
+/// 只需按顺序出现在文件名中，例如 `0105`、`1-5`、`2024 1 5`
+/// 都能匹配 `2024-01-05.log`。
+pub fn fuzzy_match(query: &str, file_name: &str) -> bool {
+    let is_separator = |c: &char| matches!(c, '-' | '_' | '/' | '.' | ' ');
+    let mut candidate = file_name.chars()
+        .filter(|c| !is_separator(c))
+        .flat_map(char::to_lowercase);
+    query.chars()
+        .filter(|c| !is_separator(c))
+        .flat_map(char::to_lowercase)
+        .all(|q| candidate.any(|c| c == q))
+}
+
+/// 搜索结果中最多显示的行数（所有文件合计），匹配总数不受限制
+pub const MAX_SEARCH_LINES: usize = 500;
+
+/// 一个日志文件中的搜索结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// 日志文件路径
+    pub path: PathBuf,
+    /// 包含匹配的行（超过 [`MAX_SEARCH_LINES`] 的部分不保留）
+    pub lines: Vec<String>,
+    /// 文件中的匹配总数
+    pub matches: usize,
+}
+
+/// 查找文本中与搜索词匹配的位置（忽略大小写），返回不重叠的字节范围
+pub fn find_matches(text: &str, query: &str) -> Vec<std::ops::Range<usize>> {
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    let mut next = 0;
+    for (start, _) in text.char_indices() {
+        if start < next {
+            continue;
+        }
+        if let Some(len) = match_len(&text[start..], &needle) {
+            matches.push(start..start + len);
+            next = start + len;
+        }
+    }
+    matches
+}
+
+/// 文本开头与（已转为小写的）搜索词匹配时返回匹配部分的字节长度
+fn match_len(text: &str, needle: &[char]) -> Option<usize> {
+    let mut pending = needle;
+    for (i, c) in text.char_indices() {
+        for lower in c.to_lowercase() {
+            match pending.split_first() {
+                Some((first, rest)) if *first == lower => pending = rest,
+                _ => return None,
+            }
+        }
+        if pending.is_empty() {
+            return Some(i + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// 在日志目录的所有日志文件（含已压缩的和 JSON 行格式的）中搜索，按文件名从新到旧排列
+///
+/// 只返回有匹配的文件；所有文件合计最多保留 [`MAX_SEARCH_LINES`] 行。
+pub fn search_logs(dir: &Path, query: &str) -> Vec<SearchResult> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|path| {
+                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    LOG_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+    
+    let mut results = Vec::new();
+    let mut kept_lines = 0;
+    for path in paths {
+        let Ok(content) = read_log_file(&path) else {
+            continue;
+        };
+        let mut result = SearchResult { path, lines: Vec::new(), matches: 0 };
+        for line in content.lines() {
+            let count = find_matches(line, query).len();
+            if count == 0 {
+                continue;
+            }
+            result.matches += count;
+            if kept_lines < MAX_SEARCH_LINES {
+                result.lines.push(line.to_string());
+                kept_lines += 1;
+            }
+        }
+        if result.matches > 0 {
+            results.push(result);
+        }
+    }
+    results
+}
+
+/// 从日志文件名（`2024-01-05.log`、`2024-01-05_02.log` 或 `2024-01-05_chrome.log`）中解析日期
+pub fn parse_log_file_date(file_name: &str) -> Option<NaiveDate> {
+    parse_log_file_name(file_name).map(|(date, _)| date)
+}
+
+/// 日志文件的扩展名（纯文本、JSON 行，以及压缩后的文件）
+const LOG_EXTENSIONS: [&str; 4] = [".log", ".log.gz", ".jsonl", ".jsonl.gz"];
+
+/// 从日志文件名中解析程序名（按程序分文件时写入的文件），默认文件返回 None
+pub fn parse_log_file_app(file_name: &str) -> Option<&str> {
+    parse_log_file_name(file_name)?.1
+}
+
+/// 解析日志文件名：`日期[_程序名][_段号].log`（压缩后为 `.log.gz`），返回日期和程序名
+///
+/// JSON 行格式的 `.jsonl`（压缩后为 `.jsonl.gz`）同样识别，保留天数和压缩一并处理。
+fn parse_log_file_name(file_name: &str) -> Option<(NaiveDate, Option<&str>)> {
+    let stem = LOG_EXTENSIONS.iter()
+        .find_map(|ext| file_name.strip_suffix(ext))?;
+    let date = NaiveDate::parse_from_str(stem.get(..10)?, "%Y-%m-%d").ok()?;
+    let suffix = &stem[10..];
+    if suffix.is_empty() {
+        return Some((date, None));
+    }
+    
+    // 段号是 `_` 加数字；程序名不会全是数字（见 `app_file_label`）
+    let is_segment = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let is_app = |part: &str| app_file_label(part).as_deref() == Some(part);
+    let mut parts = suffix.strip_prefix('_')?.split('_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(segment), None, None) if is_segment(segment) => Some((date, None)),
+        (Some(app), None, None) if is_app(app) => Some((date, Some(app))),
+        (Some(app), Some(segment), None) if is_app(app) && is_segment(segment) => Some((date, Some(app))),
+        _ => None,
+    }
+}
+
+/// 程序名在日志文件名中的形式：小写，字母数字以外的字符替换为 `-`
+///
+/// 去掉首尾的 `-` 后为空，或全是数字（会与段号混淆）时返回 None，写入默认文件。
+pub fn app_file_label(name: &str) -> Option<String> {
+    let label: String = name.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() || label.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(label.to_string())
+}
+
+/// 列出某一天的所有日志文件（按段号顺序）
+pub fn day_log_files(date: NaiveDate) -> Vec<PathBuf> {
+    let prefix = date.format("%Y-%m-%d").to_string();
+    
+    let mut files: Vec<PathBuf> = fs::read_dir(config::get_log_directory())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    name.starts_with(&prefix) && parse_log_file_date(&name) == Some(date)
+                        && !is_json_lines_log(&name)
+                })
+                .map(|e| e.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    // 文件名中的段号是两位补零，直接按文件名排序即为段顺序
+    files.sort();
+    files
+}
+
+/// 把日志文件移到回收站，系统不支持回收站时永久删除
+fn trash_log_file(path: &Path) -> io::Result<()> {
+    if trash::delete(path).is_ok() {
+        return Ok(());
+    }
+    fs::remove_file(path)
+}
+
+/// 是否是 JSON 行格式的日志（`.jsonl` 或 `.jsonl.gz`），历史和统计只读取纯文本日志
+fn is_json_lines_log(file_name: &str) -> bool {
+    file_name.ends_with(".jsonl") || file_name.ends_with(".jsonl.gz")
+}
+
+/// 日志文件是否已压缩（`.log.gz`）
+pub fn is_compressed_log(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// 读取日志文件的内容，已压缩的日志先解压
+pub fn read_log_file(path: &Path) -> io::Result<String> {
+    if !is_compressed_log(path) {
+        return fs::read_to_string(path);
+    }
+    let mut content = String::new();
+    GzDecoder::new(File::open(path)?).read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// 把日志文件压缩为同名的 `.log.gz`，完成后删除原文件
+///
+/// 先写入临时文件再改名，压缩中断时不会留下不完整的 `.log.gz`，压缩失败时删除临时文件。
+/// 已存在同名的压缩文件时不覆盖，返回 false。
+fn compress_log_file(path: &Path) -> io::Result<bool> {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+        return Ok(false);
+    };
+    let target = path.with_file_name(format!("{}.gz", name));
+    if target.exists() {
+        return Ok(false);
+    }
+    let temp = path.with_file_name(format!("{}.gz.tmp", name));
+    let written = File::create(&temp).and_then(|file| {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        io::copy(&mut File::open(path)?, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+        fs::rename(&temp, &target)
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    fs::remove_file(path)?;
+    Ok(true)
+}
+
+/// 某一天最新的日志段（正在写入的文件）
+///
+/// 按程序分文件时同一天有多个程序的文件，取最近写入的那个。
+/// 已压缩的日志不会再写入，不在候选之列。
+pub fn latest_log_path(date: NaiveDate) -> Option<PathBuf> {
+    day_log_files(date).into_iter()
+        .filter(|path| !is_compressed_log(path))
+        .max_by_key(|path| (fs::metadata(path).and_then(|m| m.modified()).ok(), path.clone()))
+}
+
+/// 读取某一天的全部日志
+///
+/// 各日志段（以及按程序分的文件）按条目的时间合并，见 [`merge_by_time`]。
+/// 这一天没有任何日志文件时返回 None
+pub fn read_day(date: NaiveDate) -> Option<String> {
+    let files = day_log_files(date);
+    if files.is_empty() {
+        return None;
+    }
+    
+    let contents: Vec<String> = files.iter()
+        .filter_map(|path| read_log_file(path).ok())
+        .collect();
+    Some(merge_by_time(&contents))
+}
+
+/// 把同一天的多个日志文件按时间合并为一份
+///
+/// 每个文件内部保持原有顺序，文件之间按时间戳交错：按程序分文件时，
+/// 在各程序之间来回切换的输入按发生的先后排列。文件头部和没有时间戳的续行
+/// 跟随其后的（头部）或之前的（续行）时间戳行；时间相同时靠前的文件优先。
+fn merge_by_time(contents: &[String]) -> String {
+    let mut queues: Vec<VecDeque<(&str, &str)>> = contents.iter()
+        .map(|content| time_blocks(content))
+        .collect();
+    let mut merged = String::with_capacity(contents.iter().map(String::len).sum());
+    while let Some(queue) = queues.iter_mut()
+        .filter(|queue| !queue.is_empty())
+        .min_by_key(|queue| queue[0].0)
+    {
+        if let Some((_, block)) = queue.pop_front() {
+            merged.push_str(block);
+        }
+    }
+    merged
+}
+
+/// 把日志内容拆分为以时间戳行开头的块：(时间, 内容)
+///
+/// 第一个时间戳行之前的内容（文件头部）并入第一块；没有时间戳行时整个内容为一块，时间为空。
+fn time_blocks(content: &str) -> VecDeque<(&str, &str)> {
+    let mut blocks = VecDeque::new();
+    let mut start = 0;
+    let mut time = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if let Some((line_time, _)) = split_timestamp(line) {
+            // 第一个时间戳行之前的头部并入第一块
+            if let Some(time) = time {
+                blocks.push_back((time, &content[start..offset]));
+                start = offset;
+            }
+            time = Some(line_time);
+        }
+        offset += line.len();
+    }
+    if start < content.len() {
+        blocks.push_back((time.unwrap_or(""), &content[start..]));
+    }
+    blocks
+}
+
+/// 要删除的日志：某一天的全部日志段，或单个日志文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTarget {
+    Date(NaiveDate),
+    Path(PathBuf),
+}
+
+impl From<NaiveDate> for LogTarget {
+    fn from(date: NaiveDate) -> Self {
+        LogTarget::Date(date)
+    }
+}
+
+impl From<PathBuf> for LogTarget {
+    fn from(path: PathBuf) -> Self {
+        LogTarget::Path(path)
+    }
+}
+
+/// 日志文件是否可能正在写入：文件名中的日期是今天（包括按程序分的文件和各个日志段）
+///
+/// 日志写入器一直打开着今天的文件，删除或改名会让之后的输入写到已不存在的文件中。
+pub fn is_active_log(path: &Path, today: NaiveDate) -> bool {
+    path.file_name()
+        .and_then(|name| parse_log_file_date(&name.to_string_lossy()))
+        .is_some_and(|date| date == today)
+}
+
+/// 今天的日期（使用日志配置的时区）
+fn log_today() -> NaiveDate {
+    config::current().log_timezone().now().date_naive()
+}
+
+/// 删除日志文件
+///
+/// 优先移到回收站，系统不支持回收站时才永久删除。
+/// 返回 true 表示已移到回收站，false 表示已永久删除。
+/// 今天的日志正在写入，不能删除。
+pub fn delete_log(target: impl Into<LogTarget>) -> io::Result<bool> {
+    let paths = match target.into() {
+        LogTarget::Date(date) => day_log_files(date),
+        LogTarget::Path(path) => vec![path],
+    };
+    if paths.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "没有找到日志文件"));
+    }
+    let today = log_today();
+    if paths.iter().any(|path| is_active_log(path, today)) {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "今天的日志正在写入，不能删除"));
+    }
+    
+    if trash::delete_all(&paths).is_ok() {
+        return Ok(true);
+    }
+    for path in &paths {
+        fs::remove_file(path)?;
+    }
+    Ok(false)
+}
+
+/// 重命名日志文件（只改文件名，保留在原目录中）
+///
+/// 新文件名沿用原文件的扩展名（`.log`、`.jsonl`，压缩的日志为 `.log.gz`、`.jsonl.gz`），
+/// 输入的文件名带有日志扩展名时先去掉；目标文件已存在时返回错误而不是覆盖。
+/// 今天的日志正在写入，不能改名，其他日志也不能改成今天的文件名。
+pub fn rename_log(path: &Path, new_name: &str) -> io::Result<PathBuf> {
+    rename_log_on(path, new_name, log_today())
+}
+
+fn rename_log_on(path: &Path, new_name: &str, today: NaiveDate) -> io::Result<PathBuf> {
+    let new_name = new_name.trim();
+    let new_name = LOG_EXTENSIONS.iter()
+        .find_map(|ext| new_name.strip_suffix(ext))
+        .unwrap_or(new_name);
+    if new_name.is_empty() || new_name.contains(['/', '\\']) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "文件名无效"));
+    }
+    if is_active_log(path, today) {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "今天的日志正在写入，不能改名"));
+    }
+    
+    let old_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = LOG_EXTENSIONS.iter()
+        .find(|ext| old_name.ends_with(**ext))
+        .copied()
+        .unwrap_or(".log");
+    let new_path = path.with_file_name(format!("{}{}", new_name, extension));
+    if is_active_log(&new_path, today) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "不能改成今天的日志文件名"));
+    }
+    if new_path.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "同名文件已存在"));
+    }
+    
+    fs::rename(path, &new_path)?;
+    Ok(new_path)
+}
+
+/// 超过 `max_chars` 个字符的粘贴内容只保留前 `max_chars` 个字符，末尾注明总字数
+fn truncate_paste(content: &str, max_chars: usize) -> Cow<'_, str> {
+    match content.char_indices().nth(max_chars) {
+        Some((end, _)) => Cow::Owned(format!(
+            "{}{}",
+            &content[..end],
+            i18n::marker_with("marker.paste_truncated", content.chars().count()),
+        )),
+        None => Cow::Borrowed(content),
+    }
+}
+
+/// 把内容中的换行统一为 `\n`，后续行缩进到时间戳之后
+///
+/// 多行粘贴的后续行以 [`CONTINUATION_INDENT`] 开头，与 Ctrl+Enter 的续行一样，
+/// 不会被当成新的时间戳行（后续行本身以 `[14:30:05]` 开头时也是如此）。
+fn indent_continuation_lines(content: &str) -> Cow<'_, str> {
+    if !content.contains(['\n', '\r']) {
+        return Cow::Borrowed(content);
+    }
+    let normalized = content.replace("\r\n", "\n").replace('\r', "\n");
+    Cow::Owned(normalized.replace('\n', &format!("\n{}", CONTINUATION_INDENT)))
+}
+
+/// 去掉文本中的时间戳和续行缩进，只保留输入内容
+///
+/// 用于从日志中摘录片段：选中的文字跨越多个时间戳时，摘录中不包含时间戳。
+pub fn strip_timestamps(text: &str) -> String {
+    text.lines()
+        .map(|line| match split_timestamp(line) {
+            Some((_, rest)) => split_window_title(rest).1,
+            None => line.strip_prefix(CONTINUATION_INDENT).unwrap_or(line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// 提取用于对比的正文行：去掉文件头、时间戳和状态标记，只保留输入内容
+fn content_lines(content: &str) -> Vec<&str> {
+    content.lines()
+        .filter_map(|line| {
+            let text = match split_timestamp(line) {
+                Some((_, text)) => split_window_title(text).1,
+                None => line.trim_start(),
+            };
+            let is_marker = is_marker_text(text);
+            (!is_header_line(line) && !is_marker && !text.trim().is_empty()).then_some(text)
+        })
+        .collect()
+}
+
+/// 表示文件句柄已失效的系统错误码，重新打开文件即可恢复
+#[cfg(windows)]
+const STALE_HANDLE_ERRORS: &[i32] = &[
+    6,  // ERROR_INVALID_HANDLE
+    59, // ERROR_UNEXP_NET_ERR
+    64, // ERROR_NETNAME_DELETED
+];
+#[cfg(target_os = "macos")]
+const STALE_HANDLE_ERRORS: &[i32] = &[9 /* EBADF */, 70 /* ESTALE */];
+#[cfg(not(any(windows, target_os = "macos")))]
+const STALE_HANDLE_ERRORS: &[i32] = &[9 /* EBADF */, 116 /* ESTALE */];
+
+/// 写入错误是否由文件句柄失效引起
+fn is_stale_handle(error: &io::Error) -> bool {
+    error.raw_os_error().is_some_and(|code| STALE_HANDLE_ERRORS.contains(&code))
+}
+
+/// 是否为文件头部的行（分隔线、日期、创建时间、设备名，以及自定义头部的行）
+pub fn is_header_line(line: &str) -> bool {
+    line.starts_with("=====")
+        || line.starts_with(CUSTOM_HEADER_MARK)
+        || HEADER_FIELDS.iter().any(|field| line.starts_with(field))
+}
+
+/// 自定义头部每行开头的标记
+const CUSTOM_HEADER_MARK: &str = "#|";
+
+/// 默认头部的第一行
+const HEADER_TITLE: &str = "================== EchoKey 日志 ==================";
+
+/// 默认头部的最后一行
+const HEADER_RULE: &str = "==================================================";
+
+/// 默认头部中各行的开头
+const HEADER_FIELDS: [&str; 6] = ["日期：", "创建时间：", "设备：", "程序：", "续接：", "恢复："];
+
+/// 文件开头是不完整的默认头部时，返回这段不完整头部的字节长度
+///
+/// 完整的头部以标题行开始、以分隔线结束，中间只有头部字段行。
+/// 不以 `=` 开头的文件（自定义头部或不写头部）不检查。
+/// 不完整的头部包括其后的空行，以及写到一半的标题、分隔线或字段行。
+pub fn truncated_header_len(content: &str) -> Option<usize> {
+    if !content.starts_with('=') {
+        return None;
+    }
+    
+    let mut lines = content.lines();
+    if lines.next() == Some(HEADER_TITLE) {
+        for line in lines {
+            if line == HEADER_RULE {
+                return None;
+            }
+            if !HEADER_FIELDS.iter().any(|field| line.starts_with(field)) {
+                break;
+            }
+        }
+    }
+    
+    let partial = |line: &str| {
+        line.is_empty()
+            || is_header_line(line)
+            || HEADER_TITLE.starts_with(line)
+            || HEADER_FIELDS.iter().any(|field| field.starts_with(line))
+    };
+    let len = content.split_inclusive('\n')
+        .take_while(|line| partial(line.trim_end_matches(['\r', '\n'])))
+        .map(str::len)
+        .sum();
+    Some(len)
+}
+
+/// 文件头部模板中可用的占位符
+pub const HEADER_TOKENS: &[&str] = &["{date}", "{time}", "{version}", "{device}"];
+
+/// 文件头部模板不合法的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderTemplateError {
+    /// 包含换行、制表符以外的控制字符
+    ControlCharacter,
+    /// 未知的占位符
+    UnknownToken(String),
+}
+
+impl std::fmt::Display for HeaderTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ControlCharacter => write!(f, "头部模板不能包含控制字符"),
+            Self::UnknownToken(token) => write!(f, "头部模板中有未知的占位符：{}", token),
+        }
+    }
+}
+
+/// 检查文件头部模板：花括号只能用于已知的占位符，不能包含换行和制表符以外的控制字符
+pub fn validate_header_template(template: &str) -> Result<(), HeaderTemplateError> {
+    if !is_valid_header_text(template) {
+        return Err(HeaderTemplateError::ControlCharacter);
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        let tail = &rest[start..];
+        let Some(token) = HEADER_TOKENS.iter().find(|t| tail.starts_with(**t)) else {
+            let end = tail.find('}').map_or(tail.len(), |i| i + 1);
+            return Err(HeaderTemplateError::UnknownToken(tail[..end].to_string()));
+        };
+        rest = &tail[token.len()..];
+    }
+    Ok(())
+}
+
+/// 展开文件头部模板中的占位符（没有设备名称时 `{device}` 展开为空）
+pub fn expand_header_template(template: &str, now: DateTime<FixedOffset>, device: Option<&str>) -> String {
+    template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H:%M:%S").to_string())
+        .replace("{version}", config::APP_VERSION)
+        .replace("{device}", device.unwrap_or(""))
+}
+
+/// 头部文本是否只包含可显示的字符（允许换行和制表符）
+fn is_valid_header_text(text: &str) -> bool {
+    !text.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+}
+
+/// 把一天的日志转换为 Markdown
+///
+/// 每个时间戳行成为一个列表项，时间加粗；续行缩进到列表项下；
+/// 状态标记（暂停、粘贴等）用斜体；文件头部替换为一级标题。
+pub fn to_markdown(date: NaiveDate, content: &str) -> String {
+    let mut output = format!("# EchoKey {}\n\n", date.format("%Y-%m-%d"));
+    for line in content.lines() {
+        if is_header_line(line) || line.trim().is_empty() {
+            continue;
+        }
+        match split_timestamp(line) {
+            Some((time, text)) => {
+                let marker = text.strip_prefix("--- ").and_then(|t| t.strip_suffix(" ---"))
+                    .or_else(|| text.strip_prefix("===== ").and_then(|t| t.strip_suffix(" =====")));
+                let text = match marker {
+                    Some(marker) => format!("*{}*", marker),
+                    None => text.to_string(),
+                };
+                output.push_str(&format!("- **{}** {}\n", time, text));
+            }
+            None => output.push_str(&format!("  {}\n", line.trim_start())),
+        }
+    }
+    output
+}
+
+/// 把某一天的日志导出为 Markdown 文件（`%LOCALAPPDATA%\EchoKey\exports\日期.md`）
+///
+/// 返回导出文件的路径，这一天没有日志时返回 NotFound。
+pub fn export_markdown(date: NaiveDate) -> io::Result<PathBuf> {
+    let content = read_day(date)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "这一天没有日志"))?;
+    let dir = config::get_data_directory().join("exports");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.md", date.format("%Y-%m-%d")));
+    fs::write(&path, to_markdown(date, &content))?;
+    Ok(path)
+}
+
+/// PDF 页面尺寸（A4，毫米）
+const PDF_PAGE_WIDTH: f32 = 210.0;
+const PDF_PAGE_HEIGHT: f32 = 297.0;
+/// PDF 页边距（毫米）
+const PDF_MARGIN: f32 = 20.0;
+/// 左侧时间戳栏的宽度（毫米）
+const PDF_TIME_COLUMN: f32 = 22.0;
+/// PDF 正文字号（磅）
+const PDF_FONT_SIZE: f32 = 10.0;
+/// PDF 行高（毫米）
+const PDF_LINE_HEIGHT: f32 = 5.5;
+/// 正文栏每行的宽度（半角字符为 1，全角字符为 2；10 磅半角字符约 1.8 毫米）
+const PDF_LINE_UNITS: usize = 80;
+/// 每页的行数（去掉页眉和页码的位置）
+const PDF_LINES_PER_PAGE: usize = 44;
+
+/// PDF 中的一行：左栏的时间戳（只在条目的第一行显示）和正文
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PdfLine {
+    time: Option<String>,
+    text: String,
+}
+
+/// 把某一天的日志导出为 PDF 文件，返回页数
+///
+/// A4 纸张，时间戳放在左栏，长行自动折行，长日志自动分页。
+/// 嵌入设置中的自定义字体（否则依次尝试系统中文字体），在没有这些字体的电脑上也能正确显示中文。
+pub fn export_pdf(date: NaiveDate, out_path: &Path) -> io::Result<usize> {
+    let content = read_day(date)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "这一天没有日志"))?;
+    let pages = layout_pdf_pages(&content, PDF_LINE_UNITS, PDF_LINES_PER_PAGE);
+    let title = format!("EchoKey {}", date.format("%Y-%m-%d"));
+    let (doc, first_page, first_layer) = printpdf::PdfDocument::new(
+        &title, printpdf::Mm(PDF_PAGE_WIDTH), printpdf::Mm(PDF_PAGE_HEIGHT), "text");
+    // 读不出来或无法嵌入的字体（例如不支持的字体集合）跳过，继续尝试下一个
+    let font = config::current().font_path.into_iter()
+        .chain(config::SYSTEM_CJK_FONTS.iter().map(PathBuf::from))
+        .find_map(|path| {
+            let bytes = fs::read(&path).ok()?;
+            doc.add_external_font(bytes.as_slice())
+                .map_err(|e| eprintln!("警告: 无法嵌入字体 {}: {}", path.display(), e))
+                .ok()
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "找不到可嵌入的中文字体，请在设置中指定字体文件"))?;
+    let black = printpdf::Color::Greyscale(printpdf::Greyscale::new(0.0, None));
+    let grey = printpdf::Color::Greyscale(printpdf::Greyscale::new(0.5, None));
+
+    for (index, lines) in pages.iter().enumerate() {
+        let (page, layer) = if index == 0 {
+            (first_page, first_layer)
+        } else {
+            doc.add_page(printpdf::Mm(PDF_PAGE_WIDTH), printpdf::Mm(PDF_PAGE_HEIGHT), "text")
+        };
+        let layer = doc.get_page(page).get_layer(layer);
+        let mm = printpdf::Mm;
+
+        // 页眉和页码
+        layer.set_fill_color(grey.clone());
+        layer.use_text(&title, 9.0, mm(PDF_MARGIN), mm(PDF_PAGE_HEIGHT - PDF_MARGIN / 2.0), &font);
+        layer.use_text(format!("{} / {}", index + 1, pages.len()), 9.0,
+            mm(PDF_PAGE_WIDTH / 2.0 - 5.0), mm(PDF_MARGIN / 2.0), &font);
+
+        let mut y = PDF_PAGE_HEIGHT - PDF_MARGIN;
+        for line in lines {
+            if let Some(time) = &line.time {
+                layer.set_fill_color(grey.clone());
+                layer.use_text(time, PDF_FONT_SIZE, mm(PDF_MARGIN), mm(y), &font);
+            }
+            layer.set_fill_color(black.clone());
+            layer.use_text(&line.text, PDF_FONT_SIZE, mm(PDF_MARGIN + PDF_TIME_COLUMN), mm(y), &font);
+            y -= PDF_LINE_HEIGHT;
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(out_path)?);
+    doc.save(&mut writer)
+        .map_err(|e| io::Error::other(format!("无法写入 PDF: {}", e)))?;
+    Ok(pages.len())
+}
+
+/// 把一天的日志排成 PDF 页面：去掉头部，时间戳移到左栏，长行按宽度折行
+///
+/// 至少返回一页（没有内容时为空白页）。
+fn layout_pdf_pages(content: &str, line_units: usize, lines_per_page: usize) -> Vec<Vec<PdfLine>> {
+    let mut lines = Vec::new();
+    for line in content.lines() {
+        if is_header_line(line) {
+            continue;
+        }
+        let (time, text) = match split_timestamp(line) {
+            Some((time, text)) => (Some(time.to_string()), text),
+            // Ctrl+Enter 续行的缩进由左栏代替
+            None => (None, line.strip_prefix("          ").unwrap_or(line)),
+        };
+        for (i, part) in wrap_by_width(text, line_units).into_iter().enumerate() {
+            lines.push(PdfLine {
+                time: if i == 0 { time.clone() } else { None },
+                text: part.to_string(),
+            });
+        }
+    }
+
+    let mut pages: Vec<Vec<PdfLine>> = lines.chunks(lines_per_page.max(1)).map(<[_]>::to_vec).collect();
+    if pages.is_empty() {
+        pages.push(Vec::new());
+    }
+    pages
+}
+
+/// 按显示宽度折行（半角字符宽 1，其他字符宽 2），至少返回一段
+fn wrap_by_width(text: &str, units: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut width = 0;
+    for (i, c) in text.char_indices() {
+        let w = if c.is_ascii() { 1 } else { 2 };
+        if width + w > units && i > start {
+            parts.push(&text[start..i]);
+            start = i;
+            width = 0;
+        }
+        width += w;
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// 是否为检查点行（识别所有语言的检查点标记）
+pub fn is_checkpoint_line(line: &str) -> bool {
+    split_timestamp(line).is_some_and(|(_, text)| {
+        i18n::Locale::ALL.iter().any(|&locale| {
+            let tag = i18n::translate(locale, "marker.checkpoint");
+            text.strip_prefix('[')
+                .and_then(|t| t.strip_prefix(tag))
+                .is_some_and(|t| t.trim_end() == "]")
+        })
+    })
+}
+
+/// 按字节数切分文本，切分点总是落在字符边界上
+fn chunks_at_char_boundaries(text: &str, size: usize) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// 对比两天日志的正文内容（`a` 为较早的一天，`b` 为较新的一天）
+///
+/// 对比前去掉时间戳，只显示内容差异。某一天没有日志时，
+/// 另一天的全部内容视为新增或删除。单侧超过 [`MAX_DIFF_LINES`] 行时截断，
+/// 并在结果末尾附加 [`DiffLine::Truncated`]。
+pub fn diff_days(a: NaiveDate, b: NaiveDate) -> Vec<DiffLine> {
+    let old = read_day(a).unwrap_or_default();
+    let new = read_day(b).unwrap_or_default();
+    diff_contents(&old, &new)
+}
+
+/// 对比两段日志文本的正文内容
+pub fn diff_contents(old: &str, new: &str) -> Vec<DiffLine> {
+    let mut old_lines = content_lines(old);
+    let mut new_lines = content_lines(new);
+    
+    let skipped_lines = old_lines.len().saturating_sub(MAX_DIFF_LINES)
+        + new_lines.len().saturating_sub(MAX_DIFF_LINES);
+    old_lines.truncate(MAX_DIFF_LINES);
+    new_lines.truncate(MAX_DIFF_LINES);
+    
+    let diff = similar::TextDiff::from_slices(&old_lines, &new_lines);
+    let mut result: Vec<DiffLine> = diff.iter_all_changes()
+        .map(|change| {
+            let text = change.value().to_string();
+            match change.tag() {
+                similar::ChangeTag::Equal => DiffLine::Unchanged(text),
+                similar::ChangeTag::Insert => DiffLine::Added(text),
+                similar::ChangeTag::Delete => DiffLine::Removed(text),
+            }
+        })
+        .collect();
+    
+    if skipped_lines > 0 {
+        result.push(DiffLine::Truncated { skipped_lines });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_raw_key_markers() {
+        assert_eq!(raw_key_marker("LShift", true), "[↓LShift]");
+        assert_eq!(strip_raw_key_markers("[↓LShift][↓A]A[↑A][↑LShift]b"), "Ab");
+        assert_eq!(strip_raw_key_markers("[↓VK_A0]"), "");
+        // 不是记号的方括号原样保留
+        assert_eq!(strip_raw_key_markers("[TODO] [↓] [↑a b]"), "[TODO] [↓] [↑a b]");
+        assert!(matches!(strip_raw_key_markers("普通文字"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_split_timestamp() {
+        assert_eq!(split_timestamp("[14:30:05] Hello"), Some(("14:30:05", "Hello")));
+        assert_eq!(split_timestamp("[14:30:05] [粘贴] abc"), Some(("14:30:05", "[粘贴] abc")));
+        assert_eq!(split_timestamp("日期：2025-11-26"), None);
+        assert_eq!(split_timestamp("[ab:cd:ef] x"), None);
+    }
+
+    #[test]
+    fn test_pdf_layout_wraps_and_paginates() {
+        let long = "字".repeat(25);
+        let content = format!("\
+================== EchoKey 日志 ==================
+日期：2024-01-05
+==================================================
+[09:00:01] {}
+          续行
+[09:00:30] a
+[09:01:00] b
+", long);
+        let pages = layout_pdf_pages(&content, 20, 3);
+        let lines: Vec<&PdfLine> = pages.iter().flatten().collect();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].len(), 3);
+        // 25 个汉字在宽度 20 下折成 3 行，时间戳只在第一行
+        assert_eq!(lines[0].time.as_deref(), Some("09:00:01"));
+        assert_eq!(lines[0].text, "字".repeat(10));
+        assert_eq!(lines[2].time, None);
+        assert_eq!(lines[2].text, "字".repeat(5));
+        assert_eq!(lines[3].text, "续行");
+        assert_eq!(lines[4].time.as_deref(), Some("09:00:30"));
+
+        assert_eq!(layout_pdf_pages("", 20, 3), vec![Vec::new()]);
+    }
+
+    #[test]
+    fn test_seek_line() {
+        let content = "\
+================== EchoKey 日志 ==================
+[09:00:00] a
+          续行
+[14:30:00] b
+[14:30:00] c
+[16:00:00] d
+";
+        let at = |s| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        assert_eq!(seek_line(content, at("14:30")), Some(3));
+        assert_eq!(seek_line(content, at("15:00")), Some(3));
+        assert_eq!(seek_line(content, at("12:00")), Some(1));
+        assert_eq!(seek_line(content, at("08:00")), Some(1));
+        assert_eq!(seek_line(content, at("23:00")), Some(5));
+        assert_eq!(seek_line("无时间戳", at("12:00")), None);
+    }
+
+    #[test]
+    fn test_sequence_prefix() {
+        assert_eq!(split_sequence("#0012 [14:30:05] Hello"), Some((12, "[14:30:05] Hello")));
+        assert_eq!(split_sequence("#标签 [14:30:05]"), None);
+        assert_eq!(split_sequence("# [14:30:05]"), None);
+        assert_eq!(split_timestamp("#0012 [14:30:05] Hello"), Some(("14:30:05", "Hello")));
+        assert_eq!(strip_sequences("#0001 [09:00:00] a\n          b\n#0002 [09:00:01] c"), "[09:00:00] a\n          b\n[09:00:01] c");
+    }
+
+    #[test]
+    fn test_sequence_numbers_per_file_and_global() {
+        let dir = temp_log_dir("sequence");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_sequence_numbers(config::SequenceNumbers::PerFile);
+        logger.write_text("a").unwrap();
+        logger.handle_enter().unwrap();
+        logger.write_paste("p").unwrap();
+        logger.write_checkpoint().unwrap();
+        let first = logger.current_path.clone().unwrap();
+        logger.new_segment().unwrap();
+        logger.write_text("b").unwrap();
+        let second = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&first).unwrap();
+        assert!(content.contains("\n#0001 [") && content.contains("] a\n#0002 ["));
+        assert!(content.contains("\n#0003 ["));
+        assert_eq!(content_lines(&content)[0], "a");
+        assert!(fs::read_to_string(&second).unwrap().contains("\n#0001 ["));
+        
+        // 续写已有文件时接着已有的序号；全局计数跨文件递增
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_sequence_numbers(config::SequenceNumbers::Global);
+        logger.write_text("c").unwrap();
+        logger.new_segment().unwrap();
+        logger.write_text("d").unwrap();
+        let third = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        assert!(fs::read_to_string(&first).unwrap().contains("\n#0004 ["));
+        assert!(fs::read_to_string(&third).unwrap().contains("\n#0005 ["));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_collapse_timestamps() {
+        let content = "\
+[14:30:05] a
+[14:30:40] bc
+[14:31:20] d
+[14:32:00] 这是一段足够长的正常输入内容
+[14:33:00] --- 暂停记录 ---
+";
+        let collapsed = collapse_timestamps(content, 5);
+        assert_eq!(collapsed, "\
+[14:30:05 - 14:31:20] a bc d
+[14:32:00] 这是一段足够长的正常输入内容
+[14:33:00] --- 暂停记录 ---
+");
+    }
+
+    #[test]
+    fn test_collapse_keeps_single_and_continuation_lines() {
+        let content = "[14:30:05] a\n[14:30:40] b\n          续行\n[14:31:00] c\n";
+        // 单独的短行和带续行的段落都原样保留
+        assert_eq!(collapse_timestamps(content, 5), content);
+    }
+
+    #[test]
+    fn test_parse_log_file_date() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        assert_eq!(parse_log_file_date("2024-01-05.log"), Some(date));
+        assert_eq!(parse_log_file_date("2024-01-05_02.log"), Some(date));
+        assert_eq!(parse_log_file_date("2024-01-05_.log"), None);
+        assert_eq!(parse_log_file_date("2024-01-05_Chrome.log"), None);
+        assert_eq!(parse_log_file_date("2024-01-05_01_02.log"), None);
+        assert_eq!(parse_log_file_date("notes.log"), None);
+    }
+
+    #[test]
+    fn test_compress_old_logs() {
+        let dir = temp_log_dir("compress");
+        fs::create_dir_all(&dir).unwrap();
+        let today = Local::now().date_naive();
+        let old = (today - chrono::Days::new(10)).format("%Y-%m-%d").to_string();
+        let recent = (today - chrono::Days::new(1)).format("%Y-%m-%d").to_string();
+        for name in [format!("{}.log", old), format!("{}_02.log", old), format!("{}.log", recent), "notes.txt".to_string()] {
+            fs::write(dir.join(&name), format!("[10:00:00] {}\n", name)).unwrap();
+        }
+        
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("今天").unwrap();
+        assert_eq!(logger.compress_old_logs(7).unwrap(), 2);
+        assert_eq!(logger.compress_old_logs(7).unwrap(), 0);
+        // 正在写入的今天的文件不压缩
+        assert_eq!(logger.compress_old_logs(0).unwrap(), 1);
+        assert!(logger.current_path.as_ref().unwrap().exists());
+        
+        let compressed = dir.join(format!("{}_02.log.gz", old));
+        assert!(!dir.join(format!("{}_02.log", old)).exists());
+        assert_eq!(parse_log_file_date(&compressed.file_name().unwrap().to_string_lossy()), Some(today - chrono::Days::new(10)));
+        assert_eq!(read_log_file(&compressed).unwrap(), format!("[10:00:00] {}_02.log\n", old));
+        assert!(dir.join("notes.txt").exists());
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compress_failure_skips_file() {
+        let dir = temp_log_dir("compress-failure");
+        fs::create_dir_all(&dir).unwrap();
+        let old = (Local::now().date_naive() - chrono::Days::new(10)).format("%Y-%m-%d").to_string();
+        for name in [format!("{}.log", old), format!("{}_02.log", old)] {
+            fs::write(dir.join(&name), "[10:00:00] a\n").unwrap();
+        }
+        // 临时文件的位置被目录占用，第一个文件无法压缩
+        fs::create_dir_all(dir.join(format!("{}.log.gz.tmp", old))).unwrap();
+        
+        let logger = Logger::with_directory(&dir).unwrap();
+        assert_eq!(logger.compress_old_logs(7).unwrap(), 1);
+        assert!(dir.join(format!("{}.log", old)).exists());
+        assert!(!dir.join(format!("{}.log.gz", old)).exists());
+        assert!(dir.join(format!("{}_02.log.gz", old)).exists());
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_enforce_retention() {
+        let dir = temp_log_dir("retention");
+        fs::create_dir_all(&dir).unwrap();
+        let today = Local::now().date_naive();
+        let day = |n: u64| (today - chrono::Days::new(n)).format("%Y-%m-%d").to_string();
+        let old = [format!("{}.log", day(40)), format!("{}_02.log", day(40)), format!("{}.log.gz", day(31))];
+        let kept = [format!("{}.log", day(30)), format!("{}_notes.txt", day(40)), "notes.log".to_string()];
+        for name in old.iter().chain(&kept) {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("今天").unwrap();
+        // 删除失败的文件跳过，其余的照常删除
+        let locked = dir.join(&old[1]);
+        let remove = |path: &Path| if path == locked.as_path() { Err(io::Error::other("占用")) } else { fs::remove_file(path) };
+        let mut expected: Vec<PathBuf> = [&old[0], &old[2]].iter().map(|name| dir.join(name)).collect();
+        expected.sort();
+        assert_eq!(logger.enforce_retention_with(Some(30), 0, remove).unwrap(), expected);
+        assert!(locked.exists());
+        assert!(kept.iter().all(|name| dir.join(name).exists()));
+        
+        // 保留 0 天时也不删除正在写入的今天的文件
+        assert_eq!(logger.enforce_retention_with(Some(0), 0, |path| fs::remove_file(path)).unwrap().len(), 2);
+        assert!(logger.current_path.as_ref().unwrap().exists());
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_app_file_names() {
+        assert_eq!(app_file_label("Chrome"), Some("chrome".to_string()));
+        assert_eq!(app_file_label("Code - Insiders"), Some("code---insiders".to_string()));
+        assert_eq!(app_file_label("微信"), Some("微信".to_string()));
+        assert_eq!(app_file_label("_7z_"), Some("7z".to_string()));
+        assert_eq!(app_file_label("2024"), None);
+        assert_eq!(app_file_label("..."), None);
+        
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(parse_log_file_name("2024-06-01_chrome.log"), Some((date, Some("chrome"))));
+        assert_eq!(parse_log_file_name("2024-06-01_chrome_01.log"), Some((date, Some("chrome"))));
+        assert_eq!(parse_log_file_name("2024-06-01_01.log"), Some((date, None)));
+        assert_eq!(parse_log_file_name("2024-06-01_chrome.jsonl"), Some((date, Some("chrome"))));
+        assert_eq!(parse_log_file_name("2024-06-01_02.jsonl.gz"), Some((date, None)));
+        assert_eq!(parse_log_file_name("2024-06-01.json"), None);
+        assert_eq!(parse_log_file_app("2024-06-01.log"), None);
+    }
+
+    #[test]
+    fn test_split_by_app_switches_files_on_write() {
+        let dir = temp_log_dir("split-by-app");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_split_by_app(true);
+        logger.set_foreground_app(Some("Chrome"));
+        logger.write_text("搜索").unwrap();
+        logger.new_segment().unwrap();
+        // 快速经过的程序不留下文件
+        logger.set_foreground_app(Some("explorer"));
+        logger.set_foreground_app(Some("notepad"));
+        logger.write_text("笔记").unwrap();
+        logger.set_foreground_app(Some("chrome"));
+        logger.write_text("继续").unwrap();
+        // 无法识别的程序写入默认文件
+        logger.set_foreground_app(None);
+        logger.write_text("其他").unwrap();
+        let date = logger.current_date.unwrap().format("%Y-%m-%d").to_string();
+        drop(logger);
+        
+        let mut names: Vec<String> = fs::read_dir(&dir).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, [
+            format!("{}.log", date),
+            format!("{}_chrome.log", date),
+            format!("{}_chrome_01.log", date),
+            format!("{}_notepad.log", date),
+        ]);
+        
+        let chrome = fs::read_to_string(dir.join(&names[1])).unwrap();
+        assert!(chrome.contains("程序：chrome\n"));
+        assert_eq!(content_lines(&chrome), vec!["搜索"]);
+        // 切换回来时接着写入该程序分出的新段
+        let chrome = fs::read_to_string(dir.join(&names[2])).unwrap();
+        assert_eq!(content_lines(&chrome), vec!["继续"]);
+        assert!(chrome.ends_with("继续\n"));
+        let notepad = fs::read_to_string(dir.join(&names[3])).unwrap();
+        assert_eq!(content_lines(&notepad), vec!["笔记"]);
+        let default = fs::read_to_string(dir.join(&names[0])).unwrap();
+        assert_eq!(content_lines(&default), vec!["其他"]);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_merge_by_time() {
+        let default = "头部\n[09:00:00] a\n          续行\n[09:10:00] d\n".to_string();
+        let chrome = "程序：chrome\n[09:05:00] b\n[09:10:00] e\n".to_string();
+        let notepad = "程序：notepad\n#0001 [09:06:00] c\n".to_string();
+        assert_eq!(
+            merge_by_time(&[default, chrome, notepad]),
+            "头部\n[09:00:00] a\n          续行\n程序：chrome\n[09:05:00] b\n\
+             程序：notepad\n#0001 [09:06:00] c\n[09:10:00] d\n[09:10:00] e\n",
+        );
+        assert_eq!(merge_by_time(&["只有头部\n".to_string()]), "只有头部\n");
+    }
+
+    #[test]
+    fn test_retention_keeps_most_recent_days() {
+        let dir = temp_log_dir("prune");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["2024-01-01.log", "2024-01-02.log", "2024-01-02_02.log", "2024-01-03.log", "notes.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let logger_on = |day: u32| Logger::with_directory(&dir).unwrap()
+            .with_clock(ManualClock::new(Local.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap()));
+        let prune = |day: u32, keep_days: Option<u32>, keep_recent_days: usize| {
+            logger_on(day).enforce_retention_with(keep_days, keep_recent_days, |path| fs::remove_file(path)).unwrap().len()
+        };
+        let names = || {
+            let mut names: Vec<String> = fs::read_dir(&dir).unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        
+        assert_eq!(prune(3, None, 0), 0);
+        assert_eq!(prune(3, None, 5), 0);
+        // 同一天的两个日志段一起保留
+        assert_eq!(prune(3, None, 2), 1);
+        assert_eq!(names(), ["2024-01-02.log", "2024-01-02_02.log", "2024-01-03.log", "notes.txt"]);
+        
+        // 时钟回拨后较新的日期也计入上限，但今天的日志不删除
+        assert_eq!(prune(2, None, 1), 0);
+        // 两项都设置时按较严格的那项删除
+        assert_eq!(prune(3, Some(30), 1), 2);
+        assert_eq!(names(), ["2024-01-03.log", "notes.txt"]);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_diff_contents_ignores_timestamps() {
+        let old = "[09:00:00] 第一行\n[09:01:00] 第二行\n";
+        let new = "[10:00:00] 第一行\n[10:05:00] --- 暂停记录 ---\n[10:06:00] 第三行\n";
+        assert_eq!(diff_contents(old, new), vec![
+            DiffLine::Unchanged("第一行".to_string()),
+            DiffLine::Removed("第二行".to_string()),
+            DiffLine::Added("第三行".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_diff_contents_missing_side() {
+        let new = "[10:00:00] 你好\n";
+        assert_eq!(diff_contents("", new), vec![DiffLine::Added("你好".to_string())]);
+        assert_eq!(diff_contents(new, ""), vec![DiffLine::Removed("你好".to_string())]);
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let content = "\
+================== EchoKey 日志 ==================
+日期：2024-01-05
+创建时间：09:00:00
+==================================================
+
+[09:00:01] 第一行
+          续行
+[09:05:00] --- 暂停记录 ---
+";
+        assert_eq!(to_markdown(date, content), "\
+# EchoKey 2024-01-05
+
+- **09:00:01** 第一行
+  续行
+- **09:05:00** *暂停记录*
+");
+    }
+
+    #[test]
+    fn test_strip_timestamps() {
+        let selection = "好\n[14:30:40] 第二行\n          续行\n[14:31:00] 第三";
+        assert_eq!(strip_timestamps(selection), "好\n第二行\n续行\n第三");
+    }
+
+    /// 为测试创建独立的临时日志目录
+    fn temp_log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("echokey-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// 测试用的内存缓冲区，克隆后共享同一份内容
+    #[derive(Clone, Default)]
+    struct MemoryBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for MemoryBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MemoryBuffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    /// 测试用的写入目标：按设定让写入失败，克隆后共享同一份设定和内容
+    #[derive(Clone, Default)]
+    struct FaultyBuffer {
+        buffer: MemoryBuffer,
+        /// 还能写入的字节数，用完后写入返回错误；None 表示不限制
+        byte_budget: Arc<Mutex<Option<usize>>>,
+        /// 接下来多少次写入返回句柄失效错误
+        stale_writes: Arc<Mutex<u32>>,
+    }
+
+    impl Write for FaultyBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            {
+                let mut stale = self.stale_writes.lock().unwrap();
+                if *stale > 0 {
+                    *stale -= 1;
+                    return Err(io::Error::from_raw_os_error(STALE_HANDLE_ERRORS[0]));
+                }
+            }
+            let mut budget = self.byte_budget.lock().unwrap();
+            let n = match *budget {
+                Some(0) => return Err(io::Error::other("模拟写入失败")),
+                Some(left) => {
+                    let n = left.min(buf.len());
+                    *budget = Some(left - n);
+                    n
+                }
+                None => buf.len(),
+            };
+            self.buffer.write(&buf[..n])
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_in_memory_writer() {
+        let buffer = MemoryBuffer::default();
+        let mut logger = Logger::with_writer(buffer.clone());
+
+        logger.write_text("ab").unwrap();
+        logger.handle_ctrl_enter().unwrap();
+        logger.write_text("cd").unwrap();
+        logger.handle_enter().unwrap();
+        logger.write_paste("粘贴").unwrap();
+        drop(logger);
+
+        // 头部之后的内容逐字节比较，时间戳替换为占位
+        let content = buffer.text();
+        let (_, body) = content.split_once(&format!("{}\n\n", HEADER_RULE)).unwrap();
+        let masked: String = body.lines()
+            .map(|line| match split_timestamp(line) {
+                Some((_, rest)) => format!("[T] {}\n", rest),
+                None => format!("{}\n", line),
+            })
+            .collect();
+        assert_eq!(masked, format!("[T] ab\n{}cd\n[T] [粘贴] 粘贴\n", CONTINUATION_INDENT));
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_in_memory_writer_with_manual_clock() {
+        let buffer = MemoryBuffer::default();
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 1, 6, 0, 0).unwrap());
+        let mut logger = Logger::with_writer(buffer.clone()).with_clock(clock.clone());
+        logger.set_timezone(LogTimezone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap()));
+        logger.set_idle_timeouts(Duration::from_secs(30), Duration::from_secs(3600));
+
+        logger.write_text("ab").unwrap();
+        clock.advance(Duration::from_secs(10));
+        logger.write_text("c").unwrap();
+        logger.handle_enter().unwrap();
+        clock.advance(Duration::from_secs(31));
+        logger.write_text("d").unwrap();
+        logger.handle_enter().unwrap();
+        clock.advance(Duration::from_secs(31));
+        logger.write_text("e").unwrap();
+        drop(logger);
+
+        // 不经过文件系统，时间戳完全由注入的时钟决定
+        let content = buffer.text();
+        let stamped: Vec<(&str, &str)> = content.lines().filter_map(split_timestamp).collect();
+        assert_eq!(stamped, vec![("14:00:00", "abc"), ("14:00:41", "d"), ("14:01:12", "e")]);
+        assert_eq!(content_lines(&content), vec!["abc", "d", "e"]);
+    }
+
+    #[test]
+    fn test_idle_boundary_with_fake_clock() {
+        let buffer = MemoryBuffer::default();
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 1, 6, 0, 0).unwrap());
+        let mut logger = Logger::with_writer(buffer.clone()).with_clock(clock.clone());
+        logger.set_timezone(LogTimezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+        logger.set_idle_timeouts(Duration::from_secs(30), Duration::from_secs(3600));
+
+        logger.write_text("一").unwrap();
+        // 恰好 30 秒不算超时，接在同一行
+        clock.advance(Duration::from_secs(30));
+        logger.write_text("二").unwrap();
+        // 空闲 31 秒后另起一行并写入新的时间戳
+        clock.advance(Duration::from_secs(31));
+        logger.write_text("三").unwrap();
+        drop(logger);
+
+        let content = buffer.text();
+        let stamped: Vec<(&str, &str)> = content.lines().filter_map(split_timestamp).collect();
+        assert_eq!(stamped, vec![("06:00:00", "一二"), ("06:01:01", "三")]);
+    }
+
+    #[test]
+    fn test_interval_flush_policy() {
+        let buffer = MemoryBuffer::default();
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 1, 6, 0, 0).unwrap());
+        let mut logger = Logger::with_writer(buffer.clone()).with_clock(clock.clone());
+        logger.set_flush_policy(FlushPolicy::Interval(Duration::from_millis(200)));
+
+        // 写入先留在缓冲中，间隔到了才落盘
+        logger.write_text("abc").unwrap();
+        assert!(!buffer.text().contains("abc"));
+        assert!(logger.file_stats().bytes >= buffer.text().len() as u64 + 3);
+        clock.advance(Duration::from_millis(100));
+        assert!(!logger.maybe_flush().unwrap());
+        clock.advance(Duration::from_millis(150));
+        assert!(logger.maybe_flush().unwrap());
+        assert!(buffer.text().contains("abc"));
+        assert!(!logger.maybe_flush().unwrap());
+
+        // 暂停和退出时不等间隔
+        logger.write_text("d").unwrap();
+        logger.pause().unwrap();
+        assert!(buffer.text().contains("abcd"));
+        logger.resume().unwrap();
+        logger.write_text("e").unwrap();
+        assert!(!buffer.text().ends_with("e"));
+        drop(logger);
+        assert!(buffer.text().ends_with("e"));
+    }
+
+    #[test]
+    fn test_json_lines_format() {
+        let dir = temp_log_dir("json-lines");
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 1, 6, 0, 0).unwrap());
+        let mut logger = Logger::with_directory(&dir).unwrap().with_clock(clock.clone());
+        logger.set_timezone(LogTimezone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap()));
+        logger.set_format(config::LogFormat::JsonLines);
+
+        logger.write_text("a").unwrap();
+        logger.write_text("\"b\"").unwrap();
+        logger.handle_enter().unwrap();
+        clock.advance(Duration::from_secs(5));
+        logger.write_paste("多行\n内容").unwrap();
+        logger.write_manual_save("保存").unwrap();
+        logger.write_window_marker("草稿 -").unwrap();
+        logger.pause().unwrap();
+        drop(logger);
+
+        // 没有头部，每一行都是一个完整的 JSON 对象
+        let path = dir.join("2024-06-01.jsonl");
+        assert!(!dir.join("2024-06-01.log").exists());
+        let content = fs::read_to_string(&path).unwrap();
+        let events: Vec<serde_json::Value> = content.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let fields: Vec<(&str, &str)> = events.iter()
+            .map(|e| (e["kind"].as_str().unwrap(), e["text"].as_str().unwrap()))
+            .collect();
+        assert_eq!(fields, vec![
+            ("char", "a"),
+            ("char", "\"b\""),
+            ("enter", ""),
+            ("paste", "多行\n内容"),
+            ("manual_save", "保存"),
+            ("marker", i18n::marker_with("marker.window", "草稿 -").as_str()),
+            ("marker", i18n::marker("marker.pause")),
+        ]);
+        assert_eq!(events[0]["ts"], "2024-06-01T14:00:00+08:00");
+        assert_eq!(events[3]["ts"], "2024-06-01T14:00:05+08:00");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_external_edit_is_reconciled() {
+        let dir = temp_log_dir("external-edit");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("秘密内容").unwrap();
+        logger.handle_enter().unwrap();
+        
+        // 模拟用户在外部编辑器中删掉了这一行
+        let path = logger.current_path.clone().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let redacted: String = content.lines()
+            .filter(|l| !l.contains("秘密内容"))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        fs::write(&path, &redacted).unwrap();
+        
+        logger.write_text("继续").unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(&redacted));
+        assert!(!content.contains("秘密内容"));
+        let tail = &content[redacted.len()..];
+        assert!(split_timestamp(tail).is_some_and(|(_, text)| text == "继续"));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recent_events_ring() {
+        let dir = temp_log_dir("recent-events");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("不保留").unwrap();
+        assert!(logger.recent_events(10).is_empty());
+        
+        logger.set_recent_events_capacity(3);
+        logger.handle_enter().unwrap();
+        logger.write_text("你").unwrap();
+        logger.write_text("好").unwrap();
+        logger.write_paste("粘贴的内容").unwrap();
+        logger.toggle_pause().unwrap();
+        logger.write_text("暂停中").unwrap();
+        
+        // 只保留最近 3 条；同一行的输入合并为一条，暂停期间的输入不记录
+        let events = logger.recent_events(10);
+        let summary: Vec<(LoggedEventKind, &str)> = events.iter().map(|e| (e.kind, e.text.as_str())).collect();
+        assert_eq!(summary, vec![
+            (LoggedEventKind::Text, "你好"),
+            (LoggedEventKind::Entry, "[粘贴] 粘贴的内容"),
+            (LoggedEventKind::Marker, "--- 暂停记录 ---"),
+        ]);
+        assert_eq!(logger.recent_events(1).len(), 1);
+        
+        // 文件被外部删改后不再保留
+        let path = logger.current_path.clone().unwrap();
+        fs::write(&path, "").unwrap();
+        assert!(logger.recent_events(10).is_empty());
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_truncated_header_len() {
+        let valid = format!("{}\n日期：2024-01-05\n创建时间：09:00:00\n{}\n\n[09:00:01] a\n", HEADER_TITLE, HEADER_RULE);
+        assert_eq!(truncated_header_len(&valid), None);
+        assert_eq!(truncated_header_len("[09:00:01] a\n"), None);
+        assert_eq!(truncated_header_len(""), None);
+        assert_eq!(truncated_header_len("========"), Some(8));
+        let partial = format!("{}\n日期：2024-01-05\n创建\n[09:00:01] a\n", HEADER_TITLE);
+        assert_eq!(truncated_header_len(&partial), partial.find('['));
+    }
+
+    #[test]
+    fn test_half_written_header_is_rewritten() {
+        let dir = temp_log_dir("truncated-header");
+        let logger = Logger::with_directory(&dir).unwrap();
+        let path = logger.get_log_path(Local::now().date_naive());
+        drop(logger);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "================== EchoKey 日志 ======\n日期：20").unwrap();
+        
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("a").unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(&format!("{}\n日期：", HEADER_TITLE)));
+        assert!(content.contains(&format!("恢复：原头部不完整，已重新写入\n{}\n\n[", HEADER_RULE)));
+        assert_eq!(truncated_header_len(&content), None);
+        assert_eq!(content_lines(&content), vec!["a"]);
+        assert!(!path.with_file_name(format!("{}.tmp", path.file_name().unwrap().to_string_lossy())).exists());
+        
+        // 完整的头部不再改动
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("b").unwrap();
+        drop(logger);
+        let again = fs::read_to_string(&path).unwrap();
+        assert!(again.starts_with(&content));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_empty_segments_are_not_created() {
+        let dir = temp_log_dir("empty-segments");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        
+        for _ in 0..3 {
+            assert!(!logger.new_segment().unwrap());
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        
+        // 有内容之后可以正常分段
+        logger.write_text("内容").unwrap();
+        assert!(logger.new_segment().unwrap());
+        assert!(!logger.new_segment().unwrap());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        
+        drop(logger);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_log() {
+        let dir = temp_log_dir("rename");
+        fs::create_dir_all(&dir).unwrap();
+        let old = dir.join("2024-01-05.log");
+        fs::write(&old, "内容").unwrap();
+        fs::write(dir.join("占用.log"), "").unwrap();
+        
+        let renamed = rename_log(&old, "旅行笔记").unwrap();
+        assert_eq!(renamed, dir.join("旅行笔记.log"));
+        assert!(!old.exists());
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "内容");
+        
+        // 不覆盖已有文件，也不允许移出日志目录
+        assert!(rename_log(&renamed, "占用.log").is_err());
+        assert!(rename_log(&renamed, "../外面").is_err());
+        assert!(renamed.exists());
+        
+        // 今天的日志正在写入：不能改名，其他日志也不能占用今天的文件名
+        let today = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+        let active = dir.join("2024-01-06_chrome_01.log");
+        fs::write(&active, "").unwrap();
+        assert!(rename_log_on(&active, "别的名字", today).is_err());
+        assert!(rename_log_on(&renamed, "2024-01-06_02", today).is_err());
+        assert!(rename_log_on(&renamed, "2024-01-04", today).is_ok());
+        
+        // 沿用原文件的扩展名，输入的日志扩展名不会重复
+        let json = dir.join("2024-01-02.jsonl");
+        fs::write(&json, "").unwrap();
+        assert_eq!(rename_log(&json, "事件").unwrap(), dir.join("事件.jsonl"));
+        assert_eq!(rename_log(&dir.join("事件.jsonl"), "事件2.jsonl").unwrap(), dir.join("事件2.jsonl"));
+        let compressed = dir.join("2024-01-03.log.gz");
+        fs::write(&compressed, "").unwrap();
+        assert_eq!(rename_log(&compressed, "归档.log.gz").unwrap(), dir.join("归档.log.gz"));
+        assert_eq!(rename_log(&dir.join("归档.log.gz"), "归档2.log").unwrap(), dir.join("归档2.log.gz"));
+        assert_eq!(rename_log(&dir.join("2024-01-04.log"), "笔记.log").unwrap(), dir.join("笔记.log"));
+        assert!(rename_log(&dir.join("笔记.log"), ".log").is_err());
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_consecutive_pastes_are_merged() {
+        let dir = temp_log_dir("paste-dedup");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_paste_dedup_window(Some(Duration::from_secs(60)));
+        
+        logger.write_paste("abc").unwrap();
+        logger.write_paste("abc").unwrap();
+        logger.write_paste("abc").unwrap();
+        logger.write_text("x").unwrap();
+        logger.write_paste("abc").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("] abc").count(), 2);
+        assert!(content.contains("[粘贴 ×2]\n"));
+        assert!(content.contains("[粘贴 ×3]\n"));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_pasted_image_saved_as_attachment() {
+        let dir = temp_log_dir("paste-image");
+        let mut logger = Logger::with_directory(&dir).unwrap()
+            .with_clock(ManualClock::new(Local.with_ymd_and_hms(2025, 11, 26, 14, 30, 5).unwrap()));
+        
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 255];
+        logger.write_pasted_image_file(2, 1, pixels.to_vec()).unwrap();
+        logger.write_pasted_image_file(2, 1, pixels.to_vec()).unwrap();
+        // 像素数据与尺寸不符时只记录尺寸
+        logger.write_pasted_image_file(3, 3, pixels.to_vec()).unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("[粘贴图片] 2×1 attachments/2025-11-26_143005.png\n"));
+        assert!(content.contains("[粘贴图片] 2×1 attachments/2025-11-26_143005_2.png\n"));
+        assert!(content.contains("[粘贴图片] 3×3\n"));
+        
+        let saved = image::open(dir.join(ATTACHMENTS_DIR).join("2025-11-26_143005.png")).unwrap().to_rgba8();
+        assert_eq!(saved.dimensions(), (2, 1));
+        assert_eq!(saved.into_raw(), pixels);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_long_and_multiline_pastes() {
+        let dir = temp_log_dir("paste-limit");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_max_paste_chars(Some(5));
+        
+        logger.write_paste("一二三四五六七").unwrap();
+        logger.write_paste("一二三四五").unwrap();
+        // 复制的内容同样截断
+        logger.write_copy("一二三四五六七").unwrap();
+        
+        // 多行内容的后续行缩进（不限制长度，避免被截断）
+        logger.set_max_paste_chars(None);
+        logger.write_paste("第一行\r\n[14:30:05] 第二行\r第三行").unwrap();
+        logger.write_copy("第一行\n第二行").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("[粘贴] 一二三四五…（已截断，共 7 字）\n"));
+        assert!(content.contains("[粘贴] 一二三四五\n"));
+        assert!(content.contains("[粘贴] 第一行\n          [14:30:05] 第二行\n          第三行\n"));
+        assert!(!content.contains('\r'));
+        // 后续行不会被当成新的时间戳行
+        assert_eq!(content.lines().filter(|line| split_timestamp(line).is_some()).count(), 5);
+        assert!(content.contains("[复制] 一二三四五…（已截断，共 7 字）\n"));
+        assert!(content.contains("[复制] 第一行\n          第二行\n"));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_device_label_in_header_and_session_marker() {
+        let dir = temp_log_dir("device-label");
+        let label = Some("书房台式机".to_string());
+        
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_device_label(label.clone(), true);
+        logger.write_text("a").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("设备：书房台式机\n"));
+        assert!(!content.contains("--- 设备"));
+        
+        // 再次启动续写同一个文件：只写会话标记，按键行不带设备名
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_device_label(label, true);
+        logger.write_text("b").unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("书房台式机").count(), 2);
+        assert!(content.contains("--- 设备：书房台式机 ---\n["));
+        assert_eq!(content_lines(&content), vec!["a", "b"]);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_header_template() {
+        assert!(validate_header_template("# {date} {time}\nEchoKey {version} @ {device}").is_ok());
+        assert_eq!(validate_header_template("{user}"), Err(HeaderTemplateError::UnknownToken("{user}".to_string())));
+        assert!(validate_header_template("{date").is_err());
+        assert!(validate_header_template("a\u{7}b").is_err());
+
+        let dir = temp_log_dir("header-template");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        assert!(logger.set_header_template(Some("{oops}".to_string())).is_err());
+        logger.set_device_label(Some("笔记本".to_string()), false);
+        logger.set_header_template(Some("# {date} / {device}\n".to_string())).unwrap();
+        logger.write_text("a").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        assert!(content.starts_with(&format!("#| # {} / 笔记本\n\n[", date)));
+        assert!(!content.contains("====="));
+        // 读取日志时跳过自定义头部
+        assert!(content.lines().next().is_some_and(is_header_line));
+        assert_eq!(content_lines(&content), ["a"]);
+        let _ = fs::remove_dir_all(&dir);
+
+        // 模板中的空行也加上标记
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_header_template(Some("标题\n\n{date}".to_string())).unwrap();
+        logger.write_text("c").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        assert!(fs::read_to_string(&path).unwrap().starts_with(&format!("#| 标题\n#|\n#| {}\n\n[", date)));
+        let _ = fs::remove_dir_all(&dir);
+
+        // 空模板不写头部
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_header_template(Some(String::new())).unwrap();
+        logger.write_text("b").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        assert!(fs::read_to_string(&path).unwrap().starts_with('['));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotates_when_file_exceeds_size_limit() {
+        let dir = temp_log_dir("size-limit");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_max_file_bytes(Some(400));
+        logger.write_text("a").unwrap();
+        let first = logger.current_path.clone().unwrap();
+        while logger.current_path.as_ref() == Some(&first) {
+            logger.handle_enter().unwrap();
+            logger.write_text("0123456789").unwrap();
+        }
+        let second = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        // 预估只计入内容本身，时间戳和换行可能略微超出上限
+        assert!(fs::metadata(&first).unwrap().len() < 450);
+        assert!(second.to_string_lossy().ends_with("_01.log"));
+        let content = fs::read_to_string(&second).unwrap();
+        let name = first.file_name().unwrap().to_string_lossy();
+        assert!(content.contains(&format!("续接：{}（超过大小上限）", name)));
+        assert!(truncated_header_len(&content).is_none());
+        assert_eq!(content_lines(&content), ["0123456789"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotating_into_existing_segment_clears_continuation() {
+        let dir = temp_log_dir("size-limit-existing");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_max_file_bytes(Some(400));
+        logger.write_text("a").unwrap();
+        let first = logger.current_path.clone().unwrap();
+        let stem = first.file_stem().unwrap().to_string_lossy().into_owned();
+        let existing = first.with_file_name(format!("{}_01.log", stem));
+        fs::write(&existing, "上次运行留下的内容\n").unwrap();
+        while logger.current_path.as_ref() == Some(&first) {
+            logger.handle_enter().unwrap();
+            logger.write_text("0123456789").unwrap();
+        }
+        assert_eq!(logger.current_path.as_ref(), Some(&existing));
+        // 之后手动分出的段不会注明续接
+        assert!(logger.new_segment().unwrap());
+        let third = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        assert!(third.to_string_lossy().ends_with("_02.log"));
+        assert!(!fs::read_to_string(&third).unwrap().contains("续接："));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_zero_idle_timeout_stamps_every_write() {
+        let dir = temp_log_dir("zero-idle-timeout");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_idle_timeout(Duration::ZERO);
+        for c in ["a", "b", "c"] {
+            logger.write_text(c).unwrap();
+        }
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content_lines(&content), ["a", "b", "c"]);
+        assert_eq!(content.lines().filter(|line| split_timestamp(line).is_some()).count(), 3);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compact_whitespace_coalesces_blank_lines() {
+        let dir = temp_log_dir("compact-whitespace");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_compact_whitespace(true);
+        logger.write_text("a").unwrap();
+        for _ in 0..3 {
+            logger.handle_enter().unwrap();
+        }
+        logger.write_text("b").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("\n\n\n"));
+        assert!(content.contains("a\n\n["));
+        assert_eq!(content_lines(&content), ["a", "b"]);
+        let _ = fs::remove_dir_all(&dir);
+
+        // 不压缩时保留每一个空行
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("a").unwrap();
+        for _ in 0..3 {
+            logger.handle_enter().unwrap();
+        }
+        logger.write_text("b").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        assert!(fs::read_to_string(&path).unwrap().contains("a\n\n\n["));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_backspace_deletes_last_char() {
+        let dir = temp_log_dir("backspace-deletes");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_backspace_deletes(true);
+        logger.write_text("你好").unwrap();
+        logger.write_text("x").unwrap();
+        logger.handle_backspace().unwrap();
+        logger.write_text("！").unwrap();
+        // 写入的文字之后有换行，退格不跨行删除
+        logger.handle_enter().unwrap();
+        logger.handle_backspace().unwrap();
+        logger.write_text("a").unwrap();
+        logger.handle_backspace().unwrap();
+        logger.handle_backspace().unwrap();
+        logger.write_paste("粘贴").unwrap();
+        logger.handle_backspace().unwrap();
+        // 原始按键记录的记号不能被退格删除
+        logger.handle_enter().unwrap();
+        logger.write_text("b").unwrap();
+        logger.write_key_marker(&raw_key_marker("Backspace", true)).unwrap();
+        logger.handle_backspace().unwrap();
+        let path = logger.current_path.clone().unwrap();
+        let stats = logger.file_stats();
+        drop(logger);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(stats.bytes, content.len() as u64);
+        assert_eq!(content_lines(&content), ["你好！", "⌫⌫", "[粘贴] 粘贴", "⌫", "b[↓Backspace]⌫"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sanitize_window_title() {
+        assert_eq!(sanitize_window_title("main.rs - Visual Studio Code"), Some("main.rs - Visual Studio Code".to_string()));
+        assert_eq!(sanitize_window_title("  第一行\r\n第二行\t "), Some("第一行 第二行".to_string()));
+        assert_eq!(sanitize_window_title("a\u{7}b"), Some("ab".to_string()));
+        assert_eq!(sanitize_window_title("文档 » 第一章"), Some("文档 › 第一章".to_string()));
+        assert_eq!(split_window_title("«文档 › 第一章» (注) 正文"), (Some("文档 › 第一章"), "(注) 正文"));
+        assert_eq!(split_window_title("(注) 正文"), (None, "(注) 正文"));
+        assert_eq!(sanitize_window_title(""), None);
+        assert_eq!(sanitize_window_title(" \n "), None);
+    }
+
+    #[test]
+    fn test_window_title_after_timestamp() {
+        let dir = temp_log_dir("window-tracking");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.window_title = || Some("笔记\n草稿".to_string());
+        logger.set_window_tracking(true);
+        logger.write_text("a").unwrap();
+        logger.write_text("b").unwrap();
+        logger.handle_enter().unwrap();
+        logger.window_title = || Some(String::new());
+        logger.write_text("c").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content_lines(&content), ["ab", "c"]);
+        assert!(content.contains("] «笔记 草稿» ab\n"));
+        assert_eq!(strip_timestamps("[14:03:22] «笔记» ab\n[14:03:30] c"), "ab\nc");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_app_start_and_exit_markers() {
+        let dir = temp_log_dir("app-markers");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_app_markers(true);
+        logger.write_text("a").unwrap();
+        logger.write_exit_marker().unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines()
+            .filter_map(|line| split_timestamp(line).map(|(_, text)| text))
+            .collect();
+        assert_eq!(lines, ["===== EchoKey 启动 =====", "a", "===== EchoKey 退出 ====="]);
+
+        // 关闭时续写不写启动/退出标记
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("b").unwrap();
+        logger.write_exit_marker().unwrap();
+        drop(logger);
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("EchoKey 启动").count(), 1);
+        assert_eq!(content.matches("EchoKey 退出").count(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_large_paste_reports_progress() {
+        let dir = temp_log_dir("paste-progress");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        
+        // 多字节字符，保证分块边界不会切开字符
+        let content = "粘".repeat(PASTE_CHUNK_BYTES / 2);
+        let mut reports = Vec::new();
+        logger.write_paste_with_progress(&content, |done, total| reports.push((done, total))).unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        assert!(reports.len() > 1);
+        assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(reports.last(), Some(&(content.len(), content.len())));
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains(&format!("[粘贴] {}\n", content)));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_checkpoint_is_written_while_paused() {
+        let dir = temp_log_dir("checkpoint");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("a").unwrap();
+        logger.toggle_pause().unwrap();
+        logger.write_checkpoint().unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        let checkpoints: Vec<&str> = content.lines().filter(|l| is_checkpoint_line(l)).collect();
+        assert_eq!(checkpoints.len(), 1);
+        assert!(!is_checkpoint_line("[12:00:00] [检查点] 不是"));
+        assert!(is_checkpoint_line("[12:00:00] [Checkpoint]"));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_file_stats_track_writes() {
+        let dir = temp_log_dir("file-stats");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        assert_eq!(logger.file_stats(), FileStats::default());
+        
+        logger.write_text("你好").unwrap();
+        logger.handle_enter().unwrap();
+        logger.write_text("b").unwrap();
+        logger.flush().unwrap();
+        let path = logger.current_path.clone().unwrap();
+        let stats = logger.file_stats();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(stats.bytes, content.len() as u64);
+        assert_eq!(stats.lines, content.matches('\n').count() as u64);
+        
+        // 重新打开已有文件时，从文件现有的大小和行数继续累加
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_checkpoint().unwrap();
+        let stats = logger.file_stats();
+        drop(logger);
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(stats.bytes, content.len() as u64);
+        assert_eq!(stats.lines, content.matches('\n').count() as u64);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fuzzy_match_file_names() {
+        assert!(fuzzy_match("0105", "2024-01-05.log"));
+        assert!(fuzzy_match("2024 1 5", "2024-01-05_02.log"));
+        assert!(fuzzy_match("01-05_02", "2024-01-05_02.log"));
+        assert!(fuzzy_match("", "2024-01-05.log"));
+        assert!(fuzzy_match("Notes", "2024-01-05 notes.log"));
+        assert!(!fuzzy_match("0501", "2024-01-05.log"));
+        assert!(!fuzzy_match("2023", "2024-01-05.log"));
+    }
+
+    #[test]
+    fn test_search_across_log_files() {
+        assert_eq!(find_matches("Hello hello HELLO", "hello"), vec![0..5, 6..11, 12..17]);
+        assert_eq!(find_matches("今天开会，明天开会", "开会"), vec![6..12, 21..27]);
+        assert_eq!(find_matches("aaaa", "aa"), vec![0..2, 2..4]);
+        assert!(find_matches("abc", "").is_empty());
+        
+        let dir = temp_log_dir("search");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("2024-01-05.log"), "[09:00:00] Rust 周报\n[09:05:00] 午饭\n").unwrap();
+        fs::write(dir.join("2024-01-06.log"), "[10:00:00] rust rust\n").unwrap();
+        fs::write(dir.join("2024-01-07.log"), "[11:00:00] 没有匹配\n").unwrap();
+        fs::write(dir.join("2024-01-08.jsonl"), "{\"kind\":\"char\",\"text\":\"rust\"}\n").unwrap();
+        fs::write(dir.join("notes.txt"), "rust").unwrap();
+        
+        let results = search_logs(&dir, "RUST");
+        let names: Vec<_> = results.iter()
+            .map(|r| r.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["2024-01-08.jsonl", "2024-01-06.log", "2024-01-05.log"]);
+        assert_eq!(results[1].matches, 2);
+        assert_eq!(results[2].lines, ["[09:00:00] Rust 周报"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_next_line_well_formed() {
+        let target = FaultyBuffer::default();
+        let mut logger = Logger::with_writer(target.clone());
+        logger.write_text("a").unwrap();
+        logger.handle_enter().unwrap();
+        
+        // 时间戳（`[HH:MM:SS] ` 共 11 字节）写入成功，内容写入失败
+        *target.byte_budget.lock().unwrap() = Some(11);
+        assert!(logger.write_text("x").is_err());
+        assert!(target.buffer.text().ends_with("] "));
+        *target.byte_budget.lock().unwrap() = None;
+        
+        logger.write_text("b").unwrap();
+        drop(logger);
+        
+        let content = target.buffer.text();
+        assert!(!content.contains('x'));
+        assert_eq!(content_lines(&content), vec!["a", "b"]);
+        // 新的一行从行首开始，不接在残留的时间戳后面
+        let last = content.lines().last().unwrap();
+        assert_eq!(split_timestamp(last).map(|(_, text)| text), Some("b"));
+        assert!(content.lines().all(|line| line.matches("] ").count() <= 1));
+    }
+
+    #[test]
+    fn test_autorepeat_run_continues() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let run = RepeatRun { ch: 'a', len: 1, last: start };
+        // 第一次重复前允许系统的重复延迟
+        assert!(run.continues('a', start + ms(500), ms(50)));
+        assert!(!run.continues('b', start + ms(10), ms(50)));
+        let run = RepeatRun { len: 2, ..run };
+        assert!(run.continues('a', start + ms(40), ms(50)));
+        assert!(!run.continues('a', start + ms(200), ms(50)));
+    }
+
+    #[test]
+    fn test_stuck_key_is_collapsed_but_double_letters_are_not() {
+        let dir = temp_log_dir("autorepeat");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_autorepeat(Some(AutorepeatPolicy { max_interval: Duration::from_secs(1), min_run: 5 }));
+        for c in "book".chars() {
+            logger.write_text(&c.to_string()).unwrap();
+        }
+        logger.handle_enter().unwrap();
+        for _ in 0..50 {
+            logger.write_text("z").unwrap();
+        }
+        logger.write_text("!").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content_lines(&content), vec!["book", "zzzzz[长按 ×50]!"]);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_gap_is_separated_by_blank_line() {
+        let dir = temp_log_dir("session-timeout");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        
+        // 会话超时短于时间戳超时时按时间戳超时处理
+        logger.set_idle_timeouts(Duration::from_secs(60), Duration::ZERO);
+        assert_eq!(logger.session_timeout, Duration::from_secs(60));
+        
+        // 超过时间戳超时、未超过会话超时：只添加新时间戳
+        logger.set_idle_timeouts(Duration::from_millis(1), Duration::from_secs(3600));
+        logger.write_text("a").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        logger.write_text("b").unwrap();
+        
+        // 超过会话超时：空一行再开始
+        logger.set_idle_timeouts(Duration::from_millis(1), Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(5));
+        logger.write_text("c").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        let body: Vec<&str> = content.lines().skip_while(|l| split_timestamp(l).is_none()).collect();
+        assert_eq!(body.len(), 4);
+        assert!(body[0].ends_with("] a"));
+        assert!(body[1].ends_with("] b"));
+        assert!(body[2].is_empty());
+        assert!(body[3].ends_with("] c"));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stale_handle_is_reopened_once() {
+        let target = FaultyBuffer::default();
+        let mut logger = Logger::with_writer(target.clone());
+        logger.write_text("a").unwrap();
+        
+        // 一次句柄失效：重新打开后写入成功
+        *target.stale_writes.lock().unwrap() = 1;
+        logger.write_text("b").unwrap();
+        
+        // 重试后仍然失效：返回错误
+        *target.stale_writes.lock().unwrap() = 2;
+        assert!(logger.write_text("x").is_err());
+        *target.stale_writes.lock().unwrap() = 0;
+        
+        logger.write_text("c").unwrap();
+        drop(logger);
+        
+        assert_eq!(content_lines(&target.buffer.text()), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_short_pause_leaves_no_markers() {
+        let dir = temp_log_dir("pause-threshold");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_pause_marker_threshold(Some(Duration::from_secs(60)));
+        logger.write_text("a").unwrap();
+        logger.toggle_pause().unwrap();
+        logger.toggle_pause().unwrap();
+        logger.write_text("b").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("暂停记录"));
+        assert!(!content.contains("恢复记录"));
+        
+        // 达到阈值的暂停：恢复时补写暂停标记，位置在恢复标记之前
+        logger.set_pause_marker_threshold(Some(Duration::ZERO));
+        logger.toggle_pause().unwrap();
+        logger.toggle_pause().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        let pause = content.find("暂停记录").unwrap();
+        let resume = content.find("恢复记录").unwrap();
+        assert!(pause < resume);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resume_starts_new_line() {
+        let dir = temp_log_dir("resume-new-line");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.write_text("前半句").unwrap();
+        logger.toggle_pause().unwrap();
+        logger.toggle_pause().unwrap();
+        logger.write_text("后半句").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        // 暂停、恢复标记各占一行，恢复后的输入从新的时间戳行开始
+        let content = fs::read_to_string(&path).unwrap();
+        let entries: Vec<&str> = content.lines().filter_map(|l| split_timestamp(l).map(|(_, t)| t)).collect();
+        assert_eq!(entries, vec!["前半句", "--- 暂停记录 ---", "--- 恢复记录 ---", "后半句"]);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_inline_resume_continues_line() {
+        let dir = temp_log_dir("resume-inline");
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_resume_style(config::ResumeStyle::Inline);
+        logger.write_text("前半句").unwrap();
+        logger.toggle_pause().unwrap();
+        logger.toggle_pause().unwrap();
+        logger.write_text("后半句").unwrap();
+        logger.handle_enter().unwrap();
+        
+        // 当前行为空时仍写成独立的标记行
+        logger.toggle_pause().unwrap();
+        logger.toggle_pause().unwrap();
+        logger.write_text("新的一行").unwrap();
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        let content = fs::read_to_string(&path).unwrap();
+        let entries: Vec<&str> = content.lines().filter_map(|l| split_timestamp(l).map(|(_, t)| t)).collect();
+        assert_eq!(entries.len(), 4, "{:?}", entries);
+        // 标记接在暂停前的那一行末尾，恢复后的输入继续写在同一行
+        let rest = entries[0].strip_prefix("前半句 [暂停记录 ").unwrap();
+        let (paused_at, rest) = rest.split_once("] [恢复记录 ").unwrap();
+        let (resumed_at, rest) = rest.split_once("] ").unwrap();
+        assert_eq!(rest, "后半句");
+        assert!(NaiveTime::parse_from_str(paused_at, "%H:%M:%S").is_ok());
+        assert!(NaiveTime::parse_from_str(resumed_at, "%H:%M:%S").is_ok());
+        assert_eq!(&entries[1..], ["--- 暂停记录 ---", "--- 恢复记录 ---", "新的一行"]);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fixed_timezone_stamps() {
+        assert_eq!(LogTimezone::parse(""), Some(LogTimezone::Local));
+        assert!(matches!(LogTimezone::parse("Asia/Shanghai"), Some(LogTimezone::Named(_))));
+        assert_eq!(LogTimezone::parse("不存在/时区"), None);
+        
+        let dir = temp_log_dir("timezone");
+        let timezone = LogTimezone::parse("+05:45").unwrap();
+        let offset = FixedOffset::east_opt(5 * 3600 + 45 * 60).unwrap();
+        assert_eq!(timezone, LogTimezone::Fixed(offset));
+        
+        let mut logger = Logger::with_directory(&dir).unwrap();
+        logger.set_timezone(timezone);
+        let before = Utc::now().with_timezone(&offset);
+        logger.write_text("a").unwrap();
+        let after = Utc::now().with_timezone(&offset);
+        let path = logger.current_path.clone().unwrap();
+        drop(logger);
+        
+        // 文件名使用该时区的日期
+        let date = parse_log_file_date(&path.file_name().unwrap().to_string_lossy()).unwrap();
+        assert!(date == before.date_naive() || date == after.date_naive());
+        
+        let content = fs::read_to_string(&path).unwrap();
+        let stamp = content.lines()
+            .find_map(|line| split_timestamp(line).filter(|(_, text)| *text == "a"))
+            .map(|(time, _)| time.to_string())
+            .unwrap();
+        let expected = [before, after].map(|t| t.format("%H:%M:%S").to_string());
+        assert!(expected.contains(&stamp), "{} 不在 {:?} 中", stamp, expected);
+        
+        let _ = fs::remove_dir_all(&dir);
     }
 }
@@ -16,30 +16,35 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::process;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use echokey::{
-    Logger, KeyboardEvent, SharedGuiState,
-    keyboard_win, clipboard, autostart, config, gui, tray,
+    Logger, InputEvent, SharedGuiState,
+    keyboard_win, uia_win, ime_win, clipboard, autostart, config, event, gui, i18n, safe_mode, tray,
 };
-
-/// 应用状态
-struct AppState {
-    logger: Logger,
-    paused: bool,
-    char_count: usize,
-}
+use echokey::event::AppState;
+use echokey::logger::LogTimezone;
 
 fn main() {
     // 打印启动信息（仅在调试模式下可见）
     eprintln!("EchoKey {} 正在启动...", config::APP_VERSION);
     
+    // 连续多次启动后很快崩溃（或命令行 --safe-mode）时进入安全模式
+    let previous_starts = safe_mode::record_start();
+    let safe = safe_mode::should_enter(safe_mode::requested(), previous_starts);
+    if safe {
+        eprintln!("安全模式：不记录输入，不修改开机自启动（之前连续 {} 次启动未能稳定运行）", previous_starts);
+        // 本次不会崩溃在钩子上，下次正常启动重新计数
+        safe_mode::clear();
+    }
+    
     // 初始化剪贴板
     if let Err(e) = clipboard::init() {
         eprintln!("警告: {}", e);
     }
     
     // 初始化日志写入器
-    let logger = match Logger::new() {
+    let mut logger = match Logger::new() {
         Ok(l) => l,
         Err(e) => {
             eprintln!("错误: 无法初始化日志系统: {}", e);
@@ -49,28 +54,87 @@ fn main() {
     
     let log_directory = logger.get_log_directory().to_path_buf();
     eprintln!("日志目录: {:?}", log_directory);
+    if config::is_portable() {
+        eprintln!("便携模式：配置和日志保存在程序旁边");
+    }
     
-    // 设置开机自启动（首次运行）
-    if !autostart::is_enabled() {
+    // 设置开机自启动（首次运行，安全模式下不修改）
+    if !safe && !autostart::is_enabled() {
         if let Err(e) = autostart::enable() {
             eprintln!("警告: 无法设置开机自启动: {}", e);
         } else {
             eprintln!("已启用开机自启动");
         }
+    } else if !safe && !autostart::is_current() {
+        // 程序移动位置后，自启动项仍指向原来的路径，登录时会启动失败
+        if let Err(e) = autostart::enable() {
+            eprintln!("警告: 无法更新开机自启动: {}", e);
+        } else {
+            eprintln!("已更新开机自启动的程序路径");
+        }
+    }
+    
+    let settings = config::current();
+    i18n::init(settings.language, settings.marker_language);
+    logger.set_paste_dedup_window(settings.paste_dedup_window());
+    logger.set_max_paste_chars(settings.max_paste_chars());
+    logger.set_pause_marker_threshold(settings.pause_marker_threshold());
+    logger.set_resume_style(settings.resume_style);
+    logger.set_compact_whitespace(settings.compact_whitespace);
+    logger.set_backspace_deletes(settings.backspace_deletes);
+    logger.set_window_tracking(settings.window_titles);
+    logger.set_max_file_bytes(settings.max_file_bytes());
+    logger.set_flush_policy(settings.flush_policy());
+    logger.set_autorepeat(settings.autorepeat_policy());
+    logger.set_sequence_numbers(settings.sequence_numbers);
+    logger.set_format(settings.log_format);
+    logger.set_split_by_app(settings.split_by_app);
+    logger.set_recent_events_capacity(settings.recent_events);
+    echokey::logger::set_tee_stdout(settings.tee_stdout);
+    if settings.session_timeout_mins.saturating_mul(60) < settings.timestamp_timeout_secs {
+        eprintln!("警告: 会话超时短于时间戳超时，按时间戳超时处理");
+    }
+    logger.set_idle_timeouts(settings.timestamp_timeout(), settings.session_timeout());
+    if let Some(name) = settings.timezone.as_deref().filter(|n| LogTimezone::parse(n).is_none()) {
+        eprintln!("警告: 无法识别的时区 {}，使用系统时区", name);
+    }
+    logger.set_timezone(settings.log_timezone());
+    if settings.segment_schedule().len() != settings.segment_times.len() {
+        eprintln!("警告: 部分自动分段时刻无法识别（格式应为 HH:MM），已忽略");
+    }
+    logger.set_device_label(settings.device_label.clone(), settings.device_session_marker);
+    logger.set_app_markers(settings.app_markers);
+    if let Err(e) = logger.set_header_template(settings.header_template.clone()) {
+        eprintln!("警告: {}，使用默认头部", e);
+    }
+    
+    // 删除超过保留天数、不在最近几天内的日志（安全模式下不删除文件）
+    if !safe && (settings.retention_days.is_some() || settings.keep_recent_days > 0) {
+        match logger.enforce_retention(settings.retention_days, settings.keep_recent_days) {
+            Ok(deleted) if !deleted.is_empty() => eprintln!("已删除 {} 个较早的日志文件", deleted.len()),
+            Ok(_) => {}
+            Err(e) => eprintln!("警告: 删除过期日志失败: {}", e),
+        }
+    }
+    
+    // 压缩较早的日志（安全模式下不修改文件）
+    if let Some(days) = settings.compress_after_days.filter(|_| !safe) {
+        match logger.compress_old_logs(days) {
+            Ok(0) => {}
+            Ok(count) => eprintln!("已压缩 {} 个较早的日志文件（{} 天以前）", count, days),
+            Err(e) => eprintln!("警告: 压缩旧日志失败: {}", e),
+        }
     }
     
     // 创建应用状态
-    let app_state = Arc::new(Mutex::new(AppState {
-        logger,
-        paused: false,
-        char_count: 0,
-    }));
+    let app_state = Arc::new(Mutex::new(AppState::new(logger, settings.clone())));
     
     // 创建 GUI 共享状态
     let gui_state = Arc::new(Mutex::new(SharedGuiState::default()));
     
     // 创建键盘事件通道
-    let (keyboard_tx, keyboard_rx) = mpsc::channel::<KeyboardEvent>();
+    // 有界通道：钩子回调不能阻塞，通道满时丢弃事件并计数
+    let (keyboard_tx, keyboard_rx) = mpsc::sync_channel::<InputEvent>(settings.event_channel_capacity.max(1));
     
     // 创建托盘事件通道
     let (tray_tx, tray_rx) = mpsc::channel::<tray::TrayEvent>();
@@ -90,8 +154,63 @@ fn main() {
         }
     };
     
+    // 应用键盘相关配置
+    keyboard_win::set_log_win_combos(settings.log_win_combos);
+    keyboard_win::set_log_unknown_keys(settings.verbose_keys);
+    // 安全模式下不记录输入，也不启动输入法监听线程
+    ime_win::set_enabled(settings.capture_ime_text && !safe);
+    keyboard_win::set_raw_mode(settings.raw_key_log);
+    keyboard_win::set_log_caps_lock(settings.log_caps_lock);
+    keyboard_win::set_log_copies(settings.log_copies);
+    keyboard_win::set_dedup_window(settings.key_dedup_ms);
+    keyboard_win::set_remote_polling_fallback(settings.remote_polling_fallback);
+    keyboard_win::set_shortcuts(settings.shortcuts.bindings());
+    
+    // 开机自启动时稍等片刻再安装钩子，避免与其他启动程序竞争
+    let startup_delay = if autostart::launched_by_autostart() {
+        Duration::from_secs(settings.startup_delay_secs)
+    } else {
+        Duration::ZERO
+    };
+    if let Ok(mut gs) = gui_state.lock() {
+        gs.capture_pending = !safe && !startup_delay.is_zero();
+        gs.safe_mode = safe;
+    }
+    
+    // 稳定运行一段时间后清零启动计数
+    if !safe {
+        thread::spawn(|| {
+            thread::sleep(safe_mode::STABLE_RUN);
+            safe_mode::clear();
+        });
+    }
+    
     // 线程2: 启动键盘监听线程（带消息循环）
+    let keyboard_gui_state = Arc::clone(&gui_state);
     let keyboard_thread = thread::spawn(move || {
+        if safe {
+            // 不安装钩子；持有发送端直到退出，业务逻辑线程照常处理界面请求
+            let _keyboard_tx = keyboard_tx;
+            while !keyboard_win::stop_requested() {
+                thread::park();
+            }
+            return;
+        }
+        if !startup_delay.is_zero() {
+            eprintln!("等待 {} 秒后开始记录", startup_delay.as_secs());
+            // 等待期间退出时提前醒来，start_listening 随即返回
+            let deadline = Instant::now() + startup_delay;
+            while !keyboard_win::stop_requested() {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                thread::park_timeout(remaining);
+            }
+            if let Ok(mut gs) = keyboard_gui_state.lock() {
+                gs.capture_pending = false;
+            }
+        }
         eprintln!("键盘监听线程启动");
         if let Err(e) = keyboard_win::start_listening(keyboard_tx) {
             eprintln!("键盘监听错误: {}", e);
@@ -111,32 +230,138 @@ fn main() {
     
     eprintln!("EchoKey 已启动");
     eprintln!("快捷键:");
-    eprintln!("  Ctrl+Shift+P: 暂停/恢复");
-    eprintln!("  Ctrl+Shift+S: 手动保存剪贴板");
-    eprintln!("  Ctrl+Shift+N: 新建日志段");
+    for binding in config::current().shortcuts.resolve().0 {
+        eprintln!("  {}: {}", binding.chord.name(), binding.action.label());
+    }
     
     // 线程1: 主线程 - 运行 GUI
     if let Err(e) = gui::run_gui(log_directory, gui_state) {
         eprintln!("GUI 错误: {}", e);
     }
+    write_exit_marker(&app_state);
+    // 正常退出不算崩溃
+    safe_mode::clear();
     
     // GUI 退出后，等待其他线程
-    // 注意：键盘线程有自己的消息循环，需要发送退出消息
+    // 键盘线程有自己的消息循环，需要发送退出消息（界面的 on_exit 已发送过，界面启动失败时在这里发送）；
+    // 键盘线程释放事件通道的发送端后，业务逻辑线程随之退出
+    keyboard_win::stop_listening();
+    keyboard_thread.thread().unpark();
     let _ = keyboard_thread.join();
     let _ = logic_thread.join();
 }
 
+/// 正常退出前写入退出标记
+fn write_exit_marker(app_state: &Arc<Mutex<AppState>>) {
+    if let Ok(mut as_) = app_state.lock() {
+        if let Err(e) = as_.logger.write_exit_marker() {
+            eprintln!("写入退出标记错误: {}", e);
+        }
+        // 托盘退出时直接结束进程，不经过 Logger 的 Drop，缓冲的内容在这里落盘
+        if let Err(e) = as_.logger.flush() {
+            eprintln!("写入日志错误: {}", e);
+        }
+    }
+}
+
 /// 业务逻辑循环
 /// 
 /// 处理来自键盘钩子和托盘的事件
 fn run_logic_loop(
-    keyboard_rx: mpsc::Receiver<KeyboardEvent>,
+    keyboard_rx: mpsc::Receiver<InputEvent>,
     tray_rx: mpsc::Receiver<tray::TrayEvent>,
     app_state: Arc<Mutex<AppState>>,
     gui_state: Arc<Mutex<SharedGuiState>>,
     log_directory: PathBuf,
 ) {
+    // 安全桌面检测状态（低频轮询）
+    let mut on_secure_desktop = false;
+    let mut on_remote_session = false;
+    let mut last_desktop_check = Instant::now();
+    let mut last_clipboard_poll = Instant::now();
+    let mut last_verify = Instant::now();
+    let mut last_snapshot: Option<String> = None;
+    let mut focused_text = uia_win::FocusedTextReader::spawn();
+    let mut backlog = event::BacklogMonitor::default();
+    let mut hook_watchdog = event::HookWatchdog::default();
+    let mut write_busy = false;
+    let busy_threshold = config::current().backlog_busy_events;
+    
     loop {
+        // 检查安全桌面切换（UAC 提示等），在日志中标记无法记录的时段
+        if last_desktop_check.elapsed() >= config::SECURE_DESKTOP_POLL_INTERVAL {
+            last_desktop_check = Instant::now();
+            let secure = keyboard_win::is_secure_desktop_active();
+            if secure != on_secure_desktop {
+                on_secure_desktop = secure;
+                if let Ok(mut as_) = app_state.lock() {
+                    let result = if secure {
+                        as_.logger.enter_secure_desktop()
+                    } else {
+                        as_.logger.leave_secure_desktop()
+                    };
+                    if let Err(e) = result {
+                        eprintln!("写入安全桌面标记错误: {}", e);
+                    }
+                }
+            }
+            
+            // 检查远程桌面会话（运行中也可能通过远程桌面连接到本会话）
+            let remote = keyboard_win::is_remote_session();
+            if remote != on_remote_session {
+                on_remote_session = remote;
+                if let Ok(mut gs) = gui_state.lock() {
+                    gs.remote_session = remote;
+                }
+                if let Ok(mut as_) = app_state.lock() {
+                    if let Err(e) = as_.logger.write_remote_session_marker(remote) {
+                        eprintln!("写入远程桌面标记错误: {}", e);
+                    }
+                }
+            }
+            
+            // 暂停持续满阈值后补写推迟的暂停标记，到了设定时刻自动分段
+            if let Ok(mut as_) = app_state.lock() {
+                if let Err(e) = as_.logger.write_pending_pause(false) {
+                    eprintln!("写入暂停标记错误: {}", e);
+                }
+                event::poll_segment_schedule(&mut as_);
+                if let Ok(mut gs) = gui_state.lock() {
+                    if event::poll_focus(&mut as_, &mut gs) {
+                        gs.notice = Some(i18n::t("focus.finished").to_string());
+                    }
+                }
+            }
+        }
+        
+        // 定期在后台线程读取焦点控件的文本，写入校验快照（内容没有变化时不重复写入）
+        // EchoKey 自身的窗口在前台时不读取
+        let verify_interval = app_state.lock().ok()
+            .filter(|as_| !as_.paused && !as_.own_window_focused && !on_secure_desktop)
+            .and_then(|as_| as_.settings.verify_interval());
+        if verify_interval.is_some_and(|interval| last_verify.elapsed() >= interval) && focused_text.request() {
+            last_verify = Instant::now();
+        }
+        if let Some(Some(text)) = focused_text.try_result() {
+            if last_snapshot.as_deref() != Some(text.as_str()) {
+                if let Ok(mut as_) = app_state.lock() {
+                    if !as_.own_window_focused {
+                        if let Err(e) = as_.logger.write_verification(&text) {
+                            eprintln!("写入校验快照错误: {}", e);
+                        }
+                    }
+                }
+                last_snapshot = Some(text);
+            }
+        }
+        
+        // 定时落盘时，把缓冲的输入按间隔写入文件
+        if let Ok(mut as_) = app_state.lock() {
+            if let Err(e) = as_.logger.maybe_flush() {
+                eprintln!("写入日志错误: {}", e);
+            }
+        }
+        
         // 检查 GUI 请求
         if let Ok(mut gs) = gui_state.lock() {
             // 同步暂停状态
@@ -153,8 +378,69 @@ fn run_logic_loop(
             if gs.request_new_segment {
                 gs.request_new_segment = false;
                 if let Ok(mut as_) = app_state.lock() {
-                    if let Err(e) = as_.logger.new_segment() {
-                        eprintln!("创建新日志段错误: {}", e);
+                    match as_.logger.new_segment() {
+                        Ok(true) => gs.notice = Some(i18n::t("status.segment_created").to_string()),
+                        Ok(false) => gs.notice = Some(i18n::t("status.segment_empty").to_string()),
+                        Err(e) => eprintln!("创建新日志段错误: {}", e),
+                    }
+                }
+            }
+            
+            // 处理摘录请求（暂停记录时丢弃）
+            if let Some(excerpt) = gs.request_excerpt.take() {
+                if let Ok(mut as_) = app_state.lock() {
+                    if as_.paused {
+                        eprintln!("记录已暂停，丢弃摘录");
+                    } else if let Err(e) = as_.logger.write_excerpt(&excerpt) {
+                        eprintln!("写入摘录错误: {}", e);
+                    }
+                }
+            }
+            
+            // 累加剪贴板：开启/关闭、写入日志、定期检测剪贴板变化
+            if let Ok(mut as_) = app_state.lock() {
+                if gs.request_toggle_accumulation {
+                    gs.request_toggle_accumulation = false;
+                    event::toggle_accumulation(&mut as_, &mut gs);
+                }
+                if gs.request_flush_accumulation {
+                    gs.request_flush_accumulation = false;
+                    event::flush_accumulation(&mut as_, &mut gs);
+                }
+                if last_clipboard_poll.elapsed() >= clipboard::MONITOR_POLL_INTERVAL {
+                    last_clipboard_poll = Instant::now();
+                    event::poll_accumulation(&mut as_, &mut gs);
+                }
+                event::poll_clipboard_watch(&mut as_, Instant::now());
+            }
+            
+            // 专注时段：开始/结束、暂停计时
+            if gs.request_toggle_focus {
+                gs.request_toggle_focus = false;
+                if let Ok(mut as_) = app_state.lock() {
+                    event::toggle_focus(&mut as_, &mut gs);
+                }
+            }
+            if gs.request_pause_focus {
+                gs.request_pause_focus = false;
+                event::pause_focus(&mut gs);
+            }
+            
+            // 应用设置页面修改后的配置
+            if gs.request_reload_settings {
+                gs.request_reload_settings = false;
+                let settings = config::current();
+                keyboard_win::set_log_win_combos(settings.log_win_combos);
+                keyboard_win::set_log_unknown_keys(settings.verbose_keys);
+                ime_win::set_enabled(settings.capture_ime_text && !gs.safe_mode);
+                keyboard_win::set_raw_mode(settings.raw_key_log);
+                keyboard_win::set_log_caps_lock(settings.log_caps_lock);
+                keyboard_win::set_log_copies(settings.log_copies);
+                keyboard_win::set_dedup_window(settings.key_dedup_ms);
+                keyboard_win::set_shortcuts(settings.shortcuts.bindings());
+                if let Ok(mut as_) = app_state.lock() {
+                    if let Err(e) = as_.reload_settings(settings) {
+                        eprintln!("警告: {}，使用默认头部", e);
                     }
                 }
             }
@@ -170,8 +456,14 @@ fn run_logic_loop(
         while let Ok(event) = tray_rx.try_recv() {
             match event {
                 tray::TrayEvent::ShowWindow => {
-                    // TODO: 通知 GUI 显示窗口
-                    eprintln!("托盘: 显示窗口");
+                    // 隐藏的窗口不会刷新，先直接显示出来，再由界面处理显示请求
+                    keyboard_win::show_own_window();
+                    if let Ok(mut gs) = gui_state.lock() {
+                        gs.request_show = true;
+                        if let Some(ctx) = &gs.repaint {
+                            ctx.request_repaint();
+                        }
+                    }
                 }
                 tray::TrayEvent::TogglePause => {
                     if let Ok(mut as_) = app_state.lock() {
@@ -189,30 +481,95 @@ fn run_logic_loop(
                 }
                 tray::TrayEvent::NewSegment => {
                     if let Ok(mut as_) = app_state.lock() {
-                        if let Err(e) = as_.logger.new_segment() {
-                            eprintln!("创建新日志段错误: {}", e);
-                        } else {
-                            eprintln!("托盘: 已创建新日志段");
+                        match as_.logger.new_segment() {
+                            Ok(true) => eprintln!("托盘: 已创建新日志段"),
+                            Ok(false) => eprintln!("托盘: 当前日志段为空"),
+                            Err(e) => eprintln!("创建新日志段错误: {}", e),
                         }
                     }
                 }
                 tray::TrayEvent::OpenLogDir => {
                     open_directory(&log_directory);
                 }
+                tray::TrayEvent::ToggleAccumulation => {
+                    if let Ok(mut gs) = gui_state.lock() {
+                        gs.request_toggle_accumulation = true;
+                    }
+                }
+                tray::TrayEvent::ToggleVerbose => {
+                    let verbose = !event::verbose();
+                    event::set_verbose(verbose);
+                    if let Ok(mut as_) = app_state.lock() {
+                        as_.verbose = verbose;
+                    }
+                    eprintln!("{}", if verbose { "托盘: 已开启详细记录" } else { "托盘: 已关闭详细记录" });
+                }
                 tray::TrayEvent::Quit => {
                     eprintln!("托盘: 退出程序");
+                    write_exit_marker(&app_state);
+                    safe_mode::clear();
                     process::exit(0);
                 }
             }
         }
         
+        // 记录通道已满时丢弃的事件
+        let dropped = keyboard_win::take_dropped_events();
+        if dropped > 0 {
+            eprintln!("警告: 事件通道已满，丢失 {} 个事件", dropped);
+            if let Ok(mut as_) = app_state.lock() {
+                if let Err(e) = as_.logger.write_dropped_events(dropped) {
+                    eprintln!("写入丢失事件标记错误: {}", e);
+                }
+            }
+            if let Ok(mut gs) = gui_state.lock() {
+                gs.dropped_events += dropped;
+            }
+        }
+        
+        // 看门狗：钩子被系统移除（回调超时）时重新安装，并在界面上提示
+        if let Some(idle) = keyboard_win::hook_idle().filter(|_| !on_secure_desktop) {
+            match hook_watchdog.check(Instant::now(), idle, keyboard_win::any_key_down) {
+                event::HookCheck::Reinstall => {
+                    eprintln!("警告: 键盘钩子可能已被系统移除，正在重新安装");
+                    keyboard_win::request_hook_reinstall();
+                    if let Ok(mut gs) = gui_state.lock() {
+                        gs.hook_lost = true;
+                    }
+                }
+                event::HookCheck::Recovered => {
+                    eprintln!("键盘钩子已恢复");
+                    if let Ok(mut gs) = gui_state.lock() {
+                        gs.hook_lost = false;
+                        gs.hook_restores += 1;
+                        gs.notice = Some(i18n::t("status.hook_restored").to_string());
+                    }
+                }
+                event::HookCheck::Ok => {}
+            }
+        }
+        
+        // 积压状态变化时才同步到界面
+        let busy = backlog.update(event::queued_events(), busy_threshold);
+        if busy != write_busy {
+            write_busy = busy;
+            if let Ok(mut gs) = gui_state.lock() {
+                gs.write_busy = busy;
+            }
+        }
+        
         // 处理键盘事件（带超时）
-        match keyboard_rx.recv_timeout(std::time::Duration::from_millis(50)) {
+        match keyboard_rx.recv_timeout(Duration::from_millis(50)) {
             Ok(event) => {
+                event::note_event_dequeued();
                 handle_keyboard_event(&app_state, &gui_state, event);
             }
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                // 超时，继续循环
+                // 超时，把合并中的计数同步到界面，写入防抖到期的窗口标记，继续循环
+                if let Ok(mut as_) = app_state.lock() {
+                    event::flush_counters(&mut as_, &gui_state);
+                    event::poll_window_marker(&mut as_);
+                }
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
                 // Channel 关闭，退出循环
@@ -226,8 +583,10 @@ fn run_logic_loop(
 fn handle_keyboard_event(
     app_state: &Arc<Mutex<AppState>>,
     gui_state: &Arc<Mutex<SharedGuiState>>,
-    event: KeyboardEvent,
+    event: InputEvent,
 ) {
+    // 复制、粘贴要等待剪贴板可用，在状态锁之外读取
+    let clipboard = event::prefetch_clipboard(app_state, &event);
     let mut state = match app_state.lock() {
         Ok(s) => s,
         Err(e) => {
@@ -236,83 +595,7 @@ fn handle_keyboard_event(
         }
     };
     
-    // 检查是否暂停
-    if state.paused && !matches!(event, KeyboardEvent::TogglePause) {
-        return;
-    }
-    
-    match event {
-        KeyboardEvent::Character(c) => {
-            if let Err(e) = state.logger.write_text(&c.to_string()) {
-                eprintln!("写入错误: {}", e);
-            } else {
-                state.char_count += 1;
-                // 更新 GUI 状态
-                if let Ok(mut gs) = gui_state.lock() {
-                    gs.today_chars = state.char_count;
-                }
-            }
-        }
-        KeyboardEvent::Enter => {
-            if let Err(e) = state.logger.handle_enter() {
-                eprintln!("写入错误: {}", e);
-            }
-        }
-        KeyboardEvent::CtrlEnter => {
-            if let Err(e) = state.logger.handle_ctrl_enter() {
-                eprintln!("写入错误: {}", e);
-            }
-        }
-        KeyboardEvent::Backspace => {
-            if let Err(e) = state.logger.write_text("⌫") {
-                eprintln!("写入错误: {}", e);
-            }
-        }
-        KeyboardEvent::Paste => {
-            if let Some(content) = clipboard::get_text() {
-                if !content.is_empty() {
-                    if let Err(e) = state.logger.write_paste(&content) {
-                        eprintln!("写入错误: {}", e);
-                    } else {
-                        state.char_count += content.chars().count();
-                        if let Ok(mut gs) = gui_state.lock() {
-                            gs.today_chars = state.char_count;
-                        }
-                    }
-                }
-            }
-        }
-        KeyboardEvent::ManualSave => {
-            if let Some(content) = clipboard::get_text() {
-                if !content.is_empty() {
-                    if let Err(e) = state.logger.write_manual_save(&content) {
-                        eprintln!("写入错误: {}", e);
-                    } else {
-                        eprintln!("已手动保存剪贴板内容");
-                    }
-                }
-            }
-        }
-        KeyboardEvent::TogglePause => {
-            match state.logger.toggle_pause() {
-                Ok(paused) => {
-                    state.paused = paused;
-                    if let Ok(mut gs) = gui_state.lock() {
-                        gs.paused = paused;
-                    }
-                    eprintln!("{}", if paused { "已暂停记录" } else { "已恢复记录" });
-                }
-                Err(e) => eprintln!("错误: {}", e),
-            }
-        }
-        KeyboardEvent::NewSegment => {
-            if let Err(e) = state.logger.new_segment() {
-                eprintln!("错误: 无法创建新日志段: {}", e);
-            } else {
-                eprintln!("已创建新日志段");
-            }
-        }
-    }
+    event::handle_event_with_clipboard(&mut state, gui_state, event, clipboard);
 }
 
 /// 打开目录
@@ -0,0 +1,89 @@
+//! 安全模式
+//!
+//! 开机自启动时如果 EchoKey 每次启动都崩溃，用户会陷入“启动—崩溃”的循环。
+//! 每次启动时把计数文件中的启动次数加一，稳定运行一分钟或正常退出后清零；
+//! 连续多次启动都没有清零，说明启动后很快就崩溃了，下次启动进入安全模式。
+//!
+//! 安全模式下不安装键盘钩子、不自动启用开机自启动，只显示界面，
+//! 用户可以在设置中修改配置或关闭开机自启动。
+//!
+//! 手动进入安全模式：在命令行中运行 `echokey.exe --safe-mode`。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config;
+
+/// 手动进入安全模式的命令行参数
+pub const SAFE_MODE_ARG: &str = "--safe-mode";
+
+/// 连续多少次启动后没有稳定运行，下次启动进入安全模式
+pub const CRASH_LIMIT: u32 = 3;
+
+/// 稳定运行多久后清零启动计数
+pub const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// 本次是否通过命令行参数要求进入安全模式
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == SAFE_MODE_ARG)
+}
+
+/// 启动计数文件路径
+fn crash_count_path() -> PathBuf {
+    config::get_data_directory().join("crash_count")
+}
+
+/// 记录一次启动，返回本次之前连续未稳定运行的启动次数
+pub fn record_start() -> u32 {
+    record_start_at(&crash_count_path())
+}
+
+/// 稳定运行或正常退出后清零启动计数
+pub fn clear() {
+    let _ = fs::remove_file(crash_count_path());
+}
+
+fn record_start_at(path: &Path) -> u32 {
+    let previous = fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0u32);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Err(e) = fs::write(path, previous.saturating_add(1).to_string()) {
+        eprintln!("警告: 无法写入启动计数: {}", e);
+    }
+    previous
+}
+
+/// 本次是否进入安全模式：命令行要求，或之前连续多次启动都很快崩溃
+pub fn should_enter(requested: bool, previous_starts: u32) -> bool {
+    requested || previous_starts >= CRASH_LIMIT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_starts_enter_safe_mode() {
+        let dir = std::env::temp_dir()
+            .join(format!("echokey-safe-mode-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("crash_count");
+
+        let starts: Vec<u32> = (0..4).map(|_| record_start_at(&path)).collect();
+        assert_eq!(starts, vec![0, 1, 2, 3]);
+        assert!(!should_enter(false, starts[2]));
+        assert!(should_enter(false, starts[3]));
+        assert!(should_enter(true, 0));
+
+        // 损坏的计数文件按 0 处理
+        fs::write(&path, "garbage").unwrap();
+        assert_eq!(record_start_at(&path), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,95 @@
+//! 会话锁定通知模块（Windows）
+//!
+//! 通过 WTSRegisterSessionNotification 接收锁屏（Win+L）和解锁通知。
+//! 通知以窗口消息的形式发送，因此在键盘监听线程上创建一个仅消息窗口，
+//! 与键盘钩子共用同一个消息循环。
+
+use once_cell::sync::OnceCell;
+use windows::core::w;
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::RemoteDesktop::{
+    WTSRegisterSessionNotification, WTSUnRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, RegisterClassW, HWND_MESSAGE,
+    WINDOW_EX_STYLE, WINDOW_STYLE, WM_WTSSESSION_CHANGE, WNDCLASSW,
+    WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+};
+
+/// 锁定状态变化时的回调，参数为是否已锁定
+static ON_CHANGE: OnceCell<fn(bool)> = OnceCell::new();
+
+/// 已注册的会话通知，释放时注销并销毁消息窗口
+pub struct SessionNotifier {
+    hwnd: HWND,
+}
+
+/// 在当前线程上开始接收锁屏/解锁通知
+///
+/// 当前线程必须运行消息循环，回调在消息循环中执行。
+pub fn register(on_change: fn(bool)) -> Result<SessionNotifier, String> {
+    let _ = ON_CHANGE.set(on_change);
+    unsafe {
+        let instance: HINSTANCE = GetModuleHandleW(None)
+            .map_err(|e| format!("无法获取模块句柄: {:?}", e))?
+            .into();
+        let class_name = w!("EchoKeySessionNotify");
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance,
+            lpszClassName: class_name,
+            ..Default::default()
+        };
+        if RegisterClassW(&class) == 0 {
+            return Err("无法注册会话通知窗口类".to_string());
+        }
+
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            class_name,
+            w!(""),
+            WINDOW_STYLE(0),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            instance,
+            None,
+        )
+        .map_err(|e| format!("无法创建会话通知窗口: {:?}", e))?;
+
+        if let Err(e) = WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) {
+            let _ = DestroyWindow(hwnd);
+            return Err(format!("无法注册会话通知: {:?}", e));
+        }
+        Ok(SessionNotifier { hwnd })
+    }
+}
+
+impl Drop for SessionNotifier {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = WTSUnRegisterSessionNotification(self.hwnd);
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+/// 消息窗口的窗口过程：只处理会话变化通知
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg == WM_WTSSESSION_CHANGE {
+        let locked = match wparam.0 as u32 {
+            WTS_SESSION_LOCK => Some(true),
+            WTS_SESSION_UNLOCK => Some(false),
+            _ => None,
+        };
+        if let (Some(locked), Some(on_change)) = (locked, ON_CHANGE.get()) {
+            on_change(locked);
+        }
+        return LRESULT(0);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
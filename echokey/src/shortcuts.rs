@@ -0,0 +1,460 @@
+//! 快捷键模块
+//!
+//! 定义快捷键表和匹配规则。键盘钩子只负责读取修饰键状态，
+//! 具体触发哪个动作由这里决定，与平台无关，便于测试和配置。
+//!
+//! 匹配规则：
+//! - 按下的修饰键必须与绑定完全一致（多按了其他修饰键时不触发）
+//! - 同一组合绑定了多个动作时，选择表中靠前的绑定
+//!
+//! 例如只绑定了 Ctrl+V 时，按下 Ctrl+Shift+V 不会触发它，
+//! 按键照常交给其他程序处理。
+//!
+//! 钩子收到的重复按键事件也在这里去重（见 [`KeyDedup`]），
+//! 死键与下一个字符的组合也在这里完成（见 [`compose_dead_key`]）。
+
+use std::time::{Duration, Instant};
+use unicode_normalization::UnicodeNormalization;
+use crate::i18n;
+
+/// 快捷键可以触发的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutAction {
+    /// 粘贴（记录剪贴板内容）
+    Paste,
+    /// 手动保存剪贴板
+    ManualSave,
+    /// 暂停/恢复记录
+    TogglePause,
+    /// 新建日志段
+    NewSegment,
+    /// 插入检查点
+    Checkpoint,
+    /// 开始/结束专注时段
+    ToggleFocus,
+}
+
+impl ShortcutAction {
+    /// 动作的说明（当前界面语言）
+    pub fn label(self) -> &'static str {
+        i18n::t(match self {
+            Self::Paste => "shortcut.paste",
+            Self::ManualSave => "shortcut.manual_save",
+            Self::TogglePause => "shortcut.toggle_pause",
+            Self::NewSegment => "shortcut.new_segment",
+            Self::Checkpoint => "shortcut.checkpoint",
+            Self::ToggleFocus => "shortcut.toggle_focus",
+        })
+    }
+}
+
+/// 修饰键状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// Windows 徽标键
+    pub win: bool,
+}
+
+impl Modifiers {
+    /// 按下的修饰键数量
+    pub fn count(&self) -> usize {
+        [self.ctrl, self.shift, self.alt, self.win].iter().filter(|&&m| m).count()
+    }
+}
+
+/// 按键组合：修饰键 + 虚拟键码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    /// Windows 虚拟键码（字母键即大写字母的 ASCII 码）
+    pub vk: u32,
+}
+
+impl Chord {
+    /// Ctrl + 字母键
+    pub const fn ctrl(letter: u8) -> Self {
+        Self {
+            modifiers: Modifiers { ctrl: true, shift: false, alt: false, win: false },
+            vk: letter as u32,
+        }
+    }
+
+    /// Ctrl + Shift + 字母键
+    pub const fn ctrl_shift(letter: u8) -> Self {
+        Self {
+            modifiers: Modifiers { ctrl: true, shift: true, alt: false, win: false },
+            vk: letter as u32,
+        }
+    }
+
+    /// 解析 `Ctrl+Shift+P` 形式的按键组合（不区分大小写）
+    ///
+    /// 键名与 [`key_name`] 相同，例如 `F2`、`Space`、`VK_BA`。
+    /// 至少要有一个修饰键，否则每次按下这个键都会触发，返回 None。
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut modifiers = Modifiers::default();
+        let mut vk = None;
+        for part in spec.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "win" => modifiers.win = true,
+                _ if vk.is_none() => {
+                    vk = Some((1..=0xFE).find(|&vk| !is_modifier_vk(vk) && key_name(vk).eq_ignore_ascii_case(part))?);
+                }
+                _ => return None,
+            }
+        }
+        let vk = vk?;
+        (modifiers.count() > 0).then_some(Self { modifiers, vk })
+    }
+
+    /// 可读名称，如 `Ctrl+Shift+P`
+    pub fn name(&self) -> String {
+        combo_name(self.modifiers, self.vk)
+    }
+}
+
+/// 一条快捷键绑定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub chord: Chord,
+    pub action: ShortcutAction,
+}
+
+/// 默认快捷键表
+pub fn default_bindings() -> Vec<Binding> {
+    vec![
+        Binding { chord: Chord::ctrl(b'V'), action: ShortcutAction::Paste },
+        Binding { chord: Chord::ctrl_shift(b'S'), action: ShortcutAction::ManualSave },
+        Binding { chord: Chord::ctrl_shift(b'P'), action: ShortcutAction::TogglePause },
+        Binding { chord: Chord::ctrl_shift(b'N'), action: ShortcutAction::NewSegment },
+        Binding { chord: Chord::ctrl_shift(b'K'), action: ShortcutAction::Checkpoint },
+    ]
+}
+
+/// 动作的默认按键组合
+///
+/// 开始/结束专注时段默认没有快捷键（常见的组合都与编辑器、浏览器的快捷键冲突），
+/// 可以在配置文件中设置。
+pub fn default_chord(action: ShortcutAction) -> Option<Chord> {
+    default_bindings().into_iter()
+        .find(|b| b.action == action)
+        .map(|b| b.chord)
+}
+
+/// 虚拟键码的可读名称（用于 `[Win+L]` 这类组合键记录）
+pub fn key_name(vk: u32) -> String {
+    match vk {
+        0x30..=0x39 | 0x41..=0x5A => char::from(vk as u8).to_string(),
+        0x09 => "Tab".to_string(),
+        0x1B => "Esc".to_string(),
+        0x20 => "Space".to_string(),
+        0x21 => "PageUp".to_string(),
+        0x22 => "PageDown".to_string(),
+        0x23 => "End".to_string(),
+        0x24 => "Home".to_string(),
+        0x25 => "Left".to_string(),
+        0x26 => "Up".to_string(),
+        0x27 => "Right".to_string(),
+        0x28 => "Down".to_string(),
+        0x2D => "Insert".to_string(),
+        0x2E => "Delete".to_string(),
+        0x70..=0x87 => format!("F{}", vk - 0x6F),
+        _ => format!("VK_{:02X}", vk),
+    }
+}
+
+/// 原始按键记录中的按键名称：在 [`key_name`] 之外区分左右修饰键，并给出 Enter 等按键的名称
+pub fn raw_key_name(vk: u32) -> String {
+    let name = match vk {
+        0x08 => "Backspace",
+        0x0D => "Enter",
+        0x10 => "Shift",
+        0x11 => "Ctrl",
+        0x12 => "Alt",
+        0x13 => "Pause",
+        0x14 => "CapsLock",
+        0x2C => "PrintScreen",
+        0x5B => "LWin",
+        0x5C => "RWin",
+        0x5D => "Menu",
+        0xA0 => "LShift",
+        0xA1 => "RShift",
+        0xA2 => "LCtrl",
+        0xA3 => "RCtrl",
+        0xA4 => "LAlt",
+        0xA5 => "RAlt",
+        _ => return key_name(vk),
+    };
+    name.to_string()
+}
+
+/// 是否为修饰键本身（Shift、Ctrl、Alt、Win，含左右键）
+pub fn is_modifier_vk(vk: u32) -> bool {
+    matches!(vk, 0x10..=0x12 | 0x5B | 0x5C | 0xA0..=0xA5)
+}
+
+/// 组合键的可读名称，如 `Ctrl+Shift+C`（用于详细模式记录）
+pub fn combo_name(modifiers: Modifiers, vk: u32) -> String {
+    let mut name = String::new();
+    for (pressed, label) in [
+        (modifiers.ctrl, "Ctrl+"),
+        (modifiers.alt, "Alt+"),
+        (modifiers.shift, "Shift+"),
+        (modifiers.win, "Win+"),
+    ] {
+        if pressed {
+            name.push_str(label);
+        }
+    }
+    name.push_str(&key_name(vk));
+    name
+}
+
+/// 按键是否通常会产生字符（字母、数字、空格、小键盘和 OEM 符号键）
+///
+/// 用于判断转换不出字符的按键是否属于键盘布局的缺口。
+pub fn is_printable_vk(vk: u32) -> bool {
+    matches!(vk, 0x20 | 0x30..=0x39 | 0x41..=0x5A | 0x60..=0x6F | 0xBA..=0xC0 | 0xDB..=0xDF | 0xE1..=0xE2)
+}
+
+/// 字母键（A–Z）：输入法处于本地语言模式时由它们开始组字
+pub fn is_letter_vk(vk: u32) -> bool {
+    matches!(vk, 0x41..=0x5A)
+}
+
+/// 死键产生的重音符号（空格形式）对应的组合附加符号
+fn combining_mark(accent: char) -> Option<char> {
+    Some(match accent {
+        '`' => '\u{300}',
+        '´' | '\'' => '\u{301}',
+        '^' | 'ˆ' => '\u{302}',
+        '~' | '˜' => '\u{303}',
+        '¯' => '\u{304}',
+        '˘' => '\u{306}',
+        '˙' => '\u{307}',
+        '¨' | '"' => '\u{308}',
+        '˚' | '°' => '\u{30A}',
+        '˝' => '\u{30B}',
+        'ˇ' => '\u{30C}',
+        '¸' => '\u{327}',
+        '˛' => '\u{328}',
+        _ => return None,
+    })
+}
+
+/// 死键与下一个字符组合后输出的文字
+///
+/// 与 Windows 的行为一致：能合成带重音的字符时输出合成的字符（`´` + `e` → `é`）；
+/// 下一个是空格时只输出重音符号本身；不能合成时（`´` + `x`、连按两个死键）两者都按原样输出。
+pub fn compose_dead_key(accent: char, next: char) -> String {
+    if next == ' ' {
+        return accent.to_string();
+    }
+    if let Some(mark) = combining_mark(accent) {
+        let composed: String = [next, mark].into_iter().nfc().collect();
+        if composed.chars().count() == 1 {
+            return composed;
+        }
+    }
+    [accent, next].into_iter().collect()
+}
+
+/// 按键去重的默认时间窗口（毫秒）
+pub const DEFAULT_KEY_DEDUP_MS: u64 = 30;
+
+/// 按键事件去重：同一按键在时间窗口内重复到达时只处理第一次
+///
+/// 比较虚拟键码、扫描码和修饰键状态。`a` 之后很快按下 Shift+`a`
+/// 是大小写不同的两次输入，修饰键状态不同，不会被当作重复丢弃。
+#[derive(Debug, Default)]
+pub struct KeyDedup {
+    /// 上一次处理的按键：虚拟键码、扫描码、修饰键状态、时间
+    last: Option<(u32, u32, Modifiers, Instant)>,
+}
+
+impl KeyDedup {
+    /// 是否处理这次按键（处理时记为上一次按键）
+    pub fn should_process(&mut self, vk: u32, scan: u32, modifiers: Modifiers, now: Instant, window: Duration) -> bool {
+        let duplicate = self.last.is_some_and(|(last_vk, last_scan, last_modifiers, time)| {
+            last_vk == vk
+                && last_scan == scan
+                && last_modifiers == modifiers
+                && now.saturating_duration_since(time) < window
+        });
+        if !duplicate {
+            self.last = Some((vk, scan, modifiers, now));
+        }
+        !duplicate
+    }
+}
+
+/// 在快捷键表中查找与当前按键匹配的动作
+///
+/// 修饰键必须完全一致（Ctrl+Shift+V 不会触发绑定在 Ctrl+V 上的动作）；
+/// 同一组合绑定了多个动作时靠前的优先，没有匹配时返回 None
+pub fn match_shortcut(bindings: &[Binding], pressed: Modifiers, vk: u32) -> Option<ShortcutAction> {
+    bindings.iter()
+        .find(|b| b.chord.vk == vk && b.chord.modifiers == pressed)
+        .map(|b| b.action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_dead_key() {
+        assert_eq!(compose_dead_key('´', 'e'), "é");
+        assert_eq!(compose_dead_key('^', 'O'), "Ô");
+        assert_eq!(compose_dead_key('¨', 'u'), "ü");
+        assert_eq!(compose_dead_key('~', 'n'), "ñ");
+        assert_eq!(compose_dead_key('¸', 'c'), "ç");
+        // 空格只输出重音符号；不能合成时按原样输出
+        assert_eq!(compose_dead_key('´', ' '), "´");
+        assert_eq!(compose_dead_key('´', 'x'), "´x");
+        assert_eq!(compose_dead_key('`', '´'), "`´");
+    }
+
+    const CTRL: Modifiers = Modifiers { ctrl: true, shift: false, alt: false, win: false };
+    const CTRL_SHIFT: Modifiers = Modifiers { ctrl: true, shift: true, alt: false, win: false };
+
+    /// 同一个键、修饰键不同的两个绑定
+    fn overlapping() -> Vec<Binding> {
+        vec![
+            Binding { chord: Chord::ctrl(b'S'), action: ShortcutAction::Paste },
+            Binding { chord: Chord::ctrl_shift(b'S'), action: ShortcutAction::ManualSave },
+        ]
+    }
+
+    #[test]
+    fn test_same_key_matches_exact_modifiers() {
+        let bindings = overlapping();
+        assert_eq!(match_shortcut(&bindings, CTRL_SHIFT, b'S' as u32), Some(ShortcutAction::ManualSave));
+        assert_eq!(match_shortcut(&bindings, CTRL, b'S' as u32), Some(ShortcutAction::Paste));
+    }
+
+    #[test]
+    fn test_exact_modifier_match_ignores_binding_order() {
+        let mut bindings = overlapping();
+        bindings.reverse();
+        assert_eq!(match_shortcut(&bindings, CTRL_SHIFT, b'S' as u32), Some(ShortcutAction::ManualSave));
+        assert_eq!(match_shortcut(&bindings, CTRL, b'S' as u32), Some(ShortcutAction::Paste));
+    }
+
+    #[test]
+    fn test_duplicate_chord_prefers_first() {
+        let bindings = vec![
+            Binding { chord: Chord::ctrl_shift(b'P'), action: ShortcutAction::TogglePause },
+            Binding { chord: Chord::ctrl_shift(b'P'), action: ShortcutAction::NewSegment },
+        ];
+        assert_eq!(match_shortcut(&bindings, CTRL_SHIFT, b'P' as u32), Some(ShortcutAction::TogglePause));
+    }
+
+    #[test]
+    fn test_dedup_keeps_key_across_modifier_change() {
+        const VK_A: u32 = 0x41;
+        const VK_LSHIFT: u32 = 0xA0;
+        let shift = Modifiers { shift: true, ..Default::default() };
+        let window = Duration::from_millis(30);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut dedup = KeyDedup::default();
+        assert!(dedup.should_process(VK_A, 0x1E, Modifiers::default(), at(0), window));
+        assert!(dedup.should_process(VK_LSHIFT, 0x2A, shift, at(5), window));
+        assert!(dedup.should_process(VK_A, 0x1E, shift, at(10), window));
+        // 修饰键相同的重复事件仍然丢弃
+        assert!(!dedup.should_process(VK_A, 0x1E, shift, at(15), window));
+        // 没有收到 Shift 按下事件，只是修饰键状态变了，也不算重复
+        assert!(dedup.should_process(VK_A, 0x1E, Modifiers::default(), at(20), window));
+        assert!(dedup.should_process(VK_A, 0x1E, Modifiers::default(), at(60), window));
+    }
+
+    #[test]
+    fn test_dedup_window_boundary() {
+        const VK_A: u32 = 0x41;
+        let none = Modifiers::default();
+        let window = Duration::from_millis(DEFAULT_KEY_DEDUP_MS);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut dedup = KeyDedup::default();
+        assert!(dedup.should_process(VK_A, 0x1E, none, at(0), window));
+        assert!(!dedup.should_process(VK_A, 0x1E, none, at(29), window));
+        // 间隔恰好等于窗口时不算重复；丢弃的事件不刷新时间
+        assert!(dedup.should_process(VK_A, 0x1E, none, at(30), window));
+        assert!(!dedup.should_process(VK_A, 0x1E, none, at(59), window));
+        assert!(dedup.should_process(VK_A, 0x1E, none, at(60), window));
+
+        // 窗口为 0 时不去重
+        assert!(dedup.should_process(VK_A, 0x1E, none, at(60), Duration::ZERO));
+    }
+
+    #[test]
+    fn test_key_name() {
+        assert_eq!(key_name(b'L' as u32), "L");
+        assert_eq!(key_name(0x09), "Tab");
+        assert_eq!(key_name(0x71), "F2");
+        assert_eq!(key_name(0xBA), "VK_BA");
+        assert_eq!(raw_key_name(0xA0), "LShift");
+        assert_eq!(raw_key_name(0x0D), "Enter");
+        assert_eq!(raw_key_name(b'L' as u32), "L");
+    }
+
+    #[test]
+    fn test_combo_name() {
+        assert_eq!(combo_name(CTRL_SHIFT, b'C' as u32), "Ctrl+Shift+C");
+        assert_eq!(combo_name(Modifiers::default(), 0x2E), "Delete");
+        assert!(is_modifier_vk(0xA2));
+        assert!(!is_modifier_vk(b'C' as u32));
+    }
+
+    #[test]
+    fn test_printable_keys() {
+        assert!(is_printable_vk(b'A' as u32));
+        assert!(is_printable_vk(0xBA));
+        assert!(is_printable_vk(0xE2));
+        assert!(!is_printable_vk(0x09));
+        assert!(!is_printable_vk(0x70));
+        assert!(is_letter_vk(b'Z' as u32));
+        assert!(!is_letter_vk(b'1' as u32));
+        assert!(!is_letter_vk(0x20));
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        assert_eq!(Chord::parse("Ctrl+Shift+P"), Some(Chord::ctrl_shift(b'P')));
+        assert_eq!(Chord::parse(" ctrl + v "), Some(Chord::ctrl(b'V')));
+        let alt_f2 = Chord::parse("Alt+F2").unwrap();
+        assert_eq!((alt_f2.modifiers.alt, alt_f2.vk), (true, 0x71));
+        assert_eq!(alt_f2.name(), "Alt+F2");
+        // 没有修饰键、没有按键、多个按键或无法识别的键名
+        assert_eq!(Chord::parse("P"), None);
+        assert_eq!(Chord::parse("Ctrl+Shift"), None);
+        assert_eq!(Chord::parse("Ctrl+P+Q"), None);
+        assert_eq!(Chord::parse("Ctrl+Hyper"), None);
+        assert_eq!(Chord::parse(""), None);
+    }
+
+    #[test]
+    fn test_missing_modifier_does_not_match() {
+        let bindings = default_bindings();
+        assert_eq!(match_shortcut(&bindings, CTRL, b'P' as u32), None);
+        assert_eq!(match_shortcut(&bindings, Modifiers::default(), b'V' as u32), None);
+    }
+
+    #[test]
+    fn test_extra_modifier_does_not_match() {
+        let bindings = default_bindings();
+        assert_eq!(match_shortcut(&bindings, CTRL, b'V' as u32), Some(ShortcutAction::Paste));
+        assert_eq!(match_shortcut(&bindings, CTRL_SHIFT, b'V' as u32), None);
+        let ctrl_alt = Modifiers { ctrl: true, shift: false, alt: true, win: false };
+        assert_eq!(match_shortcut(&bindings, ctrl_alt, b'V' as u32), None);
+    }
+}
@@ -0,0 +1,316 @@
+//! 输入统计模块
+//!
+//! 从日志文件统计每天的输入量（字符数、词数、粘贴次数、活跃分钟数、
+//! 每小时分布），并导出为 CSV 供表格软件分析。
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use chrono::NaiveDate;
+
+use crate::{i18n, logger};
+
+/// 一天的输入统计
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    /// 输入和粘贴的字符数（不含时间戳、标记和退格、方向键等编辑键的记号）
+    pub chars: usize,
+    /// 词数：每个汉字算一个词，连续的字母数字算一个词
+    pub words: usize,
+    /// 粘贴次数（合并记录的 `[粘贴 ×N]` 按 N 次计，包括文件、图片等非文本粘贴）
+    pub pastes: usize,
+    /// 有输入的分钟数
+    pub active_minutes: usize,
+    /// 每小时的字符数（按所在时间戳行的小时归类）
+    pub hourly_chars: [usize; 24],
+}
+
+impl DayStats {
+    /// 没有任何输入的一天
+    pub fn empty(date: NaiveDate) -> Self {
+        Self {
+            date,
+            chars: 0,
+            words: 0,
+            pastes: 0,
+            active_minutes: 0,
+            hourly_chars: [0; 24],
+        }
+    }
+}
+
+/// 统计一天的日志内容
+///
+/// 只统计用户输入和粘贴的内容；状态标记、手动保存、摘录等不计入。
+/// 没有时间戳的行（Ctrl+Enter 续行、多行粘贴的后续行）归入上一个时间戳行。
+pub fn day_stats(date: NaiveDate, content: &str) -> DayStats {
+    let mut stats = DayStats::empty(date);
+    let mut minutes = HashSet::new();
+    // 当前时间戳行的小时，None 表示当前条目不计入统计
+    let mut hour: Option<usize> = None;
+
+    for line in content.lines() {
+        // 原始按键记录的记号（`[↓LShift]`）不是输入的文字
+        let line = logger::strip_raw_key_markers(line);
+        let line = line.as_ref();
+        // 时间戳后的窗口标题（`«标题»`）不是输入的文字
+        let text = match logger::split_timestamp(line).map(|(time, text)| (time, logger::split_window_title(text).1)) {
+            Some((_, "")) => {
+                hour = None;
+                continue;
+            }
+            Some((time, text)) => {
+                hour = None;
+                let (tag, body) = split_tag(text);
+                let counted = match tag {
+                    None => !logger::is_marker_text(text),
+                    Some(tag) => match paste_count(tag) {
+                        Some(count) => {
+                            stats.pastes += count;
+                            true
+                        }
+                        None => {
+                            // 文件、图片等非文本粘贴只计次数，不计字符
+                            if is_other_paste(tag) {
+                                stats.pastes += 1;
+                            }
+                            false
+                        }
+                    },
+                };
+                if !counted {
+                    continue;
+                }
+                hour = time[..2].parse().ok().filter(|h| *h < 24);
+                minutes.insert(time[..5].to_string());
+                body
+            }
+            None if logger::is_header_line(line) => continue,
+            None => line.strip_prefix("          ").unwrap_or(line),
+        };
+
+        let Some(hour) = hour else {
+            continue;
+        };
+        let chars = text.chars().filter(|c| !logger::EDIT_KEY_MARKERS.contains(c)).count();
+        stats.chars += chars;
+        stats.hourly_chars[hour] += chars;
+        stats.words += count_words(text);
+    }
+
+    stats.active_minutes = minutes.len();
+    stats
+}
+
+/// 日志写入器写出的条目标签（`[粘贴] 内容` 等），粘贴标签另见 [`paste_count`]
+const ENTRY_TAGS: &[&str] = &[
+    "marker.paste_files",
+    "marker.paste_image",
+    "marker.paste_unknown",
+    "marker.copy",
+    "marker.manual_save",
+    "marker.checkpoint",
+    "marker.accumulated",
+    "marker.excerpt",
+    "marker.verify",
+];
+
+/// 拆分行首的条目标签，返回 (标签, 其后的内容)
+///
+/// 只认日志写入器写出的标签（任意语言）；行首是其他方括号内容时
+/// （输入的 `[TODO]`、`[Win+L]`、`[未知键:VK_xx]` 等）整行按输入的文字处理。
+fn split_tag(text: &str) -> (Option<&str>, &str) {
+    text.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .filter(|(tag, _)| is_entry_tag(tag))
+        .map(|(tag, body)| (Some(tag), body.strip_prefix(' ').unwrap_or(body)))
+        .unwrap_or((None, text))
+}
+
+/// 是否为日志写入器写出的条目标签
+fn is_entry_tag(tag: &str) -> bool {
+    paste_count(tag).is_some()
+        || i18n::Locale::ALL.iter().any(|&locale| {
+            ENTRY_TAGS.iter().any(|key| i18n::translate(locale, key) == tag)
+        })
+}
+
+/// 标签为粘贴标记（任意语言）时返回粘贴次数
+fn paste_count(tag: &str) -> Option<usize> {
+    i18n::Locale::ALL.iter().find_map(|&locale| {
+        let rest = tag.strip_prefix(i18n::translate(locale, "marker.paste"))?;
+        if rest.is_empty() {
+            return Some(1);
+        }
+        rest.strip_prefix(" ×")?.parse().ok()
+    })
+}
+
+/// 标签是否为非文本粘贴（文件、图片、未知格式）的标记
+fn is_other_paste(tag: &str) -> bool {
+    i18n::Locale::ALL.iter().any(|&locale| {
+        ["marker.paste_files", "marker.paste_image", "marker.paste_unknown"]
+            .iter()
+            .any(|key| i18n::translate(locale, key) == tag)
+    })
+}
+
+/// 统计词数：每个汉字（及其他 CJK 字符）算一个词，连续的字母数字算一个词
+fn count_words(text: &str) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            words += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                words += 1;
+            }
+            in_word = true;
+        } else {
+            in_word = false;
+        }
+    }
+    words
+}
+
+/// 是否为中日韩文字（按字计词）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}'   // 扩展 A
+        | '\u{4E00}'..='\u{9FFF}'   // 基本汉字
+        | '\u{AC00}'..='\u{D7AF}'   // 韩文音节
+        | '\u{F900}'..='\u{FAFF}'   // 兼容汉字
+        | '\u{20000}'..='\u{2FA1F}' // 扩展 B 及以后
+    )
+}
+
+/// 把统计导出为 CSV 文件，返回写入的行数（不含表头）
+///
+/// 日期范围可以跨月、跨年；`include_empty` 为 true 时没有日志的日期输出全零行，
+/// 否则跳过这些日期。
+pub fn export_csv(range: RangeInclusive<NaiveDate>, out_path: &Path, include_empty: bool) -> io::Result<usize> {
+    let mut writer = BufWriter::new(File::create(out_path)?);
+    let rows = write_csv(&mut writer, range, include_empty, logger::read_day)?;
+    writer.flush()?;
+    Ok(rows)
+}
+
+/// 写出 CSV 内容，`read_day` 读取某一天的日志（没有日志时返回 None）
+fn write_csv(
+    out: &mut impl Write,
+    range: RangeInclusive<NaiveDate>,
+    include_empty: bool,
+    read_day: impl Fn(NaiveDate) -> Option<String>,
+) -> io::Result<usize> {
+    let mut header: Vec<String> = ["date", "chars", "words", "pastes", "active_minutes"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    header.extend((0..24).map(|h| format!("h{:02}", h)));
+    write_csv_row(out, &header)?;
+
+    let (start, end) = range.into_inner();
+    let mut rows = 0;
+    for date in start.iter_days().take_while(|d| *d <= end) {
+        let stats = match read_day(date) {
+            Some(content) => day_stats(date, &content),
+            None if include_empty => DayStats::empty(date),
+            None => continue,
+        };
+
+        let mut fields = vec![
+            stats.date.format("%Y-%m-%d").to_string(),
+            stats.chars.to_string(),
+            stats.words.to_string(),
+            stats.pastes.to_string(),
+            stats.active_minutes.to_string(),
+        ];
+        fields.extend(stats.hourly_chars.iter().map(|n| n.to_string()));
+        write_csv_row(out, &fields)?;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+/// 写出一行 CSV（RFC 4180：含逗号、引号或换行的字段加引号，引号加倍）
+fn write_csv_row(out: &mut impl Write, fields: &[String]) -> io::Result<()> {
+    let line: Vec<String> = fields.iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    write!(out, "{}\r\n", line.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_stats() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let content = "\
+================== EchoKey 日志 ==================
+日期：2024-01-05
+==================================================
+[09:00:01] 你好 hello world⌫⌦←→↑↓⇤⇥\t
+          续行
+[09:00:30] --- 暂停记录 ---
+[09:05:00] [粘贴] ab
+cd
+[09:06:00] [粘贴 ×2]
+[09:07:00] [粘贴文件] C:\\a.txt
+[14:00:00] [手动保存] 不计入
+[14:00:05] [TODO] 写完
+[15:00:00] [↓LShift][↑LShift]
+[15:00:01] «记事本» [↓A]a[↑A]
+";
+        let stats = day_stats(date, content);
+        let todo = "[TODO] 写完".chars().count();
+        assert_eq!(stats.chars, "你好 hello world".chars().count() + 2 + 4 + todo + 1);
+        assert_eq!(stats.words, 2 + 2 + 2 + 1 + 1 + 3 + 1);
+        assert_eq!(stats.pastes, 4);
+        assert_eq!(stats.active_minutes, 5);
+        assert_eq!(stats.hourly_chars[9], stats.chars - todo - 1);
+        assert_eq!(stats.hourly_chars[15], 1);
+        assert_eq!(stats.hourly_chars[14], "[TODO] 写完".chars().count());
+    }
+
+    #[test]
+    fn test_write_csv_spans_months_and_empty_days() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 30).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 2, 2).unwrap();
+        let with_log = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let read = |date| (date == with_log).then(|| "[10:00:00] abc\n".to_string());
+
+        let mut out = Vec::new();
+        assert_eq!(write_csv(&mut out, start..=end, true, read).unwrap(), 4);
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("date,chars,words,pastes,active_minutes,h00,"));
+        assert!(lines[1].starts_with("2024-01-30,0,0,0,0,"));
+        assert!(lines[3].starts_with("2024-02-01,3,1,0,1,"));
+        assert_eq!(lines[3].split(',').nth(5 + 10), Some("3"));
+
+        let mut out = Vec::new();
+        assert_eq!(write_csv(&mut out, start..=end, false, read).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let mut out = Vec::new();
+        let fields = ["a,b".to_string(), "say \"hi\"".to_string(), "x\ny".to_string(), "plain".to_string()];
+        write_csv_row(&mut out, &fields).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\"a,b\",\"say \"\"hi\"\"\",\"x\ny\",plain\r\n");
+    }
+}
@@ -0,0 +1,156 @@
+//! 实时预览模块
+//!
+//! 增量读取当前日志文件的新增内容，供 GUI 显示最近输入的文字。
+//! 只读取上次位置之后追加的字节，不会每次重新读取整个文件。
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// 预览中保留的最大字符数
+pub const MAX_TAIL_CHARS: usize = 4000;
+
+/// 日志文件的增量读取器
+#[derive(Debug, Default)]
+pub struct LogTail {
+    /// 当前跟踪的文件
+    path: Option<PathBuf>,
+    /// 已读取到的字节位置
+    position: u64,
+    /// 最近的内容
+    content: String,
+    /// 上次读取末尾不完整的 UTF-8 序列（写入方还没写完一个多字节字符），留到下次读取
+    carry: Vec<u8>,
+}
+
+impl LogTail {
+    /// 创建空的读取器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取文件的新增内容
+    ///
+    /// 文件切换（新日志段、跨天）或被截断时从头读取。返回内容是否有变化。
+    pub fn poll(&mut self, path: &Path) -> io::Result<bool> {
+        if self.path.as_deref() != Some(path) {
+            self.path = Some(path.to_path_buf());
+            self.position = 0;
+            self.content.clear();
+            self.carry.clear();
+        }
+
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+
+        if len < self.position {
+            // 文件被截断或替换
+            self.position = 0;
+            self.content.clear();
+            self.carry.clear();
+        }
+        if len == self.position {
+            return Ok(false);
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut bytes = std::mem::take(&mut self.carry);
+        let read = file.take(len - self.position).read_to_end(&mut bytes)?;
+        self.position += read as u64;
+
+        let complete = complete_utf8_len(&bytes);
+        self.carry = bytes.split_off(complete);
+        if bytes.is_empty() {
+            return Ok(false);
+        }
+        self.content.push_str(&String::from_utf8_lossy(&bytes));
+        self.trim();
+
+        Ok(true)
+    }
+
+    /// 最近的内容
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// 只保留最后 MAX_TAIL_CHARS 个字符（尽量从整行开始）
+    fn trim(&mut self) {
+        let count = self.content.chars().count();
+        if count <= MAX_TAIL_CHARS {
+            return;
+        }
+
+        let cut = self.content.char_indices()
+            .nth(count - MAX_TAIL_CHARS)
+            .map_or(0, |(i, _)| i);
+        let start = self.content[cut..].find('\n')
+            .map_or(cut, |i| cut + i + 1);
+        self.content.drain(..start);
+    }
+}
+
+/// 去掉末尾不完整的 UTF-8 序列后的长度
+///
+/// 只检查最后几个字节：末尾是一个多字节字符的开头部分时，返回它开始的位置；
+/// 其他情况（包括真正无效的字节）返回整个长度，交给有损解码处理。
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    let len = bytes.len();
+    for back in 1..=len.min(3) {
+        let byte = bytes[len - back];
+        if byte & 0xC0 == 0x80 {
+            // 后续字节，继续向前找首字节
+            continue;
+        }
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if needed > back { len - back } else { len };
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    #[test]
+    fn test_multibyte_char_split_across_reads() {
+        let path = std::env::temp_dir()
+            .join(format!("echokey-tail-{}.log", std::process::id()));
+        fs::write(&path, "[10:00:00] a").unwrap();
+        let mut tail = LogTail::new();
+        assert!(tail.poll(&path).unwrap());
+
+        // “你”的三个字节分两次写入
+        let bytes = "你".as_bytes();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&bytes[..2]).unwrap();
+        assert!(!tail.poll(&path).unwrap());
+        assert_eq!(tail.content(), "[10:00:00] a");
+
+        file.write_all(&bytes[2..]).unwrap();
+        file.write_all("好".as_bytes()).unwrap();
+        assert!(tail.poll(&path).unwrap());
+        assert_eq!(tail.content(), "[10:00:00] a你好");
+        assert!(!tail.content().contains('\u{FFFD}'));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_complete_utf8_len() {
+        let emoji = "😀".as_bytes();
+        assert_eq!(complete_utf8_len(b"abc"), 3);
+        assert_eq!(complete_utf8_len(&emoji[..1]), 0);
+        assert_eq!(complete_utf8_len(&emoji[..3]), 0);
+        assert_eq!(complete_utf8_len(emoji), 4);
+        // 无效字节不推迟
+        assert_eq!(complete_utf8_len(&[b'a', 0x80]), 2);
+    }
+}
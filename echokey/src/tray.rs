@@ -6,10 +6,12 @@
 use std::sync::{Arc, Mutex, mpsc};
 use tray_icon::{
     TrayIcon, TrayIconBuilder,
-    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem},
     Icon,
 };
 
+use crate::i18n;
+
 /// 托盘事件
 #[derive(Debug, Clone)]
 pub enum TrayEvent {
@@ -21,6 +23,10 @@ pub enum TrayEvent {
     NewSegment,
     /// 打开日志目录
     OpenLogDir,
+    /// 开启/关闭本次运行的详细记录模式
+    ToggleVerbose,
+    /// 开启/关闭累加剪贴板
+    ToggleAccumulation,
     /// 退出程序
     Quit,
 }
@@ -40,17 +46,22 @@ pub fn create_tray(
     // 创建菜单
     let menu = Menu::new();
     
-    let show_item = MenuItem::new("显示窗口", true, None);
-    let pause_item = MenuItem::new("暂停记录", true, None);
-    let new_segment_item = MenuItem::new("新建日志段", true, None);
-    let open_log_item = MenuItem::new("打开日志目录", true, None);
-    let quit_item = MenuItem::new("退出", true, None);
+    let show_item = MenuItem::new(i18n::t("tray.show"), true, None);
+    let pause_item = MenuItem::new(i18n::t("tray.pause"), true, None);
+    let new_segment_item = MenuItem::new(i18n::t("tray.new_segment"), true, None);
+    let open_log_item = MenuItem::new(i18n::t("tray.open_log_dir"), true, None);
+    // 勾选状态由菜单自身切换，与详细记录模式保持一致
+    let verbose_item = CheckMenuItem::new(i18n::t("tray.verbose"), true, false, None);
+    let accumulate_item = MenuItem::new(i18n::t("tray.accumulate"), true, None);
+    let quit_item = MenuItem::new(i18n::t("tray.quit"), true, None);
     
     // 保存菜单项 ID
     let show_id = show_item.id().clone();
     let pause_id = pause_item.id().clone();
     let new_segment_id = new_segment_item.id().clone();
     let open_log_id = open_log_item.id().clone();
+    let verbose_id = verbose_item.id().clone();
+    let accumulate_id = accumulate_item.id().clone();
     let quit_id = quit_item.id().clone();
     
     menu.append(&show_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
@@ -58,6 +69,8 @@ pub fn create_tray(
     menu.append(&pause_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
     menu.append(&new_segment_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
     menu.append(&open_log_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
+    menu.append(&verbose_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
+    menu.append(&accumulate_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
     menu.append(&PredefinedMenuItem::separator()).map_err(|e| format!("添加分隔符失败: {}", e))?;
     menu.append(&quit_item).map_err(|e| format!("添加菜单项失败: {}", e))?;
     
@@ -67,7 +80,7 @@ pub fn create_tray(
     // 创建托盘图标
     let tray = TrayIconBuilder::new()
         .with_menu(Box::new(menu))
-        .with_tooltip("EchoKey - 记录中")
+        .with_tooltip(i18n::t("tray.tooltip_recording"))
         .with_icon(icon)
         .build()
         .map_err(|e| format!("创建托盘图标失败: {}", e))?;
@@ -86,6 +99,10 @@ pub fn create_tray(
                     TrayEvent::NewSegment
                 } else if event.id == open_log_id {
                     TrayEvent::OpenLogDir
+                } else if event.id == verbose_id {
+                    TrayEvent::ToggleVerbose
+                } else if event.id == accumulate_id {
+                    TrayEvent::ToggleAccumulation
                 } else if event.id == quit_id {
                     TrayEvent::Quit
                 } else {
@@ -185,9 +202,9 @@ fn is_in_letter_e(x: u32, y: u32, size: u32) -> bool {
 /// 更新托盘图标的暂停状态
 pub fn update_pause_state(tray: &TrayIcon, paused: bool) {
     let tooltip = if paused {
-        "EchoKey - 已暂停"
+        i18n::t("tray.tooltip_paused")
     } else {
-        "EchoKey - 记录中"
+        i18n::t("tray.tooltip_recording")
     };
     
     let _ = tray.set_tooltip(Some(tooltip));
@@ -0,0 +1,107 @@
+//! UI Automation 读取模块（Windows）
+//!
+//! 通过 IUIAutomation 读取当前焦点控件的文本（TextPattern），
+//! 用于定期写入校验快照，与记录的按键流对照。
+//! 不支持 TextPattern 的控件和密码框直接跳过。
+//!
+//! 跨进程读取可能很慢（焦点程序未响应时会一直等待），因此在单独的线程中读取，
+//! 见 [`FocusedTextReader`]。
+
+use std::cell::OnceCell;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+use windows::Win32::UI::Accessibility::{
+    CUIAutomation, IUIAutomation, IUIAutomationTextPattern, UIA_TextPatternId,
+};
+
+/// 每次读取的最大字符数，焦点是大文档时只读开头部分
+pub const MAX_TEXT_CHARS: i32 = 100_000;
+
+thread_local! {
+    /// 每个线程创建一次的 UI Automation 对象，创建失败时为 None
+    static AUTOMATION: OnceCell<Option<IUIAutomation>> = const { OnceCell::new() };
+}
+
+/// 读取当前焦点控件的文本（最多 [`MAX_TEXT_CHARS`] 个字符）
+///
+/// 焦点控件不支持 TextPattern、是密码框或读取失败时返回 None。
+pub fn read_focused_text() -> Option<String> {
+    AUTOMATION.with(|cell| {
+        let automation = cell.get_or_init(create_automation).as_ref()?;
+        unsafe {
+            let element = automation.GetFocusedElement().ok()?;
+            if element.CurrentIsPassword().is_ok_and(|password| password.as_bool()) {
+                return None;
+            }
+            let pattern: IUIAutomationTextPattern = element.GetCurrentPatternAs(UIA_TextPatternId).ok()?;
+            let text = pattern.DocumentRange().ok()?.GetText(MAX_TEXT_CHARS).ok()?;
+            Some(text.to_string())
+        }
+    })
+}
+
+/// 在当前线程初始化 COM 并创建 UI Automation 对象
+fn create_automation() -> Option<IUIAutomation> {
+    unsafe {
+        // 当前线程已用其他模式初始化 COM 时返回错误，不影响后续调用
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        match CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER) {
+            Ok(automation) => Some(automation),
+            Err(e) => {
+                eprintln!("警告: 无法创建 UI Automation: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// 在后台线程读取焦点控件文本
+///
+/// [`request`](Self::request) 请求读取一次，结果通过 [`try_result`](Self::try_result) 取回。
+/// 上一次读取还没完成时不重复请求，焦点程序未响应也不会阻塞调用方。
+pub struct FocusedTextReader {
+    requests: Sender<()>,
+    results: Receiver<Option<String>>,
+    pending: bool,
+}
+
+impl FocusedTextReader {
+    /// 启动读取线程（调用方丢弃读取器后线程退出）
+    pub fn spawn() -> Self {
+        let (requests, request_rx) = mpsc::channel::<()>();
+        let (result_tx, results) = mpsc::channel();
+        thread::spawn(move || {
+            for () in request_rx {
+                if result_tx.send(read_focused_text()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results, pending: false }
+    }
+
+    /// 请求读取一次，上一次还没读完时返回 false
+    pub fn request(&mut self) -> bool {
+        if self.pending {
+            return false;
+        }
+        self.pending = self.requests.send(()).is_ok();
+        self.pending
+    }
+
+    /// 取回读取结果：还没读完时返回 None，读完但没有读到文本时返回 `Some(None)`
+    pub fn try_result(&mut self) -> Option<Option<String>> {
+        match self.results.try_recv() {
+            Ok(text) => {
+                self.pending = false;
+                Some(text)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.pending = false;
+                None
+            }
+        }
+    }
+}
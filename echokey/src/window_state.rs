@@ -0,0 +1,95 @@
+//! 窗口位置和大小
+//!
+//! 界面运行中调整窗口大小或移动窗口后，保存到数据目录中的 `window.toml`，
+//! 下次启动时按保存的大小打开，并移回原来的位置。
+//! 显示器断开后保存的位置可能在屏幕之外，恢复前先限制在主显示器的范围内。
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// 窗口的外框位置和内容区大小（逻辑像素）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl WindowGeometry {
+    /// 限制在屏幕范围内的位置：窗口尽量完整显示，比屏幕还大时对齐左上角
+    pub fn clamped_position(&self, screen_width: f32, screen_height: f32) -> (f32, f32) {
+        let max_x = (screen_width - self.width).max(0.0);
+        let max_y = (screen_height - self.height).max(0.0);
+        (self.x.clamp(0.0, max_x), self.y.clamp(0.0, max_y))
+    }
+
+    /// 各项都是有限值且大小为正（文件被手动改坏时忽略）
+    fn is_valid(&self) -> bool {
+        [self.x, self.y, self.width, self.height].iter().all(|v| v.is_finite())
+            && self.width > 0.0
+            && self.height > 0.0
+    }
+}
+
+/// 窗口状态文件路径
+fn state_path() -> PathBuf {
+    config::get_data_directory().join("window.toml")
+}
+
+/// 读取上次保存的窗口位置和大小，没有保存过或文件无效时返回 None
+pub fn load() -> Option<WindowGeometry> {
+    load_from(&state_path())
+}
+
+/// 保存窗口位置和大小
+pub fn save(geometry: &WindowGeometry) {
+    if let Err(e) = save_to(&state_path(), geometry) {
+        eprintln!("警告: 无法保存窗口位置: {}", e);
+    }
+}
+
+fn load_from(path: &Path) -> Option<WindowGeometry> {
+    let content = fs::read_to_string(path).ok()?;
+    toml::from_str::<WindowGeometry>(&content).ok().filter(WindowGeometry::is_valid)
+}
+
+fn save_to(path: &Path, geometry: &WindowGeometry) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = toml::to_string(geometry).map_err(io::Error::other)?;
+    fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_geometry_round_trip_and_clamp() {
+        let dir = std::env::temp_dir()
+            .join(format!("echokey-window-state-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("window.toml");
+        assert_eq!(load_from(&path), None);
+
+        let geometry = WindowGeometry { x: 2500.0, y: -40.0, width: 400.0, height: 640.0 };
+        save_to(&path, &geometry).unwrap();
+        assert_eq!(load_from(&path), Some(geometry));
+
+        // 原来在第二个显示器上：移回 1920×1080 的主显示器
+        assert_eq!(geometry.clamped_position(1920.0, 1080.0), (1520.0, 0.0));
+        // 屏幕比窗口还小时对齐左上角
+        assert_eq!(geometry.clamped_position(300.0, 500.0), (0.0, 0.0));
+
+        fs::write(&path, "x = 0.0\ny = 0.0\nwidth = 0.0\nheight = 600.0\n").unwrap();
+        assert_eq!(load_from(&path), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}